@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+
+/// Largest JSON body kept per response, larger bodies are dropped
+pub const MAX_API_RESPONSE_BYTES: usize = 256 * 1024;
+
+/// Most responses kept per page
+pub const MAX_API_RESPONSES: usize = 50;
+
+/// Wraps `fetch` and `XMLHttpRequest` so JSON responses the page receives from
+/// now on are recorded in `window.__scApiResponses`. Runs right after
+/// navigation, so it sees requests triggered by interactions and late
+/// rendering; earlier requests are recovered by `COLLECT_API_RESPONSES_SCRIPT`.
+pub const INSTALL_API_HOOKS_SCRIPT: &str = r#"
+    if (window.__scApiResponses) return false;
+    window.__scApiResponses = [];
+    const record = (url, method, status, contentType, text) => {
+        if (!contentType || !contentType.toLowerCase().includes('json')) return;
+        if (window.__scApiResponses.length >= arguments[1]) return;
+        if (!text || text.length > arguments[0]) return;
+        window.__scApiResponses.push({ url: new URL(url, location.href).href, method, status, content_type: contentType, text });
+    };
+
+    const originalFetch = window.fetch;
+    if (originalFetch) {
+        window.fetch = function(input, init) {
+            const method = ((init && init.method) || (input && input.method) || 'GET').toUpperCase();
+            const url = typeof input === 'string' ? input : (input && input.url) || String(input);
+            return originalFetch.apply(this, arguments).then(response => {
+                response.clone().text()
+                    .then(text => record(url, method, response.status, response.headers.get('content-type'), text))
+                    .catch(() => {});
+                return response;
+            });
+        };
+    }
+
+    const originalOpen = XMLHttpRequest.prototype.open;
+    XMLHttpRequest.prototype.open = function(method, url) {
+        this.__scRequest = { method: String(method).toUpperCase(), url: String(url) };
+        this.addEventListener('load', () => {
+            if (this.responseType && this.responseType !== 'text' && this.responseType !== 'json') return;
+            const text = this.responseType === 'json' ? JSON.stringify(this.response) : this.responseText;
+            record(this.__scRequest.url, this.__scRequest.method, this.status,
+                this.getResponseHeader('content-type'), text);
+        });
+        return originalOpen.apply(this, arguments);
+    };
+    return true;
+"#;
+
+/// Async script returning the recorded responses. Fetch/XHR requests the page
+/// made before the hooks were installed are listed from the resource timing
+/// entries and fetched again with GET, so their bodies reflect the current
+/// server state and non-GET calls (e.g. GraphQL POSTs) are only seen by the hooks.
+pub const COLLECT_API_RESPONSES_SCRIPT: &str = r#"
+    const [maxBytes, maxResponses, done] = arguments;
+    const recorded = window.__scApiResponses || [];
+    const seen = new Set(recorded.map(response => response.url));
+    const missed = performance.getEntriesByType('resource')
+        .filter(entry => entry.initiatorType === 'fetch' || entry.initiatorType === 'xmlhttprequest')
+        .map(entry => entry.name)
+        .filter((url, index, urls) => !seen.has(url) && urls.indexOf(url) === index)
+        .slice(0, Math.max(0, maxResponses - recorded.length));
+
+    Promise.all(missed.map(url =>
+        fetch(url, { credentials: 'include' })
+            .then(response => {
+                const contentType = response.headers.get('content-type') || '';
+                if (!contentType.toLowerCase().includes('json')) return null;
+                return response.text().then(text => text.length > maxBytes ? null :
+                    { url, method: 'GET', status: response.status, content_type: contentType, text });
+            })
+            .catch(() => null)
+    )).then(refetched => done(recorded.concat(refetched.filter(Boolean)).slice(0, maxResponses)));
+"#;
+
+/// A JSON response fetched by the page itself while it rendered
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApiResponse {
+    pub url: String,
+    pub method: String,
+    pub status: u16,
+    pub content_type: String,
+    pub body: serde_json::Value,
+}
+
+/// Convert the array returned by `COLLECT_API_RESPONSES_SCRIPT`, dropping
+/// entries whose body isn't valid JSON and repeated GETs of the same URL
+pub fn parse_api_responses(value: &serde_json::Value) -> Vec<ApiResponse> {
+    let mut responses: Vec<ApiResponse> = Vec::new();
+    for entry in value.as_array().into_iter().flatten() {
+        let text = |key: &str| entry.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+        let Ok(body) = serde_json::from_str::<serde_json::Value>(text("text")) else {
+            continue;
+        };
+        let response = ApiResponse {
+            url: text("url").to_string(),
+            method: text("method").to_string(),
+            status: entry.get("status").and_then(|v| v.as_u64()).unwrap_or(0) as u16,
+            content_type: text("content_type").to_string(),
+            body,
+        };
+
+        let duplicate = response.method == "GET"
+            && responses
+                .iter()
+                .any(|r| r.method == "GET" && r.url == response.url);
+        if !duplicate {
+            responses.push(response);
+        }
+    }
+    responses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_api_responses() {
+        let responses = parse_api_responses(&json!([
+            {
+                "url": "https://shop.example.com/api/products?page=1",
+                "method": "GET",
+                "status": 200,
+                "content_type": "application/json; charset=utf-8",
+                "text": "{\"products\":[{\"name\":\"Widget\",\"price\":49}]}"
+            },
+            {
+                "url": "https://shop.example.com/api/products?page=1",
+                "method": "GET",
+                "status": 200,
+                "content_type": "application/json",
+                "text": "{\"products\":[]}"
+            },
+            {
+                "url": "https://shop.example.com/graphql",
+                "method": "POST",
+                "status": 200,
+                "content_type": "application/json",
+                "text": "{\"data\":{\"cart\":null}}"
+            },
+            {
+                "url": "https://shop.example.com/api/broken",
+                "method": "GET",
+                "status": 500,
+                "content_type": "application/json",
+                "text": "<html>Server error</html>"
+            }
+        ]));
+
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].body["products"][0]["name"], "Widget");
+        assert_eq!(responses[0].status, 200);
+        assert_eq!(responses[1].method, "POST");
+        assert!(parse_api_responses(&serde_json::Value::Null).is_empty());
+    }
+}
@@ -0,0 +1,242 @@
+use serde::{Deserialize, Serialize};
+
+/// Attribute the annotation script writes the rendered box of each element to
+pub const BOX_ATTRIBUTE: &str = "data-sc-box";
+
+/// Attribute holding "font-size,font-weight" for elements that directly contain text
+pub const STYLE_ATTRIBUTE: &str = "data-sc-style";
+
+/// Annotates every rendered element, including those in open shadow roots,
+/// with its page-relative bounding box so the position survives into the
+/// captured HTML source. Returns the counts and page
+/// size read by `BoundingBoxReport::from_value`.
+pub const ANNOTATE_BOXES_SCRIPT: &str = r#"
+    let annotated = 0;
+    let styled = 0;
+    const scrollX = window.scrollX || 0;
+    const scrollY = window.scrollY || 0;
+    const elements = [];
+    const collect = root => {
+        for (const element of root.querySelectorAll('*')) {
+            elements.push(element);
+            if (element.shadowRoot) collect(element.shadowRoot);
+        }
+    };
+    collect(document);
+    for (const element of elements) {
+        const rect = element.getBoundingClientRect();
+        if (rect.width === 0 && rect.height === 0) {
+            element.removeAttribute('data-sc-box');
+            continue;
+        }
+        element.setAttribute('data-sc-box', [
+            Math.round(rect.left + scrollX),
+            Math.round(rect.top + scrollY),
+            Math.round(rect.width),
+            Math.round(rect.height)
+        ].join(','));
+        const hasText = Array.from(element.childNodes)
+            .some(node => node.nodeType === Node.TEXT_NODE && node.textContent.trim());
+        if (hasText) {
+            const style = window.getComputedStyle(element);
+            element.setAttribute('data-sc-style',
+                parseFloat(style.fontSize) + ',' + (parseInt(style.fontWeight, 10) || 400));
+            styled++;
+        }
+        annotated++;
+    }
+    const root = document.documentElement;
+    return {
+        annotated,
+        styled,
+        page_width: root.scrollWidth,
+        page_height: root.scrollHeight,
+        viewport_height: window.innerHeight
+    };
+"#;
+
+/// Draws an outlined, labelled box over every item of each sibling group.
+/// Takes `[{ label, boxes: [[x, y, width, height], ...] }, ...]` as its only
+/// argument and returns the number of boxes drawn. Replaces earlier overlays.
+pub const OVERLAY_GROUPS_SCRIPT: &str = r#"
+    const groups = arguments[0];
+    const colors = ['#e6194b', '#3cb44b', '#4363d8', '#f58231', '#911eb4', '#42d4f4', '#f032e6', '#9a6324'];
+    const previous = document.getElementById('sc-group-overlays');
+    if (previous) previous.remove();
+    const container = document.createElement('div');
+    container.id = 'sc-group-overlays';
+    container.style.cssText = 'position:absolute;left:0;top:0;width:0;height:0;pointer-events:none;z-index:2147483647;';
+    let count = 0;
+    groups.forEach((group, index) => {
+        const color = colors[index % colors.length];
+        group.boxes.forEach(([x, y, width, height], item) => {
+            const box = document.createElement('div');
+            box.style.cssText = `position:absolute;left:${x}px;top:${y}px;width:${width}px;height:${height}px;` +
+                `border:2px solid ${color};background:${color}22;box-sizing:border-box;`;
+            if (item === 0) {
+                const label = document.createElement('span');
+                label.textContent = `${index + 1}: ${group.label}`;
+                label.style.cssText = `position:absolute;left:0;top:-18px;font:12px monospace;` +
+                    `color:#fff;background:${color};padding:1px 4px;white-space:nowrap;`;
+                box.appendChild(label);
+            }
+            container.appendChild(box);
+            count++;
+        });
+    });
+    document.body.appendChild(container);
+    return count;
+"#;
+
+/// Full scrollable size of the document, used to size the window for page screenshots
+pub const DOCUMENT_SIZE_SCRIPT: &str = r#"
+    const root = document.documentElement;
+    return [
+        Math.max(root.scrollWidth, document.body ? document.body.scrollWidth : 0),
+        Math.max(root.scrollHeight, document.body ? document.body.scrollHeight : 0)
+    ];
+"#;
+
+/// Summary of one `ANNOTATE_BOXES_SCRIPT` run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBoxReport {
+    /// Elements that received a `data-sc-box` attribute
+    pub annotated: u64,
+    /// Elements with direct text that also received a `data-sc-style` attribute
+    pub styled: u64,
+    /// Scrollable page size in CSS pixels
+    pub page_width: f64,
+    pub page_height: f64,
+    pub viewport_height: f64,
+}
+
+impl BoundingBoxReport {
+    /// Read the object returned by the annotation script, treating missing
+    /// fields as zero
+    pub fn from_value(value: &serde_json::Value) -> Self {
+        let number = |key: &str| value.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        BoundingBoxReport {
+            annotated: number("annotated") as u64,
+            styled: number("styled") as u64,
+            page_width: number("page_width"),
+            page_height: number("page_height"),
+            viewport_height: number("viewport_height"),
+        }
+    }
+}
+
+/// Rendered position and size of an element in CSS pixels, relative to the page origin
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl BoundingBox {
+    /// Parse the "x,y,width,height" value written by `ANNOTATE_BOXES_SCRIPT`
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        let parts: Vec<f64> = value
+            .split(',')
+            .map(|part| part.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .ok()?;
+
+        match parts.as_slice() {
+            [x, y, width, height] if *width >= 0.0 && *height >= 0.0 => Some(BoundingBox {
+                x: *x,
+                y: *y,
+                width: *width,
+                height: *height,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+
+    /// The `[x, y, width, height]` form taken by `OVERLAY_GROUPS_SCRIPT`
+    pub fn as_array(&self) -> [f64; 4] {
+        [self.x, self.y, self.width, self.height]
+    }
+}
+
+/// Computed text style of a rendered element
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RenderedStyle {
+    /// Font size in CSS pixels
+    pub font_size: f64,
+    /// Numeric font weight, 400 is normal and 700 bold
+    pub font_weight: u16,
+}
+
+impl RenderedStyle {
+    /// Parse the "font-size,font-weight" value written by `ANNOTATE_BOXES_SCRIPT`
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        let (font_size, font_weight) = value.split_once(',')?;
+        let font_size: f64 = font_size.trim().parse().ok()?;
+        let font_weight: u16 = font_weight.trim().parse().ok()?;
+
+        if font_size > 0.0 {
+            Some(RenderedStyle {
+                font_size,
+                font_weight,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rendered_style_from_attribute() {
+        let style = RenderedStyle::from_attribute("32,700").unwrap();
+        assert_eq!(style.font_size, 32.0);
+        assert_eq!(style.font_weight, 700);
+
+        assert!(RenderedStyle::from_attribute("0,400").is_none());
+        assert!(RenderedStyle::from_attribute("16").is_none());
+    }
+
+    #[test]
+    fn test_bounding_box_from_attribute() {
+        let bbox = BoundingBox::from_attribute("10,-20, 300,40").unwrap();
+        assert_eq!(bbox.x, 10.0);
+        assert_eq!(bbox.y, -20.0);
+        assert_eq!(bbox.width, 300.0);
+        assert_eq!(bbox.height, 40.0);
+        assert_eq!(bbox.area(), 12000.0);
+
+        assert!(BoundingBox::from_attribute("10,20,30").is_none());
+        assert!(BoundingBox::from_attribute("a,b,c,d").is_none());
+        assert!(BoundingBox::from_attribute("0,0,-5,10").is_none());
+    }
+
+    #[test]
+    fn test_bounding_box_report_from_value() {
+        let report = BoundingBoxReport::from_value(&serde_json::json!({
+            "annotated": 120,
+            "styled": 45,
+            "page_width": 1280,
+            "page_height": 3400.5,
+            "viewport_height": 900
+        }));
+        assert_eq!(report.annotated, 120);
+        assert_eq!(report.styled, 45);
+        assert_eq!(report.page_height, 3400.5);
+        assert_eq!(report.viewport_height, 900.0);
+
+        // Older drivers may return null from the script
+        assert_eq!(
+            BoundingBoxReport::from_value(&serde_json::Value::Null),
+            BoundingBoxReport::default()
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use crate::fetch_limits::read_body;
+use crate::network_guard::{check_url, http_client};
+use crate::utils::url_to_file_stem;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+use tracing::warn;
+use url::Url;
+
+/// Largest asset downloaded, so a mislabelled video doesn't fill the disk
+pub const MAX_ASSET_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrandAssetKind {
+    Favicon,
+    Logo,
+    /// Share image from `og:image`
+    OgImage,
+}
+
+/// A favicon, logo or share image of a site
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BrandAsset {
+    pub kind: BrandAssetKind,
+    pub url: String,
+    /// Where the asset was saved, when assets are downloaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Favicons (`link rel=icon`, `apple-touch-icon`), logos (a JSON-LD
+/// Organization `logo`, else header and nav images or images whose class,
+/// ID, alt text or file name mention "logo") and the `og:image` of a page,
+/// without duplicate URLs
+pub fn extract_brand_assets(html: &str, page_url: &str) -> Vec<BrandAsset> {
+    let document = Html::parse_document(html);
+    let Ok(base) = Url::parse(page_url) else {
+        return Vec::new();
+    };
+    let mut assets: Vec<BrandAsset> = Vec::new();
+    let mut add = |kind: BrandAssetKind, href: &str| {
+        let Some(url) = base.join(href.trim()).ok().map(String::from) else {
+            return;
+        };
+        if !href.trim().is_empty() && !assets.iter().any(|asset| asset.url == url) {
+            assets.push(BrandAsset {
+                kind,
+                url,
+                path: None,
+            });
+        }
+    };
+
+    let icon_selector = Selector::parse("link[rel][href]").unwrap();
+    for link in document.select(&icon_selector) {
+        let rel = link.value().attr("rel").unwrap_or_default().to_lowercase();
+        if rel
+            .split_whitespace()
+            .any(|rel| rel == "icon" || rel == "apple-touch-icon")
+        {
+            add(
+                BrandAssetKind::Favicon,
+                link.value().attr("href").unwrap_or_default(),
+            );
+        }
+    }
+
+    let logos = json_ld_logos(&document);
+    if logos.is_empty() {
+        let image_selector = Selector::parse("img[src]").unwrap();
+        for image in document.select(&image_selector).filter(is_logo_image) {
+            add(
+                BrandAssetKind::Logo,
+                image.value().attr("src").unwrap_or_default(),
+            );
+        }
+    }
+    for logo in &logos {
+        add(BrandAssetKind::Logo, logo);
+    }
+
+    let og_selector = Selector::parse(r#"meta[property="og:image"][content]"#).unwrap();
+    if let Some(meta) = document.select(&og_selector).next() {
+        add(
+            BrandAssetKind::OgImage,
+            meta.value().attr("content").unwrap_or_default(),
+        );
+    }
+    assets
+}
+
+/// Logo URLs of JSON-LD Organization objects, as a string or an ImageObject
+fn json_ld_logos(document: &Html) -> Vec<String> {
+    let script_selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    let mut logos = Vec::new();
+    for script in document.select(&script_selector) {
+        if let Ok(value) = serde_json::from_str::<Value>(&script.text().collect::<String>()) {
+            collect_logos(&value, &mut logos);
+        }
+    }
+    logos
+}
+
+fn collect_logos(value: &Value, logos: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| collect_logos(item, logos)),
+        Value::Object(fields) => {
+            let logo = match fields.get("logo") {
+                Some(Value::String(url)) => Some(url.as_str()),
+                Some(logo) => logo.get("url").and_then(Value::as_str),
+                None => None,
+            };
+            if let Some(logo) = logo {
+                logos.push(logo.to_string());
+            }
+            fields
+                .values()
+                .for_each(|field| collect_logos(field, logos));
+        }
+        _ => {}
+    }
+}
+
+/// Images in the page header or navigation, or that call themselves a logo
+fn is_logo_image(image: &ElementRef) -> bool {
+    let element = image.value();
+    let mentions_logo = [
+        element.attr("class"),
+        element.attr("id"),
+        element.attr("alt"),
+        element.attr("src"),
+    ]
+    .into_iter()
+    .flatten()
+    .any(|text| text.to_lowercase().contains("logo"));
+    let in_header = image
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .any(|ancestor| {
+            matches!(ancestor.value().name(), "header" | "nav")
+                || ancestor
+                    .value()
+                    .attr("role")
+                    .is_some_and(|role| role == "banner")
+        });
+    mentions_logo || in_header
+}
+
+/// File name of a downloaded asset: the URL as a file stem, with the
+/// extension of the URL path when it has one
+fn asset_file_name(url: &str) -> String {
+    let extension = Url::parse(url).ok().and_then(|url| {
+        let path = url.path().to_string();
+        let (_, extension) = path.rsplit_once('.')?;
+        (extension.len() <= 4 && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+            .then(|| extension.to_lowercase())
+    });
+    let stem = url_to_file_stem(url);
+    match extension {
+        Some(extension) if !stem.ends_with(&format!(".{extension}")) => {
+            format!("{stem}.{extension}")
+        }
+        _ => stem,
+    }
+}
+
+/// Download assets into `dir`, recording where each was saved. Assets that
+/// fail to download keep no path, as do assets on or redirecting to private
+/// networks unless `allow_private`.
+pub async fn download_brand_assets(
+    assets: &mut [BrandAsset],
+    dir: &Path,
+    allow_private: bool,
+) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+    let client = http_client(Duration::from_secs(30), allow_private);
+
+    for asset in assets.iter_mut() {
+        if !allow_private {
+            if let Err(e) = check_url(&asset.url).await {
+                warn!("Skipping {}: {}", asset.url, e);
+                continue;
+            }
+        }
+        // Read chunk by chunk, stopping at the limit
+        let bytes = match client
+            .get(&asset.url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(response) => read_body(response, Some(MAX_ASSET_BYTES as u64)).await,
+            Err(e) => Err(e.to_string()),
+        };
+        match bytes {
+            Ok(bytes) => {
+                let path = dir.join(asset_file_name(&asset.url));
+                std::fs::write(&path, &bytes)
+                    .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+                asset.path = Some(path.display().to_string());
+            }
+            Err(e) => warn!("Failed to download {}: {}", asset.url, e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(assets: &[BrandAsset], kind: BrandAssetKind) -> Vec<&str> {
+        assets
+            .iter()
+            .filter(|asset| asset.kind == kind)
+            .map(|asset| asset.url.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_brand_assets() {
+        let html = r#"<html><head>
+            <link rel="shortcut icon" href="/favicon.ico">
+            <link rel="apple-touch-icon" href="https://cdn.example.com/touch.png">
+            <link rel="stylesheet" href="/site.css">
+            <meta property="og:image" content="/share.jpg">
+        </head><body>
+            <header><a href="/"><img src="/img/brand.svg" alt="Acme"></a></header>
+            <main><img src="/photos/team.jpg"><img class="partner-logo" src="/partners/globex.png"></main>
+        </body></html>"#;
+        let assets = extract_brand_assets(html, "https://example.com/about");
+        assert_eq!(
+            urls(&assets, BrandAssetKind::Favicon),
+            vec![
+                "https://example.com/favicon.ico",
+                "https://cdn.example.com/touch.png"
+            ]
+        );
+        assert_eq!(
+            urls(&assets, BrandAssetKind::Logo),
+            vec![
+                "https://example.com/img/brand.svg",
+                "https://example.com/partners/globex.png"
+            ]
+        );
+        assert_eq!(
+            urls(&assets, BrandAssetKind::OgImage),
+            vec!["https://example.com/share.jpg"]
+        );
+    }
+
+    #[test]
+    fn test_json_ld_logo_wins() {
+        let html = r#"<html><head><script type="application/ld+json">
+            {"@type": "Organization", "name": "Acme", "logo": {"@type": "ImageObject", "url": "https://example.com/acme.png"}}
+        </script></head><body><header><img src="/banner.jpg"></header></body></html>"#;
+        let assets = extract_brand_assets(html, "https://example.com/");
+        assert_eq!(
+            urls(&assets, BrandAssetKind::Logo),
+            vec!["https://example.com/acme.png"]
+        );
+        assert_eq!(
+            asset_file_name("https://example.com/acme.png"),
+            "example.com_acme.png"
+        );
+        assert_eq!(
+            asset_file_name("https://example.com/logo?size=2"),
+            "example.com_logo_size_2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_stops_at_limit() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A small logo, a video announcing its size and one sent in chunks
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let head = match request.split_whitespace().nth(1) {
+                    Some("/logo.svg") => "Content-Length: 5\r\n\r\n<svg>".to_string(),
+                    Some("/announced.png") => {
+                        format!("Content-Length: {}\r\n\r\n", MAX_ASSET_BYTES + 1)
+                    }
+                    _ => "Transfer-Encoding: chunked\r\n\r\n".to_string(),
+                };
+                let response = format!("HTTP/1.1 200 OK\r\nConnection: close\r\n{head}");
+                if stream.write_all(response.as_bytes()).await.is_err() || !head.contains("chunked")
+                {
+                    continue;
+                }
+                let chunk = format!("10000\r\n{}\r\n", "x".repeat(0x10000));
+                for _ in 0..=MAX_ASSET_BYTES / 0x10000 {
+                    if stream.write_all(chunk.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+                stream.write_all(b"0\r\n\r\n").await.ok();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut assets: Vec<BrandAsset> = ["logo.svg", "announced.png", "chunked.png"]
+            .iter()
+            .map(|name| BrandAsset {
+                kind: BrandAssetKind::Logo,
+                url: format!("http://127.0.0.1:{port}/{name}"),
+                path: None,
+            })
+            .collect();
+        download_brand_assets(&mut assets, dir.path(), true)
+            .await
+            .unwrap();
+
+        let saved = assets[0].path.as_deref().unwrap();
+        assert_eq!(std::fs::read_to_string(saved).unwrap(), "<svg>");
+        assert_eq!(assets[1].path, None);
+        assert_eq!(assets[2].path, None);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
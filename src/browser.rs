@@ -1,7 +1,56 @@
-use fantoccini::{Client, ClientBuilder};
-use serde_json::json;
-use std::time::Duration;
+use crate::api_capture::{
+    parse_api_responses, ApiResponse, COLLECT_API_RESPONSES_SCRIPT, INSTALL_API_HOOKS_SCRIPT,
+    MAX_API_RESPONSES, MAX_API_RESPONSE_BYTES,
+};
+use crate::bounding_box::{
+    BoundingBoxReport, ANNOTATE_BOXES_SCRIPT, DOCUMENT_SIZE_SCRIPT, OVERLAY_GROUPS_SCRIPT,
+};
+#[cfg(feature = "cdp")]
+use crate::cdp::CdpSession;
+use crate::encoding::DOCUMENT_CHARSET_SCRIPT;
+use crate::interaction::{path_to_css_selector, Interaction};
+use crate::list_extraction::{overlay_groups, write_lists, ExtractedList};
+use crate::network_guard::check_url;
+use crate::request_blocking::BlockRules;
+use crate::shadow_dom::FLATTEN_SHADOW_DOM_SCRIPT;
+use crate::structured_content::{BODY_TEXT_SCRIPT, CONTENT_TYPE_SCRIPT};
+use crate::wait::{WaitStrategy, RESOURCE_COUNT_SCRIPT};
+use crate::wayback::DOCUMENT_STATUS_SCRIPT;
+use fantoccini::elements::Element;
+use fantoccini::error::{CmdError, ErrorStatus};
+use fantoccini::wd::TimeoutConfiguration;
+use fantoccini::{Client, ClientBuilder, Locator};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tokio::time::{error::Elapsed, timeout};
+use tracing::{debug, trace, warn};
+
+/// Longest a wait strategy may take before the page is captured as it is
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest a page may take to load before navigation gives up
+pub const DEFAULT_NAVIGATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest a script or the HTML capture may run
+pub const DEFAULT_SCRIPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest a whole page, from navigation to capture, may take
+pub const DEFAULT_PAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long a stuck session gets to close before it's abandoned
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a session gets to answer a health check
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval between checks of a polled wait condition
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tallest window used for full page screenshots, long pages are cut off here
+const MAX_SCREENSHOT_HEIGHT: u64 = 16_384;
 
 #[derive(Error, Debug)]
 pub enum BrowserError {
@@ -11,25 +60,617 @@ pub enum BrowserError {
     WebDriverNotAvailable { port: u16 },
     #[error("Failed to extract HTML: {0}")]
     HtmlExtractionError(String),
+    #[error("Failed to write output: {0}")]
+    OutputError(String),
+    #[error("No element matches path '{path}'")]
+    ElementNotFound { path: String },
+    #[cfg(feature = "cdp")]
+    #[error("Chrome DevTools error: {0}")]
+    CdpError(Box<chromiumoxide::error::CdpError>),
+    #[error("The {0} engine is not available, rebuild with `--features {0}`")]
+    EngineNotAvailable(&'static str),
+    #[error("{operation} timed out after {after:?}")]
+    Timeout {
+        operation: &'static str,
+        after: Duration,
+    },
+    #[error("Refusing to read a page on a private network: {0}")]
+    PrivateNetwork(String),
+}
+
+impl BrowserError {
+    /// Whether the session is gone, e.g. the browser crashed or the WebDriver
+    /// server dropped it, so every later command would fail too
+    pub fn is_session_lost(&self) -> bool {
+        match self {
+            BrowserError::ConnectionError(e) => match e {
+                CmdError::Lost(_) | CmdError::Failed(_) | CmdError::FailedC(_) => true,
+                CmdError::Standard(e) => {
+                    let message = e.message.to_lowercase();
+                    e.error == ErrorStatus::InvalidSessionId
+                        || ["chrome not reachable", "tab crashed", "disconnected"]
+                            .iter()
+                            .any(|lost| message.contains(lost))
+                }
+                _ => false,
+            },
+            #[cfg(feature = "cdp")]
+            BrowserError::CdpError(e) => {
+                use chromiumoxide::error::CdpError;
+                matches!(
+                    **e,
+                    CdpError::Ws(_) | CdpError::ChannelSendError(_) | CdpError::NoResponse
+                )
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Limits that keep a hanging page from stalling the crawl
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageTimeouts {
+    #[serde(with = "crate::wait::duration_ms")]
+    pub navigation: Duration,
+    /// Applies to each script and to capturing the HTML
+    #[serde(with = "crate::wait::duration_ms")]
+    pub script: Duration,
+    /// Watchdog over everything done for one page
+    #[serde(with = "crate::wait::duration_ms")]
+    pub page: Duration,
+}
+
+impl Default for PageTimeouts {
+    fn default() -> Self {
+        PageTimeouts {
+            navigation: DEFAULT_NAVIGATION_TIMEOUT,
+            script: DEFAULT_SCRIPT_TIMEOUT,
+            page: DEFAULT_PAGE_TIMEOUT,
+        }
+    }
+}
+
+#[cfg(feature = "cdp")]
+impl From<chromiumoxide::error::CdpError> for BrowserError {
+    fn from(e: chromiumoxide::error::CdpError) -> Self {
+        BrowserError::CdpError(Box::new(e))
+    }
+}
+
+/// Protocol the crawler drives the browser with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserEngine {
+    /// A WebDriver server (chromedriver or geckodriver) on the configured port
+    #[default]
+    WebDriver,
+    /// Chrome launched by the crawler and driven over the DevTools Protocol,
+    /// which allows request blocking and captures full pages without
+    /// resizing the window. Needs the `cdp` feature.
+    Cdp,
+}
+
+impl BrowserEngine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BrowserEngine::WebDriver => "webdriver",
+            BrowserEngine::Cdp => "cdp",
+        }
+    }
+}
+
+impl std::str::FromStr for BrowserEngine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "webdriver" => Ok(BrowserEngine::WebDriver),
+            "cdp" => Ok(BrowserEngine::Cdp),
+            _ => Err(format!("Unknown engine: {s} (expected webdriver or cdp)")),
+        }
+    }
+}
+
+/// Geographic position reported to pages that use the Geolocation API
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Geolocation {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl std::str::FromStr for Geolocation {
+    type Err = String;
+
+    /// Parse "latitude,longitude", e.g. "52.52,13.405"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid geolocation: {s} (expected LAT,LON)");
+        let (latitude, longitude) = s.split_once(',').ok_or_else(invalid)?;
+        let latitude: f64 = latitude.trim().parse().map_err(|_| invalid())?;
+        let longitude: f64 = longitude.trim().parse().map_err(|_| invalid())?;
+
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(invalid());
+        }
+        Ok(Geolocation {
+            latitude,
+            longitude,
+        })
+    }
+}
+
+/// Locale overrides applied to the browser session so region-gated content
+/// renders for a chosen market rather than the crawler host's locale
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LocaleOptions {
+    /// Value for the Accept-Language header and `navigator.languages`, e.g. "de-DE,de"
+    pub accept_language: Option<String>,
+    /// IANA timezone, e.g. "Europe/Berlin". Applied by Firefox (geckodriver) and
+    /// the CDP engine, Chrome has no WebDriver capability for it.
+    pub timezone: Option<String>,
+    pub geolocation: Option<Geolocation>,
+}
+
+/// An open browser tab, driven through WebDriver or the DevTools Protocol.
+/// Element methods take CSS selectors and return false when nothing matches.
+enum Session {
+    WebDriver(Client),
+    #[cfg(feature = "cdp")]
+    Cdp(Box<CdpSession>),
+}
+
+impl Session {
+    async fn goto(&mut self, url: &str) -> Result<(), BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.goto(url).await?),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.goto(url).await,
+        }
+    }
+
+    async fn execute(&mut self, script: &str, args: Vec<Value>) -> Result<Value, BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.execute(script, args).await?),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.execute(script, args).await,
+        }
+    }
+
+    async fn execute_async(
+        &mut self,
+        script: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.execute_async(script, args).await?),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.execute_async(script, args).await,
+        }
+    }
+
+    async fn current_url(&mut self) -> Result<String, BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.current_url().await?.to_string()),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.current_url().await,
+        }
+    }
+
+    async fn source(&mut self) -> Result<String, BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.source().await?),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.source().await,
+        }
+    }
+
+    async fn title(&mut self) -> Result<String, BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.title().await?),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.title().await,
+        }
+    }
+
+    async fn full_page_screenshot(&mut self) -> Result<Vec<u8>, BrowserError> {
+        match self {
+            Session::WebDriver(client) => webdriver_full_page_screenshot(client).await,
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.full_page_screenshot().await,
+        }
+    }
+
+    async fn click(&mut self, selector: &str) -> Result<bool, BrowserError> {
+        match self {
+            Session::WebDriver(client) => match first_element(client, selector).await? {
+                Some(element) => {
+                    element.click().await?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.click(selector).await,
+        }
+    }
+
+    async fn type_into(&mut self, selector: &str, text: &str) -> Result<bool, BrowserError> {
+        match self {
+            Session::WebDriver(client) => match first_element(client, selector).await? {
+                Some(element) => {
+                    element.clear().await?;
+                    element.send_keys(text).await?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.type_into(selector, text).await,
+        }
+    }
+
+    async fn select_option(&mut self, selector: &str, value: &str) -> Result<bool, BrowserError> {
+        match self {
+            Session::WebDriver(client) => match first_element(client, selector).await? {
+                Some(element) => {
+                    if element.select_by_value(value).await.is_err() {
+                        element.select_by_label(value).await?;
+                    }
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.select_option(selector, value).await,
+        }
+    }
+
+    fn blocked_requests(&self) -> Option<usize> {
+        match self {
+            Session::WebDriver(_) => None,
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.blocked_requests(),
+        }
+    }
+
+    async fn close(self) -> Result<(), BrowserError> {
+        match self {
+            Session::WebDriver(client) => Ok(client.close().await?),
+            #[cfg(feature = "cdp")]
+            Session::Cdp(session) => session.close().await,
+        }
+    }
+}
+
+/// PNG screenshot of the whole page. WebDriver only captures the
+/// viewport, so its window is temporarily resized to the document size
+/// (capped at `MAX_SCREENSHOT_HEIGHT`).
+async fn webdriver_full_page_screenshot(client: &mut Client) -> Result<Vec<u8>, BrowserError> {
+    let (original_width, original_height) = client.get_window_size().await?;
+    let size = client.execute(DOCUMENT_SIZE_SCRIPT, vec![]).await?;
+    let dimension = |index: usize, fallback: u64| {
+        size.get(index)
+            .and_then(|value| value.as_u64())
+            .unwrap_or(fallback)
+    };
+    let width = dimension(0, original_width).max(original_width);
+    let height = dimension(1, original_height).min(MAX_SCREENSHOT_HEIGHT);
+
+    client.set_window_size(width as u32, height as u32).await?;
+    let png = client.screenshot().await;
+    client
+        .set_window_size(original_width as u32, original_height as u32)
+        .await?;
+    Ok(png?)
+}
+
+async fn first_element(
+    client: &mut Client,
+    selector: &str,
+) -> Result<Option<Element>, BrowserError> {
+    Ok(client
+        .find_all(Locator::Css(selector))
+        .await?
+        .into_iter()
+        .next())
 }
 
 pub struct Browser {
-    client: Option<Client>,
+    session: Option<Session>,
+    engine: BrowserEngine,
     port: u16,
+    locale: LocaleOptions,
+    block_rules: BlockRules,
+    capture_bounding_boxes: bool,
+    interactions: Vec<Interaction>,
+    wait_strategy: WaitStrategy,
+    wait_timeout: Duration,
+    capture_api_responses: bool,
+    flatten_shadow_dom: bool,
+    /// Pages on a private network address, e.g. by redirecting there, fail
+    /// to load
+    block_private_networks: bool,
+    timeouts: PageTimeouts,
+    /// A command timed out, so the session may still be busy with it
+    timed_out: bool,
 }
 
 impl Browser {
     pub fn new(port: u16) -> Self {
-        Browser { client: None, port }
+        Self::with_locale(port, LocaleOptions::default())
     }
 
-    pub async fn connect(&mut self) -> Result<(), BrowserError> {
+    pub fn with_locale(port: u16, locale: LocaleOptions) -> Self {
+        Browser {
+            session: None,
+            engine: BrowserEngine::default(),
+            port,
+            locale,
+            block_rules: BlockRules::default(),
+            capture_bounding_boxes: false,
+            interactions: Vec::new(),
+            wait_strategy: WaitStrategy::default(),
+            wait_timeout: DEFAULT_WAIT_TIMEOUT,
+            capture_api_responses: false,
+            flatten_shadow_dom: false,
+            block_private_networks: false,
+            timeouts: PageTimeouts::default(),
+            timed_out: false,
+        }
+    }
+
+    pub fn set_engine(&mut self, engine: BrowserEngine) {
+        self.engine = engine;
+    }
+
+    /// Requests the browser refuses to load, e.g. images or ads. Only the
+    /// CDP engine can block requests.
+    pub fn set_block_rules(&mut self, rules: BlockRules) {
+        self.block_rules = rules;
+    }
+
+    /// Annotate elements with their rendered bounding boxes before the HTML
+    /// source is captured, so they end up on the parsed `HtmlNode`s
+    pub fn set_capture_bounding_boxes(&mut self, enabled: bool) {
+        self.capture_bounding_boxes = enabled;
+    }
+
+    /// Interactions performed, in order, after every navigation. A failing
+    /// interaction is logged and skipped so pages without e.g. a cookie banner
+    /// still get captured.
+    pub fn set_interactions(&mut self, interactions: Vec<Interaction>) {
+        self.interactions = interactions;
+    }
+
+    /// Record JSON responses fetched by the page itself, returned by
+    /// `collect_api_responses`
+    pub fn set_capture_api_responses(&mut self, enabled: bool) {
+        self.capture_api_responses = enabled;
+    }
+
+    /// Capture HTML with open shadow roots inlined instead of the plain page
+    /// source, for sites built from web components
+    pub fn set_flatten_shadow_dom(&mut self, enabled: bool) {
+        self.flatten_shadow_dom = enabled;
+    }
+
+    /// Refuse pages on loopback, LAN or link-local addresses: their URL is
+    /// checked before navigating and where the page ended up after
+    /// redirects. The CDP engine also fails every request the page makes to
+    /// such an address, redirects and subresources included; WebDriver
+    /// sessions can't intercept requests, so those still reach the network.
+    pub fn set_block_private_networks(&mut self, enabled: bool) {
+        self.block_private_networks = enabled;
+    }
+
+    /// Navigation and script limits. WebDriver servers enforce them too, so
+    /// a hung page load is interrupted in the browser.
+    pub fn set_timeouts(&mut self, timeouts: PageTimeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// How to decide a page has finished rendering after navigation. Waits
+    /// give up after `timeout` and the page is captured as it is.
+    pub fn set_wait_strategy(&mut self, strategy: WaitStrategy, timeout: Duration) {
+        self.wait_strategy = strategy;
+        self.wait_timeout = timeout;
+    }
+
+    fn build_capabilities(&self) -> serde_json::map::Map<String, serde_json::Value> {
+        let mut chrome_args = vec![
+            "--headless".to_string(),
+            "--no-sandbox".to_string(),
+            "--disable-dev-shm-usage".to_string(),
+        ];
+        let mut chrome_prefs = serde_json::map::Map::new();
+        let mut firefox_prefs = serde_json::map::Map::new();
+        let mut firefox_env = serde_json::map::Map::new();
+
+        if let Some(accept_language) = &self.locale.accept_language {
+            if let Some(primary) = accept_language.split(',').next() {
+                chrome_args.push(format!("--lang={}", primary.trim()));
+            }
+            chrome_prefs.insert("intl.accept_languages".to_string(), json!(accept_language));
+            firefox_prefs.insert("intl.accept_languages".to_string(), json!(accept_language));
+        }
+
+        if let Some(timezone) = &self.locale.timezone {
+            firefox_env.insert("TZ".to_string(), json!(timezone));
+        }
+
+        if let Some(geolocation) = &self.locale.geolocation {
+            chrome_prefs.insert(
+                "profile.default_content_setting_values.geolocation".to_string(),
+                json!(1),
+            );
+            let location = json!({
+                "location": { "lat": geolocation.latitude, "lng": geolocation.longitude },
+                "accuracy": 100.0
+            });
+            firefox_prefs.insert(
+                "geo.provider.network.url".to_string(),
+                json!(format!("data:application/json,{location}")),
+            );
+            firefox_prefs.insert("geo.prompt.testing".to_string(), json!(true));
+            firefox_prefs.insert("geo.prompt.testing.allow".to_string(), json!(true));
+            firefox_prefs.insert("permissions.default.geo".to_string(), json!(1));
+        }
+
+        let mut chrome_opts = json!({ "args": chrome_args });
+        if !chrome_prefs.is_empty() {
+            chrome_opts["prefs"] = serde_json::Value::Object(chrome_prefs);
+        }
+
         let mut caps = serde_json::map::Map::new();
-        let chrome_opts = json!({
-            "args": ["--headless", "--no-sandbox", "--disable-dev-shm-usage"]
-        });
         caps.insert("goog:chromeOptions".to_string(), chrome_opts);
 
+        if !firefox_prefs.is_empty() || !firefox_env.is_empty() {
+            let mut firefox_opts = serde_json::map::Map::new();
+            if !firefox_prefs.is_empty() {
+                firefox_opts.insert(
+                    "prefs".to_string(),
+                    serde_json::Value::Object(firefox_prefs),
+                );
+            }
+            if !firefox_env.is_empty() {
+                firefox_opts.insert("env".to_string(), serde_json::Value::Object(firefox_env));
+            }
+            caps.insert(
+                "moz:firefoxOptions".to_string(),
+                serde_json::Value::Object(firefox_opts),
+            );
+        }
+
+        caps
+    }
+
+    fn session(&mut self) -> Result<&mut Session, BrowserError> {
+        self.session.as_mut().ok_or_else(|| {
+            BrowserError::HtmlExtractionError("Not connected to browser".to_string())
+        })
+    }
+
+    /// The result of a command run under a timeout, remembering that the
+    /// session may be stuck when it didn't finish
+    fn finish<T>(
+        &mut self,
+        operation: &'static str,
+        after: Duration,
+        result: Result<Result<T, BrowserError>, Elapsed>,
+    ) -> Result<T, BrowserError> {
+        result.unwrap_or_else(|_| {
+            self.timed_out = true;
+            Err(BrowserError::Timeout { operation, after })
+        })
+    }
+
+    /// Run a script in the page, giving up after the script timeout
+    async fn execute(&mut self, script: &str, args: Vec<Value>) -> Result<Value, BrowserError> {
+        let after = self.timeouts.script;
+        let result = timeout(after, self.session()?.execute(script, args)).await;
+        self.finish("script", after, result)
+    }
+
+    async fn execute_async(
+        &mut self,
+        script: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, BrowserError> {
+        let after = self.timeouts.script;
+        let result = timeout(after, self.session()?.execute_async(script, args)).await;
+        self.finish("script", after, result)
+    }
+
+    /// Whether the session still answers commands. A session that is gone
+    /// or doesn't answer within `HEALTH_CHECK_TIMEOUT` is dead and should be
+    /// replaced with `reconnect`.
+    pub async fn is_alive(&mut self) -> bool {
+        let Some(session) = self.session.as_mut() else {
+            return false;
+        };
+        match timeout(HEALTH_CHECK_TIMEOUT, session.title()).await {
+            Ok(Ok(_)) => true,
+            Ok(Err(e)) => !e.is_session_lost(),
+            Err(_) => false,
+        }
+    }
+
+    /// Whether a command timed out since the session was opened. The session
+    /// may still be busy with it, so it should be replaced with `reconnect`.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    /// Chrome has no capability for a fixed position, so the Geolocation API is
+    /// overridden in the page after navigation instead
+    async fn apply_geolocation_override(&mut self) -> Result<(), BrowserError> {
+        let Some(geolocation) = self.locale.geolocation else {
+            return Ok(());
+        };
+
+        let script = r#"
+            const [latitude, longitude] = arguments;
+            const position = {
+                coords: { latitude, longitude, accuracy: 100, altitude: null,
+                          altitudeAccuracy: null, heading: null, speed: null },
+                timestamp: Date.now()
+            };
+            if (navigator.geolocation) {
+                navigator.geolocation.getCurrentPosition = (success) => success(position);
+                navigator.geolocation.watchPosition = (success) => { success(position); return 0; };
+            }
+        "#;
+        self.execute(
+            script,
+            vec![json!(geolocation.latitude), json!(geolocation.longitude)],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Open a session with the configured engine: connect to the WebDriver
+    /// server, or launch Chrome for the CDP engine
+    pub async fn connect(&mut self) -> Result<(), BrowserError> {
+        let session = match self.engine {
+            BrowserEngine::WebDriver => Session::WebDriver(self.connect_webdriver().await?),
+            #[cfg(feature = "cdp")]
+            BrowserEngine::Cdp => Session::Cdp(Box::new(
+                CdpSession::launch(&self.locale, &self.block_rules, self.block_private_networks)
+                    .await?,
+            )),
+            #[cfg(not(feature = "cdp"))]
+            BrowserEngine::Cdp => return Err(BrowserError::EngineNotAvailable("cdp")),
+        };
+        self.session = Some(session);
+        self.timed_out = false;
+        Ok(())
+    }
+
+    /// Replace the session with a new one, after a command timed out and
+    /// left it busy. The old session gets `CLOSE_TIMEOUT` to close and is
+    /// abandoned otherwise.
+    pub async fn reconnect(&mut self) -> Result<(), BrowserError> {
+        if let Some(session) = self.session.take() {
+            match timeout(CLOSE_TIMEOUT, session.close()).await {
+                Ok(Err(e)) => debug!("Failed to close the stuck session: {}", e),
+                Err(_) => warn!("Stuck session didn't close, abandoning it"),
+                Ok(Ok(())) => {}
+            }
+        }
+        self.connect().await
+    }
+
+    async fn connect_webdriver(&self) -> Result<Client, BrowserError> {
+        if !self.block_rules.is_empty() {
+            warn!("Request blocking needs the cdp engine, loading all requests");
+        }
+        if self.block_private_networks {
+            debug!(
+                "Blocking requests to private networks needs the cdp engine, only checking page URLs"
+            );
+        }
+        let caps = self.build_capabilities();
+
         let client = ClientBuilder::rustls()
             .map_err(|e| {
                 BrowserError::HtmlExtractionError(format!("Failed to create client: {e}"))
@@ -44,56 +685,333 @@ impl Browser {
                     BrowserError::HtmlExtractionError(e.to_string())
                 }
             })?;
-
-        self.client = Some(client);
-        Ok(())
+        client
+            .update_timeouts(TimeoutConfiguration::new(
+                Some(self.timeouts.script),
+                Some(self.timeouts.navigation),
+                Some(Duration::ZERO),
+            ))
+            .await?;
+        Ok(client)
     }
 
     pub async fn navigate_to(&mut self, url: &str) -> Result<(), BrowserError> {
-        if let Some(client) = &mut self.client {
-            client.goto(url).await?;
-            tokio::time::sleep(Duration::from_millis(2000)).await;
-            Ok(())
+        if self.block_private_networks {
+            check_url(url).await.map_err(BrowserError::PrivateNetwork)?;
+        }
+        let after = self.timeouts.navigation;
+        let result = timeout(after, self.session()?.goto(url)).await;
+        let navigated = self.finish("navigation", after, result);
+        // A session that timed out is replaced before anything else is read
+        if self.block_private_networks && !self.timed_out {
+            match (navigated, self.check_location().await) {
+                (_, Err(e @ BrowserError::PrivateNetwork(_))) | (Err(e), _) | (Ok(()), Err(e)) => {
+                    return Err(e)
+                }
+                (Ok(()), Ok(())) => {}
+            }
         } else {
-            Err(BrowserError::HtmlExtractionError(
-                "Not connected to browser".to_string(),
-            ))
+            navigated?;
+        }
+        if self.capture_api_responses {
+            let args = vec![json!(MAX_API_RESPONSE_BYTES), json!(MAX_API_RESPONSES)];
+            if let Err(e) = self.execute(INSTALL_API_HOOKS_SCRIPT, args).await {
+                debug!("Failed to install API response hooks on {}: {}", url, e);
+            }
         }
+        let strategy = self.wait_strategy.clone();
+        self.wait_for(&strategy).await?;
+        self.apply_geolocation_override().await?;
+
+        for interaction in self.interactions.clone() {
+            if let Err(e) = self.perform(&interaction).await {
+                warn!("Skipping interaction on {}: {}", url, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Leave a page that ended up on a private network address, or whose
+    /// address can't be read, for `about:blank`, so nothing of it is read
+    /// afterwards, e.g. as the HTML kept for a failed page. When it can't be
+    /// left either, the session is marked for replacement; a lost session
+    /// has nothing left to read.
+    async fn check_location(&mut self) -> Result<(), BrowserError> {
+        let refused = match self.current_url().await {
+            Ok(location) => match check_url(&location).await {
+                Ok(()) => return Ok(()),
+                Err(e) => BrowserError::PrivateNetwork(e),
+            },
+            Err(e) => e,
+        };
+        if !self.timed_out && !refused.is_session_lost() {
+            let after = self.timeouts.navigation;
+            let left = timeout(after, self.session()?.goto("about:blank")).await;
+            self.timed_out = match left {
+                Ok(Ok(())) => false,
+                Ok(Err(e)) => !e.is_session_lost(),
+                Err(_) => true,
+            };
+        }
+        Err(refused)
     }
 
     pub async fn get_html_source(&mut self) -> Result<String, BrowserError> {
-        if let Some(client) = &mut self.client {
-            let html = client.source().await?;
-            Ok(html)
-        } else {
-            Err(BrowserError::HtmlExtractionError(
-                "Not connected to browser".to_string(),
-            ))
+        if self.capture_bounding_boxes {
+            self.annotate_bounding_boxes().await?;
         }
+
+        if self.flatten_shadow_dom {
+            let html = self.execute(FLATTEN_SHADOW_DOM_SCRIPT, vec![]).await?;
+            return html.as_str().map(str::to_string).ok_or_else(|| {
+                BrowserError::HtmlExtractionError(
+                    "Shadow DOM serializer returned no HTML".to_string(),
+                )
+            });
+        }
+        let after = self.timeouts.script;
+        let result = timeout(after, self.session()?.source()).await;
+        self.finish("HTML capture", after, result)
     }
 
-    pub async fn get_page_title(&mut self) -> Result<String, BrowserError> {
-        if let Some(client) = &mut self.client {
-            let title = client.title().await?;
-            Ok(title)
+    /// JSON responses the current page fetched, empty unless API capture is enabled
+    pub async fn collect_api_responses(&mut self) -> Result<Vec<ApiResponse>, BrowserError> {
+        if !self.capture_api_responses {
+            return Ok(Vec::new());
+        }
+
+        let args = vec![json!(MAX_API_RESPONSE_BYTES), json!(MAX_API_RESPONSES)];
+        let result = self
+            .execute_async(COLLECT_API_RESPONSES_SCRIPT, args)
+            .await?;
+        let responses = parse_api_responses(&result);
+        debug!("Captured {} JSON API responses", responses.len());
+        Ok(responses)
+    }
+
+    /// Write each element's rendered box to a `data-sc-box` attribute, returning
+    /// how many elements were annotated and the page size
+    pub async fn annotate_bounding_boxes(&mut self) -> Result<BoundingBoxReport, BrowserError> {
+        let result = self.execute(ANNOTATE_BOXES_SCRIPT, vec![]).await?;
+        trace!("Bounding box script result: {}", result);
+        let report = BoundingBoxReport::from_value(&result);
+        debug!(
+            "Annotated {} elements with bounding boxes ({} styled) on a {}x{} page",
+            report.annotated, report.styled, report.page_width, report.page_height
+        );
+        Ok(report)
+    }
+
+    /// Outline every item of each sibling group on the current page, labelling
+    /// the first item of a group. Groups without captured boxes are skipped.
+    pub async fn visualize_sibling_groups(
+        &mut self,
+        lists: &[ExtractedList],
+    ) -> Result<u64, BrowserError> {
+        let result = self
+            .execute(OVERLAY_GROUPS_SCRIPT, vec![overlay_groups(lists)])
+            .await?;
+        let count = result.as_u64().unwrap_or(0);
+        debug!("Drew {} sibling group overlays", count);
+        Ok(count)
+    }
+
+    /// PNG screenshot of the whole page. With WebDriver the window is
+    /// temporarily resized, so pages taller than `MAX_SCREENSHOT_HEIGHT`
+    /// are cut off; CDP captures beyond the viewport.
+    pub async fn full_page_screenshot(&mut self) -> Result<Vec<u8>, BrowserError> {
+        self.session()?.full_page_screenshot().await
+    }
+
+    /// Draw the sibling groups over the current page and save `<name>.png`
+    /// plus `<name>.groups.json` to `output_dir`, so the grouping can be
+    /// reviewed after a headless run. Returns the screenshot path.
+    pub async fn save_group_visualization(
+        &mut self,
+        lists: &[ExtractedList],
+        output_dir: &Path,
+        name: &str,
+    ) -> Result<PathBuf, BrowserError> {
+        std::fs::create_dir_all(output_dir)
+            .map_err(|e| BrowserError::OutputError(format!("{}: {e}", output_dir.display())))?;
+
+        self.visualize_sibling_groups(lists).await?;
+        let png = self.full_page_screenshot().await?;
+
+        let screenshot_path = output_dir.join(format!("{name}.png"));
+        std::fs::write(&screenshot_path, png).map_err(|e| {
+            BrowserError::OutputError(format!("{}: {e}", screenshot_path.display()))
+        })?;
+
+        let groups_path = output_dir.join(format!("{name}.groups.json"));
+        write_lists(&groups_path.to_string_lossy(), lists).map_err(BrowserError::OutputError)?;
+
+        Ok(screenshot_path)
+    }
+
+    /// Wait until the strategy's condition holds or the wait timeout elapses.
+    /// Returns whether the condition was met; a timeout is not an error.
+    pub async fn wait_for(&mut self, strategy: &WaitStrategy) -> Result<bool, BrowserError> {
+        let wait_timeout = self.wait_timeout;
+        let started = Instant::now();
+        let deadline = started + wait_timeout;
+        let met = match strategy {
+            WaitStrategy::Fixed { duration } => {
+                tokio::time::sleep(*duration).await;
+                true
+            }
+            WaitStrategy::NetworkIdle { idle } => {
+                let mut last_count = None;
+                let mut last_change = Instant::now();
+                loop {
+                    let state = self.execute(RESOURCE_COUNT_SCRIPT, vec![]).await?;
+                    let count = state.get(0).and_then(|v| v.as_u64());
+                    let loaded = state.get(1).and_then(|v| v.as_bool()).unwrap_or(false);
+                    if count != last_count {
+                        last_count = count;
+                        last_change = Instant::now();
+                    } else if loaded && last_change.elapsed() >= *idle {
+                        break true;
+                    }
+                    if Instant::now() >= deadline {
+                        break false;
+                    }
+                    tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+                }
+            }
+            WaitStrategy::ReadyState
+            | WaitStrategy::Selector { .. }
+            | WaitStrategy::Condition { .. } => {
+                let script = strategy.condition_script().unwrap_or_default();
+                let args = match strategy {
+                    WaitStrategy::Selector { selector } => vec![json!(selector)],
+                    _ => vec![],
+                };
+                loop {
+                    let result = self.execute(&script, args.clone()).await?;
+                    if result.as_bool().unwrap_or(false) {
+                        break true;
+                    }
+                    if Instant::now() >= deadline {
+                        break false;
+                    }
+                    tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+                }
+            }
+        };
+
+        if met {
+            debug!("Wait {:?} finished after {:?}", strategy, started.elapsed());
         } else {
-            Err(BrowserError::HtmlExtractionError(
-                "Not connected to browser".to_string(),
-            ))
+            warn!(
+                "Wait {:?} timed out after {:?}, capturing the page as is",
+                strategy, wait_timeout
+            );
+        }
+        Ok(met)
+    }
+
+    pub async fn click_element(&mut self, path: &str) -> Result<(), BrowserError> {
+        let selector = path_to_css_selector(path);
+        let found = self.session()?.click(&selector).await?;
+        element_found(found, path)
+    }
+
+    /// Clear the matching input and type text into it
+    pub async fn type_into(&mut self, path: &str, text: &str) -> Result<(), BrowserError> {
+        let selector = path_to_css_selector(path);
+        let found = self.session()?.type_into(&selector, text).await?;
+        element_found(found, path)
+    }
+
+    /// Choose an option of the matching `select` by its value, falling back to
+    /// its visible label
+    pub async fn select_option(&mut self, path: &str, value: &str) -> Result<(), BrowserError> {
+        let selector = path_to_css_selector(path);
+        let found = self.session()?.select_option(&selector, value).await?;
+        element_found(found, path)
+    }
+
+    pub async fn perform(&mut self, interaction: &Interaction) -> Result<(), BrowserError> {
+        debug!("Performing {:?}", interaction);
+        match interaction {
+            Interaction::Click { path } => self.click_element(path).await,
+            Interaction::Type { path, text } => self.type_into(path, text).await,
+            Interaction::Select { path, value } => self.select_option(path, value).await,
+            Interaction::Wait { strategy } => self.wait_for(strategy).await.map(|_| ()),
         }
     }
 
+    /// MIME type the current document was loaded as, e.g. `application/json`
+    pub async fn document_content_type(&mut self) -> Result<String, BrowserError> {
+        self.execute_string(CONTENT_TYPE_SCRIPT).await
+    }
+
+    /// Visible text of the current document, the raw payload for JSON and text documents
+    pub async fn body_text(&mut self) -> Result<String, BrowserError> {
+        self.execute_string(BODY_TEXT_SCRIPT).await
+    }
+
+    async fn execute_string(&mut self, script: &str) -> Result<String, BrowserError> {
+        let result = self.execute(script, vec![]).await?;
+        Ok(result.as_str().unwrap_or_default().to_string())
+    }
+
+    /// Encoding the browser decoded the current document with
+    pub async fn document_charset(&mut self) -> Result<String, BrowserError> {
+        self.execute_string(DOCUMENT_CHARSET_SCRIPT).await
+    }
+
+    /// HTTP status the current document was served with, None when the
+    /// browser doesn't report it
+    pub async fn document_status(&mut self) -> Result<Option<u16>, BrowserError> {
+        let status = self.execute(DOCUMENT_STATUS_SCRIPT, vec![]).await?;
+        Ok(status
+            .as_u64()
+            .and_then(|status| u16::try_from(status).ok()))
+    }
+
+    /// Requests blocked while loading the current page, None unless the
+    /// browser blocks requests
+    pub fn blocked_requests(&self) -> Option<usize> {
+        self.session.as_ref().and_then(Session::blocked_requests)
+    }
+
+    /// URL of the current document, after any redirects
+    pub async fn current_url(&mut self) -> Result<String, BrowserError> {
+        let after = self.timeouts.script;
+        let result = timeout(after, self.session()?.current_url()).await;
+        self.finish("current URL", after, result)
+    }
+
+    pub async fn get_page_title(&mut self) -> Result<String, BrowserError> {
+        let after = self.timeouts.script;
+        let result = timeout(after, self.session()?.title()).await;
+        self.finish("title", after, result)
+    }
+
     pub async fn close(&mut self) -> Result<(), BrowserError> {
-        if let Some(client) = self.client.take() {
-            client.close().await?;
+        if let Some(session) = self.session.take() {
+            session.close().await?;
         }
         Ok(())
     }
 }
 
+fn element_found(found: bool, path: &str) -> Result<(), BrowserError> {
+    if found {
+        Ok(())
+    } else {
+        Err(BrowserError::ElementNotFound {
+            path: path.to_string(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::webdriver_mock::{MockPage, MockWebDriver};
 
     #[tokio::test]
     async fn test_browser_connection_error() {
@@ -111,6 +1029,110 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_geolocation_parsing() {
+        let location: Geolocation = "52.52, 13.405".parse().unwrap();
+        assert_eq!(location.latitude, 52.52);
+        assert_eq!(location.longitude, 13.405);
+
+        assert!("52.52".parse::<Geolocation>().is_err());
+        assert!("95,10".parse::<Geolocation>().is_err());
+        assert!("north,east".parse::<Geolocation>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_engine_selection() {
+        assert_eq!("CDP".parse::<BrowserEngine>().unwrap(), BrowserEngine::Cdp);
+        assert_eq!(
+            "webdriver".parse::<BrowserEngine>().unwrap(),
+            BrowserEngine::WebDriver
+        );
+        assert!("puppeteer".parse::<BrowserEngine>().is_err());
+
+        if cfg!(not(feature = "cdp")) {
+            let mut browser = Browser::new(4444);
+            browser.set_engine(BrowserEngine::Cdp);
+            assert!(matches!(
+                browser.connect().await,
+                Err(BrowserError::EngineNotAvailable("cdp"))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_timeout_marks_session() {
+        let mut browser = Browser::new(4444);
+        let after = Duration::from_millis(1);
+        let result = timeout(after, std::future::pending::<Result<(), BrowserError>>()).await;
+        let error = browser.finish("navigation", after, result).unwrap_err();
+        assert_eq!(error.to_string(), "navigation timed out after 1ms");
+        assert!(browser.timed_out());
+    }
+
+    #[tokio::test]
+    async fn test_session_lost() {
+        let lost = |status, message| {
+            BrowserError::from(CmdError::Standard(fantoccini::error::WebDriver::new(
+                status, message,
+            )))
+            .is_session_lost()
+        };
+        assert!(lost(ErrorStatus::InvalidSessionId, "session deleted"));
+        assert!(lost(
+            ErrorStatus::UnknownError,
+            "unknown error: Chrome not reachable"
+        ));
+        assert!(!lost(ErrorStatus::NoSuchElement, "no such element"));
+        assert!(!BrowserError::ElementNotFound {
+            path: "div".to_string()
+        }
+        .is_session_lost());
+
+        // A browser that never connected has no session to check
+        assert!(!Browser::new(4444).is_alive().await);
+    }
+
+    #[test]
+    fn test_default_capabilities() {
+        let caps = Browser::new(4444).build_capabilities();
+        assert_eq!(
+            caps["goog:chromeOptions"]["args"],
+            json!(["--headless", "--no-sandbox", "--disable-dev-shm-usage"])
+        );
+        assert!(!caps.contains_key("moz:firefoxOptions"));
+    }
+
+    #[test]
+    fn test_locale_capabilities() {
+        let browser = Browser::with_locale(
+            4444,
+            LocaleOptions {
+                accept_language: Some("de-DE,de".to_string()),
+                timezone: Some("Europe/Berlin".to_string()),
+                geolocation: Some(Geolocation {
+                    latitude: 52.52,
+                    longitude: 13.405,
+                }),
+            },
+        );
+        let caps = browser.build_capabilities();
+
+        let chrome = &caps["goog:chromeOptions"];
+        assert!(chrome["args"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("--lang=de-DE")));
+        assert_eq!(chrome["prefs"]["intl.accept_languages"], "de-DE,de");
+
+        let firefox = &caps["moz:firefoxOptions"];
+        assert_eq!(firefox["prefs"]["intl.accept_languages"], "de-DE,de");
+        assert_eq!(firefox["env"]["TZ"], "Europe/Berlin");
+        assert!(firefox["prefs"]["geo.provider.network.url"]
+            .as_str()
+            .unwrap()
+            .contains("52.52"));
+    }
+
     #[tokio::test]
     async fn test_browser_operations_without_connection() {
         let mut browser = Browser::new(4444);
@@ -125,6 +1147,97 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    async fn test_mock_webdriver_session() {
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://example.com/",
+            MockPage {
+                title: "Example Domain".to_string(),
+                html: "<html><body><h1>Example</h1></body></html>".to_string(),
+                ..MockPage::default()
+            },
+        );
+        driver.page(
+            "https://example.com/slow",
+            MockPage {
+                delay: Duration::from_millis(500),
+                ..MockPage::default()
+            },
+        );
+        let mut browser = Browser::new(driver.port());
+        browser.set_wait_strategy(WaitStrategy::ReadyState, Duration::from_secs(1));
+        driver.script("readyState", json!(true));
+        browser.set_timeouts(PageTimeouts {
+            navigation: Duration::from_millis(100),
+            ..PageTimeouts::default()
+        });
+
+        browser.connect().await.unwrap();
+        browser.navigate_to("https://example.com/").await.unwrap();
+        assert_eq!(browser.get_page_title().await.unwrap(), "Example Domain");
+        assert!(browser
+            .get_html_source()
+            .await
+            .unwrap()
+            .contains("<h1>Example</h1>"));
+        assert!(browser.is_alive().await);
+
+        let error = browser
+            .navigate_to("https://example.com/slow")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BrowserError::Timeout { .. }));
+        assert!(browser.timed_out());
+        browser.reconnect().await.unwrap();
+        assert!(!browser.timed_out());
+        assert_eq!(driver.sessions_created(), 2);
+
+        let requests = driver.requests();
+        assert_eq!(requests[0], "POST /session");
+        assert!(requests.contains(&"POST /session/{id}/timeouts".to_string()));
+        assert!(requests.contains(&"POST /session/{id}/url".to_string()));
+        browser.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_block_private_networks() {
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://example.com/admin",
+            MockPage {
+                html: "<html><body>Router settings</body></html>".to_string(),
+                redirect: Some("http://192.168.1.1/".to_string()),
+                ..MockPage::default()
+            },
+        );
+        let mut browser = Browser::new(driver.port());
+        browser.set_block_private_networks(true);
+        browser.connect().await.unwrap();
+
+        let error = browser
+            .navigate_to("http://10.0.0.5/admin")
+            .await
+            .unwrap_err();
+        assert!(matches!(error, BrowserError::PrivateNetwork(_)));
+        assert!(!driver
+            .requests()
+            .contains(&"POST /session/{id}/url".to_string()));
+
+        let error = browser
+            .navigate_to("https://example.com/admin")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("192.168.1.1"), "{error}");
+        assert_eq!(browser.current_url().await.unwrap(), "about:blank");
+        assert!(!browser
+            .get_html_source()
+            .await
+            .unwrap()
+            .contains("Router settings"));
+        browser.close().await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_browser_connect_to_example() {
         rustls::crypto::ring::default_provider()
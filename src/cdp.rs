@@ -0,0 +1,300 @@
+use crate::browser::{BrowserError, LocaleOptions};
+use crate::network_guard::check_host;
+use crate::request_blocking::BlockRules;
+use chromiumoxide::cdp::browser_protocol::emulation::SetTimezoneOverrideParams;
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    ContinueRequestParams, EnableParams, EventRequestPaused, FailRequestParams, RequestPattern,
+};
+use chromiumoxide::cdp::browser_protocol::network::{ErrorReason, ResourceType};
+use chromiumoxide::cdp::js_protocol::runtime::EvaluateParams;
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::{BrowserConfig, Page};
+use futures::StreamExt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+/// Chooses an option of a `select` by value, else by label, and fires the
+/// events a user's choice would. Returns false when no element matches.
+const SELECT_OPTION_SCRIPT: &str = r#"
+    const [selector, value] = arguments;
+    const select = document.querySelector(selector);
+    if (!select) return false;
+    const options = [...select.options];
+    const option = options.find(o => o.value === value)
+        || options.find(o => o.text.trim() === value);
+    if (!option) throw new Error(`No option ${value}`);
+    select.value = option.value;
+    select.dispatchEvent(new Event('input', { bubbles: true }));
+    select.dispatchEvent(new Event('change', { bubbles: true }));
+    return true;
+"#;
+
+/// A Chrome launched by the crawler and driven over the DevTools Protocol,
+/// one tab per session
+pub struct CdpSession {
+    browser: chromiumoxide::Browser,
+    page: Page,
+    handler: JoinHandle<()>,
+    /// Requests blocked since the last navigation, None when nothing is blocked
+    blocked_requests: Option<Arc<AtomicUsize>>,
+    blocker: Option<JoinHandle<()>>,
+}
+
+impl CdpSession {
+    /// Launch headless Chrome (found on the PATH or in the usual install
+    /// locations) with the locale applied, requests matching `rules` blocked
+    /// and, when `block_private`, requests to private network addresses
+    pub async fn launch(
+        locale: &LocaleOptions,
+        rules: &BlockRules,
+        block_private: bool,
+    ) -> Result<Self, BrowserError> {
+        let mut config = BrowserConfig::builder()
+            .no_sandbox()
+            .arg("--disable-dev-shm-usage");
+        if let Some(primary) = locale
+            .accept_language
+            .as_deref()
+            .and_then(|accept_language| accept_language.split(',').next())
+        {
+            config = config.arg(format!("--lang={}", primary.trim()));
+        }
+        let config = config.build().map_err(BrowserError::HtmlExtractionError)?;
+
+        let (browser, mut handler) = chromiumoxide::Browser::launch(config).await?;
+        let handler = tokio::spawn(async move { while handler.next().await.is_some() {} });
+        let page = browser.new_page("about:blank").await?;
+
+        if let Some(timezone) = &locale.timezone {
+            page.emulate_timezone(SetTimezoneOverrideParams::new(timezone.clone()))
+                .await?;
+        }
+        let blocked_requests = (!rules.is_empty()).then(|| Arc::new(AtomicUsize::new(0)));
+        let blocker = if rules.is_empty() && !block_private {
+            None
+        } else {
+            Some(
+                Self::intercept_requests(&page, rules, block_private, blocked_requests.clone())
+                    .await?,
+            )
+        };
+        Ok(CdpSession {
+            browser,
+            page,
+            handler,
+            blocked_requests,
+            blocker,
+        })
+    }
+
+    /// Pause the requests matching the rules, or every request when
+    /// `block_private`. Those matching the rules fail as blocked by the
+    /// client, counted in `count`, and those to a private network address
+    /// as unreachable; the others go ahead.
+    async fn intercept_requests(
+        page: &Page,
+        rules: &BlockRules,
+        block_private: bool,
+        count: Option<Arc<AtomicUsize>>,
+    ) -> Result<JoinHandle<()>, BrowserError> {
+        let patterns: Vec<RequestPattern> = if block_private {
+            vec![RequestPattern::builder().url_pattern("*").build()]
+        } else {
+            let by_type = rules
+                .resource_types()
+                .into_iter()
+                .filter_map(|resource_type| resource_type.parse::<ResourceType>().ok())
+                .map(|resource_type| {
+                    RequestPattern::builder()
+                        .url_pattern("*")
+                        .resource_type(resource_type)
+                        .build()
+                });
+            let by_url = rules
+                .all_url_patterns()
+                .into_iter()
+                .map(|pattern| RequestPattern::builder().url_pattern(pattern).build());
+            by_type.chain(by_url).collect()
+        };
+
+        let mut paused = page.event_listener::<EventRequestPaused>().await?;
+        page.execute(EnableParams::builder().patterns(patterns).build())
+            .await?;
+
+        let page = page.clone();
+        let rules = rules.clone();
+        Ok(tokio::spawn(async move {
+            // Hosts checked in this session, resolved once each
+            let mut checked: HashMap<String, Result<(), String>> = HashMap::new();
+            while let Some(request) = paused.next().await {
+                let url = &request.request.url;
+                if !block_private || rules.matches(url, request.resource_type.as_ref()) {
+                    let fail = FailRequestParams::new(
+                        request.request_id.clone(),
+                        ErrorReason::BlockedByClient,
+                    );
+                    if page.execute(fail).await.is_ok() {
+                        if let Some(count) = &count {
+                            count.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                    continue;
+                }
+                let host = url::Url::parse(url)
+                    .ok()
+                    .and_then(|url| url.host_str().map(str::to_string));
+                let allowed = match host {
+                    Some(host) => match checked.get(&host) {
+                        Some(allowed) => allowed.clone(),
+                        None => {
+                            let allowed = check_host(&host).await;
+                            checked.insert(host, allowed.clone());
+                            allowed
+                        }
+                    },
+                    None => Ok(()),
+                };
+                let result = match allowed {
+                    Ok(()) => page
+                        .execute(ContinueRequestParams::new(request.request_id.clone()))
+                        .await
+                        .map(|_| ()),
+                    Err(e) => {
+                        debug!("Blocked request to {}: {}", url, e);
+                        page.execute(FailRequestParams::new(
+                            request.request_id.clone(),
+                            ErrorReason::AddressUnreachable,
+                        ))
+                        .await
+                        .map(|_| ())
+                    }
+                };
+                if let Err(e) = result {
+                    debug!("Failed to resume request to {}: {}", url, e);
+                }
+            }
+        }))
+    }
+
+    /// Requests blocked since the last navigation, None when nothing is blocked
+    pub fn blocked_requests(&self) -> Option<usize> {
+        self.blocked_requests
+            .as_ref()
+            .map(|count| count.load(Ordering::Relaxed))
+    }
+
+    pub async fn goto(&mut self, url: &str) -> Result<(), BrowserError> {
+        if let Some(count) = &self.blocked_requests {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.page.goto(url).await?;
+        self.page.wait_for_navigation().await?;
+        Ok(())
+    }
+
+    /// Run a WebDriver-style script body, which reads its arguments from
+    /// `arguments` and returns a value
+    pub async fn execute(&mut self, script: &str, args: Vec<Value>) -> Result<Value, BrowserError> {
+        let args = Value::Array(args);
+        self.evaluate(format!("(function() {{ {script} \n}}).apply(null, {args})"))
+            .await
+    }
+
+    /// Run a WebDriver-style async script body, which calls the callback
+    /// passed as its last argument with the result
+    pub async fn execute_async(
+        &mut self,
+        script: &str,
+        args: Vec<Value>,
+    ) -> Result<Value, BrowserError> {
+        let args = Value::Array(args);
+        self.evaluate(format!(
+            "new Promise(resolve => (function() {{ {script} \n}}).apply(null, [...{args}, resolve]))"
+        ))
+        .await
+    }
+
+    async fn evaluate(&mut self, expression: String) -> Result<Value, BrowserError> {
+        let params = EvaluateParams::builder()
+            .expression(expression)
+            .await_promise(true)
+            .return_by_value(true)
+            .build()
+            .map_err(BrowserError::HtmlExtractionError)?;
+        let result = self.page.evaluate_expression(params).await?;
+        Ok(result.value().cloned().unwrap_or(Value::Null))
+    }
+
+    pub async fn source(&mut self) -> Result<String, BrowserError> {
+        Ok(self.page.content().await?)
+    }
+
+    pub async fn current_url(&mut self) -> Result<String, BrowserError> {
+        Ok(self.page.url().await?.unwrap_or_default())
+    }
+
+    pub async fn title(&mut self) -> Result<String, BrowserError> {
+        Ok(self.page.get_title().await?.unwrap_or_default())
+    }
+
+    /// PNG of the whole document, captured beyond the viewport without
+    /// resizing the window
+    pub async fn full_page_screenshot(&mut self) -> Result<Vec<u8>, BrowserError> {
+        Ok(self
+            .page
+            .screenshot(ScreenshotParams::builder().full_page(true).build())
+            .await?)
+    }
+
+    /// Click the first element matching a CSS selector; false if none matches
+    pub async fn click(&mut self, selector: &str) -> Result<bool, BrowserError> {
+        let Some(element) = self.page.find_elements(selector).await?.into_iter().next() else {
+            return Ok(false);
+        };
+        element.click().await?;
+        Ok(true)
+    }
+
+    /// Clear the first input matching a CSS selector and type into it; false
+    /// if none matches
+    pub async fn type_into(&mut self, selector: &str, text: &str) -> Result<bool, BrowserError> {
+        let Some(element) = self.page.find_elements(selector).await?.into_iter().next() else {
+            return Ok(false);
+        };
+        element
+            .call_js_fn("function() { this.value = ''; }", false)
+            .await?;
+        element.click().await?.type_str(text).await?;
+        Ok(true)
+    }
+
+    /// Choose an option of the first `select` matching a CSS selector by its
+    /// value or label; false if none matches
+    pub async fn select_option(
+        &mut self,
+        selector: &str,
+        value: &str,
+    ) -> Result<bool, BrowserError> {
+        let result = self
+            .execute(
+                SELECT_OPTION_SCRIPT,
+                vec![Value::from(selector), Value::from(value)],
+            )
+            .await?;
+        Ok(result.as_bool().unwrap_or(false))
+    }
+
+    pub async fn close(mut self) -> Result<(), BrowserError> {
+        self.browser.close().await?;
+        self.browser.wait().await.ok();
+        self.handler.abort();
+        if let Some(blocker) = self.blocker {
+            blocker.abort();
+        }
+        Ok(())
+    }
+}
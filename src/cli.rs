@@ -1,133 +1,2464 @@
-use clap::{Arg, Command};
+use crate::browser::{BrowserEngine, Geolocation, LocaleOptions, PageTimeouts};
+use crate::crawler::SmartCrawlerBuilder;
+use crate::entities::{parse_entity_kinds, EntityKind};
+use crate::fetch_limits::FetchLimits;
+use crate::graph_export::GraphFormat;
+use crate::interaction::Interaction;
+use crate::known_entities::{load_known_entities, KnownEntities};
+use crate::language::LanguageFilter;
+use crate::presets::ObjectivePreset;
+use crate::profiles::CrawlProfile;
+use crate::request_blocking::{parse_block_categories, BlockCategory};
+use crate::results::ContentMode;
+use crate::scheduler::load_domains_file;
+use crate::search_seed::SearchConfig;
+use crate::sitemap::{parse_lastmod, parse_modified_within, SamplingStrategy, SitemapFormat};
+use crate::spill::SpillConfig;
+use crate::storage::RetentionConfig;
+use crate::survey::SurveyConfig;
+use crate::url_selection::UrlSelectionMethod;
+use crate::utils::{check_local_path, normalize_domain, site_host};
+use crate::validation::{load_validation_rules, ValidationRule};
+use crate::wait::{parse_duration, WaitStrategy};
+use crate::wayback::WaybackConfig;
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::Level;
 use url::Url;
 
+/// What the CLI was asked to do
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum CliCommand {
+    /// Crawl a domain and print the scraped pages
+    #[default]
+    Crawl,
+    /// Discover template paths across a domain
+    Prep,
+    /// Crawl and write repeated sibling groups as records
+    Extract,
+    /// Crawl and save screenshots with sibling groups outlined
+    Boxes,
+    /// List a domain's sitemap URLs without crawling them
+    Sitemap { format: SitemapFormat },
+    /// Render a page to check the browser setup
+    Test { url: String },
+    /// Scrape a single page and print it as JSON
+    AnalyzeUrl { url: String },
+    /// Fold a reviewed review file into a pages file
+    MergeReviews { pages: String, reviews: String },
+    /// Check tracked values for changes and alert
+    Monitor {
+        config: String,
+        history: String,
+        /// Ask the server whether pages changed before rendering them
+        conditional: bool,
+    },
+    /// Compare the pages files of two runs
+    Diff {
+        before: String,
+        after: String,
+        json: bool,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct CliArgs {
+    pub command: CliCommand,
+    /// The first domain, the only one for single-domain commands. Empty when
+    /// every domain comes from `--search`
     pub domain: String,
+    /// Every domain to crawl, in the order given
+    pub domains: Vec<String>,
+    pub domain_priorities: HashMap<String, i32>,
+    /// File the domains were read from, re-read with `--watch`
+    pub domains_file: Option<String>,
+    pub watch: bool,
+    /// Share the domains to crawl with other workers through `db_url`
+    pub worker: bool,
+    pub max_concurrent_domains: usize,
+    /// Pages a crawl fetches before another domain gets its slot
+    pub fair_share: Option<usize>,
+    /// Related domains found in crawled pages' links that may be queued
+    pub auto_expand_domains: usize,
+    /// Search API queried with the objective for more domains to crawl
+    pub search: Option<SearchConfig>,
+    /// JSON lines file for entities that need a human check
+    pub review_file: Option<String>,
+    /// Markdown or HTML summary of the crawl
+    pub report: Option<String>,
+    /// schema.org graph of the pages and their entities
+    pub export_graph: Option<String>,
+    pub graph_format: GraphFormat,
+    /// JSON file with one merged profile per crawled domain
+    pub domain_profiles: Option<String>,
     pub prep: bool,
+    pub objective: Option<String>,
+    pub preset: Option<&'static ObjectivePreset>,
+    /// Speed/coverage trade-off applied before the options given explicitly
+    pub profile: Option<&'static CrawlProfile>,
+    /// Pages rendered per domain, the crawl's default (or the profile's) if None
+    pub max_pages: Option<usize>,
+    pub synonyms: Option<String>,
+    pub output: Option<String>,
+    pub content_mode: ContentMode,
+    /// Survey candidate pages over HTTP before rendering the best ones
+    pub survey: bool,
+    pub survey_candidates: Option<usize>,
+    pub survey_time: Option<Duration>,
+    /// How surveyed candidate pages are picked for rendering
+    pub url_selection: UrlSelectionMethod,
+    pub max_sitemap_urls: Option<usize>,
+    /// Search the site with its own search form for the objective
+    pub site_search: bool,
+    /// Fall back to Wayback Machine snapshots of missing and blocked pages
+    pub wayback: Option<WaybackConfig>,
+    /// Paths probed when a domain has no sitemap, the crawler's default if None
+    pub probe_paths: Option<Vec<String>>,
+    pub sitemap_sampling: SamplingStrategy,
+    pub modified_since: Option<DateTime<Utc>>,
+    pub languages: LanguageFilter,
+    pub locale: LocaleOptions,
+    pub bounding_boxes: bool,
+    pub extract_lists: Option<String>,
+    pub visualize_groups: Option<String>,
+    pub log_level: Level,
+    pub interactions: Vec<Interaction>,
+    /// Page settings left to the profile or the crawl's defaults when None
+    pub wait: Option<WaitStrategy>,
+    pub wait_timeout: Option<Duration>,
+    pub navigation_timeout: Option<Duration>,
+    pub script_timeout: Option<Duration>,
+    pub page_timeout: Option<Duration>,
+    pub capture_api: bool,
+    pub shadow_dom: bool,
+    pub allow_private: bool,
+    /// Size and content type limits for fetched pages
+    pub fetch_limits: FetchLimits,
+    pub engine: BrowserEngine,
+    /// URL patterns the browser doesn't load
+    pub blocked_urls: Vec<String>,
+    /// Kinds of requests the browser doesn't load
+    pub block_categories: Vec<BlockCategory>,
+    pub ignore_meta_robots: bool,
+    pub redact_pii: bool,
+    pub keep_pii_entities: bool,
+    /// Entity types extracted, every type when empty
+    pub entity_types: Vec<EntityKind>,
+    /// Rules from `--entity-rules`
+    pub entity_rules: Vec<ValidationRule>,
+    /// Entities of earlier runs from `--known-entities`
+    pub known_entities: Option<KnownEntities>,
+    /// Database extracted entities are upserted into
+    pub db_url: Option<String>,
+    pub html_retention: RetentionConfig,
+    /// Move finished pages to disk past a memory cap
+    pub spill: Option<SpillConfig>,
+    pub deterministic: bool,
+    pub brand_assets: bool,
+    pub assets_dir: Option<String>,
+    pub progress: bool,
+    pub dry_run: bool,
 }
 
 impl CliArgs {
     pub fn parse() -> Result<Self, String> {
-        let matches = Command::new("smart-crawler")
+        let matches = Self::command().get_matches();
+        if matches.subcommand().is_none() && matches.contains_id("domain") {
+            eprintln!(
+                "warning: `smart-crawler --domain ...` is deprecated and will be removed in the next release, use `smart-crawler crawl DOMAIN` or `smart-crawler prep DOMAIN`"
+            );
+        }
+        if let Some(("sitemap", sub)) = matches.subcommand() {
+            if sub.subcommand().is_none() && sub.contains_id("domain") {
+                eprintln!(
+                    "warning: `smart-crawler sitemap DOMAIN` is deprecated and will be removed in the next release, use `smart-crawler sitemap list DOMAIN`"
+                );
+            }
+        }
+        Self::from_matches(&matches)
+    }
+
+    /// Parse an explicit argument list, e.g. `["smart-crawler", "crawl", "example.com"]`
+    pub fn try_parse_from<I, T>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = Self::command()
+            .try_get_matches_from(args)
+            .map_err(|e| e.to_string())?;
+        Self::from_matches(&matches)
+    }
+
+    /// The `smart-crawler` command line. Running without a subcommand but with
+    /// `--domain` (and optionally `--prep`) is the pre-subcommand interface,
+    /// kept as a deprecated alias for one release.
+    pub fn command() -> Command {
+        let domain = || {
+            Arg::new("domain")
+                .value_name("DOMAIN")
+                .help("Domain to crawl. Can be a URL or domain name")
+                .required(true)
+        };
+        let domains = || {
+            Arg::new("domain")
+                .value_name("DOMAIN")
+                .help("Domains to crawl. Each can be a URL or domain name, http:// for sites without https")
+                .num_args(1..)
+                .required_unless_present_any(["domains-file", "search", "worker"])
+        };
+        let without = |id: &'static str| {
+            Self::crawl_args()
+                .into_iter()
+                .filter(move |arg| arg.get_id() != id)
+        };
+
+        Command::new("smart-crawler")
             .version("0.4.1")
             .about("A web crawler that uses WebDriver to extract and parse HTML content")
+            .subcommand(
+                Command::new("crawl")
+                    .about("Crawl a domain and print the scraped pages")
+                    .arg(domains())
+                    .args(Self::crawl_args()),
+            )
+            .subcommand(
+                Command::new("prep")
+                    .about("Discover template patterns across a domain's pages")
+                    .arg(domains())
+                    .args(Self::crawl_args()),
+            )
+            .subcommand(
+                Command::new("extract")
+                    .about("Crawl a domain and write repeated sibling groups (lists, tables, cards) as records")
+                    .arg(domains())
+                    .arg(
+                        Arg::new("extract-lists")
+                            .short('o')
+                            .long("lists")
+                            .value_name("FILE")
+                            .help("Where to write the records (.csv for CSV, JSON otherwise)")
+                            .required(true),
+                    )
+                    .args(without("extract-lists")),
+            )
+            .subcommand(
+                Command::new("boxes")
+                    .about("Crawl a domain and save screenshots with sibling groups outlined")
+                    .arg(domains())
+                    .arg(
+                        Arg::new("visualize-groups")
+                            .short('o')
+                            .long("dir")
+                            .value_name("DIR")
+                            .help("Directory for the screenshots and group JSON files")
+                            .required(true),
+                    )
+                    .args(without("visualize-groups")),
+            )
+            .subcommand(
+                Command::new("sitemap")
+                    .about("Read a domain's sitemaps without crawling")
+                    .args_conflicts_with_subcommands(true)
+                    .subcommand_negates_reqs(true)
+                    .subcommand(
+                        Command::new("list")
+                            .about("List the URLs in a domain's sitemaps with their lastmod and priority")
+                            .arg(domain())
+                            .args(Self::sitemap_args()),
+                    )
+                    // `sitemap DOMAIN`, a deprecated alias of `sitemap list DOMAIN`
+                    .arg(domain().required(false))
+                    .args(Self::sitemap_args()),
+            )
+            .subcommand(
+                Command::new("test")
+                    .about("Check the browser setup by rendering a page and printing its title")
+                    .arg(
+                        Arg::new("url")
+                            .value_name("URL")
+                            .help("Page to render, e.g. one the crawl will visit")
+                            .required(true),
+                    )
+                    .args(Self::crawl_args().into_iter().filter(|arg| {
+                        matches!(
+                            arg.get_id().as_str(),
+                            "engine"
+                                | "wait"
+                                | "wait-timeout"
+                                | "navigation-timeout"
+                                | "script-timeout"
+                                | "page-timeout"
+                                | "allow-private"
+                                | "accept-language"
+                                | "timezone"
+                                | "geolocation"
+                        )
+                    })),
+            )
+            .subcommand(
+                Command::new("analyze-url")
+                    .about("Scrape a single page and print it as JSON, without sitemap or link discovery")
+                    .arg(
+                        Arg::new("url")
+                            .value_name("URL")
+                            .help("Page to scrape")
+                            .required(true),
+                    )
+                    .args(Self::crawl_args().into_iter().filter(|arg| {
+                        !matches!(
+                            arg.get_id().as_str(),
+                            "max-pages"
+                                | "max-sitemap-urls"
+                                | "sitemap-sampling"
+                                | "probe-paths"
+                                | "site-search"
+                                | "since"
+                                | "modified-within"
+                                | "languages"
+                                | "dry-run"
+                                | "priority"
+                                | "max-concurrent-domains"
+                                | "fair-share"
+                                | "auto-expand-domains"
+                                | "search"
+                                | "search-endpoint"
+                                | "search-results"
+                                | "domains-file"
+                                | "watch"
+                                | "worker"
+                        )
+                    })),
+            )
+            .subcommand(
+                Command::new("merge-reviews")
+                    .about("Apply the corrections of a reviewed --review-file to a pages file")
+                    .arg(
+                        Arg::new("pages")
+                            .value_name("PAGES")
+                            .help("Pages JSON written with --output")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("reviews")
+                            .value_name("REVIEWS")
+                            .help("Review file with accepted or rejected entities")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("output")
+                            .long("output")
+                            .value_name("FILE")
+                            .help("Write the merged pages to FILE instead of stdout"),
+                    ),
+            )
+            .subcommand(
+                Command::new("monitor")
+                    .about("Scrape tracked values (prices, availability) and alert when they change")
+                    .arg(
+                        Arg::new("config")
+                            .value_name("CONFIG")
+                            .help("JSON file listing the targets to track and an optional webhook")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("history")
+                            .long("history")
+                            .value_name("FILE")
+                            .help("JSON lines file the values are recorded in and compared against")
+                            .default_value("monitor-history.jsonl"),
+                    )
+                    .arg(
+                        Arg::new("conditional")
+                            .long("conditional")
+                            .help("Send the ETag and Last-Modified of the last run first and keep the last value of pages answered 304 Not Modified, without rendering them")
+                            .action(clap::ArgAction::SetTrue),
+                    )
+                    .args(Self::crawl_args().into_iter().filter(|arg| {
+                        matches!(
+                            arg.get_id().as_str(),
+                            "accept-language"
+                                | "timezone"
+                                | "geolocation"
+                                | "interact"
+                                | "wait"
+                                | "wait-timeout"
+                                | "navigation-timeout"
+                                | "script-timeout"
+                                | "page-timeout"
+                                | "shadow-dom"
+                                | "allow-private"
+                                | "engine"
+                                | "block-url"
+                                | "block"
+                        )
+                    })),
+            )
+            .subcommand(
+                Command::new("diff")
+                    .about("Compare the pages files of two runs: pages and entities added, removed or changed")
+                    .arg(
+                        Arg::new("before")
+                            .value_name("BEFORE")
+                            .help("Pages JSON of the earlier run")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("after")
+                            .value_name("AFTER")
+                            .help("Pages JSON of the later run")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("json")
+                            .long("json")
+                            .help("Print the differences as JSON")
+                            .action(clap::ArgAction::SetTrue),
+                    )
+                    .arg(
+                        Arg::new("output")
+                            .long("output")
+                            .value_name("FILE")
+                            .help("Write the differences to FILE instead of stdout"),
+                    ),
+            )
             .arg(
                 Arg::new("domain")
                     .long("domain")
                     .value_name("DOMAIN")
-                    .help("Domain to crawl. Can be a URL or domain name")
-                    .required(true),
+                    .help("Deprecated: use `smart-crawler crawl DOMAIN`")
+                    .hide(true),
             )
             .arg(
                 Arg::new("prep")
                     .long("prep")
-                    .help(
-                        "Enable preparation mode to discover template patterns across domain pages",
-                    )
-                    .action(clap::ArgAction::SetTrue),
+                    .help("Deprecated: use `smart-crawler prep DOMAIN`")
+                    .action(clap::ArgAction::SetTrue)
+                    .hide(true),
+            )
+            .args(Self::crawl_args().into_iter().map(|arg| arg.hide(true)))
+            .arg(
+                Arg::new("verbose")
+                    .short('v')
+                    .long("verbose")
+                    .help("Log more detail: -v for debug, -vv for trace")
+                    .action(clap::ArgAction::Count)
+                    .global(true),
             )
-            .get_matches();
+            .arg(
+                Arg::new("quiet")
+                    .short('q')
+                    .long("quiet")
+                    .help("Only log errors")
+                    .conflicts_with("verbose")
+                    .action(clap::ArgAction::SetTrue)
+                    .global(true),
+            )
+    }
+
+    /// Options shared by every crawling subcommand
+    fn crawl_args() -> Vec<Arg> {
+        vec![
+            Arg::new("objective")
+                .long("objective")
+                .value_name("TEXT")
+                .help("What the crawl is looking for; matching content is kept during duplicate filtering"),
+            Arg::new("preset")
+                .long("preset")
+                .value_name("NAME")
+                .help("Named objective with URL hints for a common task; --objective overrides its phrasing")
+                .value_parser(ObjectivePreset::names()),
+            Arg::new("site-search")
+                .long("site-search")
+                .help("Search for the objective (or preset) keywords with the homepage's search form and crawl the results first")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("wayback")
+                .long("wayback")
+                .help("Crawl the Wayback Machine snapshot of pages that 404, are blocked or fail to load")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("wayback-date")
+                .long("wayback-date")
+                .value_name("DATE")
+                .help("Use the snapshot closest to this date (YYYY, YYYYMM or YYYYMMDD) instead of the latest")
+                .requires("wayback"),
+            Arg::new("as-of")
+                .long("as-of")
+                .value_name("DATE")
+                .help("Crawl the site as it was on DATE (YYYY-MM-DD), loading every page from the Wayback Machine")
+                .conflicts_with("wayback"),
+            Arg::new("max-pages")
+                .long("max-pages")
+                .value_name("COUNT")
+                .help("Pages rendered per domain (default 3, 10 for prep, or the profile's)")
+                .value_parser(clap::value_parser!(usize)),
+            Arg::new("profile")
+                .long("profile")
+                .value_name("NAME")
+                .help("Pages, waits, timeouts and survey tuned for speed or coverage; options given explicitly win")
+                .value_parser(CrawlProfile::names()),
+            Arg::new("synonyms")
+                .long("synonyms")
+                .value_name("FILE")
+                .help("JSON object of extra synonyms per objective keyword, e.g. {\"menu\": [\"dishes\"]} (needs --objective or --preset)"),
+            Arg::new("output")
+                .long("output")
+                .value_name("FILE")
+                .help("Write scraped pages as JSON to this file"),
+            Arg::new("content")
+                .long("content")
+                .value_name("MODE")
+                .help("Page content to include in the output file")
+                .value_parser(["raw", "filtered", "both"])
+                .default_value("filtered"),
+            Arg::new("survey")
+                .long("survey")
+                .help("Crawl in two phases: read the title, description and headings of more candidate pages over plain HTTP, then render only the most relevant")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("survey-candidates")
+                .long("survey-candidates")
+                .value_name("COUNT")
+                .help("Candidate pages surveyed per domain")
+                .value_parser(clap::value_parser!(usize))
+                .requires("survey"),
+            Arg::new("survey-time")
+                .long("survey-time")
+                .value_name("DURATION")
+                .help("Time the survey of a domain may take")
+                .requires("survey"),
+            Arg::new("url-selection")
+                .long("url-selection")
+                .value_name("STRATEGY")
+                .help("How the surveyed pages to render are picked: by objective keywords, or in discovery order")
+                .value_parser(["keywords", "order"])
+                .default_value("keywords")
+                .requires("survey"),
+            Arg::new("max-sitemap-urls")
+                .long("max-sitemap-urls")
+                .value_name("COUNT")
+                .help("Maximum number of sitemap URLs considered per domain (0 disables sitemaps)")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10000"),
+            Arg::new("probe-paths")
+                .long("probe-paths")
+                .value_name("PATHS")
+                .help("Comma separated paths checked for pages when a domain has no sitemap (default about,team,products,blog,careers,contact; empty disables)"),
+            Arg::new("sitemap-sampling")
+                .long("sitemap-sampling")
+                .value_name("STRATEGY")
+                .help("How sitemap URLs are sampled once the maximum is reached")
+                .value_parser(["first", "reservoir", "prefix"])
+                .default_value("prefix"),
+            Arg::new("since")
+                .long("since")
+                .value_name("DATE")
+                .help("Only consider sitemap URLs modified on or after this date (e.g. 2024-01-01)")
+                .conflicts_with("modified-within"),
+            Arg::new("modified-within")
+                .long("modified-within")
+                .value_name("WINDOW")
+                .help("Only consider sitemap URLs modified within this window (e.g. 12h, 30d, 4w)"),
+            Arg::new("languages")
+                .long("languages")
+                .value_name("LANGS")
+                .help("Comma separated languages to crawl (e.g. en,de); other-language alternates are skipped"),
+            Arg::new("accept-language")
+                .long("accept-language")
+                .value_name("LANGS")
+                .help("Accept-Language sent by the browser (e.g. de-DE,de)"),
+            Arg::new("timezone")
+                .long("timezone")
+                .value_name("TZ")
+                .help("IANA timezone for the browser session (e.g. Europe/Berlin, Firefox or --engine cdp only)"),
+            Arg::new("geolocation")
+                .long("geolocation")
+                .value_name("LAT,LON")
+                .help("Position reported to pages through the Geolocation API"),
+            Arg::new("bounding-boxes")
+                .long("bounding-boxes")
+                .help("Record the rendered position and size of every element in the output")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("extract-lists")
+                .long("extract-lists")
+                .value_name("FILE")
+                .help("Write repeated sibling groups as records to FILE (.csv for CSV, JSON otherwise)"),
+            Arg::new("visualize-groups")
+                .long("visualize-groups")
+                .value_name("DIR")
+                .help("Save a screenshot with sibling groups outlined, plus the groups as JSON, for each page to DIR (implies --bounding-boxes)"),
+            Arg::new("interact")
+                .long("interact")
+                .value_name("ACTION")
+                .help("Interaction to perform on each page before capture: click:PATH, type:PATH=TEXT or select:PATH=VALUE (repeatable, PATH as in template paths, e.g. div.cookie-banner button.accept)")
+                .action(clap::ArgAction::Append),
+            Arg::new("wait")
+                .long("wait")
+                .value_name("STRATEGY")
+                .help("When a page is ready: a fixed time (2s), ready, selector:CSS, network-idle[:DURATION] or js:EXPR")
+                .default_value("2s"),
+            Arg::new("wait-timeout")
+                .long("wait-timeout")
+                .value_name("DURATION")
+                .help("Give up waiting and capture the page after DURATION")
+                .default_value("10s"),
+            Arg::new("navigation-timeout")
+                .long("navigation-timeout")
+                .value_name("DURATION")
+                .help("Mark a page as timed out when loading it takes longer than DURATION")
+                .default_value("30s"),
+            Arg::new("script-timeout")
+                .long("script-timeout")
+                .value_name("DURATION")
+                .help("Mark a page as timed out when a script or capturing its HTML takes longer than DURATION")
+                .default_value("30s"),
+            Arg::new("page-timeout")
+                .long("page-timeout")
+                .value_name("DURATION")
+                .help("Mark a page as timed out when everything done for it takes longer than DURATION")
+                .default_value("60s"),
+            Arg::new("capture-api")
+                .long("capture-api")
+                .help("Record JSON responses fetched by each page (XHR/fetch) and include them in the output")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("shadow-dom")
+                .long("shadow-dom")
+                .help("Inline open shadow roots into the captured HTML (for web component based sites)")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("allow-private")
+                .long("allow-private")
+                .help("Crawl hosts on private networks (localhost, LAN, link-local), which are refused by default along with redirects to them, in the browser and in sitemap, probe, survey and download requests. Only the cdp engine also blocks the requests a page makes to them")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("max-file-size")
+                .long("max-file-size")
+                .value_name("BYTES")
+                .help("Skip pages whose Content-Length is over this size (default 50 MB, 0 for no limit)")
+                .value_parser(clap::value_parser!(u64)),
+            Arg::new("content-type")
+                .long("content-type")
+                .value_name("TYPE")
+                .help("Also fetch pages of this content type, e.g. application/pdf or image/* (repeatable; pages, text, JSON, CSV and XML are always fetched)")
+                .action(clap::ArgAction::Append),
+            Arg::new("engine")
+                .long("engine")
+                .value_name("ENGINE")
+                .help("How the browser is driven: webdriver (a WebDriver server on port 4444) or cdp (launches Chrome over the DevTools Protocol, needs the cdp feature)")
+                .value_parser(["webdriver", "cdp"])
+                .default_value("webdriver"),
+            Arg::new("block-url")
+                .long("block-url")
+                .value_name("PATTERN")
+                .help("Don't load requests whose URL matches PATTERN, * matching anything (repeatable, e.g. *.doubleclick.net/*, needs --engine cdp)")
+                .action(clap::ArgAction::Append),
+            Arg::new("block")
+                .long("block")
+                .value_name("TYPES")
+                .help("Don't load these kinds of requests so pages render faster: comma separated images, fonts, media, ads and analytics (needs --engine cdp)"),
+            Arg::new("ignore-meta-robots")
+                .long("ignore-meta-robots")
+                .help("Keep pages marked noindex and follow nofollow links")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("redact-pii")
+                .long("redact-pii")
+                .help("Mask emails, phone numbers and street addresses in stored HTML, page content and analysis text")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("keep-pii-entities")
+                .long("keep-pii-entities")
+                .help("With --redact-pii, keep unmasked values in the extracted entities")
+                .requires("redact-pii")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("entity-types")
+                .long("entity-types")
+                .value_name("TYPES")
+                .help("Only extract these entity types: comma separated email, phone, price, date, address, area, weight and duration"),
+            Arg::new("entity-rules")
+                .long("entity-rules")
+                .value_name("FILE")
+                .help("Flag or drop extracted entities that fail the rules in FILE, a JSON array like [{\"kind\": \"price\", \"min\": 0.01}]"),
+            Arg::new("known-entities")
+                .long("known-entities")
+                .value_name("FILE")
+                .help("Mark extracted entities new, updated or duplicate against the entities of earlier runs in FILE, one JSON object per line"),
+            Arg::new("db-url")
+                .long("db-url")
+                .value_name("URL")
+                .help("Upsert extracted entities into a SQLite (sqlite://entities.db?mode=rwc) or Postgres database (needs a build with --features db)"),
+            Arg::new("keep-html")
+                .long("keep-html")
+                .value_name("PAGES")
+                .help("Pages whose raw HTML is kept in memory after parsing")
+                .value_parser(["none", "failed-only", "all"])
+                .default_value("all"),
+            Arg::new("compress-html")
+                .long("compress-html")
+                .help("Gzip kept raw HTML")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("max-html-bytes")
+                .long("max-html-bytes")
+                .value_name("BYTES")
+                .help("Cut kept raw HTML to this size")
+                .value_parser(clap::value_parser!(usize)),
+            Arg::new("max-memory")
+                .long("max-memory")
+                .value_name("BYTES")
+                .help("Keep the HTML and trees of finished pages under this size in memory, moving the least recently used ones to disk")
+                .value_parser(clap::value_parser!(usize)),
+            Arg::new("cache-dir")
+                .long("cache-dir")
+                .value_name("DIR")
+                .help("Where --max-memory moves pages (default: a directory in the system temp dir)")
+                .requires("max-memory"),
+            Arg::new("deterministic")
+                .long("deterministic")
+                .help("Sample sitemaps with a fixed seed and write results in domain order, so runs can be diffed")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("brand-assets")
+                .long("brand-assets")
+                .help("Record the favicons, logos and share image of each homepage in the output")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("assets-dir")
+                .long("assets-dir")
+                .value_name("DIR")
+                .help("Download brand assets to DIR (implies --brand-assets)"),
+            Arg::new("review-file")
+                .long("review-file")
+                .value_name("FILE")
+                .help("Write low-confidence entities to FILE as JSON lines for review (see merge-reviews)"),
+            Arg::new("report")
+                .long("report")
+                .value_name("FILE")
+                .help("Write a readable report of the crawl: HTML for .html files, Markdown otherwise"),
+            Arg::new("domain-profiles")
+                .long("domain-profiles")
+                .value_name("FILE")
+                .help("Write one profile per domain as JSON: site name, logo, contact details and the other entities, merged across pages"),
+            Arg::new("export-graph")
+                .long("export-graph")
+                .value_name("FILE")
+                .help("Write the pages, the site's organization and the entities found as a graph to FILE"),
+            Arg::new("export-graph-format")
+                .long("export-graph-format")
+                .value_name("FORMAT")
+                .help("Format of the graph: jsonld, ttl (Turtle) or cypher (Neo4j statements); defaults to ttl for .ttl files, cypher for .cypher and .cql, jsonld otherwise")
+                .value_parser(["jsonld", "ttl", "cypher"])
+                .requires("export-graph"),
+            Arg::new("progress")
+                .long("progress")
+                .help("Print a line to stderr for every discovered, scraped or failed page")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("domains-file")
+                .long("domains-file")
+                .value_name("FILE")
+                .help("Also crawl the domains in FILE, one per line with an optional priority (e.g. example.com 5)"),
+            Arg::new("watch")
+                .long("watch")
+                .help("Keep running and crawl domains added to --domains-file until interrupted with Ctrl-C")
+                .requires("domains-file")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("worker")
+                .long("worker")
+                .help("Queue the given domains in the --db-url database and keep crawling domains any worker queued there, until interrupted with Ctrl-C")
+                .requires("db-url")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("priority")
+                .long("priority")
+                .value_name("DOMAIN=N")
+                .help("Crawl DOMAIN before domains with a lower priority (default 0, repeatable)")
+                .action(clap::ArgAction::Append),
+            Arg::new("max-concurrent-domains")
+                .long("max-concurrent-domains")
+                .value_name("COUNT")
+                .help("Domains crawled at the same time, each in its own WebDriver session")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1"),
+            Arg::new("fair-share")
+                .long("fair-share")
+                .value_name("PAGES")
+                .help("Crawl domains in turns of PAGES pages each, so a slow site doesn't keep the others waiting")
+                .value_parser(clap::value_parser!(usize)),
+            Arg::new("auto-expand-domains")
+                .long("auto-expand-domains")
+                .value_name("COUNT")
+                .help("Also crawl up to COUNT other sites linked from crawled pages that match the objective")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("0"),
+            Arg::new("search")
+                .long("search")
+                .value_name("PROVIDER")
+                .help("Also crawl the domains a web search for the objective returns: searxng, brave or serpapi (API key in SMART_CRAWLER_SEARCH_API_KEY)"),
+            Arg::new("search-endpoint")
+                .long("search-endpoint")
+                .value_name("URL")
+                .help("Search API URL, required for searxng (e.g. https://searx.example.org/search)")
+                .requires("search"),
+            Arg::new("search-results")
+                .long("search-results")
+                .value_name("COUNT")
+                .help("Search results read for --search")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .requires("search"),
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print the URLs that would be crawled, in order, then exit; only the homepage is fetched")
+                .action(clap::ArgAction::SetTrue),
+        ]
+    }
+
+    /// Options of `sitemap list`
+    fn sitemap_args() -> Vec<Arg> {
+        let mut args = vec![
+            Arg::new("json")
+                .long("json")
+                .help("Print the URLs as a JSON array")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("csv")
+                .long("csv")
+                .help("Print the URLs as CSV")
+                .conflicts_with("json")
+                .action(clap::ArgAction::SetTrue),
+            Arg::new("output")
+                .long("output")
+                .value_name("FILE")
+                .help("Write the list to FILE instead of stdout"),
+        ];
+        args.extend(Self::crawl_args().into_iter().filter(|arg| {
+            matches!(
+                arg.get_id().as_str(),
+                "max-sitemap-urls"
+                    | "sitemap-sampling"
+                    | "since"
+                    | "modified-within"
+                    | "deterministic"
+            )
+        }));
+        args
+    }
+
+    fn from_matches(matches: &ArgMatches) -> Result<Self, String> {
+        if let Some((name, _)) = matches.subcommand() {
+            // Only -v/-q are shared; legacy flags belong to the bare invocation
+            let legacy = matches.ids().find(|id| {
+                !matches!(id.as_str(), "verbose" | "quiet")
+                    && matches.value_source(id.as_str()) == Some(ValueSource::CommandLine)
+            });
+            if let Some(id) = legacy {
+                return Err(format!(
+                    "--{id} can't be combined with the `{name}` command, pass options after the command"
+                ));
+            }
+        }
+
+        let (command, matches) = match matches.subcommand() {
+            Some(("crawl", sub)) => (CliCommand::Crawl, sub),
+            Some(("prep", sub)) => (CliCommand::Prep, sub),
+            Some(("extract", sub)) => (CliCommand::Extract, sub),
+            Some(("boxes", sub)) => (CliCommand::Boxes, sub),
+            Some(("sitemap", sub)) => {
+                let sub = match sub.subcommand() {
+                    Some(("list", list)) => list,
+                    _ if sub.contains_id("domain") => sub,
+                    _ => return Err(
+                        "A sitemap command is required: list (see smart-crawler sitemap --help)"
+                            .to_string(),
+                    ),
+                };
+                let format = if sub.get_flag("json") {
+                    SitemapFormat::Json
+                } else if sub.get_flag("csv") {
+                    SitemapFormat::Csv
+                } else {
+                    SitemapFormat::Text
+                };
+                (CliCommand::Sitemap { format }, sub)
+            }
+            Some(("merge-reviews", sub)) => {
+                let pages = string_arg(sub, "pages").ok_or("A pages file is required")?;
+                let reviews = string_arg(sub, "reviews").ok_or("A review file is required")?;
+                (
+                    CliCommand::MergeReviews {
+                        pages: pages.clone(),
+                        reviews: reviews.clone(),
+                    },
+                    sub,
+                )
+            }
+            Some(("monitor", sub)) => {
+                let config = string_arg(sub, "config").ok_or("A monitor config is required")?;
+                let history = string_arg(sub, "history").ok_or("A history file is required")?;
+                (
+                    CliCommand::Monitor {
+                        config: config.clone(),
+                        history: history.clone(),
+                        conditional: flag_arg(sub, "conditional"),
+                    },
+                    sub,
+                )
+            }
+            Some(("diff", sub)) => {
+                let before = string_arg(sub, "before").ok_or("Two pages files are required")?;
+                let after = string_arg(sub, "after").ok_or("Two pages files are required")?;
+                (
+                    CliCommand::Diff {
+                        before: before.clone(),
+                        after: after.clone(),
+                        json: sub.get_flag("json"),
+                    },
+                    sub,
+                )
+            }
+            Some(("analyze-url", sub)) => {
+                let url = string_arg(sub, "url").ok_or("A URL is required")?;
+                let url = Self::normalize_url(url)?;
+                (CliCommand::AnalyzeUrl { url }, sub)
+            }
+            Some(("test", sub)) => {
+                let url = string_arg(sub, "url").ok_or("A URL is required")?;
+                let url = Self::normalize_url(url)?;
+                (CliCommand::Test { url }, sub)
+            }
+            Some((name, _)) => return Err(format!("Unknown command: {name}")),
+            None if flag_arg(matches, "prep") => (CliCommand::Prep, matches),
+            None => (CliCommand::Crawl, matches),
+        };
+
+        let mut domain_inputs: Vec<&String> = matches
+            .try_get_many::<String>("domain")
+            .ok()
+            .flatten()
+            .into_iter()
+            .flatten()
+            .chain(string_arg(matches, "url"))
+            .collect();
+        let mut domain_priorities = HashMap::new();
+        let domains_file = string_arg(matches, "domains-file").cloned();
+        let file_entries = match &domains_file {
+            Some(path) => load_domains_file(path)?,
+            None => Vec::new(),
+        };
+        domain_inputs.extend(file_entries.iter().map(|entry| &entry.domain));
+
+        let mut domains = Vec::new();
+        for input in domain_inputs {
+            let domain = normalize_domain(input)?;
+            if !domains
+                .iter()
+                .any(|known: &String| site_host(known) == site_host(&domain))
+            {
+                domains.push(domain);
+            }
+        }
+        for entry in &file_entries {
+            domain_priorities.insert(normalize_domain(&entry.domain)?, entry.priority);
+        }
+        for entry in matches
+            .try_get_many::<String>("priority")
+            .ok()
+            .flatten()
+            .into_iter()
+            .flatten()
+        {
+            let (domain, priority) = Self::parse_priority(entry)?;
+            domain_priorities.insert(domain, priority);
+        }
+
+        let prep = command == CliCommand::Prep;
+        let objective = string_arg(matches, "objective")
+            .map(|objective| objective.trim().to_string())
+            .filter(|objective| !objective.is_empty());
+
+        let preset = string_arg(matches, "preset").and_then(|name| ObjectivePreset::find(name));
+        let profile = string_arg(matches, "profile").and_then(|name| CrawlProfile::find(name));
+        let synonyms = string_arg(matches, "synonyms").cloned();
+        if synonyms.is_some() && objective.is_none() && preset.is_none() {
+            return Err("--synonyms needs --objective or --preset".to_string());
+        }
+
+        let search = match string_arg(matches, "search") {
+            Some(provider) => {
+                if objective.is_none() && preset.is_none() {
+                    return Err("--search needs --objective or --preset".to_string());
+                }
+                let mut config = SearchConfig::new(provider.parse()?);
+                config.endpoint = string_arg(matches, "search-endpoint").cloned();
+                if let Some(max_results) = matches
+                    .try_get_one::<usize>("search-results")
+                    .ok()
+                    .flatten()
+                {
+                    config.max_results = *max_results;
+                }
+                Some(config)
+            }
+            None => None,
+        };
+
+        // Searched domains are only known once the search has run
+        let validated_domain = match domains.first() {
+            Some(domain) => domain.clone(),
+            None if search.is_some()
+                || flag_arg(matches, "worker")
+                || matches!(
+                    command,
+                    CliCommand::MergeReviews { .. }
+                        | CliCommand::Monitor { .. }
+                        | CliCommand::Diff { .. }
+                ) =>
+            {
+                String::new()
+            }
+            None => return Err(
+                "A command is required: crawl, prep, extract, boxes, sitemap, analyze-url, merge-reviews, monitor, diff or test (see smart-crawler --help)".to_string(),
+            ),
+        };
+
+        // Fail before crawling rather than when the results are written
+        for id in [
+            "output",
+            "export-graph",
+            "report",
+            "review-file",
+            "domain-profiles",
+            "extract-lists",
+            "visualize-groups",
+            "assets-dir",
+            "cache-dir",
+            "history",
+        ] {
+            if let Some(path) = string_arg(matches, id) {
+                check_local_path(path)?;
+            }
+        }
+        let output = string_arg(matches, "output").cloned();
+        let export_graph = string_arg(matches, "export-graph").cloned();
+        let graph_format = match string_arg(matches, "export-graph-format") {
+            Some(format) => format.parse()?,
+            None => export_graph
+                .as_deref()
+                .map(GraphFormat::from_path)
+                .unwrap_or_default(),
+        };
+        let content_mode = string_arg(matches, "content")
+            .map(|mode| mode.parse())
+            .transpose()?
+            .unwrap_or_default();
+
+        let survey_time = string_arg(matches, "survey-time")
+            .map(|time| parse_duration(time))
+            .transpose()?;
+        let url_selection = string_arg(matches, "url-selection")
+            .map(|strategy| strategy.parse())
+            .transpose()?
+            .unwrap_or_default();
+        let sitemap_sampling = string_arg(matches, "sitemap-sampling")
+            .map(|strategy| strategy.parse())
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut fetch_limits = FetchLimits::default();
+        if let Some(max) = matches.try_get_one::<u64>("max-file-size").ok().flatten() {
+            fetch_limits.max_file_size = (*max > 0).then_some(*max);
+        }
+        fetch_limits.content_types.extend(
+            matches
+                .try_get_many::<String>("content-type")
+                .ok()
+                .flatten()
+                .into_iter()
+                .flatten()
+                .cloned(),
+        );
 
-        let domain_input = matches
-            .get_one::<String>("domain")
-            .ok_or("Domain argument is required")?;
+        let html_retention = RetentionConfig {
+            keep_html: string_arg(matches, "keep-html")
+                .map(|keep| keep.parse())
+                .transpose()?
+                .unwrap_or_default(),
+            compress_html: flag_arg(matches, "compress-html"),
+            max_html_bytes: matches
+                .try_get_one::<usize>("max-html-bytes")
+                .ok()
+                .flatten()
+                .copied(),
+        };
+        let spill = matches
+            .try_get_one::<usize>("max-memory")
+            .ok()
+            .flatten()
+            .map(|&max_resident_bytes| SpillConfig {
+                dir: string_arg(matches, "cache-dir")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(SpillConfig::default_dir),
+                max_resident_bytes,
+            });
 
-        let validated_domain = Self::extract_domain(domain_input)?;
-        let prep = matches.get_flag("prep");
+        let modified_since = Self::parse_modified_since(
+            string_arg(matches, "since").map(String::as_str),
+            string_arg(matches, "modified-within").map(String::as_str),
+        )?;
+
+        let as_of = string_arg(matches, "as-of")
+            .map(|date| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .map_err(|_| format!("Invalid --as-of {date} (expected YYYY-MM-DD)"))
+            })
+            .transpose()?;
+        let wayback = if let Some(as_of) = as_of {
+            Some(WaybackConfig {
+                as_of: Some(as_of),
+                ..WaybackConfig::default()
+            })
+        } else if flag_arg(matches, "wayback") {
+            let timestamp = string_arg(matches, "wayback-date").cloned();
+            if let Some(date) = &timestamp {
+                if !(4..=14).contains(&date.len()) || !date.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(format!(
+                        "Invalid --wayback-date {date} (expected YYYY, YYYYMM or YYYYMMDD)"
+                    ));
+                }
+            }
+            Some(WaybackConfig {
+                timestamp,
+                ..WaybackConfig::default()
+            })
+        } else {
+            None
+        };
+
+        let probe_paths = string_arg(matches, "probe-paths").map(|list| {
+            list.split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(str::to_string)
+                .collect()
+        });
+
+        let languages = string_arg(matches, "languages")
+            .map(|list| LanguageFilter::parse(list))
+            .unwrap_or_default();
+
+        let locale = LocaleOptions {
+            accept_language: string_arg(matches, "accept-language").cloned(),
+            timezone: string_arg(matches, "timezone").cloned(),
+            geolocation: string_arg(matches, "geolocation")
+                .map(|location| location.parse::<Geolocation>())
+                .transpose()?,
+        };
+
+        let interactions = matches
+            .try_get_many::<String>("interact")
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .map(|action| action.parse::<Interaction>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let wait = explicit_arg(matches, "wait")
+            .map(|strategy| strategy.parse::<WaitStrategy>())
+            .transpose()?;
+        let explicit_duration = |id| explicit_arg(matches, id).map(parse_duration).transpose();
+
+        let engine = string_arg(matches, "engine")
+            .map(|engine| engine.parse::<BrowserEngine>())
+            .transpose()?
+            .unwrap_or_default();
+        let blocked_urls: Vec<String> = matches
+            .try_get_many::<String>("block-url")
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .cloned()
+            .collect();
+        let block_categories = string_arg(matches, "block")
+            .map(|categories| parse_block_categories(categories))
+            .transpose()?
+            .unwrap_or_default();
+        if !blocked_urls.is_empty() && engine != BrowserEngine::Cdp {
+            return Err("--block-url needs --engine cdp".to_string());
+        }
+        if !block_categories.is_empty() && engine != BrowserEngine::Cdp {
+            return Err("--block needs --engine cdp".to_string());
+        }
+
+        let visualize_groups = string_arg(matches, "visualize-groups").cloned();
 
         Ok(CliArgs {
+            command,
             domain: validated_domain,
+            domains,
+            domain_priorities,
+            domains_file,
+            watch: flag_arg(matches, "watch"),
+            worker: flag_arg(matches, "worker"),
+            max_concurrent_domains: matches
+                .try_get_one::<usize>("max-concurrent-domains")
+                .ok()
+                .flatten()
+                .copied()
+                .unwrap_or(1),
+            fair_share: matches
+                .try_get_one::<usize>("fair-share")
+                .ok()
+                .flatten()
+                .copied(),
+            auto_expand_domains: matches
+                .try_get_one::<usize>("auto-expand-domains")
+                .ok()
+                .flatten()
+                .copied()
+                .unwrap_or(0),
+            search,
+            review_file: string_arg(matches, "review-file").cloned(),
+            report: string_arg(matches, "report").cloned(),
+            export_graph,
+            graph_format,
+            domain_profiles: string_arg(matches, "domain-profiles").cloned(),
             prep,
+            objective,
+            preset,
+            profile,
+            max_pages: explicit_usize(matches, "max-pages"),
+            synonyms,
+            output,
+            content_mode,
+            survey: flag_arg(matches, "survey"),
+            survey_candidates: explicit_usize(matches, "survey-candidates"),
+            survey_time,
+            max_sitemap_urls: explicit_usize(matches, "max-sitemap-urls"),
+            probe_paths,
+            site_search: flag_arg(matches, "site-search"),
+            wayback,
+            url_selection,
+            sitemap_sampling,
+            modified_since,
+            languages,
+            locale,
+            bounding_boxes: flag_arg(matches, "bounding-boxes") || visualize_groups.is_some(),
+            extract_lists: string_arg(matches, "extract-lists").cloned(),
+            visualize_groups,
+            log_level: Self::log_level(matches.get_count("verbose"), flag_arg(matches, "quiet")),
+            interactions,
+            wait,
+            wait_timeout: explicit_duration("wait-timeout")?,
+            navigation_timeout: explicit_duration("navigation-timeout")?,
+            script_timeout: explicit_duration("script-timeout")?,
+            page_timeout: explicit_duration("page-timeout")?,
+            capture_api: flag_arg(matches, "capture-api"),
+            shadow_dom: flag_arg(matches, "shadow-dom"),
+            allow_private: flag_arg(matches, "allow-private"),
+            fetch_limits,
+            engine,
+            blocked_urls,
+            block_categories,
+            ignore_meta_robots: flag_arg(matches, "ignore-meta-robots"),
+            redact_pii: flag_arg(matches, "redact-pii"),
+            keep_pii_entities: flag_arg(matches, "keep-pii-entities"),
+            entity_types: match string_arg(matches, "entity-types") {
+                Some(list) => parse_entity_kinds(list)?,
+                None => Vec::new(),
+            },
+            entity_rules: match string_arg(matches, "entity-rules") {
+                Some(path) => load_validation_rules(path)?,
+                None => Vec::new(),
+            },
+            known_entities: string_arg(matches, "known-entities")
+                .map(|path| load_known_entities(path))
+                .transpose()?,
+            db_url: match string_arg(matches, "db-url") {
+                Some(_) if cfg!(not(feature = "db")) => {
+                    return Err("--db-url needs a build with --features db".to_string())
+                }
+                url => url.cloned(),
+            },
+            html_retention,
+            spill,
+            deterministic: flag_arg(matches, "deterministic"),
+            brand_assets: flag_arg(matches, "brand-assets")
+                || string_arg(matches, "assets-dir").is_some(),
+            assets_dir: string_arg(matches, "assets-dir").cloned(),
+            progress: flag_arg(matches, "progress"),
+            dry_run: flag_arg(matches, "dry-run"),
         })
     }
 
-    fn extract_domain(input: &str) -> Result<String, String> {
-        let trimmed = input.trim();
+    fn log_level(verbose: u8, quiet: bool) -> Level {
+        match (quiet, verbose) {
+            (true, _) => Level::ERROR,
+            (false, 0) => Level::INFO,
+            (false, 1) => Level::DEBUG,
+            (false, _) => Level::TRACE,
+        }
+    }
 
-        // Always try to parse as URL to validate the domain
-        let url_str = if trimmed.starts_with("http://") || trimmed.starts_with("https://") {
+    fn parse_modified_since(
+        since: Option<&str>,
+        modified_within: Option<&str>,
+    ) -> Result<Option<DateTime<Utc>>, String> {
+        if let Some(since) = since {
+            return parse_lastmod(since)
+                .map(Some)
+                .ok_or_else(|| format!("Invalid date for --since: {since}"));
+        }
+        if let Some(window) = modified_within {
+            return Ok(Some(Utc::now() - parse_modified_within(window)?));
+        }
+        Ok(None)
+    }
+
+    /// Full URL of a page, defaulting to https when no scheme is given
+    fn normalize_url(input: &str) -> Result<String, String> {
+        let trimmed = input.trim();
+        let url_str = if trimmed.contains("://") {
             trimmed.to_string()
         } else {
             format!("https://{trimmed}")
         };
-
         match Url::parse(&url_str) {
-            Ok(url) => {
-                if let Some(domain) = url.host_str() {
-                    Ok(domain.to_string())
-                } else {
-                    Err(format!("Could not extract domain from: {input}"))
-                }
+            Ok(url) if url.host_str().is_some() => Ok(url.to_string()),
+            _ => Err(format!("Invalid URL: {input}")),
+        }
+    }
+
+    /// Apply `--profile` to a crawl, then the pages, sitemap, survey, wait
+    /// and timeout options given explicitly, which win over it
+    pub fn apply_profiled_options(&self, mut builder: SmartCrawlerBuilder) -> SmartCrawlerBuilder {
+        if let Some(profile) = self.profile {
+            builder = builder.profile(profile);
+        }
+        if let Some(max_pages) = self.max_pages {
+            builder = builder.max_pages(max_pages);
+        }
+        if let Some(max_urls) = self.max_sitemap_urls {
+            builder = builder.max_sitemap_urls(max_urls);
+        }
+
+        let config = builder.config();
+        let wait = self.wait.clone().unwrap_or_else(|| config.wait.clone());
+        let wait_timeout = self.wait_timeout.unwrap_or(config.wait_timeout);
+        let timeouts = PageTimeouts {
+            navigation: self
+                .navigation_timeout
+                .unwrap_or(config.timeouts.navigation),
+            script: self.script_timeout.unwrap_or(config.timeouts.script),
+            page: self.page_timeout.unwrap_or(config.timeouts.page),
+        };
+        let survey = config
+            .survey
+            .clone()
+            .or_else(|| self.survey.then(SurveyConfig::default));
+        builder = builder.wait(wait, wait_timeout).timeouts(timeouts);
+        if let Some(mut survey) = survey {
+            if let Some(max_candidates) = self.survey_candidates {
+                survey.max_candidates = max_candidates;
+            }
+            if let Some(time_budget) = self.survey_time {
+                survey.time_budget = time_budget;
             }
-            Err(_) => Err(format!("Invalid domain or URL: {input}")),
+            builder = builder
+                .survey(survey)
+                .url_selection(self.url_selection.strategy());
         }
+        if self.shadow_dom {
+            builder = builder.shadow_dom(true);
+        }
+        builder
     }
+
+    /// `DOMAIN=N` from `--priority`
+    fn parse_priority(entry: &str) -> Result<(String, i32), String> {
+        let (domain, priority) = entry
+            .rsplit_once('=')
+            .ok_or_else(|| format!("Invalid --priority {entry} (expected DOMAIN=N)"))?;
+        let priority = priority
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid priority in --priority {entry}"))?;
+        Ok((normalize_domain(domain)?, priority))
+    }
+}
+
+/// Not every subcommand defines every option, e.g. `sitemap` has no `--wait`
+fn string_arg<'a>(matches: &'a ArgMatches, id: &str) -> Option<&'a String> {
+    matches.try_get_one::<String>(id).ok().flatten()
+}
+
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(ValueSource::CommandLine)
+}
+
+/// Value of an option given on the command line; None when left to its
+/// default, which the crawl applies after `--profile`
+fn explicit_arg<'a>(matches: &'a ArgMatches, id: &str) -> Option<&'a str> {
+    string_arg(matches, id)
+        .filter(|_| given(matches, id))
+        .map(String::as_str)
+}
+
+fn explicit_usize(matches: &ArgMatches, id: &str) -> Option<usize> {
+    matches
+        .try_get_one::<usize>(id)
+        .ok()
+        .flatten()
+        .copied()
+        .filter(|_| given(matches, id))
+}
+
+fn flag_arg(matches: &ArgMatches, id: &str) -> bool {
+    matches
+        .try_get_one::<bool>(id)
+        .ok()
+        .flatten()
+        .copied()
+        .unwrap_or(false)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crawler::SmartCrawler;
+    use crate::search_seed::SearchProvider;
+    use crate::storage::HtmlRetention;
 
     #[test]
     fn test_single_domain_parsing() {
         // Test that single domain parsing works correctly
         let args = CliArgs {
+            command: CliCommand::Crawl,
             domain: "example.com".to_string(),
+            domains: vec!["example.com".to_string()],
+            domain_priorities: HashMap::new(),
+            domains_file: None,
+            watch: false,
+            worker: false,
+            max_concurrent_domains: 1,
+            fair_share: None,
+            auto_expand_domains: 0,
+            search: None,
+            review_file: None,
+            report: None,
+            export_graph: None,
+            graph_format: GraphFormat::JsonLd,
+            domain_profiles: None,
             prep: false,
+            objective: None,
+            preset: None,
+            profile: None,
+            max_pages: None,
+            synonyms: None,
+            output: None,
+            content_mode: ContentMode::default(),
+            survey: false,
+            survey_candidates: None,
+            survey_time: None,
+            max_sitemap_urls: None,
+            probe_paths: None,
+            site_search: false,
+            wayback: None,
+            url_selection: UrlSelectionMethod::default(),
+            sitemap_sampling: SamplingStrategy::default(),
+            modified_since: None,
+            languages: LanguageFilter::default(),
+            locale: LocaleOptions::default(),
+            bounding_boxes: false,
+            extract_lists: None,
+            visualize_groups: None,
+            log_level: Level::INFO,
+            interactions: Vec::new(),
+            wait: None,
+            wait_timeout: None,
+            navigation_timeout: None,
+            script_timeout: None,
+            page_timeout: None,
+            capture_api: false,
+            shadow_dom: false,
+            allow_private: false,
+            fetch_limits: FetchLimits::default(),
+            engine: BrowserEngine::WebDriver,
+            blocked_urls: Vec::new(),
+            block_categories: Vec::new(),
+            ignore_meta_robots: false,
+            redact_pii: false,
+            keep_pii_entities: false,
+            entity_types: Vec::new(),
+            entity_rules: Vec::new(),
+            known_entities: None,
+            db_url: None,
+            html_retention: RetentionConfig::default(),
+            spill: None,
+            deterministic: false,
+            brand_assets: false,
+            assets_dir: None,
+            progress: false,
+            dry_run: false,
+        };
+
+        assert_eq!(args.domain, "example.com");
+        assert!(!args.prep);
+    }
+
+    #[test]
+    fn test_parse_modified_since() {
+        assert_eq!(CliArgs::parse_modified_since(None, None).unwrap(), None);
+
+        let since = CliArgs::parse_modified_since(Some("2024-01-01"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(since.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+
+        let within = CliArgs::parse_modified_since(None, Some("30d"))
+            .unwrap()
+            .unwrap();
+        let expected = Utc::now() - chrono::Duration::days(30);
+        assert!((within - expected).num_seconds().abs() < 5);
+
+        assert!(CliArgs::parse_modified_since(Some("January"), None).is_err());
+        assert!(CliArgs::parse_modified_since(None, Some("soon")).is_err());
+    }
+
+    #[test]
+    fn test_cli_prep_flag() {
+        // Test that prep flag is properly parsed (this is a simplified test
+        // since we can't easily test the full CLI parsing in unit tests)
+        let args = CliArgs {
+            command: CliCommand::Prep,
+            domain: "example.com".to_string(),
+            domains: vec!["example.com".to_string()],
+            domain_priorities: HashMap::new(),
+            domains_file: None,
+            watch: false,
+            worker: false,
+            max_concurrent_domains: 1,
+            fair_share: None,
+            auto_expand_domains: 0,
+            search: None,
+            review_file: None,
+            report: None,
+            export_graph: None,
+            graph_format: GraphFormat::JsonLd,
+            domain_profiles: None,
+            prep: true,
+            objective: None,
+            preset: None,
+            profile: None,
+            max_pages: None,
+            synonyms: None,
+            output: None,
+            content_mode: ContentMode::default(),
+            survey: false,
+            survey_candidates: None,
+            survey_time: None,
+            max_sitemap_urls: None,
+            probe_paths: None,
+            site_search: false,
+            wayback: None,
+            url_selection: UrlSelectionMethod::default(),
+            sitemap_sampling: SamplingStrategy::default(),
+            modified_since: None,
+            languages: LanguageFilter::default(),
+            locale: LocaleOptions::default(),
+            bounding_boxes: false,
+            extract_lists: None,
+            visualize_groups: None,
+            log_level: Level::INFO,
+            interactions: Vec::new(),
+            wait: None,
+            wait_timeout: None,
+            navigation_timeout: None,
+            script_timeout: None,
+            page_timeout: None,
+            capture_api: false,
+            shadow_dom: false,
+            allow_private: false,
+            fetch_limits: FetchLimits::default(),
+            engine: BrowserEngine::WebDriver,
+            blocked_urls: Vec::new(),
+            block_categories: Vec::new(),
+            ignore_meta_robots: false,
+            redact_pii: false,
+            keep_pii_entities: false,
+            entity_types: Vec::new(),
+            entity_rules: Vec::new(),
+            known_entities: None,
+            db_url: None,
+            html_retention: RetentionConfig::default(),
+            spill: None,
+            deterministic: false,
+            brand_assets: false,
+            assets_dir: None,
+            progress: false,
+            dry_run: false,
         };
 
+        assert!(args.prep);
+        assert_eq!(args.domain, "example.com");
+    }
+
+    #[test]
+    fn test_subcommands() {
+        let args = CliArgs::try_parse_from(["smart-crawler", "crawl", "https://example.com/about"])
+            .unwrap();
+        assert_eq!(args.command, CliCommand::Crawl);
         assert_eq!(args.domain, "example.com");
         assert!(!args.prep);
+        assert!(!args.ignore_meta_robots);
+        assert_eq!(args.probe_paths, None);
+        assert!(!args.site_search);
+        assert_eq!(args.wayback, None);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--wayback",
+            "--wayback-date",
+            "2022",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.wayback
+                .and_then(|wayback| wayback.timestamp)
+                .as_deref(),
+            Some("2022")
+        );
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--wayback",
+            "--wayback-date",
+            "last year",
+        ])
+        .is_err());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--as-of",
+            "2023-06-01",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.wayback.and_then(|wayback| wayback.as_of),
+            NaiveDate::from_ymd_opt(2023, 6, 1)
+        );
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--as-of",
+            "June 2023",
+        ])
+        .is_err());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--probe-paths",
+            "/shop, pricing",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.probe_paths,
+            Some(vec!["/shop".to_string(), "pricing".to_string()])
+        );
+
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "-v", "prep", "example.com", "--dry-run"])
+                .unwrap();
+        assert!(args.dry_run);
+        assert_eq!(args.command, CliCommand::Prep);
+        assert!(args.prep);
+        assert_eq!(args.log_level, Level::DEBUG);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "extract",
+            "example.com",
+            "--lists",
+            "lists.csv",
+            "--wait",
+            "ready",
+            "--preset",
+            "products",
+            "--ignore-meta-robots",
+        ])
+        .unwrap();
+        assert_eq!(args.preset.map(|preset| preset.name), Some("products"));
+        assert!(args.ignore_meta_robots);
+
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--synonyms",
+            "synonyms.json"
+        ])
+        .is_err());
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--preset",
+            "recipes"
+        ])
+        .is_err());
+        assert_eq!(args.command, CliCommand::Extract);
+        assert!(!args.dry_run);
+        assert_eq!(args.extract_lists.as_deref(), Some("lists.csv"));
+        assert_eq!(args.wait, Some(WaitStrategy::ReadyState));
+        assert!(!args.redact_pii);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--redact-pii",
+            "--keep-pii-entities",
+        ])
+        .unwrap();
+        assert!(args.redact_pii && args.keep_pii_entities);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--entity-types",
+            "price,date",
+        ])
+        .unwrap();
+        assert_eq!(args.entity_types, vec![EntityKind::Price, EntityKind::Date]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.json");
+        std::fs::write(
+            &rules,
+            r#"[{"kind": "price", "min": 0.01, "action": "drop"}]"#,
+        )
+        .unwrap();
+        let rules = rules.to_string_lossy().to_string();
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--entity-rules",
+            &rules,
+        ])
+        .unwrap();
+        assert_eq!(args.entity_rules[0].label(), "price >= 0.01");
+        std::fs::write(&rules, r#"[{"kind": "email", "pattern": "("}]"#).unwrap();
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--entity-rules",
+            &rules
+        ])
+        .is_err());
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--entity-types",
+            "person"
+        ])
+        .is_err());
+        let known = dir.path().join("known.jsonl");
+        std::fs::write(&known, r#"{"kind": "price", "value": "49 USD"}"#).unwrap();
+        let known = known.to_string_lossy().to_string();
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--known-entities",
+            &known
+        ])
+        .is_err());
+        if cfg!(not(feature = "db")) {
+            assert!(CliArgs::try_parse_from([
+                "smart-crawler",
+                "crawl",
+                "example.com",
+                "--db-url",
+                "sqlite://entities.db"
+            ])
+            .is_err());
+        } else {
+            let args = CliArgs::try_parse_from([
+                "smart-crawler",
+                "crawl",
+                "--worker",
+                "--db-url",
+                "sqlite://entities.db",
+            ])
+            .unwrap();
+            assert!(args.worker);
+            assert!(args.domains.is_empty());
+        }
+        assert!(CliArgs::try_parse_from(["smart-crawler", "crawl", "--worker"]).is_err());
+        for args in [
+            ["crawl", "example.com", "--output", "s3://bucket/pages.json"],
+            [
+                "crawl",
+                "example.com",
+                "--report",
+                "gs://bucket/report.html",
+            ],
+            [
+                "crawl",
+                "example.com",
+                "--visualize-groups",
+                "s3://bucket/groups/",
+            ],
+        ] {
+            let error =
+                CliArgs::try_parse_from(["smart-crawler"].into_iter().chain(args)).unwrap_err();
+            assert!(error.contains("URLs aren't supported"));
+        }
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--assets-dir",
+            "assets",
+        ])
+        .unwrap();
+        assert!(args.brand_assets);
+        assert_eq!(args.assets_dir.as_deref(), Some("assets"));
+        assert_eq!(args.html_retention, RetentionConfig::default());
+        assert_eq!(args.engine, BrowserEngine::WebDriver);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--engine",
+            "cdp",
+            "--block-url",
+            "*.doubleclick.net/*",
+            "--block-url",
+            "*.woff2",
+        ])
+        .unwrap();
+        assert_eq!(args.engine, BrowserEngine::Cdp);
+        assert_eq!(args.blocked_urls, vec!["*.doubleclick.net/*", "*.woff2"]);
+        assert!(args.block_categories.is_empty());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--engine",
+            "cdp",
+            "--block",
+            "images,fonts,analytics",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.block_categories,
+            vec![
+                BlockCategory::Images,
+                BlockCategory::Fonts,
+                BlockCategory::Analytics
+            ]
+        );
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--block",
+            "images"
+        ])
+        .is_err());
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--block-url",
+            "*.woff2",
+        ])
+        .is_err());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--keep-html",
+            "failed-only",
+            "--compress-html",
+            "--max-html-bytes",
+            "65536",
+            "--max-file-size",
+            "0",
+            "--content-type",
+            "application/pdf",
+        ])
+        .unwrap();
+        assert_eq!(args.fetch_limits.max_file_size, None);
+        assert!(args.fetch_limits.allows_content_type("application/pdf"));
+        assert!(args.fetch_limits.allows_content_type("text/html"));
+        assert_eq!(
+            args.html_retention,
+            RetentionConfig {
+                keep_html: HtmlRetention::FailedOnly,
+                compress_html: true,
+                max_html_bytes: Some(65536),
+            }
+        );
+        assert!(args.spill.is_none());
+        assert!(!args.survey);
+        assert_eq!(args.url_selection, UrlSelectionMethod::Keywords);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--survey",
+            "--survey-candidates",
+            "50",
+            "--survey-time",
+            "45s",
+            "--url-selection",
+            "order",
+        ])
+        .unwrap();
+        assert_eq!(args.url_selection, UrlSelectionMethod::DiscoveryOrder);
+        assert_eq!(
+            args.apply_profiled_options(SmartCrawler::builder())
+                .config()
+                .survey,
+            Some(SurveyConfig {
+                max_candidates: 50,
+                time_budget: Duration::from_secs(45),
+                ..SurveyConfig::default()
+            })
+        );
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--survey-time",
+            "45s"
+        ])
+        .is_err());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--max-memory",
+            "268435456",
+            "--cache-dir",
+            "crawl-cache",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.spill,
+            Some(SpillConfig {
+                dir: PathBuf::from("crawl-cache"),
+                max_resident_bytes: 268435456,
+            })
+        );
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--cache-dir",
+            "crawl-cache"
+        ])
+        .is_err());
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--keep-pii-entities"
+        ])
+        .is_err());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--navigation-timeout",
+            "15s",
+            "--page-timeout",
+            "2m",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.apply_profiled_options(SmartCrawler::builder())
+                .config()
+                .timeouts,
+            PageTimeouts {
+                navigation: Duration::from_secs(15),
+                script: Duration::from_secs(30),
+                page: Duration::from_secs(120),
+            }
+        );
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--script-timeout",
+            "soon",
+        ])
+        .is_err());
+
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "boxes", "example.com", "-o", "shots"])
+                .unwrap();
+        assert_eq!(args.command, CliCommand::Boxes);
+        assert_eq!(args.visualize_groups.as_deref(), Some("shots"));
+        assert!(args.bounding_boxes);
+
+        // extract and boxes need somewhere to write
+        assert!(CliArgs::try_parse_from(["smart-crawler", "extract", "example.com"]).is_err());
+        assert!(CliArgs::try_parse_from(["smart-crawler", "boxes", "example.com"]).is_err());
+        assert!(CliArgs::try_parse_from(["smart-crawler"]).is_err());
+    }
+
+    #[test]
+    fn test_multiple_domains() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "a.com",
+            "https://b.com/about",
+            "a.com",
+            "--priority",
+            "b.com=5",
+            "--max-concurrent-domains",
+            "2",
+            "--fair-share",
+            "5",
+            "--auto-expand-domains",
+            "3",
+        ])
+        .unwrap();
+        assert_eq!(args.domain, "a.com");
+        assert_eq!(args.domains, vec!["a.com", "b.com"]);
+        assert_eq!(args.domain_priorities.get("b.com"), Some(&5));
+        assert_eq!(args.max_concurrent_domains, 2);
+        assert_eq!(args.fair_share, Some(5));
+        assert_eq!(args.auto_expand_domains, 3);
+
+        assert!(CliArgs::parse_priority("b.com").is_err());
+        assert!(CliArgs::parse_priority("b.com=high").is_err());
+        assert_eq!(
+            CliArgs::parse_priority("https://b.com=-1").unwrap(),
+            ("b.com".to_string(), -1)
+        );
+        // Single-domain commands take exactly one
+        assert!(CliArgs::try_parse_from(["smart-crawler", "sitemap", "a.com", "b.com"]).is_err());
+    }
+
+    #[test]
+    fn test_domains_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("domains.txt");
+        std::fs::write(&path, "b.com 5\nhttps://c.com/events\na.com\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "a.com",
+            "--domains-file",
+            &path,
+            "--priority",
+            "c.com=7",
+        ])
+        .unwrap();
+        assert_eq!(args.domains, vec!["a.com", "b.com", "c.com"]);
+        assert_eq!(args.domain_priorities.get("b.com"), Some(&5));
+        assert_eq!(args.domain_priorities.get("c.com"), Some(&7));
+        assert!(!args.watch);
+
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "prep", "--domains-file", &path, "--watch"])
+                .unwrap();
+        assert_eq!(args.domain, "b.com");
+        assert!(args.watch);
+
+        assert!(CliArgs::try_parse_from(["smart-crawler", "crawl", "a.com", "--watch"]).is_err());
+        assert!(CliArgs::try_parse_from(["smart-crawler", "crawl"]).is_err());
+    }
+
+    #[test]
+    fn test_merge_reviews_subcommand() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "merge-reviews",
+            "pages.json",
+            "review.jsonl",
+            "--output",
+            "merged.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            CliCommand::MergeReviews {
+                pages: "pages.json".to_string(),
+                reviews: "review.jsonl".to_string()
+            }
+        );
+        assert_eq!(args.output.as_deref(), Some("merged.json"));
+        assert!(CliArgs::try_parse_from(["smart-crawler", "merge-reviews", "pages.json"]).is_err());
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--review-file",
+            "review.jsonl",
+            "--report",
+            "report.html",
+            "--domain-profiles",
+            "profiles.json",
+        ])
+        .unwrap();
+        assert_eq!(args.review_file.as_deref(), Some("review.jsonl"));
+        assert_eq!(args.report.as_deref(), Some("report.html"));
+        assert_eq!(args.domain_profiles.as_deref(), Some("profiles.json"));
+        assert_eq!(args.export_graph, None);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--export-graph",
+            "graph.ttl",
+        ])
+        .unwrap();
+        assert_eq!(args.export_graph.as_deref(), Some("graph.ttl"));
+        assert_eq!(args.graph_format, GraphFormat::Turtle);
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--export-graph",
+            "graph.out",
+            "--export-graph-format",
+            "cypher",
+        ])
+        .unwrap();
+        assert_eq!(args.graph_format, GraphFormat::Cypher);
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--export-graph-format",
+            "jsonld",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_monitor_subcommand() {
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "monitor", "prices.json", "--wait", "ready"])
+                .unwrap();
+        assert_eq!(
+            args.command,
+            CliCommand::Monitor {
+                config: "prices.json".to_string(),
+                history: "monitor-history.jsonl".to_string(),
+                conditional: false,
+            }
+        );
+        assert_eq!(args.wait, Some(WaitStrategy::ReadyState));
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "monitor",
+            "prices.json",
+            "--objective",
+            "prices"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn test_extract_domain() {
-        // Test URL with protocol
+    fn test_diff_subcommand() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "diff",
+            "monday.json",
+            "tuesday.json",
+            "--json",
+        ])
+        .unwrap();
         assert_eq!(
-            CliArgs::extract_domain("https://example.com").unwrap(),
-            "example.com"
+            args.command,
+            CliCommand::Diff {
+                before: "monday.json".to_string(),
+                after: "tuesday.json".to_string(),
+                json: true
+            }
         );
+        assert!(CliArgs::try_parse_from(["smart-crawler", "diff", "monday.json"]).is_err());
+    }
+
+    #[test]
+    fn test_search_seeding() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "--objective",
+            "rust conferences",
+            "--search",
+            "searxng",
+            "--search-endpoint",
+            "https://searx.example.org/search",
+            "--search-results",
+            "5",
+        ])
+        .unwrap();
+        assert!(args.domains.is_empty());
+        let search = args.search.unwrap();
+        assert_eq!(search.provider, SearchProvider::Searxng);
         assert_eq!(
-            CliArgs::extract_domain("http://example.com/path").unwrap(),
-            "example.com"
+            search.endpoint.as_deref(),
+            Some("https://searx.example.org/search")
         );
+        assert_eq!(search.max_results, 5);
+
+        // The search query is the objective
+        assert!(CliArgs::try_parse_from(["smart-crawler", "crawl", "--search", "brave"]).is_err());
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "--preset",
+            "jobs",
+            "--search",
+            "bing"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--profile",
+            "thorough",
+            "--page-timeout",
+            "90s",
+            "--max-pages",
+            "4",
+        ])
+        .unwrap();
+        assert_eq!(args.profile.map(|profile| profile.name), Some("thorough"));
+        assert_eq!(args.wait_timeout, None);
+        let builder = args.apply_profiled_options(SmartCrawler::builder());
+        let config = builder.config();
+        assert_eq!(config.wait_timeout, Duration::from_secs(20));
+        assert_eq!(config.timeouts.navigation, Duration::from_secs(60));
+        // Options given explicitly win over the profile
+        assert_eq!(config.timeouts.page, Duration::from_secs(90));
+        assert_eq!(config.max_pages(), 4);
+        assert!(config.shadow_dom);
+        assert_eq!(config.survey.as_ref().unwrap().max_candidates, 50);
+
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--profile",
+            "fast",
+            "--wait",
+            "1s",
+            "--survey",
+        ])
+        .unwrap();
+        let builder = args.apply_profiled_options(SmartCrawler::builder());
+        let config = builder.config();
+        assert_eq!(config.max_pages(), 3);
+        assert_eq!(
+            config.wait,
+            WaitStrategy::Fixed {
+                duration: Duration::from_secs(1)
+            }
+        );
+        assert_eq!(config.wait_timeout, Duration::from_secs(5));
+        assert_eq!(config.max_sitemap_urls, 1000);
+        assert_eq!(config.survey, Some(SurveyConfig::default()));
+
+        // Prep keeps its own page count
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "prep", "example.com", "--profile", "fast"])
+                .unwrap();
+        let builder = args.apply_profiled_options(SmartCrawler::builder().prep(args.prep));
+        assert_eq!(builder.config().max_pages(), 10);
 
-        // Test domain without protocol
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "crawl",
+            "example.com",
+            "--profile",
+            "exhaustive"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_sitemap_subcommand() {
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "sitemap", "list", "example.com"]).unwrap();
         assert_eq!(
-            CliArgs::extract_domain("example.com").unwrap(),
-            "example.com"
+            args.command,
+            CliCommand::Sitemap {
+                format: SitemapFormat::Text
+            }
         );
+        assert_eq!(args.domain, "example.com");
+        // The pre-`list` form still parses
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "sitemap", "example.com", "--json"]).unwrap();
         assert_eq!(
-            CliArgs::extract_domain("  example.com  ").unwrap(),
-            "example.com"
+            args.command,
+            CliCommand::Sitemap {
+                format: SitemapFormat::Json
+            }
         );
+        assert!(CliArgs::try_parse_from(["smart-crawler", "sitemap"]).is_err());
 
-        // Test edge case - the URL crate behavior with multiple dots
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "sitemap",
+            "list",
+            "example.com",
+            "--csv",
+            "--max-sitemap-urls",
+            "50",
+            "--since",
+            "2024-01-01",
+            "--deterministic",
+        ])
+        .unwrap();
         assert_eq!(
-            CliArgs::extract_domain("invalid..domain").unwrap(),
-            "invalid..domain"
+            args.command,
+            CliCommand::Sitemap {
+                format: SitemapFormat::Csv
+            }
         );
+        assert!(args.deterministic);
+        assert_eq!(args.max_sitemap_urls, Some(50));
+        assert!(args.modified_since.is_some());
+        assert_eq!(args.wait, None);
+
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "sitemap",
+            "list",
+            "example.com",
+            "--json",
+            "--csv"
+        ])
+        .is_err());
+        // Browser options don't apply to a sitemap listing
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "sitemap",
+            "list",
+            "example.com",
+            "--wait",
+            "ready"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn test_extract_domain_error() {
-        // Test that invalid domain extraction returns error
-        let result = CliArgs::extract_domain("://invalid");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Invalid domain or URL"));
+    fn test_analyze_url_subcommand() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "analyze-url",
+            "example.com/team",
+            "--objective",
+            "team members",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.command,
+            CliCommand::AnalyzeUrl {
+                url: "https://example.com/team".to_string()
+            }
+        );
+        assert_eq!(args.domain, "example.com");
+        assert_eq!(args.objective.as_deref(), Some("team members"));
+        assert!(args.preset.is_none());
+
+        assert!(CliArgs::try_parse_from(["smart-crawler", "analyze-url"]).is_err());
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "analyze-url",
+            "https://example.com",
+            "--since",
+            "2024-01-01"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn test_cli_prep_flag() {
-        // Test that prep flag is properly parsed (this is a simplified test
-        // since we can't easily test the full CLI parsing in unit tests)
-        let args = CliArgs {
-            domain: "example.com".to_string(),
-            prep: true,
-        };
+    fn test_test_subcommand() {
+        let args =
+            CliArgs::try_parse_from(["smart-crawler", "test", "example.com", "--wait", "ready"])
+                .unwrap();
+        assert_eq!(
+            args.command,
+            CliCommand::Test {
+                url: "https://example.com/".to_string()
+            }
+        );
+        assert_eq!(args.domain, "example.com");
 
-        assert!(args.prep);
+        assert!(CliArgs::try_parse_from(["smart-crawler", "test"]).is_err());
+        // Only browser options apply
+        assert!(CliArgs::try_parse_from([
+            "smart-crawler",
+            "test",
+            "example.com",
+            "--objective",
+            "team"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_legacy_flags_still_parse() {
+        let args = CliArgs::try_parse_from([
+            "smart-crawler",
+            "--domain",
+            "example.com",
+            "--prep",
+            "--objective",
+            "team",
+        ])
+        .unwrap();
+        assert_eq!(args.command, CliCommand::Prep);
         assert_eq!(args.domain, "example.com");
+        assert_eq!(args.objective.as_deref(), Some("team"));
+
+        let args = CliArgs::try_parse_from(["smart-crawler", "--domain", "example.com"]).unwrap();
+        assert_eq!(args.command, CliCommand::Crawl);
+
+        // Legacy flags can't be mixed with a subcommand
+        assert!(
+            CliArgs::try_parse_from(["smart-crawler", "--domain", "a.com", "crawl", "b.com"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_log_level_from_verbosity_flags() {
+        assert_eq!(CliArgs::log_level(0, false), Level::INFO);
+        assert_eq!(CliArgs::log_level(1, false), Level::DEBUG);
+        assert_eq!(CliArgs::log_level(3, false), Level::TRACE);
+        assert_eq!(CliArgs::log_level(0, true), Level::ERROR);
     }
 }
@@ -0,0 +1,189 @@
+use crate::entities::{Entity, EntityKind};
+use crate::keywords::KeywordMatcher;
+use crate::network_guard::{check_url, http_client};
+use crate::page_type::PageType;
+use crate::results::ScrapedWebPage;
+use crate::utils::construct_root_url;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Paths crawled for contact objectives whether or not the homepage links them
+pub const CONTACT_PATHS: &[&str] = &["contact", "about", "imprint", "impressum"];
+
+/// Objective words that make a crawl about contact details
+const CONTACT_WORDS: &[&str] = &["contact", "email", "phone", "address", "imprint"];
+
+/// Whether an objective asks for contact details, which the contact pages
+/// fast-path finds without ranking or guessing
+pub fn is_contact_objective(objective: &str) -> bool {
+    KeywordMatcher::new(CONTACT_WORDS).matches(objective)
+}
+
+/// The page for each of `CONTACT_PATHS`: the first homepage link whose path
+/// starts with it (e.g. `/contact-us`), else the bare path on the domain,
+/// which still has to be checked with `page_exists`. The flag is true for
+/// linked pages.
+pub fn contact_page_candidates(links: &[String], domain: &str) -> Vec<(String, bool)> {
+    CONTACT_PATHS
+        .iter()
+        .map(|path| {
+            let linked = links.iter().find(|link| {
+                url::Url::parse(link).is_ok_and(|url| {
+                    url.path_segments()
+                        .and_then(|mut segments| segments.next())
+                        .is_some_and(|first| first.to_lowercase().starts_with(path))
+                })
+            });
+            match linked {
+                Some(link) => (link.clone(), true),
+                None => (format!("{}{path}", construct_root_url(domain)), false),
+            }
+        })
+        .collect()
+}
+
+/// Whether a URL answers with a success status, asked with HEAD and, for
+/// servers that don't allow HEAD, GET. Unless `allow_private`, pages on or
+/// redirecting to private networks don't exist.
+pub async fn page_exists(url: &str, allow_private: bool) -> bool {
+    if !allow_private && check_url(url).await.is_err() {
+        return false;
+    }
+    let client = http_client(Duration::from_secs(10), allow_private);
+    match client.head(url).send().await {
+        Ok(response) if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED => client
+            .get(url)
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success()),
+        Ok(response) => response.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+/// Emails, phone numbers and addresses of a site, merged across its pages
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContactInfo {
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    pub addresses: Vec<String>,
+    /// Pages the details were found on
+    pub sources: Vec<String>,
+}
+
+impl ContactInfo {
+    pub fn is_empty(&self) -> bool {
+        self.emails.is_empty() && self.phones.is_empty() && self.addresses.is_empty()
+    }
+}
+
+/// Merge the contact entities of crawled pages: contact, about and legal
+/// pages first, then the rest (usually the same footer again). Values are
+/// kept once each, in the order they were first found.
+pub fn merge_contact_info(pages: &[ScrapedWebPage]) -> ContactInfo {
+    let is_contact_page = |page: &&ScrapedWebPage| {
+        matches!(
+            page.page_type,
+            Some(PageType::Contact | PageType::About | PageType::Legal)
+        )
+    };
+    let mut info = ContactInfo::default();
+    let ordered = pages
+        .iter()
+        .filter(is_contact_page)
+        .chain(pages.iter().filter(|page| !is_contact_page(page)));
+    for page in ordered {
+        let mut found = false;
+        for entity in &page.entities {
+            found |= add_contact(&mut info, entity);
+        }
+        if found {
+            info.sources.push(page.url.clone());
+        }
+    }
+    info
+}
+
+/// Add a contact entity unless its value is known; true if it was added
+fn add_contact(info: &mut ContactInfo, entity: &Entity) -> bool {
+    let values = match entity.kind {
+        EntityKind::Email => &mut info.emails,
+        EntityKind::Phone => &mut info.phones,
+        EntityKind::Address => &mut info.addresses,
+        _ => return false,
+    };
+    if values.contains(&entity.value) {
+        return false;
+    }
+    values.push(entity.value.clone());
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+    use crate::html_parser::HtmlParser;
+    use crate::results::ContentMode;
+    use crate::storage::UrlData;
+
+    #[test]
+    fn test_contact_objective_and_candidates() {
+        assert!(is_contact_objective(
+            "Find the support email and phone number"
+        ));
+        assert!(is_contact_objective("contact details"));
+        assert!(!is_contact_objective("Find the pricing plans"));
+
+        let links = vec![
+            "https://example.com/pricing".to_string(),
+            "https://example.com/Contact-Us".to_string(),
+            "https://example.com/about/team".to_string(),
+        ];
+        assert_eq!(
+            contact_page_candidates(&links, "example.com"),
+            vec![
+                ("https://example.com/Contact-Us".to_string(), true),
+                ("https://example.com/about/team".to_string(), true),
+                ("https://example.com/imprint".to_string(), false),
+                ("https://example.com/impressum".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_contact_info() {
+        let parser = HtmlParser::new();
+        let extractor = EntityExtractor::new();
+        let page = |url: &str, page_type: PageType, html: &str| {
+            let mut url_data = UrlData::new(url.to_string());
+            url_data.set_html_data(html.to_string(), parser.parse(html), None);
+            url_data.page_type = Some(page_type);
+            ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw)
+                .with_entities(&extractor, &url_data)
+        };
+        let footer = r#"<footer><a href="mailto:hello@example.com">Email us</a></footer>"#;
+        let pages = vec![
+            page(
+                "https://example.com/",
+                PageType::Home,
+                &format!("<html><body><p>From $20</p>{footer}</body></html>"),
+            ),
+            page(
+                "https://example.com/contact",
+                PageType::Contact,
+                &format!(
+                    r#"<html><body><a href="tel:+44 20 7946 0000">Call</a>
+                    <div class="h-adr">221 Baker Street, London NW1 6XE</div>{footer}</body></html>"#
+                ),
+            ),
+        ];
+
+        let info = merge_contact_info(&pages);
+        assert_eq!(info.emails, vec!["hello@example.com"]);
+        assert_eq!(info.phones, vec!["+442079460000"]);
+        assert_eq!(info.addresses, vec!["221 Baker Street, London NW1 6XE"]);
+        assert_eq!(info.sources, vec!["https://example.com/contact"]);
+        assert!(merge_contact_info(&pages[..0]).is_empty());
+    }
+}
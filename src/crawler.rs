@@ -0,0 +1,3028 @@
+//! The crawl pipeline behind `SmartCrawler`: discovery, fetching and
+//! analysis of a domain
+
+use crate::brand_assets::{download_brand_assets, extract_brand_assets};
+use crate::browser::{
+    Browser, BrowserEngine, BrowserError, LocaleOptions, PageTimeouts, DEFAULT_WAIT_TIMEOUT,
+};
+use crate::contact::{
+    contact_page_candidates, is_contact_objective, merge_contact_info, page_exists, ContactInfo,
+};
+use crate::domain_profile::DomainProfile;
+use crate::entities::{EntityExtractor, EntityKind};
+use crate::fetch_limits::{check_fetch, FetchLimits};
+use crate::html_parser::HtmlParser;
+use crate::interaction::Interaction;
+use crate::keywords::{KeywordMatcher, KeywordTerm};
+use crate::language::{is_excluded, LanguageFilter};
+use crate::list_extraction::ListExtractor;
+use crate::network_guard::{check_host, check_url};
+use crate::observer::CrawlObserver;
+use crate::page_structure::extract_page_structure;
+use crate::page_type::{classify_page, guess_page_type, preferred_page_type, PageType};
+use crate::presets::ObjectivePreset;
+use crate::probe::{probe_paths, COMMON_PATHS};
+use crate::profiles::CrawlProfile;
+use crate::redaction::redact_url_data;
+use crate::request_blocking::{BlockCategory, BlockRules};
+use crate::results::{ContentMode, ScrapedWebPage};
+use crate::review::{review_items, ReviewItem, REVIEW_CONFIDENCE_THRESHOLD};
+use crate::site_search::find_search_form;
+use crate::sitemap::{SamplingStrategy, SitemapConfig, SitemapParser, DETERMINISTIC_SEED};
+use crate::spill::SpillConfig;
+use crate::storage::{
+    DedupConfig, FetchStatus, HtmlRetention, RetentionConfig, StoredHtml, UrlData, UrlStorage,
+};
+use crate::structured_content::{
+    fetch_structured, StructuredKind, StructuredPayload, MAX_STRUCTURED_CHARS,
+};
+use crate::survey::{survey_pages, SurveyConfig};
+use crate::template_detection::{TemplateDetector, TemplatePathStore};
+use crate::text_normalize::normalize_text;
+use crate::url_selection::{KeywordRanking, UrlSelection, UrlSelectionStrategy};
+use crate::utils::{construct_root_url, extract_objective_keywords, site_host, url_to_file_stem};
+use crate::validation::{EntityValidator, RuleStats, ValidationRule};
+use crate::wait::WaitStrategy;
+use crate::wayback::{
+    find_snapshot, snapshot_as_of, unavailable_reason, ArchivedSnapshot, WaybackConfig,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, error, info, warn};
+
+#[derive(Error, Debug)]
+pub enum CrawlError {
+    #[error("Invalid crawl configuration: {0}")]
+    InvalidConfig(String),
+    #[error("Failed to connect to the browser: {0}")]
+    WebDriver(#[from] BrowserError),
+    #[error("Refusing to crawl a private network: {0}")]
+    PrivateNetwork(String),
+}
+
+/// Everything a crawl needs, filled in through `SmartCrawlerBuilder`. It
+/// round-trips through JSON; missing fields take their defaults, and
+/// `SmartCrawlerBuilder::from_config` validates it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrawlConfig {
+    pub domain: String,
+    /// The site is only served over plain http, set for a domain given as
+    /// `http://domain`
+    pub http_only: bool,
+    /// Discover template paths instead of filtering duplicates
+    pub prep: bool,
+    pub objective: Option<String>,
+    /// Extra synonyms per objective keyword
+    pub synonyms: HashMap<String, Vec<String>>,
+    /// Extra words that rank homepage links higher, without affecting filtering
+    pub url_hints: Vec<String>,
+    /// Pages to fetch per domain, 3 by default and 10 in prep mode
+    pub max_pages: Option<usize>,
+    /// Survey more candidates over plain HTTP and render only the best
+    /// `max_pages` of them
+    pub survey: Option<SurveyConfig>,
+    pub webdriver_port: u16,
+    pub engine: BrowserEngine,
+    /// Requests the browser doesn't load, CDP engine only
+    pub block_rules: BlockRules,
+    pub max_sitemap_urls: usize,
+    pub sitemap_sampling: SamplingStrategy,
+    /// Paths checked for pages when the sitemap has none
+    pub probe_paths: Vec<String>,
+    /// Search the site for the objective with its own search form
+    pub site_search: bool,
+    /// Crawl Wayback Machine snapshots of missing and blocked pages
+    pub wayback: Option<WaybackConfig>,
+    pub modified_since: Option<DateTime<Utc>>,
+    pub languages: LanguageFilter,
+    pub locale: LocaleOptions,
+    pub bounding_boxes: bool,
+    pub interactions: Vec<Interaction>,
+    pub wait: WaitStrategy,
+    #[serde(with = "crate::wait::duration_ms")]
+    pub wait_timeout: Duration,
+    /// Limits on navigation, scripts and each page as a whole, after which
+    /// the page is marked as timed out
+    pub timeouts: PageTimeouts,
+    pub capture_api: bool,
+    pub shadow_dom: bool,
+    /// Crawl hosts on loopback, LAN and link-local addresses, which are
+    /// refused by default, as are redirects to them
+    pub allow_private: bool,
+    /// Size and content type limits checked before each page is fetched
+    pub fetch_limits: FetchLimits,
+    /// Save sibling group screenshots to this directory
+    pub visualize_groups: Option<PathBuf>,
+    /// Keep noindex pages and follow nofollow links
+    pub ignore_meta_robots: bool,
+    /// Mask emails, phone numbers and street addresses in stored pages
+    pub redact_pii: bool,
+    /// Keep unmasked PII in the extracted entities when redacting
+    pub keep_pii_entities: bool,
+    /// Entity types extracted from pages, every type when empty
+    pub entity_types: Vec<EntityKind>,
+    /// Rules extracted entities are flagged or dropped by
+    pub entity_rules: Vec<ValidationRule>,
+    /// Which raw HTML is kept, and how
+    pub html_retention: RetentionConfig,
+    /// Move finished pages to disk when they take too much memory
+    pub spill: Option<SpillConfig>,
+    /// Sample sitemaps with a fixed seed, so runs over an unchanged site
+    /// visit the same pages
+    pub deterministic: bool,
+    /// Collect the homepage's favicons, logos and share image
+    pub brand_assets: bool,
+    /// Download brand assets to this directory
+    pub assets_dir: Option<PathBuf>,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        CrawlConfig {
+            domain: String::new(),
+            http_only: false,
+            prep: false,
+            objective: None,
+            synonyms: HashMap::new(),
+            url_hints: Vec::new(),
+            max_pages: None,
+            survey: None,
+            webdriver_port: 4444,
+            engine: BrowserEngine::default(),
+            block_rules: BlockRules::default(),
+            max_sitemap_urls: SitemapConfig::default().max_urls,
+            sitemap_sampling: SamplingStrategy::default(),
+            probe_paths: COMMON_PATHS.iter().map(|path| path.to_string()).collect(),
+            site_search: false,
+            wayback: None,
+            modified_since: None,
+            languages: LanguageFilter::default(),
+            locale: LocaleOptions::default(),
+            bounding_boxes: false,
+            interactions: Vec::new(),
+            wait: WaitStrategy::default(),
+            wait_timeout: DEFAULT_WAIT_TIMEOUT,
+            timeouts: PageTimeouts::default(),
+            capture_api: false,
+            shadow_dom: false,
+            allow_private: false,
+            fetch_limits: FetchLimits::default(),
+            visualize_groups: None,
+            ignore_meta_robots: false,
+            redact_pii: false,
+            keep_pii_entities: false,
+            entity_types: Vec::new(),
+            entity_rules: Vec::new(),
+            html_retention: RetentionConfig::default(),
+            spill: None,
+            deterministic: false,
+            brand_assets: false,
+            assets_dir: None,
+        }
+    }
+}
+
+impl CrawlConfig {
+    pub fn max_pages(&self) -> usize {
+        self.max_pages.unwrap_or(if self.prep { 10 } else { 3 })
+    }
+
+    /// The domain as sitemaps, link extraction and contact pages take it:
+    /// `http://domain` for a site served over plain http
+    pub fn site(&self) -> String {
+        if self.http_only {
+            format!("http://{}", self.domain)
+        } else {
+            self.domain.clone()
+        }
+    }
+
+    pub fn root_url(&self) -> String {
+        construct_root_url(&self.site())
+    }
+
+    /// Budgets that would crawl nothing and settings that contradict each other
+    fn validate(&self) -> Result<(), String> {
+        if self.max_pages == Some(0) {
+            return Err("max_pages must be at least 1".to_string());
+        }
+        if self.webdriver_port == 0 {
+            return Err("webdriver_port must be set".to_string());
+        }
+        for (name, timeout) in [
+            ("navigation", self.timeouts.navigation),
+            ("script", self.timeouts.script),
+            ("page", self.timeouts.page),
+        ] {
+            if timeout.is_zero() {
+                return Err(format!("the {name} timeout must be longer than zero"));
+            }
+        }
+        if let Some(survey) = &self.survey {
+            if survey.max_candidates == 0 || survey.concurrency == 0 {
+                return Err(
+                    "a survey needs at least 1 candidate and 1 concurrent fetch".to_string()
+                );
+            }
+        }
+        if !self.block_rules.is_empty() && self.engine != BrowserEngine::Cdp {
+            return Err("blocking requests needs the cdp engine".to_string());
+        }
+        EntityValidator::new(&self.entity_rules)?;
+        if self.keep_pii_entities && !self.redact_pii {
+            return Err("keep_pii_entities needs redact_pii".to_string());
+        }
+        if self.assets_dir.is_some() && !self.brand_assets {
+            return Err("assets_dir needs brand_assets".to_string());
+        }
+        if let Some(as_of) = self.as_of() {
+            if self.site_search || self.survey.is_some() {
+                return Err(format!(
+                    "a crawl as of {as_of} can't use site search or a survey, they read the live site"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Day a historical crawl reads the site as of
+    pub fn as_of(&self) -> Option<NaiveDate> {
+        self.wayback.as_ref().and_then(|wayback| wayback.as_of)
+    }
+}
+
+/// Builds a `SmartCrawler`; only the domain is required
+#[derive(Clone, Default)]
+pub struct SmartCrawlerBuilder {
+    config: CrawlConfig,
+    observers: Vec<Arc<dyn CrawlObserver>>,
+    url_selection: Option<Arc<dyn UrlSelectionStrategy>>,
+}
+
+impl SmartCrawlerBuilder {
+    /// The configuration so far, before `build` normalizes and checks it
+    pub fn config(&self) -> &CrawlConfig {
+        &self.config
+    }
+
+    /// Start from a whole configuration, e.g. one read from a file; `build`
+    /// validates it like one made with the other methods
+    pub fn from_config(config: CrawlConfig) -> Self {
+        SmartCrawlerBuilder {
+            config,
+            ..SmartCrawlerBuilder::default()
+        }
+    }
+
+    /// Domain to crawl, either a bare domain or a URL on it
+    pub fn domain(mut self, domain: impl Into<String>) -> Self {
+        self.config.domain = domain.into();
+        self
+    }
+
+    pub fn objective(mut self, objective: impl Into<String>) -> Self {
+        self.config.objective = Some(objective.into());
+        self
+    }
+
+    /// Use a preset's objective, unless one is set explicitly, and its URL hints
+    pub fn preset(mut self, preset: &ObjectivePreset) -> Self {
+        self.config
+            .objective
+            .get_or_insert_with(|| preset.objective.to_string());
+        self.config
+            .url_hints
+            .extend(preset.url_hints.iter().map(|hint| hint.to_string()));
+        self
+    }
+
+    /// Use a profile's pages, waits, timeouts and survey. Settings made
+    /// after it win over it, and a prep crawl keeps its own page count, so
+    /// call it after `prep` and before the settings to keep.
+    pub fn profile(mut self, profile: &CrawlProfile) -> Self {
+        if !self.config.prep {
+            self.config.max_pages = Some(profile.max_pages);
+        }
+        self.config.wait = profile.wait.clone();
+        self.config.wait_timeout = profile.wait_timeout;
+        if let Some(timeout) = profile.navigation_timeout {
+            self.config.timeouts.navigation = timeout;
+        }
+        if let Some(timeout) = profile.page_timeout {
+            self.config.timeouts.page = timeout;
+        }
+        if let Some(max_urls) = profile.max_sitemap_urls {
+            self.config.max_sitemap_urls = max_urls;
+        }
+        if let Some(max_candidates) = profile.survey_candidates {
+            self.config.survey = Some(SurveyConfig {
+                max_candidates,
+                ..SurveyConfig::default()
+            });
+        }
+        self.config.shadow_dom |= profile.shadow_dom;
+        self
+    }
+
+    pub fn url_hints(mut self, hints: Vec<String>) -> Self {
+        self.config.url_hints = hints;
+        self
+    }
+
+    pub fn synonyms(mut self, synonyms: HashMap<String, Vec<String>>) -> Self {
+        self.config.synonyms = synonyms;
+        self
+    }
+
+    pub fn prep(mut self, prep: bool) -> Self {
+        self.config.prep = prep;
+        self
+    }
+
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.config.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Crawl in two phases: survey the title, description and headings of
+    /// more candidate pages over plain HTTP, then render only the
+    /// `max_pages` most relevant to the objective
+    pub fn survey(mut self, survey: SurveyConfig) -> Self {
+        self.config.survey = Some(survey);
+        self
+    }
+
+    pub fn webdriver_port(mut self, port: u16) -> Self {
+        self.config.webdriver_port = port;
+        self
+    }
+
+    pub fn engine(mut self, engine: BrowserEngine) -> Self {
+        self.config.engine = engine;
+        self
+    }
+
+    /// URL patterns (with `*` wildcards) the browser doesn't load, e.g.
+    /// ads and trackers. Needs the CDP engine.
+    pub fn blocked_urls(mut self, patterns: Vec<String>) -> Self {
+        self.config.block_rules.url_patterns = patterns;
+        self
+    }
+
+    /// Kinds of requests the browser doesn't load, e.g. images and fonts,
+    /// so pages render faster. Needs the CDP engine.
+    pub fn block(mut self, categories: Vec<BlockCategory>) -> Self {
+        self.config.block_rules.categories = categories;
+        self
+    }
+
+    pub fn max_sitemap_urls(mut self, max_urls: usize) -> Self {
+        self.config.max_sitemap_urls = max_urls;
+        self
+    }
+
+    /// Submit the objective keywords through the homepage's search form, if
+    /// it has one, and make the results the first candidates
+    pub fn site_search(mut self, enabled: bool) -> Self {
+        self.config.site_search = enabled;
+        self
+    }
+
+    /// When a page 404s, is blocked or fails to load, crawl its Wayback
+    /// Machine snapshot instead, flagged in the page's `archived` field
+    pub fn wayback(mut self, config: WaybackConfig) -> Self {
+        self.config.wayback = Some(config);
+        self
+    }
+
+    /// Paths checked with HEAD requests for pages to crawl when the site
+    /// has no sitemap (default `COMMON_PATHS`, empty disables probing)
+    pub fn probe_paths(mut self, paths: Vec<String>) -> Self {
+        self.config.probe_paths = paths;
+        self
+    }
+
+    pub fn sitemap_sampling(mut self, sampling: SamplingStrategy) -> Self {
+        self.config.sitemap_sampling = sampling;
+        self
+    }
+
+    pub fn modified_since(mut self, since: Option<DateTime<Utc>>) -> Self {
+        self.config.modified_since = since;
+        self
+    }
+
+    pub fn languages(mut self, languages: LanguageFilter) -> Self {
+        self.config.languages = languages;
+        self
+    }
+
+    pub fn locale(mut self, locale: LocaleOptions) -> Self {
+        self.config.locale = locale;
+        self
+    }
+
+    pub fn bounding_boxes(mut self, enabled: bool) -> Self {
+        self.config.bounding_boxes = enabled;
+        self
+    }
+
+    pub fn interactions(mut self, interactions: Vec<Interaction>) -> Self {
+        self.config.interactions = interactions;
+        self
+    }
+
+    pub fn wait(mut self, strategy: WaitStrategy, timeout: Duration) -> Self {
+        self.config.wait = strategy;
+        self.config.wait_timeout = timeout;
+        self
+    }
+
+    pub fn timeouts(mut self, timeouts: PageTimeouts) -> Self {
+        self.config.timeouts = timeouts;
+        self
+    }
+
+    pub fn capture_api(mut self, enabled: bool) -> Self {
+        self.config.capture_api = enabled;
+        self
+    }
+
+    pub fn shadow_dom(mut self, enabled: bool) -> Self {
+        self.config.shadow_dom = enabled;
+        self
+    }
+
+    /// Crawl hosts that resolve to private network addresses, e.g. an
+    /// intranet or localhost
+    pub fn allow_private(mut self, allow: bool) -> Self {
+        self.config.allow_private = allow;
+        self
+    }
+
+    /// Skip pages over a size or of content types other than pages and
+    /// documents (default: 50 MB, text, HTML, JSON, CSV and XML)
+    pub fn fetch_limits(mut self, limits: FetchLimits) -> Self {
+        self.config.fetch_limits = limits;
+        self
+    }
+
+    /// Save sibling group screenshots to a directory; implies bounding boxes
+    pub fn visualize_groups(mut self, dir: Option<PathBuf>) -> Self {
+        self.config.bounding_boxes |= dir.is_some();
+        self.config.visualize_groups = dir;
+        self
+    }
+
+    pub fn ignore_meta_robots(mut self, enabled: bool) -> Self {
+        self.config.ignore_meta_robots = enabled;
+        self
+    }
+
+    /// Mask PII in stored pages; `keep_entities` keeps it in the entities
+    pub fn redact_pii(mut self, enabled: bool, keep_entities: bool) -> Self {
+        self.config.redact_pii = enabled;
+        self.config.keep_pii_entities = keep_entities;
+        self
+    }
+
+    /// Only extract entities of these types (all of them when empty)
+    pub fn entity_types(mut self, kinds: Vec<EntityKind>) -> Self {
+        self.config.entity_types = kinds;
+        self
+    }
+
+    /// Flag or drop extracted entities that fail these rules
+    pub fn entity_rules(mut self, rules: Vec<ValidationRule>) -> Self {
+        self.config.entity_rules = rules;
+        self
+    }
+
+    pub fn html_retention(mut self, retention: RetentionConfig) -> Self {
+        self.config.html_retention = retention;
+        self
+    }
+
+    /// Keep the HTML and trees of finished pages under a memory cap by
+    /// moving the least recently used ones to disk
+    pub fn spill_to_disk(mut self, spill: SpillConfig) -> Self {
+        self.config.spill = Some(spill);
+        self
+    }
+
+    pub fn deterministic(mut self, enabled: bool) -> Self {
+        self.config.deterministic = enabled;
+        self
+    }
+
+    /// Collect brand assets; a directory downloads them and implies collecting
+    pub fn brand_assets(mut self, enabled: bool, dir: Option<PathBuf>) -> Self {
+        self.config.brand_assets = enabled || dir.is_some();
+        self.config.assets_dir = dir;
+        self
+    }
+
+    /// Register callbacks for crawl progress; observers run in registration order
+    pub fn observer(mut self, observer: impl CrawlObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// How discovered candidates beyond `max_pages` are narrowed down
+    /// (default: `KeywordRanking`)
+    pub fn url_selection(mut self, strategy: Arc<dyn UrlSelectionStrategy>) -> Self {
+        self.url_selection = Some(strategy);
+        self
+    }
+
+    pub fn build(self) -> Result<SmartCrawler, CrawlError> {
+        let mut config = self.config;
+        let site =
+            crate::utils::normalize_domain(&config.domain).map_err(CrawlError::InvalidConfig)?;
+        config.http_only |= site.starts_with("http://");
+        config.domain = site_host(&site).to_string();
+        config.validate().map_err(CrawlError::InvalidConfig)?;
+        Ok(SmartCrawler {
+            config,
+            observers: self.observers,
+            url_selection: self
+                .url_selection
+                .unwrap_or_else(|| Arc::new(KeywordRanking)),
+        })
+    }
+}
+
+/// Something that happened during a crawl, recorded in order in `CrawlResult::events`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum CrawlEvent {
+    UrlDiscovered {
+        url: String,
+        source: DiscoverySource,
+    },
+    PageScraped {
+        url: String,
+    },
+    PageFailed {
+        url: String,
+        error: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoverySource {
+    Root,
+    Homepage,
+    Sitemap,
+    /// Contact, about or imprint page crawled for a contact objective
+    ContactPage,
+    /// Common path found by probing a site without a sitemap
+    Probe,
+    /// Result of searching the site for the objective
+    SiteSearch,
+}
+
+/// Stage of a crawl, for telling where a failure happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrawlPhase {
+    /// Rendering the homepage and reading the sitemap for URLs
+    Discovery,
+    /// Surveying candidates to pick the pages to render
+    Selection,
+    /// Rendering the picked pages
+    Scrape,
+    /// Working with scraped pages: brand assets, group visualizations
+    Analyze,
+}
+
+impl CrawlPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CrawlPhase::Discovery => "discovery",
+            CrawlPhase::Selection => "selection",
+            CrawlPhase::Scrape => "scrape",
+            CrawlPhase::Analyze => "analyze",
+        }
+    }
+}
+
+impl std::fmt::Display for CrawlPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Something that went wrong during a crawl without stopping it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrawlFailure {
+    pub domain: String,
+    /// Page that failed; None when the domain as a whole did, e.g. its sitemap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub phase: CrawlPhase,
+    /// The page ran into the page timeouts rather than failing outright
+    #[serde(default)]
+    pub timed_out: bool,
+    pub error: String,
+}
+
+impl CrawlFailure {
+    /// The URL, or the domain for domain-wide failures
+    pub fn target(&self) -> &str {
+        self.url.as_deref().unwrap_or(&self.domain)
+    }
+
+    /// A page the crawl meant to keep couldn't be fetched
+    pub fn is_page_failure(&self) -> bool {
+        self.url.is_some() && matches!(self.phase, CrawlPhase::Discovery | CrawlPhase::Scrape)
+    }
+}
+
+impl std::fmt::Display for CrawlFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} failed: {}",
+            self.phase,
+            self.target(),
+            self.error
+        )
+    }
+}
+
+/// Outcome of `SmartCrawler::plan`: what `run` would crawl, in crawl order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrawlPlan {
+    pub domain: String,
+    pub max_pages: usize,
+    /// Objective keywords and URL hints with the stems and synonyms they match
+    pub keywords: Vec<KeywordTerm>,
+    pub urls: Vec<UrlSelection>,
+}
+
+/// A crawl between `SmartCrawler::start` and `SmartCrawler::finish`: the
+/// discovered URLs and the pages fetched so far
+pub struct CrawlSession {
+    run: CrawlRun,
+    /// Open while pages are being fetched
+    browser: Option<Browser>,
+    urls: Vec<String>,
+    /// Index in `urls` of the next page to fetch
+    next: usize,
+    objective_keywords: KeywordMatcher,
+}
+
+impl CrawlSession {
+    /// Discovered pages not yet fetched
+    pub fn remaining(&self) -> usize {
+        self.urls.len() - self.next
+    }
+}
+
+/// Outcome of `SmartCrawler::run`
+#[derive(Debug)]
+pub struct CrawlResult {
+    pub domain: String,
+    /// Fetched pages with their parsed and filtered trees
+    pub storage: UrlStorage,
+    /// Template paths found across pages, set in prep mode
+    pub template_paths: Option<TemplatePathStore>,
+    pub events: Vec<CrawlEvent>,
+    /// Pages picked for the crawl, in crawl order, with why each was picked
+    pub selected_urls: Vec<UrlSelection>,
+    /// Pages and steps that failed, in order
+    pub failures: Vec<CrawlFailure>,
+    /// Why the crawl stopped before fetching every page, e.g. a browser
+    /// that couldn't be restarted. Pages fetched until then are kept.
+    pub aborted: Option<String>,
+    /// Keywords of the objective, used to pick out matching page text
+    pub objective_keywords: KeywordMatcher,
+    /// Keep pages whose robots meta tag says noindex
+    pub ignore_meta_robots: bool,
+    /// Entity types extracted from the pages, every type when empty
+    pub entity_types: Vec<EntityKind>,
+    /// Rules the entities are validated against
+    pub entity_rules: Vec<ValidationRule>,
+}
+
+impl CrawlResult {
+    /// Extracts the entity types the crawl was asked for
+    pub fn entity_extractor(&self) -> EntityExtractor {
+        EntityExtractor::new().kinds(&self.entity_types)
+    }
+
+    /// Validates entities against the crawl's rules; rules that don't
+    /// compile are left out (`SmartCrawlerBuilder::build` rejects them)
+    pub fn entity_validator(&self) -> EntityValidator {
+        EntityValidator::new(&self.entity_rules).unwrap_or_default()
+    }
+
+    /// How many entities of the output pages each rule checked and failed
+    pub fn validation_stats(&self) -> Vec<RuleStats> {
+        let extractor = self.entity_extractor();
+        let validator = self.entity_validator();
+        let today = Utc::now().date_naive();
+        let mut stats = validator.empty_stats();
+        for url_data in self.output_urls() {
+            let entities = self.storage.load(url_data).extract_entities(&extractor);
+            validator.validate_counting(entities, today, &mut stats);
+        }
+        stats
+    }
+
+    /// Pages that weren't fetched, with why, e.g. a zip file
+    pub fn skipped_pages(&self) -> Vec<(&str, &str)> {
+        self.storage
+            .get_all_urls()
+            .into_iter()
+            .filter_map(|url_data| match &url_data.status {
+                FetchStatus::Skipped(reason) => Some((url_data.url.as_str(), reason.as_str())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Completed pages, without noindex pages unless meta robots are ignored
+    fn listed_urls(&self) -> Vec<&UrlData> {
+        self.storage
+            .get_completed_urls()
+            .into_iter()
+            .filter(|url_data| self.ignore_meta_robots || !url_data.robots.noindex)
+            .collect()
+    }
+
+    /// Completed pages that are analyzed for the output: listed pages
+    /// without those repeating an earlier page's content
+    pub fn output_urls(&self) -> Vec<&UrlData> {
+        self.listed_urls()
+            .into_iter()
+            .filter(|url_data| url_data.duplicate_of.is_none())
+            .collect()
+    }
+
+    /// Output pages, with deduplicated pages listed by URL only
+    pub fn pages(&self, content_mode: ContentMode) -> Vec<ScrapedWebPage> {
+        let entity_extractor = self.entity_extractor();
+        let validator = self.entity_validator();
+        self.listed_urls()
+            .into_iter()
+            .map(|url_data| {
+                if url_data.duplicate_of.is_some() {
+                    return ScrapedWebPage::from_url_data(url_data, content_mode);
+                }
+                let url_data = self.storage.load(url_data);
+                let mut page = ScrapedWebPage::from_url_data(&url_data, content_mode)
+                    .with_objective_matches(&self.objective_keywords, &url_data);
+                page.entities = validator.validate(
+                    url_data.extract_entities(&entity_extractor),
+                    Utc::now().date_naive(),
+                );
+                page
+            })
+            .collect()
+    }
+
+    /// Emails, phone numbers and addresses merged across the output pages,
+    /// contact pages first
+    pub fn contact_info(&self) -> ContactInfo {
+        merge_contact_info(&self.pages(ContentMode::Filtered))
+    }
+
+    /// The output pages merged into one record of the domain: site name,
+    /// logo, contact details and the other entities, each once
+    pub fn domain_profile(&self) -> DomainProfile {
+        DomainProfile::from_pages(&self.domain, &self.pages(ContentMode::Filtered))
+    }
+
+    pub fn duplicate_count(&self) -> usize {
+        self.storage
+            .get_domain_duplicates(&self.domain)
+            .map(|duplicates| duplicates.get_duplicate_count())
+            .unwrap_or(0)
+    }
+
+    /// Entities of the crawled pages that need a human check, with the page
+    /// screenshot when groups were visualized into `screenshot_dir`
+    pub fn review_items(&self, screenshot_dir: Option<&Path>) -> Vec<ReviewItem> {
+        let entity_extractor = self.entity_extractor();
+        let validator = self.entity_validator();
+        self.output_urls()
+            .into_iter()
+            .flat_map(|url_data| {
+                let url_data = self.storage.load(url_data);
+                let Some(tree) = &url_data.html_tree else {
+                    return Vec::new();
+                };
+                let entities = validator.validate(
+                    url_data.extract_entities(&entity_extractor),
+                    Utc::now().date_naive(),
+                );
+                let screenshot = screenshot_dir
+                    .map(|dir| dir.join(format!("{}.png", url_to_file_stem(&url_data.url))));
+                review_items(
+                    &url_data.url,
+                    Some(tree),
+                    &entities,
+                    screenshot.as_deref(),
+                    REVIEW_CONFIDENCE_THRESHOLD,
+                )
+            })
+            .collect()
+    }
+
+    /// Other sites linked from the crawled pages, most relevant first: by how
+    /// many objective keywords their links mention, then by how many pages
+    /// link to them. With an objective, sites whose links mention none of its
+    /// keywords are left out. Social networks are always left out.
+    pub fn related_domains(&self, limit: usize) -> Vec<String> {
+        let parser = HtmlParser::new();
+        // (domain, keyword score, linking pages), in order of first link
+        let mut candidates: Vec<(String, usize, usize)> = Vec::new();
+        for url_data in self.storage.get_completed_urls() {
+            let url_data = self.storage.load(url_data);
+            let Some(tree) = &url_data.html_tree else {
+                continue;
+            };
+            let mut linked_from_page = Vec::new();
+            for link in parser.extract_external_links(tree, &url_data.url) {
+                if is_social_domain(&link.domain) {
+                    continue;
+                }
+                let score = self.objective_keywords.match_count(&link.text)
+                    + self.objective_keywords.score_url(&link.url);
+                let index = match candidates.iter().position(|(d, _, _)| *d == link.domain) {
+                    Some(index) => index,
+                    None => {
+                        candidates.push((link.domain.clone(), 0, 0));
+                        candidates.len() - 1
+                    }
+                };
+                candidates[index].1 += score;
+                if !linked_from_page.contains(&link.domain) {
+                    candidates[index].2 += 1;
+                    linked_from_page.push(link.domain);
+                }
+            }
+        }
+
+        if !self.objective_keywords.is_empty() {
+            candidates.retain(|(_, score, _)| *score > 0);
+        }
+        candidates.sort_by_key(|(_, score, pages)| std::cmp::Reverse((*score, *pages)));
+        candidates
+            .into_iter()
+            .take(limit)
+            .map(|(domain, _, _)| domain)
+            .collect()
+    }
+}
+
+/// Sites linked from nearly every page (share buttons, profiles) that say
+/// nothing about what a site is related to
+const SOCIAL_DOMAINS: &[&str] = &[
+    "facebook.com",
+    "twitter.com",
+    "x.com",
+    "instagram.com",
+    "linkedin.com",
+    "youtube.com",
+    "tiktok.com",
+    "pinterest.com",
+];
+
+fn is_social_domain(domain: &str) -> bool {
+    SOCIAL_DOMAINS
+        .iter()
+        .any(|social| domain == *social || domain.ends_with(&format!(".{social}")))
+}
+
+/// A configured crawl of one domain: discovers pages from the homepage and
+/// sitemap, renders them through WebDriver, then filters domain-wide
+/// duplicates (or collects template paths in prep mode)
+#[derive(Clone)]
+pub struct SmartCrawler {
+    config: CrawlConfig,
+    observers: Vec<Arc<dyn CrawlObserver>>,
+    url_selection: Arc<dyn UrlSelectionStrategy>,
+}
+
+impl std::fmt::Debug for SmartCrawler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SmartCrawler")
+            .field("config", &self.config)
+            .field("observers", &self.observers.len())
+            .field("url_selection", &self.url_selection.name())
+            .finish()
+    }
+}
+
+impl SmartCrawler {
+    pub fn builder() -> SmartCrawlerBuilder {
+        SmartCrawlerBuilder::default()
+    }
+
+    pub fn config(&self) -> &CrawlConfig {
+        &self.config
+    }
+
+    pub async fn run(&self) -> Result<CrawlResult, CrawlError> {
+        let mut session = self.start().await?;
+        self.crawl_pages(&mut session, usize::MAX).await;
+        Ok(self.finish(session))
+    }
+
+    /// Discover the URLs to crawl, rendering only the homepage. The pages
+    /// are fetched by `crawl_pages` and the result built by `finish`; `run`
+    /// does all three at once.
+    pub async fn start(&self) -> Result<CrawlSession, CrawlError> {
+        info!("Starting SmartCrawler with domain: {}", self.config.domain);
+        self.check_network(&self.config.domain).await?;
+
+        let objective_keywords = self.objective_keywords();
+        let mut run = self.start_run(&objective_keywords);
+        let mut browser = self.connect_browser().await?;
+
+        let urls = self
+            .discover_urls(
+                &mut browser,
+                &mut run,
+                &self.url_ranking(&objective_keywords),
+            )
+            .await;
+        // The browser is still on the homepage rendered during discovery
+        self.visualize_page_groups(&mut browser, &mut run, &urls[0])
+            .await;
+        self.download_brand_assets(&mut run, &urls[0]).await;
+
+        info!("Processing all discovered URLs");
+        run.phase = CrawlPhase::Scrape;
+        Ok(CrawlSession {
+            run,
+            browser: Some(browser),
+            urls,
+            next: 0,
+            objective_keywords,
+        })
+    }
+
+    /// Phase 2: fetch up to `budget` more of the discovered pages, returning
+    /// true once every page is done. A session with pages left closes its
+    /// browser, so a paused crawl holds no WebDriver session; the next call
+    /// opens a new one.
+    pub async fn crawl_pages(&self, session: &mut CrawlSession, budget: usize) -> bool {
+        let config = &self.config;
+        let mut fetched = 0;
+        while session.next < session.urls.len() && fetched < budget {
+            let run = &mut session.run;
+            if let Some(reason) = &run.aborted {
+                warn!(
+                    "Stopping the crawl of {} with {} of {} pages fetched: {}",
+                    config.domain,
+                    run.storage.get_completed_urls().len(),
+                    session.urls.len(),
+                    reason
+                );
+                session.next = session.urls.len();
+                break;
+            }
+            let url = session.urls[session.next].clone();
+            session.next += 1;
+            if let Some(url_data) = run.storage.get_url_data(&url) {
+                if matches!(
+                    url_data.status,
+                    FetchStatus::Success | FetchStatus::Skipped(_)
+                ) {
+                    continue; // Already processed
+                }
+            }
+
+            let browser = match &mut session.browser {
+                Some(browser) => browser,
+                None => match self.connect_browser().await {
+                    Ok(browser) => session.browser.insert(browser),
+                    Err(e) => {
+                        run.browser_lost(&e);
+                        continue;
+                    }
+                },
+            };
+            fetched += 1;
+            if self.process_url(browser, run, &url, false).await.is_ok() {
+                self.visualize_page_groups(browser, run, &url).await;
+            }
+        }
+
+        if let Some(mut browser) = session.browser.take() {
+            let _ = browser.close().await;
+        }
+        session.next >= session.urls.len()
+    }
+
+    /// Phase 3: analyze the fetched pages into the crawl's result
+    pub fn finish(&self, session: CrawlSession) -> CrawlResult {
+        let config = &self.config;
+        let CrawlSession {
+            mut run,
+            urls: all_urls,
+            objective_keywords,
+            ..
+        } = session;
+
+        // Phase 3: Template analysis (prep mode) or standard duplicate analysis
+        let template_paths = if config.prep {
+            info!("Running template detection analysis in prep mode");
+            let mut combined_store = TemplatePathStore::new();
+            let template_detector = TemplateDetector::new();
+            for url_data in run.storage.get_completed_urls() {
+                if let Some(html_tree) = &run.storage.load(url_data).html_tree {
+                    let url_store = template_detector.extract_templates_with_paths(html_tree);
+                    for path in url_store.get_paths() {
+                        combined_store.add_path(path.clone());
+                    }
+                }
+            }
+            info!(
+                "Template analysis complete, found {} unique template paths",
+                combined_store.get_paths().len()
+            );
+            Some(combined_store)
+        } else {
+            info!("Running standard duplicate analysis");
+            run.storage.analyze_domain_duplicates(&config.domain);
+            run.storage
+                .filter_domain_pages(&config.domain, &TemplateDetector::new());
+            None
+        };
+
+        let result = CrawlResult {
+            domain: config.domain.clone(),
+            storage: run.storage,
+            template_paths,
+            events: run.events,
+            selected_urls: run.selected_urls,
+            failures: run.failures,
+            aborted: run.aborted,
+            objective_keywords,
+            ignore_meta_robots: config.ignore_meta_robots,
+            entity_types: config.entity_types.clone(),
+            entity_rules: config.entity_rules.clone(),
+        };
+        if !config.prep {
+            let duplicate_count = result.duplicate_count();
+            if duplicate_count > 0 {
+                info!(
+                    "Found {} duplicate node patterns for domain {}",
+                    duplicate_count, config.domain
+                );
+            } else {
+                info!(
+                    "No duplicate patterns found for domain {} (likely insufficient pages)",
+                    config.domain
+                );
+            }
+        }
+        info!("SmartCrawler finished processing {} URLs", all_urls.len());
+        result
+    }
+
+    /// Discover the URLs `run` would crawl without scraping them. Only the
+    /// homepage is rendered, to read its links.
+    pub async fn plan(&self) -> Result<CrawlPlan, CrawlError> {
+        self.check_network(&self.config.domain).await?;
+        let objective_keywords = self.objective_keywords();
+        let mut run = self.start_run(&objective_keywords);
+        let mut browser = self.connect_browser().await?;
+        let url_ranking = self.url_ranking(&objective_keywords);
+        self.discover_urls(&mut browser, &mut run, &url_ranking)
+            .await;
+        let _ = browser.close().await;
+        Ok(CrawlPlan {
+            domain: self.config.domain.clone(),
+            max_pages: self.config.max_pages(),
+            keywords: url_ranking.terms().to_vec(),
+            urls: run.selected_urls,
+        })
+    }
+
+    /// Check the browser setup: open a session with the configured engine
+    /// and render `url`, returning the page title
+    pub async fn check_browser(&self, url: &str) -> Result<String, CrawlError> {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(str::to_string))
+            .ok_or_else(|| CrawlError::InvalidConfig(format!("Invalid URL {url}")))?;
+        self.check_network(&host).await?;
+
+        let mut browser = self.connect_browser().await?;
+        let title = match browser.navigate_to(url).await {
+            Ok(()) => browser.get_page_title().await,
+            Err(e) => Err(e),
+        };
+        let _ = browser.close().await;
+        Ok(title?)
+    }
+
+    /// Scrape a single URL: no sitemap or link discovery and no domain-wide
+    /// duplicate filtering, though template values are still normalized
+    pub async fn analyze_url(&self, url: &str) -> Result<CrawlResult, CrawlError> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| CrawlError::InvalidConfig(format!("Invalid URL {url}: {e}")))?;
+        let domain = parsed
+            .host_str()
+            .ok_or_else(|| CrawlError::InvalidConfig(format!("URL has no host: {url}")))?
+            .to_string();
+        let url = parsed.to_string();
+        self.check_network(&domain).await?;
+
+        let objective_keywords = self.objective_keywords();
+        let mut run = self.start_run(&objective_keywords);
+        run.domain.clone_from(&domain);
+        run.phase = CrawlPhase::Scrape;
+        let mut browser = self.connect_browser().await?;
+
+        let url_ranking = self.url_ranking(&objective_keywords);
+        run.add_url(
+            &mut Vec::new(),
+            UrlSelection::new(url.clone(), DiscoverySource::Root, &url_ranking),
+        );
+        if self
+            .process_url(&mut browser, &mut run, &url, false)
+            .await
+            .is_ok()
+        {
+            self.visualize_page_groups(&mut browser, &mut run, &url)
+                .await;
+        }
+        let _ = browser.close().await;
+
+        run.storage
+            .filter_domain_pages(&domain, &TemplateDetector::new());
+        Ok(CrawlResult {
+            domain,
+            storage: run.storage,
+            template_paths: None,
+            events: run.events,
+            selected_urls: run.selected_urls,
+            failures: run.failures,
+            aborted: run.aborted,
+            objective_keywords,
+            ignore_meta_robots: self.config.ignore_meta_robots,
+            entity_types: self.config.entity_types.clone(),
+            entity_rules: self.config.entity_rules.clone(),
+        })
+    }
+
+    fn objective_keywords(&self) -> KeywordMatcher {
+        let mut keywords = self
+            .config
+            .objective
+            .as_deref()
+            .map(KeywordMatcher::from_objective)
+            .unwrap_or_default();
+        keywords.add_synonyms(&self.config.synonyms);
+        keywords
+    }
+
+    /// Objective keywords plus URL hints, for ordering discovered links
+    fn url_ranking(&self, objective_keywords: &KeywordMatcher) -> KeywordMatcher {
+        let mut ranking = objective_keywords.clone();
+        ranking.add_keywords(&self.config.url_hints);
+        ranking
+    }
+
+    fn start_run(&self, objective_keywords: &KeywordMatcher) -> CrawlRun {
+        let dedup_config = DedupConfig {
+            keep_keywords: objective_keywords.clone(),
+            ..DedupConfig::default()
+        };
+        let mut storage = UrlStorage::with_dedup_config(dedup_config);
+        storage.set_retention_config(self.config.html_retention.clone());
+        if let Some(spill) = &self.config.spill {
+            storage.set_spill_config(spill.clone());
+        }
+        CrawlRun {
+            domain: self.config.domain.clone(),
+            phase: CrawlPhase::Discovery,
+            storage,
+            events: Vec::new(),
+            selected_urls: Vec::new(),
+            failures: Vec::new(),
+            aborted: None,
+            observers: self.observers.clone(),
+            entity_extractor: EntityExtractor::new().kinds(&self.config.entity_types),
+            entity_validator: EntityValidator::new(&self.config.entity_rules).unwrap_or_default(),
+        }
+    }
+
+    /// Refuse a domain on a private network unless `allow_private` is set,
+    /// before its sitemap or pages are requested
+    async fn check_network(&self, host: &str) -> Result<(), CrawlError> {
+        if self.config.allow_private {
+            return Ok(());
+        }
+        check_host(host).await.map_err(|e| {
+            CrawlError::PrivateNetwork(format!("{e}, pass --allow-private to crawl it"))
+        })
+    }
+
+    async fn connect_browser(&self) -> Result<Browser, BrowserError> {
+        let config = &self.config;
+        let mut browser = Browser::with_locale(config.webdriver_port, config.locale.clone());
+        browser.set_engine(config.engine);
+        browser.set_block_rules(config.block_rules.clone());
+        browser.set_capture_bounding_boxes(config.bounding_boxes);
+        browser.set_interactions(config.interactions.clone());
+        browser.set_wait_strategy(config.wait.clone(), config.wait_timeout);
+        browser.set_timeouts(config.timeouts);
+        browser.set_capture_api_responses(config.capture_api);
+        browser.set_flatten_shadow_dom(config.shadow_dom);
+        browser.set_block_private_networks(!config.allow_private);
+        browser.connect().await?;
+        info!("Connected to the browser with {}", config.engine.as_str());
+        Ok(browser)
+    }
+
+    /// Phase 1: collect up to `max_pages` URLs from the homepage links, then the
+    /// sitemap, or common paths when there's no sitemap. Returns them in
+    /// discovery order, which is also the crawl order.
+    async fn discover_urls(
+        &self,
+        browser: &mut Browser,
+        run: &mut CrawlRun,
+        url_ranking: &KeywordMatcher,
+    ) -> Vec<String> {
+        let config = &self.config;
+        let domain = &config.domain;
+        let site = config.site();
+        let max_urls_per_domain = config.max_pages();
+        let parser = HtmlParser::new();
+
+        let root_url = config.root_url();
+        let mut urls: Vec<String> = Vec::new();
+        run.add_url(
+            &mut urls,
+            UrlSelection::new(root_url.clone(), DiscoverySource::Root, url_ranking),
+        );
+
+        info!("Starting URL discovery for domains");
+        // The live sitemap and site don't describe the past, so historical
+        // crawls only follow the links of archived pages
+        let live = match config.as_of() {
+            Some(as_of) => {
+                info!(
+                    "Crawling {} as of {} from the Wayback Machine, following archived links only",
+                    domain, as_of
+                );
+                false
+            }
+            None => true,
+        };
+        let mut excluded_language_prefixes: Vec<String> = Vec::new();
+        let mut homepage_links: Vec<String> = Vec::new();
+
+        // With a survey, more pages are discovered than rendered and the
+        // survey picks which ones the browser gets
+        let candidate_limit = config
+            .survey
+            .as_ref()
+            .map_or(max_urls_per_domain, |survey| {
+                survey.max_candidates.max(max_urls_per_domain)
+            });
+        let mut candidates: Vec<UrlSelection> = Vec::new();
+        let add_candidate = |candidates: &mut Vec<UrlSelection>, candidate: UrlSelection| {
+            if candidate.url == root_url || candidates.iter().any(|c| c.url == candidate.url) {
+                return false;
+            }
+            candidates.push(candidate);
+            true
+        };
+
+        if urls.len() < candidate_limit {
+            info!(
+                "Domain {} has {} URL(s), searching for more (max: {})...",
+                domain,
+                urls.len(),
+                candidate_limit
+            );
+
+            match self.process_url(browser, run, &root_url, true).await {
+                Ok(html_source) => {
+                    let alternates = parser.extract_hreflang_alternates(&html_source, &site);
+                    excluded_language_prefixes = config.languages.excluded_prefixes(&alternates);
+                    let links = if config.ignore_meta_robots {
+                        parser.extract_links(&html_source, &site)
+                    } else if parser.meta_robots(&html_source).nofollow {
+                        info!(
+                            "Homepage of {} is nofollow, not following its links",
+                            domain
+                        );
+                        Vec::new()
+                    } else {
+                        parser.extract_followed_links(&html_source, &site)
+                    };
+                    let mut additional_urls = config
+                        .languages
+                        .filter_urls(links, &excluded_language_prefixes);
+                    // Visit links that mention the objective first, then pages
+                    // of the type the objective is best served by
+                    let preferred = preferred_page_type(config.objective.as_deref());
+                    additional_urls.sort_by_cached_key(|url| {
+                        let is_preferred = preferred.is_some() && guess_page_type(url) == preferred;
+                        std::cmp::Reverse((url_ranking.score_url(url), is_preferred))
+                    });
+                    homepage_links.clone_from(&additional_urls);
+
+                    if config.site_search && live {
+                        let results = self
+                            .search_site(browser, run, &html_source, &homepage_links)
+                            .await;
+                        let results = config
+                            .languages
+                            .filter_urls(results, &excluded_language_prefixes);
+                        let mut added_count = 0;
+                        for url in results {
+                            if urls.len() + candidates.len() >= candidate_limit {
+                                break;
+                            }
+                            let candidate =
+                                UrlSelection::new(url, DiscoverySource::SiteSearch, url_ranking);
+                            if add_candidate(&mut candidates, candidate) {
+                                added_count += 1;
+                            }
+                        }
+                        info!("Found {} pages by searching domain {}", added_count, domain);
+                    }
+
+                    let mut added_count = 0;
+                    for additional_url in additional_urls {
+                        if urls.len() + candidates.len() >= candidate_limit {
+                            break;
+                        }
+                        let mut candidate = UrlSelection::new(
+                            additional_url,
+                            DiscoverySource::Homepage,
+                            url_ranking,
+                        );
+                        candidate.preferred_page_type =
+                            preferred.is_some() && guess_page_type(&candidate.url) == preferred;
+                        if add_candidate(&mut candidates, candidate) {
+                            added_count += 1;
+                        }
+                    }
+                    info!(
+                        "Found {} additional URLs for domain {}",
+                        added_count, domain
+                    );
+                }
+                Err(e) => error!("Failed to extract links from {}: {}", root_url, e),
+            }
+        }
+
+        // Fill remaining slots from the sitemap when the homepage didn't link to enough pages
+        let mut sitemap_missing = false;
+        if urls.len() + candidates.len() < candidate_limit && config.max_sitemap_urls > 0 && live {
+            let sitemap_parser = SitemapParser::new(SitemapConfig {
+                max_urls: config.max_sitemap_urls,
+                sampling: config.sitemap_sampling,
+                modified_since: config.modified_since,
+                seed: config.deterministic.then_some(DETERMINISTIC_SEED),
+                allow_private: config.allow_private,
+                ..SitemapConfig::default()
+            });
+
+            match sitemap_parser.get_all_urls(&site).await {
+                Ok(sitemap_urls) => {
+                    sitemap_missing = sitemap_urls.is_empty();
+                    let mut added_count = 0;
+                    for sitemap_url in sitemap_urls {
+                        if urls.len() + candidates.len() >= candidate_limit {
+                            break;
+                        }
+                        if is_excluded(&sitemap_url.loc, &excluded_language_prefixes) {
+                            continue;
+                        }
+                        let mut candidate = UrlSelection::new(
+                            sitemap_url.loc,
+                            DiscoverySource::Sitemap,
+                            url_ranking,
+                        );
+                        candidate.sitemap_priority = sitemap_url.priority;
+                        if add_candidate(&mut candidates, candidate) {
+                            added_count += 1;
+                        }
+                    }
+                    info!(
+                        "Found {} URLs in sitemap for domain {}",
+                        added_count, domain
+                    );
+                }
+                Err(e) => {
+                    sitemap_missing = true;
+                    error!("Failed to read sitemap for {}: {}", domain, e);
+                    run.record_failure(CrawlFailure {
+                        domain: domain.clone(),
+                        url: None,
+                        phase: CrawlPhase::Discovery,
+                        timed_out: false,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        // Without a sitemap, JS-heavy homepages may link to few pages: check
+        // whether the usual ones exist
+        if sitemap_missing
+            && urls.len() + candidates.len() < candidate_limit
+            && !config.probe_paths.is_empty()
+        {
+            let mut added_count = 0;
+            for url in probe_paths(&root_url, &config.probe_paths, config.allow_private).await {
+                if urls.len() + candidates.len() >= candidate_limit {
+                    break;
+                }
+                if is_excluded(&url, &excluded_language_prefixes) {
+                    continue;
+                }
+                let candidate = UrlSelection::new(url, DiscoverySource::Probe, url_ranking);
+                if add_candidate(&mut candidates, candidate) {
+                    added_count += 1;
+                }
+            }
+            info!(
+                "Found {} pages by probing common paths for domain {}",
+                added_count, domain
+            );
+        }
+
+        let slots = max_urls_per_domain.saturating_sub(urls.len());
+        if candidates.len() > slots {
+            if let Some(survey) = config.survey.as_ref().filter(|_| live) {
+                self.survey_candidates(run, &mut candidates, survey, url_ranking)
+                    .await;
+            }
+            info!(
+                "Selecting {} of {} candidate pages by {}",
+                slots,
+                candidates.len(),
+                self.url_selection.name()
+            );
+            candidates = self.url_selection.select(candidates, slots);
+        }
+        for candidate in candidates {
+            run.add_url(&mut urls, candidate);
+        }
+
+        // Contact objectives always get the site's contact, about and imprint
+        // pages, on top of max_pages: they're predictable and cheap to mine
+        if config
+            .objective
+            .as_deref()
+            .is_some_and(is_contact_objective)
+        {
+            let mut added_count = 0;
+            for (url, linked) in contact_page_candidates(&homepage_links, &site) {
+                if urls.contains(&url)
+                    || (!linked && (!live || !page_exists(&url, config.allow_private).await))
+                {
+                    continue;
+                }
+                let selection = UrlSelection::new(url, DiscoverySource::ContactPage, url_ranking);
+                if run.add_url(&mut urls, selection) {
+                    added_count += 1;
+                }
+            }
+            info!("Found {} contact pages for domain {}", added_count, domain);
+        }
+
+        // The root URL, then homepage links by objective relevance and page
+        // type, then sitemap URLs or probed paths, then contact pages
+        urls
+    }
+
+    /// Submit the objective keywords through the homepage's search form and
+    /// return the links of the results page that the homepage doesn't have
+    /// (its navigation and footer), leaving out further search pages
+    async fn search_site(
+        &self,
+        browser: &mut Browser,
+        run: &mut CrawlRun,
+        homepage_html: &str,
+        homepage_links: &[String],
+    ) -> Vec<String> {
+        let config = &self.config;
+        let keywords = config
+            .objective
+            .as_deref()
+            .map(extract_objective_keywords)
+            .unwrap_or_default();
+        if keywords.is_empty() {
+            info!("Site search needs objective keywords, skipping it");
+            return Vec::new();
+        }
+        let root_url = config.root_url();
+        let Some(form) = find_search_form(homepage_html, &root_url) else {
+            info!("No search form found on {}", root_url);
+            return Vec::new();
+        };
+
+        let search_url = form.url(&keywords.join(" "));
+        info!("Searching {} for \"{}\"", config.domain, keywords.join(" "));
+        let html = match browser.navigate_to(&search_url).await {
+            Ok(()) => browser.get_html_source().await,
+            Err(e) => Err(e),
+        };
+        let html = match html {
+            Ok(html) => html,
+            Err(e) => {
+                warn!("Failed to search {}: {}", config.domain, e);
+                run.record_failure(CrawlFailure {
+                    domain: config.domain.clone(),
+                    url: Some(search_url),
+                    phase: CrawlPhase::Discovery,
+                    timed_out: browser.timed_out(),
+                    error: e.to_string(),
+                });
+                return Vec::new();
+            }
+        };
+
+        HtmlParser::new()
+            .extract_followed_links(&html, &config.site())
+            .into_iter()
+            .filter(|link| {
+                !homepage_links.contains(link)
+                    && *link != root_url
+                    && link.split(['?', '#']).next() != Some(form.action.as_str())
+                    && guess_page_type(link) != Some(PageType::Search)
+            })
+            .collect()
+    }
+
+    /// Phase one of a two-phase crawl: read the title, description and
+    /// headings of every candidate over plain HTTP and score them against
+    /// the objective, for the URL selection strategy to rank by
+    async fn survey_candidates(
+        &self,
+        run: &mut CrawlRun,
+        candidates: &mut [UrlSelection],
+        survey: &SurveyConfig,
+        url_ranking: &KeywordMatcher,
+    ) {
+        if url_ranking.is_empty() {
+            info!("Survey needs an objective or URL hints to rank pages, skipping it");
+            return;
+        }
+        let urls: Vec<String> = candidates
+            .iter()
+            .map(|candidate| candidate.url.clone())
+            .collect();
+        let results = survey_pages(
+            &urls,
+            survey,
+            &self.config.fetch_limits,
+            self.config.allow_private,
+        )
+        .await;
+        info!(
+            "Surveyed {} of {} candidate pages for domain {}",
+            results.surveys.len(),
+            candidates.len(),
+            self.config.domain
+        );
+        for (url, error) in results.failures {
+            run.record_failure(CrawlFailure {
+                domain: self.config.domain.clone(),
+                url: Some(url),
+                phase: CrawlPhase::Selection,
+                timed_out: false,
+                error,
+            });
+        }
+        for candidate in candidates {
+            candidate.survey_score = results
+                .surveys
+                .iter()
+                .find(|survey| survey.url == candidate.url)
+                .map(|survey| survey.score(url_ranking));
+        }
+    }
+
+    /// Render a URL and store its parsed tree, or its payload for JSON/CSV URLs.
+    /// Returns the HTML source when `return_html` is set.
+    async fn process_url(
+        &self,
+        browser: &mut Browser,
+        run: &mut CrawlRun,
+        url: &str,
+        return_html: bool,
+    ) -> Result<String, String> {
+        info!("Processing URL: {}", url);
+        run.set_status(url, FetchStatus::InProgress);
+        // Subdomains and data endpoints may resolve elsewhere than the domain
+        if !self.config.allow_private {
+            if let Err(e) = check_url(url).await {
+                error!("Failed to process {}: {}", url, e);
+                run.set_status(url, FetchStatus::Failed(e.clone()));
+                run.page_failed(url, &e, false);
+                return Err(e);
+            }
+        }
+        // Archived captures are fetched whatever the live page is now
+        if self.config.as_of().is_none() {
+            if let Some(reason) =
+                check_fetch(url, &self.config.fetch_limits, self.config.allow_private).await
+            {
+                info!("Skipping {}: {}", url, reason);
+                run.set_status(url, FetchStatus::Skipped(reason.clone()));
+                return Err(format!("Skipped: {reason}"));
+            }
+        }
+
+        let page_timeout = self.config.timeouts.page;
+        let snapshot = self.snapshot_as_of(url).await;
+        let location = match &snapshot {
+            Ok(Some(snapshot)) => snapshot.raw_url(),
+            _ => url.to_string(),
+        };
+        let mut restarted = false;
+        let (mut result, timed_out) = loop {
+            if let Err(e) = &snapshot {
+                break (Err(e.clone()), false);
+            }
+            let (result, timed_out) = match tokio::time::timeout(
+                page_timeout,
+                Self::fetch_page(
+                    browser,
+                    &mut run.storage,
+                    url,
+                    &location,
+                    return_html,
+                    &self.config,
+                ),
+            )
+            .await
+            {
+                Ok(result) => {
+                    let timed_out = result.is_err() && browser.timed_out();
+                    (result, timed_out)
+                }
+                Err(_) => (Err(format!("Page timed out after {page_timeout:?}")), true),
+            };
+            // A crashed browser would fail every page after this one, so the
+            // session is recreated and the page tried once more
+            if result.is_err() && !timed_out && !restarted && !browser.is_alive().await {
+                warn!(
+                    "Browser session lost while processing {}, restarting it",
+                    url
+                );
+                restarted = true;
+                match browser.reconnect().await {
+                    Ok(()) => continue,
+                    Err(e) => run.browser_lost(&e),
+                }
+            }
+            break (result, timed_out);
+        };
+        if !timed_out {
+            if let Some(archived) = self
+                .fetch_archived(browser, run, url, &result, return_html)
+                .await
+            {
+                result = archived;
+            }
+        }
+        match &result {
+            Err(e) if timed_out => {
+                warn!("Timed out processing {}: {}", url, e);
+                run.set_status(url, FetchStatus::TimedOut);
+                run.page_failed(url, e, true);
+            }
+            Ok(_) => {
+                info!("Successfully processed {}", url);
+                if let Ok(Some(snapshot)) = snapshot {
+                    if let Some(url_data) = run.storage.get_url_data_mut(url) {
+                        url_data.archived = Some(snapshot);
+                    }
+                }
+                if self.config.brand_assets && url == self.config.root_url() {
+                    if let Some(url_data) = run.storage.get_url_data_mut(url) {
+                        let assets = url_data.html().map(|html| extract_brand_assets(&html, url));
+                        url_data.brand_assets = assets.unwrap_or_default();
+                    }
+                }
+                if self.config.redact_pii {
+                    if let Some(url_data) = run.storage.get_url_data_mut(url) {
+                        redact_url_data(
+                            &run.entity_extractor,
+                            url_data,
+                            self.config.keep_pii_entities,
+                        );
+                    }
+                }
+                run.set_status(url, FetchStatus::Success);
+                run.page_scraped(url);
+            }
+            Err(e) => {
+                error!("Failed to process {}: {}", url, e);
+                if self.config.html_retention.keep_html != HtmlRetention::None {
+                    self.keep_failed_html(browser, run, url).await;
+                }
+                run.set_status(url, FetchStatus::Failed(e.clone()));
+                run.page_failed(url, e, false);
+            }
+        }
+        // A command that timed out may still be running in the browser, so
+        // the session is replaced before the next page
+        if timed_out || browser.timed_out() {
+            if let Err(e) = browser.reconnect().await {
+                run.browser_lost(&e);
+            }
+        }
+        result
+    }
+
+    /// In a historical crawl, the capture of `url` to load instead of the
+    /// live page; an error when it wasn't captured by then
+    async fn snapshot_as_of(&self, url: &str) -> Result<Option<ArchivedSnapshot>, String> {
+        let Some(wayback) = &self.config.wayback else {
+            return Ok(None);
+        };
+        let Some(as_of) = wayback.as_of else {
+            return Ok(None);
+        };
+        match snapshot_as_of(url, as_of, wayback, self.config.allow_private).await? {
+            Some(snapshot) => Ok(Some(snapshot)),
+            None => Err(format!("No Wayback Machine capture on or before {as_of}")),
+        }
+    }
+
+    /// With `wayback` set, load the latest (or configured) Wayback Machine
+    /// snapshot of a page that failed to load or rendered as an error or
+    /// block page. Returns the snapshot's result, None to keep the live one.
+    async fn fetch_archived(
+        &self,
+        browser: &mut Browser,
+        run: &mut CrawlRun,
+        url: &str,
+        live: &Result<String, String>,
+        return_html: bool,
+    ) -> Option<Result<String, String>> {
+        let wayback = self.config.wayback.as_ref()?;
+        if wayback.as_of.is_some() || run.aborted.is_some() {
+            return None;
+        }
+        let reason = match live {
+            Err(e) => e.clone(),
+            Ok(_) => {
+                let url_data = run.storage.get_url_data(url)?;
+                unavailable_reason(url_data.http_status, url_data.title.as_deref())?
+            }
+        };
+        let snapshot = match find_snapshot(url, &reason, wayback, self.config.allow_private).await {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => {
+                info!("{} is unavailable ({}) and was never archived", url, reason);
+                return None;
+            }
+            Err(e) => {
+                warn!("{} is unavailable ({}): {}", url, reason, e);
+                return None;
+            }
+        };
+
+        info!(
+            "{} is unavailable ({}), using its snapshot from {}",
+            url,
+            reason,
+            snapshot.timestamp.format("%Y-%m-%d")
+        );
+        let page_timeout = self.config.timeouts.page;
+        let location = snapshot.raw_url();
+        let result = tokio::time::timeout(
+            page_timeout,
+            Self::fetch_page(
+                browser,
+                &mut run.storage,
+                url,
+                &location,
+                return_html,
+                &self.config,
+            ),
+        )
+        .await
+        .unwrap_or_else(|_| Err(format!("Page timed out after {page_timeout:?}")));
+        match result {
+            Ok(html) => {
+                if let Some(url_data) = run.storage.get_url_data_mut(url) {
+                    url_data.archived = Some(snapshot);
+                }
+                Some(Ok(html))
+            }
+            Err(e) => {
+                warn!("Failed to load the snapshot of {}: {}", url, e);
+                None
+            }
+        }
+    }
+
+    /// Store whatever the browser shows for a failed page, to debug it
+    async fn keep_failed_html(&self, browser: &mut Browser, run: &mut CrawlRun, url: &str) {
+        let Ok(html_source) = browser.get_html_source().await else {
+            return;
+        };
+        if let Some(url_data) = run.storage.get_url_data_mut(url) {
+            url_data.html_source = Some(StoredHtml::Plain(html_source));
+            if self.config.redact_pii {
+                redact_url_data(&run.entity_extractor, url_data, false);
+            }
+        }
+    }
+
+    /// Load `location`, the page itself or a snapshot of it, and store it as `url`
+    async fn fetch_page(
+        browser: &mut Browser,
+        storage: &mut UrlStorage,
+        url: &str,
+        location: &str,
+        return_html: bool,
+        config: &CrawlConfig,
+    ) -> Result<String, String> {
+        // Data endpoints skip the browser; CSV files would be downloaded, not rendered
+        if StructuredKind::from_url(url).is_some() {
+            let (payload, encoding) =
+                fetch_structured(location, &config.fetch_limits, config.allow_private).await?;
+            return Ok(Self::store_structured_payload(
+                storage,
+                url,
+                payload,
+                Some(encoding.to_string()),
+            ));
+        }
+
+        browser
+            .navigate_to(location)
+            .await
+            .map_err(|e| format!("Failed to navigate: {e}"))?;
+        debug!("Successfully navigated to {}", location);
+
+        let http_status = browser.document_status().await.ok().flatten();
+        let content_type = browser.document_content_type().await.unwrap_or_default();
+        let encoding = browser
+            .document_charset()
+            .await
+            .ok()
+            .filter(|charset| !charset.is_empty());
+        if let Some(kind) = StructuredKind::from_content_type(&content_type) {
+            let body = browser
+                .body_text()
+                .await
+                .map_err(|e| format!("Failed to read {kind:?} body: {e}"))?;
+            let payload =
+                StructuredPayload::from_body(&content_type, kind, &body, MAX_STRUCTURED_CHARS)?;
+            return Ok(Self::store_structured_payload(
+                storage, url, payload, encoding,
+            ));
+        }
+
+        let html_source = browser
+            .get_html_source()
+            .await
+            .map_err(|e| format!("Failed to get HTML source: {e}"))?;
+        let parser = HtmlParser::new();
+        let title = browser
+            .get_page_title()
+            .await
+            .ok()
+            .map(|title| normalize_text(&title));
+        let html_tree = parser.parse(&html_source);
+        let language = parser.detect_language(&html_source);
+        let robots = parser.meta_robots(&html_source);
+        let structure = extract_page_structure(&html_source, url);
+        let page_type = classify_page(url, &html_tree);
+        let detected_language = crate::language::detect_text_language(&html_tree.text_content());
+        let api_responses = browser.collect_api_responses().await.unwrap_or_else(|e| {
+            error!("Failed to collect API responses from {}: {}", url, e);
+            Vec::new()
+        });
+        let blocked_requests = browser.blocked_requests();
+        if let Some(blocked) = blocked_requests {
+            debug!("Blocked {} requests on {}", blocked, url);
+        }
+
+        if let Some(url_data) = storage.get_url_data_mut(url) {
+            url_data.set_html_data(html_source.clone(), html_tree, title);
+            url_data.content_type = (!content_type.is_empty()).then_some(content_type);
+            url_data.http_status = http_status;
+            url_data.encoding = encoding;
+            url_data.language = language;
+            url_data.detected_language = detected_language;
+            url_data.robots = robots;
+            url_data.structure = structure;
+            url_data.page_type = Some(page_type);
+            url_data.api_responses = api_responses;
+            url_data.blocked_requests = blocked_requests;
+        }
+
+        if return_html {
+            Ok(html_source)
+        } else {
+            Ok(String::new())
+        }
+    }
+
+    /// Record a JSON/CSV payload in place of an HTML tree
+    fn store_structured_payload(
+        storage: &mut UrlStorage,
+        url: &str,
+        payload: StructuredPayload,
+        encoding: Option<String>,
+    ) -> String {
+        info!(
+            "Captured {:?} payload from {} ({})",
+            payload.kind, url, payload.content_type
+        );
+        if let Some(url_data) = storage.get_url_data_mut(url) {
+            url_data.set_structured_data(payload);
+            url_data.encoding = encoding;
+        }
+        String::new()
+    }
+
+    /// Save the brand assets found on a page to the assets directory, when set
+    async fn download_brand_assets(&self, run: &mut CrawlRun, url: &str) {
+        let Some(dir) = &self.config.assets_dir else {
+            return;
+        };
+        let Some(url_data) = run.storage.get_url_data_mut(url) else {
+            return;
+        };
+        let result =
+            download_brand_assets(&mut url_data.brand_assets, dir, self.config.allow_private).await;
+        match result {
+            Ok(()) => info!(
+                "Saved {} brand assets of {} to {}",
+                url_data
+                    .brand_assets
+                    .iter()
+                    .filter(|asset| asset.path.is_some())
+                    .count(),
+                url,
+                dir.display()
+            ),
+            Err(e) => {
+                error!("Failed to save brand assets of {}: {}", url, e);
+                run.analysis_failed(url, format!("Failed to save brand assets: {e}"));
+            }
+        }
+    }
+
+    /// Outline the sibling groups of the page currently loaded in the browser
+    /// and save the screenshot and groups JSON, when enabled
+    async fn visualize_page_groups(&self, browser: &mut Browser, run: &mut CrawlRun, url: &str) {
+        let Some(dir) = &self.config.visualize_groups else {
+            return;
+        };
+        let Some(lists) = run.storage.get_url_data(url).and_then(|url_data| {
+            let url_data = run.storage.load(url_data);
+            let tree = url_data.html_tree.as_ref()?;
+            Some(ListExtractor::new().extract(tree, url))
+        }) else {
+            return;
+        };
+        match browser
+            .save_group_visualization(&lists, dir, &url_to_file_stem(url))
+            .await
+        {
+            Ok(path) => info!(
+                "Saved {} sibling groups for {} to {}",
+                lists.len(),
+                url,
+                path.display()
+            ),
+            Err(e) => {
+                error!("Failed to visualize sibling groups for {}: {}", url, e);
+                run.analysis_failed(url, format!("Failed to visualize sibling groups: {e}"));
+            }
+        }
+    }
+}
+
+/// Mutable state of one `SmartCrawler::run`
+struct CrawlRun {
+    domain: String,
+    /// Phase the run is in, recorded with page failures
+    phase: CrawlPhase,
+    storage: UrlStorage,
+    events: Vec<CrawlEvent>,
+    selected_urls: Vec<UrlSelection>,
+    failures: Vec<CrawlFailure>,
+    /// Set when the browser is gone for good; no more pages are fetched
+    aborted: Option<String>,
+    observers: Vec<Arc<dyn CrawlObserver>>,
+    /// Extracts the configured entity types and redacts PII
+    entity_extractor: EntityExtractor,
+    entity_validator: EntityValidator,
+}
+
+impl CrawlRun {
+    /// Track a newly discovered URL in discovery order, returning false if it
+    /// was already known
+    fn add_url(&mut self, urls: &mut Vec<String>, selection: UrlSelection) -> bool {
+        if urls.contains(&selection.url) {
+            return false;
+        }
+        let url = selection.url.clone();
+        let source = selection.source;
+        urls.push(url.clone());
+        self.storage.add_url(url.clone());
+        for observer in &self.observers {
+            observer.on_url_discovered(&url, source);
+        }
+        self.events.push(CrawlEvent::UrlDiscovered { url, source });
+        self.selected_urls.push(selection);
+        true
+    }
+
+    fn page_scraped(&mut self, url: &str) {
+        if let Some(url_data) = self.storage.get_url_data(url) {
+            for observer in &self.observers {
+                observer.on_page_scraped(url_data);
+            }
+            if !self.observers.is_empty() {
+                let entities = self.entity_validator.validate(
+                    self.storage
+                        .load(url_data)
+                        .extract_entities(&self.entity_extractor),
+                    Utc::now().date_naive(),
+                );
+                if !entities.is_empty() {
+                    for observer in &self.observers {
+                        observer.on_entities_extracted(url, &entities);
+                    }
+                }
+            }
+        }
+        self.events.push(CrawlEvent::PageScraped {
+            url: url.to_string(),
+        });
+    }
+
+    /// Record a page that failed in the current phase
+    fn page_failed(&mut self, url: &str, error: &str, timed_out: bool) {
+        self.events.push(CrawlEvent::PageFailed {
+            url: url.to_string(),
+            error: error.to_string(),
+        });
+        self.record_failure(CrawlFailure {
+            domain: self.domain.clone(),
+            url: Some(url.to_string()),
+            phase: self.phase,
+            timed_out,
+            error: error.to_string(),
+        });
+    }
+
+    /// Record a step after scraping that failed for a page, which itself
+    /// was scraped fine
+    fn analysis_failed(&mut self, url: &str, error: String) {
+        self.record_failure(CrawlFailure {
+            domain: self.domain.clone(),
+            url: Some(url.to_string()),
+            phase: CrawlPhase::Analyze,
+            timed_out: false,
+            error,
+        });
+    }
+
+    /// The browser session couldn't be restarted, so the crawl stops with
+    /// the pages fetched so far
+    fn browser_lost(&mut self, e: &BrowserError) {
+        error!("Failed to restart the browser session: {}", e);
+        let error = format!("Browser session couldn't be restarted: {e}");
+        self.record_failure(CrawlFailure {
+            domain: self.domain.clone(),
+            url: None,
+            phase: self.phase,
+            timed_out: false,
+            error: error.clone(),
+        });
+        self.aborted = Some(error);
+    }
+
+    fn record_failure(&mut self, failure: CrawlFailure) {
+        for observer in &self.observers {
+            observer.on_error(failure.target(), &failure.error);
+        }
+        self.failures.push(failure);
+    }
+
+    fn set_status(&mut self, url: &str, status: FetchStatus) {
+        self.storage.update_status(url, status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webdriver_mock::{MockPage, MockWebDriver};
+
+    #[test]
+    fn test_builder_normalizes_domain() {
+        let crawler = SmartCrawler::builder()
+            .domain("https://www.example.com/pricing")
+            .objective("pricing")
+            .build()
+            .unwrap();
+        assert_eq!(crawler.config().domain, "www.example.com");
+        assert_eq!(crawler.config().objective.as_deref(), Some("pricing"));
+        assert_eq!(crawler.config().max_pages(), 3);
+        assert_eq!(crawler.config().webdriver_port, 4444);
+        assert_eq!(crawler.config().root_url(), "https://www.example.com/");
+
+        let http = SmartCrawler::builder()
+            .domain("http://legacy.example.com/")
+            .build()
+            .unwrap();
+        assert_eq!(http.config().domain, "legacy.example.com");
+        assert!(http.config().http_only);
+        assert_eq!(http.config().root_url(), "http://legacy.example.com/");
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(matches!(
+            SmartCrawler::builder().build(),
+            Err(CrawlError::InvalidConfig(_))
+        ));
+        assert!(SmartCrawler::builder()
+            .domain("example.com")
+            .max_pages(0)
+            .build()
+            .is_err());
+
+        let invalid = |builder: SmartCrawlerBuilder| match builder.domain("example.com").build() {
+            Err(CrawlError::InvalidConfig(message)) => message,
+            _ => panic!("expected an invalid configuration"),
+        };
+        assert_eq!(
+            invalid(SmartCrawler::builder().timeouts(PageTimeouts {
+                page: Duration::ZERO,
+                ..PageTimeouts::default()
+            })),
+            "the page timeout must be longer than zero"
+        );
+        assert!(invalid(SmartCrawler::builder().survey(SurveyConfig {
+            concurrency: 0,
+            ..SurveyConfig::default()
+        }))
+        .contains("concurrent"));
+        assert!(invalid(
+            SmartCrawler::builder()
+                .site_search(true)
+                .wayback(WaybackConfig {
+                    as_of: NaiveDate::from_ymd_opt(2023, 6, 1),
+                    ..WaybackConfig::default()
+                })
+        )
+        .contains("live site"));
+
+        let prep = SmartCrawler::builder()
+            .domain("example.com")
+            .prep(true)
+            .visualize_groups(Some(PathBuf::from("groups")))
+            .build()
+            .unwrap();
+        assert_eq!(prep.config().max_pages(), 10);
+        assert!(prep.config().bounding_boxes);
+    }
+
+    #[test]
+    fn test_config_round_trip() {
+        let crawler = SmartCrawler::builder()
+            .domain("https://www.example.com/about")
+            .objective("team members")
+            .languages(LanguageFilter::parse("en,de"))
+            .wait(WaitStrategy::ReadyState, Duration::from_millis(2500))
+            .survey(SurveyConfig::default())
+            .build()
+            .unwrap();
+        let json = serde_json::to_string(crawler.config()).unwrap();
+        let config: CrawlConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(&config, crawler.config());
+        let rebuilt = SmartCrawlerBuilder::from_config(config).build().unwrap();
+        assert_eq!(rebuilt.config(), crawler.config());
+
+        // Fields left out take their defaults, and files are validated too
+        let config: CrawlConfig =
+            serde_json::from_str(r#"{"domain": "example.com", "max_pages": 8}"#).unwrap();
+        assert_eq!(config.webdriver_port, 4444);
+        assert_eq!(config.max_pages(), 8);
+        let config: CrawlConfig =
+            serde_json::from_str(r#"{"domain": "example.com", "keep_pii_entities": true}"#)
+                .unwrap();
+        assert!(SmartCrawlerBuilder::from_config(config).build().is_err());
+    }
+
+    #[test]
+    fn test_output_urls_skip_noindex() {
+        let mut storage = UrlStorage::new();
+        for (url, noindex) in [
+            ("https://example.com/", false),
+            ("https://example.com/cart", true),
+        ] {
+            storage.add_url(url.to_string());
+            let url_data = storage.get_url_data_mut(url).unwrap();
+            url_data.robots.noindex = noindex;
+            url_data.update_status(FetchStatus::Success);
+        }
+        let mut result = CrawlResult {
+            domain: "example.com".to_string(),
+            storage,
+            template_paths: None,
+            events: Vec::new(),
+            selected_urls: Vec::new(),
+            failures: Vec::new(),
+            aborted: None,
+            objective_keywords: KeywordMatcher::default(),
+            ignore_meta_robots: false,
+            entity_types: Vec::new(),
+            entity_rules: Vec::new(),
+        };
+
+        let urls: Vec<&str> = result
+            .output_urls()
+            .iter()
+            .map(|url_data| url_data.url.as_str())
+            .collect();
+        assert_eq!(urls, vec!["https://example.com/"]);
+
+        result.ignore_meta_robots = true;
+        assert_eq!(result.output_urls().len(), 2);
+    }
+
+    #[test]
+    fn test_related_domains() {
+        let parser = HtmlParser::new();
+        let mut storage = UrlStorage::new();
+        let pages = [
+            (
+                "https://example.com/",
+                r#"<a href="https://eurorust.eu/">EuroRust conference</a><a href="https://shop.io/">Shop</a><a href="https://twitter.com/example">Conference updates</a>"#,
+            ),
+            (
+                "https://example.com/events",
+                r#"<a href="https://rustconf.com/">RustConf</a><a href="https://eurorust.eu/">Tickets</a><a href="https://shop.io/cart">Cart</a>"#,
+            ),
+        ];
+        for (url, html) in pages {
+            storage.add_url(url.to_string());
+            let url_data = storage.get_url_data_mut(url).unwrap();
+            url_data.set_html_data(html.to_string(), parser.parse(html), None);
+            url_data.update_status(FetchStatus::Success);
+        }
+        let mut result = CrawlResult {
+            domain: "example.com".to_string(),
+            storage,
+            template_paths: None,
+            events: Vec::new(),
+            selected_urls: Vec::new(),
+            failures: Vec::new(),
+            aborted: None,
+            objective_keywords: KeywordMatcher::default(),
+            ignore_meta_robots: false,
+            entity_types: Vec::new(),
+            entity_rules: Vec::new(),
+        };
+
+        assert_eq!(
+            result.related_domains(10),
+            vec!["eurorust.eu", "shop.io", "rustconf.com"]
+        );
+        assert_eq!(result.related_domains(1), vec!["eurorust.eu"]);
+
+        result.objective_keywords = KeywordMatcher::from_objective("conferences");
+        assert_eq!(result.related_domains(10), vec!["eurorust.eu"]);
+    }
+
+    #[test]
+    fn test_crawl_run_records_discovery_events() {
+        let mut run = CrawlRun {
+            domain: "example.com".to_string(),
+            phase: CrawlPhase::Discovery,
+            storage: UrlStorage::new(),
+            events: Vec::new(),
+            selected_urls: Vec::new(),
+            failures: Vec::new(),
+            aborted: None,
+            observers: Vec::new(),
+            entity_extractor: EntityExtractor::new(),
+            entity_validator: EntityValidator::default(),
+        };
+        let mut urls = Vec::new();
+        assert!(run.add_url(
+            &mut urls,
+            UrlSelection::new(
+                "https://example.com/".to_string(),
+                DiscoverySource::Root,
+                &KeywordMatcher::default()
+            ),
+        ));
+        assert!(!run.add_url(
+            &mut urls,
+            UrlSelection::new(
+                "https://example.com/".to_string(),
+                DiscoverySource::Homepage,
+                &KeywordMatcher::default()
+            ),
+        ));
+        assert_eq!(
+            run.events,
+            vec![CrawlEvent::UrlDiscovered {
+                url: "https://example.com/".to_string(),
+                source: DiscoverySource::Root
+            }]
+        );
+        assert!(run.storage.get_url_data("https://example.com/").is_some());
+    }
+
+    #[test]
+    fn test_builder_preset() {
+        let preset = ObjectivePreset::find("jobs").unwrap();
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .preset(preset)
+            .build()
+            .unwrap();
+        assert_eq!(
+            crawler.config().objective.as_deref(),
+            Some(preset.objective)
+        );
+        assert!(crawler.config().url_hints.contains(&"careers".to_string()));
+
+        // URL hints rank links without becoming objective keywords
+        let keywords = crawler.objective_keywords();
+        assert_eq!(keywords.score_url("https://example.com/join-us"), 0);
+        let ranking = crawler.url_ranking(&keywords);
+        assert_eq!(ranking.score_url("https://example.com/join-us"), 1);
+
+        // An explicit objective wins over the preset's phrasing
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .objective("engineering roles")
+            .preset(preset)
+            .build()
+            .unwrap();
+        assert_eq!(
+            crawler.config().objective.as_deref(),
+            Some("engineering roles")
+        );
+    }
+
+    #[test]
+    fn test_url_selection() {
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .objective("pricing plans")
+            .build()
+            .unwrap();
+        let ranking = crawler.url_ranking(&crawler.objective_keywords());
+        let mut run = CrawlRun {
+            domain: "example.com".to_string(),
+            phase: CrawlPhase::Discovery,
+            storage: UrlStorage::new(),
+            events: Vec::new(),
+            selected_urls: Vec::new(),
+            failures: Vec::new(),
+            aborted: None,
+            observers: Vec::new(),
+            entity_extractor: EntityExtractor::new(),
+            entity_validator: EntityValidator::default(),
+        };
+        let mut urls = Vec::new();
+        for (url, source) in [
+            ("https://example.com/", DiscoverySource::Root),
+            ("https://example.com/pricing", DiscoverySource::Homepage),
+            ("https://example.com/blog/launch", DiscoverySource::Sitemap),
+        ] {
+            let mut selection = UrlSelection::new(url.to_string(), source, &ranking);
+            if source == DiscoverySource::Sitemap {
+                selection.sitemap_priority = Some(0.8);
+            }
+            run.add_url(&mut urls, selection);
+        }
+
+        let selected: Vec<(&str, DiscoverySource, Vec<String>)> = run
+            .selected_urls
+            .iter()
+            .map(|selection| {
+                (
+                    selection.url.as_str(),
+                    selection.source,
+                    selection.url_keywords.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            selected,
+            vec![
+                ("https://example.com/", DiscoverySource::Root, vec![]),
+                (
+                    "https://example.com/pricing",
+                    DiscoverySource::Homepage,
+                    vec!["pricing".to_string(), "plans".to_string()]
+                ),
+                (
+                    "https://example.com/blog/launch",
+                    DiscoverySource::Sitemap,
+                    vec![]
+                ),
+            ]
+        );
+        assert_eq!(run.selected_urls[1].objective_score(), 2);
+        assert_eq!(run.selected_urls[2].sitemap_priority, Some(0.8));
+
+        // Unset explanations are left out of the JSON
+        let json = serde_json::to_value(&run.selected_urls[1]).unwrap();
+        assert!(json.get("survey_score").is_none());
+        assert!(json.get("sitemap_priority").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_process_url_with_mock_webdriver() {
+        let driver = MockWebDriver::start().await;
+        let page = |title: &str| MockPage {
+            title: title.to_string(),
+            html: format!("<html><body><h1>{title}</h1><p>Page text</p></body></html>"),
+            ..MockPage::default()
+        };
+        driver.page("https://example.com/", page("Home"));
+        driver.page(
+            "https://example.com/crash",
+            MockPage {
+                crash: true,
+                ..page("Recovered")
+            },
+        );
+        driver.page(
+            "https://example.com/slow",
+            MockPage {
+                delay: Duration::from_millis(500),
+                ..page("Slow")
+            },
+        );
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .webdriver_port(driver.port())
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            )
+            .timeouts(PageTimeouts {
+                navigation: Duration::from_millis(100),
+                ..PageTimeouts::default()
+            })
+            .build()
+            .unwrap();
+        let mut run = crawler.start_run(&KeywordMatcher::default());
+        run.phase = CrawlPhase::Scrape;
+        let mut browser = crawler.connect_browser().await.unwrap();
+        let mut urls = Vec::new();
+        for url in ["/", "/crash", "/slow"] {
+            let url = format!("https://example.com{url}");
+            run.add_url(
+                &mut urls,
+                UrlSelection::new(url, DiscoverySource::Root, &KeywordMatcher::default()),
+            );
+        }
+        let status = |run: &CrawlRun, url: &str| {
+            run.storage
+                .get_url_data(&format!("https://example.com{url}"))
+                .unwrap()
+                .status
+                .clone()
+        };
+
+        crawler
+            .process_url(&mut browser, &mut run, "https://example.com/", false)
+            .await
+            .unwrap();
+        let home = run.storage.get_url_data("https://example.com/").unwrap();
+        assert_eq!(home.title.as_deref(), Some("Home"));
+        assert!(matches!(home.status, FetchStatus::Success));
+
+        // A crashed session is restarted and the page tried again
+        crawler
+            .process_url(&mut browser, &mut run, "https://example.com/crash", false)
+            .await
+            .unwrap();
+        assert!(matches!(status(&run, "/crash"), FetchStatus::Success));
+        assert_eq!(driver.sessions_created(), 2);
+
+        // A timed out page isn't retried, but the session is replaced
+        assert!(crawler
+            .process_url(&mut browser, &mut run, "https://example.com/slow", false)
+            .await
+            .is_err());
+        assert!(matches!(status(&run, "/slow"), FetchStatus::TimedOut));
+        assert_eq!(driver.sessions_created(), 3);
+        assert_eq!(run.failures.len(), 1);
+        let failure = &run.failures[0];
+        assert_eq!(failure.url.as_deref(), Some("https://example.com/slow"));
+        assert_eq!(failure.phase, CrawlPhase::Scrape);
+        assert!(failure.timed_out && failure.is_page_failure());
+        let _ = browser.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_run_keeps_pages_when_browser_is_lost() {
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://example.com/",
+            MockPage {
+                title: "Home".to_string(),
+                html: r#"<html><body><h1>Home</h1>
+                    <a href="/about">About</a><a href="/team">Team</a>
+                </body></html>"#
+                    .to_string(),
+                ..MockPage::default()
+            },
+        );
+        driver.page(
+            "https://example.com/about",
+            MockPage {
+                crash: true,
+                ..MockPage::default()
+            },
+        );
+        driver.max_sessions(1);
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .webdriver_port(driver.port())
+            .max_sitemap_urls(0)
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            )
+            .build()
+            .unwrap();
+
+        let result = crawler.run().await.unwrap();
+        assert!(result
+            .aborted
+            .as_deref()
+            .is_some_and(|reason| reason.contains("couldn't be restarted")));
+        let completed: Vec<&str> = result
+            .storage
+            .get_completed_urls()
+            .iter()
+            .map(|url_data| url_data.url.as_str())
+            .collect();
+        assert_eq!(completed, vec!["https://example.com/"]);
+        assert!(matches!(
+            result
+                .storage
+                .get_url_data("https://example.com/team")
+                .unwrap()
+                .status,
+            FetchStatus::Pending
+        ));
+        let failed: Vec<&str> = result
+            .failures
+            .iter()
+            .map(|failure| failure.target())
+            .collect();
+        assert_eq!(failed, vec!["example.com", "https://example.com/about"]);
+    }
+
+    /// Serve `body` as the JSON response to every request, returning the URL
+    async fn serve_json(body: serde_json::Value) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api", listener.local_addr().unwrap());
+        let body = body.to_string();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                tokio::io::AsyncReadExt::read(&mut stream, &mut request)
+                    .await
+                    .ok();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes())
+                    .await
+                    .ok();
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_wayback_snapshot_replaces_missing_page() {
+        let availability_url = serve_json(serde_json::json!({"archived_snapshots": {"closest": {
+            "available": true,
+            "url": "http://web.archive.org/web/20220315093000/https://example.com/pricing",
+            "timestamp": "20220315093000"
+        }}}))
+        .await;
+
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://example.com/pricing",
+            MockPage {
+                title: "Page Not Found".to_string(),
+                html: "<html><body><h1>Oops</h1></body></html>".to_string(),
+                ..MockPage::default()
+            },
+        );
+        driver.page(
+            "https://web.archive.org/web/20220315093000id_/https://example.com/pricing",
+            MockPage {
+                title: "Pricing".to_string(),
+                html: "<html><body><h1>Pricing</h1><p>Pro: $20</p></body></html>".to_string(),
+                ..MockPage::default()
+            },
+        );
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .webdriver_port(driver.port())
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            )
+            .wayback(WaybackConfig {
+                availability_url,
+                ..WaybackConfig::default()
+            })
+            .build()
+            .unwrap();
+        let mut run = crawler.start_run(&KeywordMatcher::default());
+        let mut browser = crawler.connect_browser().await.unwrap();
+        let url = "https://example.com/pricing";
+        run.add_url(
+            &mut Vec::new(),
+            UrlSelection::new(
+                url.to_string(),
+                DiscoverySource::Root,
+                &KeywordMatcher::default(),
+            ),
+        );
+
+        crawler
+            .process_url(&mut browser, &mut run, url, false)
+            .await
+            .unwrap();
+        let page = run.storage.get_url_data(url).unwrap();
+        assert_eq!(page.title.as_deref(), Some("Pricing"));
+        let archived = page.archived.as_ref().unwrap();
+        assert_eq!(archived.reason, "error page \"page not found\"");
+        assert_eq!(archived.timestamp.to_rfc3339(), "2022-03-15T09:30:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_as_of_crawls_captures() {
+        let cdx_url = serve_json(serde_json::json!([
+            ["timestamp", "original"],
+            ["20230528120000", "https://example.com/pricing"]
+        ]))
+        .await;
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://web.archive.org/web/20230528120000id_/https://example.com/pricing",
+            MockPage {
+                title: "Pricing 2023".to_string(),
+                html: "<html><body><h1>Pricing</h1><p>Pro: $15</p></body></html>".to_string(),
+                ..MockPage::default()
+            },
+        );
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .webdriver_port(driver.port())
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            )
+            .wayback(WaybackConfig {
+                as_of: NaiveDate::from_ymd_opt(2023, 6, 1),
+                cdx_url,
+                ..WaybackConfig::default()
+            })
+            .build()
+            .unwrap();
+        let mut run = crawler.start_run(&KeywordMatcher::default());
+        let mut browser = crawler.connect_browser().await.unwrap();
+        let url = "https://example.com/pricing";
+        run.add_url(
+            &mut Vec::new(),
+            UrlSelection::new(
+                url.to_string(),
+                DiscoverySource::Root,
+                &KeywordMatcher::default(),
+            ),
+        );
+
+        crawler
+            .process_url(&mut browser, &mut run, url, false)
+            .await
+            .unwrap();
+        let page = run.storage.get_url_data(url).unwrap();
+        assert_eq!(page.title.as_deref(), Some("Pricing 2023"));
+        assert_eq!(page.archived.as_ref().unwrap().reason, "as of 2023-06-01");
+    }
+
+    #[tokio::test]
+    async fn test_site_search_results_come_first() {
+        let driver = MockWebDriver::start().await;
+        let nav = r#"<a href="/about">About</a><a href="/blog">Blog</a>"#;
+        driver.page(
+            "https://example.com/",
+            MockPage {
+                html: format!(
+                    r#"<html><body>{nav}<form action="/search"><input name="q"></form></body></html>"#
+                ),
+                ..MockPage::default()
+            },
+        );
+        driver.page(
+            "https://example.com/search?q=pricing+plans",
+            MockPage {
+                html: format!(
+                    r#"<html><body>{nav}
+                    <a href="/docs/billing">Billing</a>
+                    <a href="/search?q=pricing+plans&page=2">Next</a>
+                    </body></html>"#
+                ),
+                ..MockPage::default()
+            },
+        );
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .webdriver_port(driver.port())
+            .objective("pricing plans")
+            .site_search(true)
+            .max_pages(2)
+            .max_sitemap_urls(0)
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            )
+            .build()
+            .unwrap();
+
+        let result = crawler.run().await.unwrap();
+        let selected: Vec<(&str, DiscoverySource)> = result
+            .selected_urls
+            .iter()
+            .map(|selection| (selection.url.as_str(), selection.source))
+            .collect();
+        assert_eq!(
+            selected,
+            vec![
+                ("https://example.com/", DiscoverySource::Root),
+                (
+                    "https://example.com/docs/billing",
+                    DiscoverySource::SiteSearch
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_links_are_skipped() {
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://example.com/",
+            MockPage {
+                html: r#"<html><body>
+                    <a href="/files/export.zip">Export</a><a href="/about">About</a>
+                    </body></html>"#
+                    .to_string(),
+                ..MockPage::default()
+            },
+        );
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .webdriver_port(driver.port())
+            .max_pages(3)
+            .max_sitemap_urls(0)
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            )
+            .build()
+            .unwrap();
+
+        let result = crawler.run().await.unwrap();
+        assert_eq!(
+            result.skipped_pages(),
+            vec![("https://example.com/files/export.zip", ".zip file")]
+        );
+        assert!(result.failures.is_empty());
+        assert!(!driver
+            .requests()
+            .iter()
+            .any(|request| request.contains("zip")));
+    }
+
+    #[tokio::test]
+    async fn test_private_network_guard() {
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://example.com/",
+            MockPage {
+                html: r#"<html><body><a href="/admin">Admin</a></body></html>"#.to_string(),
+                ..MockPage::default()
+            },
+        );
+        driver.page(
+            "https://example.com/admin",
+            MockPage {
+                html: "<html><body>Router settings</body></html>".to_string(),
+                redirect: Some("http://192.168.1.1/".to_string()),
+                ..MockPage::default()
+            },
+        );
+        let builder = SmartCrawler::builder()
+            .webdriver_port(driver.port())
+            .max_pages(2)
+            .max_sitemap_urls(0)
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            );
+
+        let result = builder
+            .clone()
+            .domain("example.com")
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .unwrap();
+        let failure = result
+            .failures
+            .iter()
+            .find(|failure| failure.target() == "https://example.com/admin")
+            .unwrap();
+        assert!(failure.error.contains("192.168.1.1"), "{}", failure.error);
+
+        let local = builder.clone().domain("127.0.0.1").build().unwrap();
+        assert!(matches!(
+            local.run().await,
+            Err(CrawlError::PrivateNetwork(_))
+        ));
+        assert!(matches!(
+            local.plan().await,
+            Err(CrawlError::PrivateNetwork(_))
+        ));
+        assert!(builder
+            .domain("127.0.0.1")
+            .allow_private(true)
+            .build()
+            .unwrap()
+            .run()
+            .await
+            .is_ok());
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        calls: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl CrawlObserver for Arc<RecordingObserver> {
+        fn on_url_discovered(&self, url: &str, source: DiscoverySource) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("discovered {url} {source:?}"));
+        }
+
+        fn on_page_scraped(&self, page: &crate::storage::UrlData) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("scraped {}", page.url));
+        }
+
+        fn on_entities_extracted(&self, url: &str, entities: &[crate::entities::Entity]) {
+            let values: Vec<&str> = entities
+                .iter()
+                .map(|entity| entity.value.as_str())
+                .collect();
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("entities {url} {}", values.join(", ")));
+        }
+
+        fn on_error(&self, target: &str, error: &str) {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("error {target} {error}"));
+        }
+    }
+
+    #[test]
+    fn test_observers_are_notified() {
+        let recorder = Arc::new(RecordingObserver::default());
+        let crawler = SmartCrawler::builder()
+            .domain("example.com")
+            .observer(recorder.clone())
+            .build()
+            .unwrap();
+
+        let mut run = CrawlRun {
+            domain: "example.com".to_string(),
+            phase: CrawlPhase::Discovery,
+            storage: UrlStorage::new(),
+            events: Vec::new(),
+            selected_urls: Vec::new(),
+            failures: Vec::new(),
+            aborted: None,
+            observers: crawler.observers.clone(),
+            entity_extractor: EntityExtractor::new(),
+            entity_validator: EntityValidator::default(),
+        };
+        let mut urls = Vec::new();
+        run.add_url(
+            &mut urls,
+            UrlSelection::new(
+                "https://example.com/".to_string(),
+                DiscoverySource::Root,
+                &KeywordMatcher::default(),
+            ),
+        );
+        run.add_url(
+            &mut urls,
+            UrlSelection::new(
+                "https://example.com/team".to_string(),
+                DiscoverySource::Sitemap,
+                &KeywordMatcher::default(),
+            ),
+        );
+        let html = "<p>Write to sales@example.com</p>";
+        run.storage
+            .get_url_data_mut("https://example.com/")
+            .unwrap()
+            .set_html_data(html.to_string(), HtmlParser::new().parse(html), None);
+        run.page_scraped("https://example.com/");
+        run.page_scraped("https://example.com/team");
+        run.page_failed("https://example.com/team", "timeout", true);
+
+        assert_eq!(
+            *recorder.calls.lock().unwrap(),
+            vec![
+                "discovered https://example.com/ Root",
+                "discovered https://example.com/team Sitemap",
+                "scraped https://example.com/",
+                "entities https://example.com/ sales@example.com",
+                "scraped https://example.com/team",
+                "error https://example.com/team timeout",
+            ]
+        );
+        assert_eq!(run.events.len(), 5);
+        assert_eq!(
+            run.failures[0].to_string(),
+            "discovery of https://example.com/team failed: timeout"
+        );
+
+        run.record_failure(CrawlFailure {
+            domain: "example.com".to_string(),
+            url: None,
+            phase: CrawlPhase::Discovery,
+            timed_out: false,
+            error: "sitemap not found".to_string(),
+        });
+        assert_eq!(run.failures[1].target(), "example.com");
+        assert!(!run.failures[1].is_page_failure());
+        assert_eq!(
+            recorder.calls.lock().unwrap().last().unwrap(),
+            "error example.com sitemap not found"
+        );
+    }
+}
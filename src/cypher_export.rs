@@ -0,0 +1,197 @@
+use crate::html_parser::HtmlNode;
+use crate::results::ScrapedWebPage;
+use std::collections::HashSet;
+use std::fmt::Write;
+use url::Url;
+
+/// Uniqueness constraints on the keys statements MERGE on, so loading the
+/// file again updates the graph instead of duplicating it
+const CONSTRAINTS: &[&str] = &[
+    "CREATE CONSTRAINT IF NOT EXISTS FOR (d:Domain) REQUIRE d.name IS UNIQUE;",
+    "CREATE CONSTRAINT IF NOT EXISTS FOR (p:Page) REQUIRE p.url IS UNIQUE;",
+    "CREATE CONSTRAINT IF NOT EXISTS FOR (e:Entity) REQUIRE e.id IS UNIQUE;",
+];
+
+/// A single-quoted Cypher string literal
+fn cypher_string(text: &str) -> String {
+    let mut out = String::from("'");
+    for c in text.chars() {
+        match c {
+            '\'' => out.push_str("\\'"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('\'');
+    out
+}
+
+/// Crawled pages a page links to, in document order and without the page
+/// itself, resolved against the page URL and without fragments
+fn page_links(page: &ScrapedWebPage, crawled: &HashSet<&str>) -> Vec<String> {
+    fn collect(node: &HtmlNode, base: &Url, links: &mut Vec<String>) {
+        if let Some(mut url) = node.href.as_deref().and_then(|href| base.join(href).ok()) {
+            url.set_fragment(None);
+            let url = url.to_string();
+            if !links.contains(&url) {
+                links.push(url);
+            }
+        }
+        for child in &node.children {
+            collect(child, base, links);
+        }
+    }
+
+    let (Ok(base), Some(tree)) = (
+        Url::parse(&page.url),
+        page.content.as_ref().or(page.filtered_content.as_ref()),
+    ) else {
+        return Vec::new();
+    };
+    let mut links = Vec::new();
+    collect(tree, &base, &mut links);
+    links.retain(|link| *link != page.url && crawled.contains(link.as_str()));
+    links
+}
+
+/// Cypher statements, one per line, that build the crawl as a property
+/// graph: `Domain`, `Page` and `Entity` nodes, pages `PART_OF` their domain
+/// and `LINKS_TO` the other crawled pages they link to, and each page
+/// `MENTIONS` its entities, which are `EXTRACTED_FROM` it with the text as
+/// found and the confidence. Duplicate pages are left out. Statements MERGE
+/// on the domain name, page URL and entity ID, so crawls of several runs
+/// load into one graph.
+pub fn render_cypher(pages: &[ScrapedWebPage]) -> String {
+    let pages: Vec<&ScrapedWebPage> = pages
+        .iter()
+        .filter(|page| page.duplicate_of.is_none())
+        .collect();
+    let mut out = String::new();
+    for constraint in CONSTRAINTS {
+        let _ = writeln!(out, "{constraint}");
+    }
+
+    let mut domains: Vec<&str> = Vec::new();
+    for page in &pages {
+        if !domains.contains(&page.domain.as_str()) {
+            domains.push(&page.domain);
+            let _ = writeln!(
+                out,
+                "MERGE (d:Domain {{name: {}}});",
+                cypher_string(&page.domain)
+            );
+        }
+    }
+
+    for page in &pages {
+        let mut properties = Vec::new();
+        if let Some(title) = &page.title {
+            properties.push(format!("p.title = {}", cypher_string(title)));
+        }
+        if let Some(page_type) = page.page_type {
+            properties.push(format!(
+                "p.page_type = {}",
+                cypher_string(page_type.as_str())
+            ));
+        }
+        let set = if properties.is_empty() {
+            String::new()
+        } else {
+            format!(" SET {}", properties.join(", "))
+        };
+        let _ = writeln!(
+            out,
+            "MATCH (d:Domain {{name: {}}}) MERGE (p:Page {{url: {}}}){set} MERGE (p)-[:PART_OF]->(d);",
+            cypher_string(&page.domain),
+            cypher_string(&page.url)
+        );
+    }
+
+    for page in &pages {
+        for entity in &page.entities {
+            let _ = writeln!(
+                out,
+                "MATCH (p:Page {{url: {}}}) MERGE (e:Entity {{id: {}}}) SET e.kind = {}, e.value = {} \
+                 MERGE (p)-[:MENTIONS]->(e) MERGE (e)-[r:EXTRACTED_FROM]->(p) SET r.text = {}, r.confidence = {};",
+                cypher_string(&page.url),
+                cypher_string(&entity.id),
+                cypher_string(entity.kind.as_str()),
+                cypher_string(&entity.value),
+                cypher_string(&entity.text),
+                entity.confidence
+            );
+        }
+    }
+
+    let crawled: HashSet<&str> = pages.iter().map(|page| page.url.as_str()).collect();
+    for page in &pages {
+        for link in page_links(page, &crawled) {
+            let _ = writeln!(
+                out,
+                "MATCH (a:Page {{url: {}}}), (b:Page {{url: {}}}) MERGE (a)-[:LINKS_TO]->(b);",
+                cypher_string(&page.url),
+                cypher_string(&link)
+            );
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+    use crate::html_parser::HtmlParser;
+    use serde_json::json;
+
+    fn page(url: &str, html: &str) -> ScrapedWebPage {
+        let mut page: ScrapedWebPage = serde_json::from_value(json!({
+            "url": url,
+            "domain": "example.com",
+            "title": "Bob's \"shop\"",
+        }))
+        .unwrap();
+        let tree = HtmlParser::new().parse(html);
+        page.entities = EntityExtractor::new().extract_from_tree(&tree);
+        page.content = Some(tree);
+        page
+    }
+
+    #[test]
+    fn test_render_cypher() {
+        let pages = vec![
+            page(
+                "https://example.com/",
+                r##"<a href="/pricing#plans">Pricing</a><a href="/">Home</a><a href="https://other.com/">Partner</a>"##,
+            ),
+            page(
+                "https://example.com/pricing",
+                r#"<p>Pro plan €49/mo</p><a href="https://example.com/">Back</a>"#,
+            ),
+        ];
+        let cypher = render_cypher(&pages);
+        let lines: Vec<&str> = cypher.lines().collect();
+        assert!(lines[0].starts_with("CREATE CONSTRAINT IF NOT EXISTS FOR (d:Domain)"));
+        assert_eq!(lines[3], "MERGE (d:Domain {name: 'example.com'});");
+        assert_eq!(
+            lines[4],
+            "MATCH (d:Domain {name: 'example.com'}) MERGE (p:Page {url: 'https://example.com/'}) \
+             SET p.title = 'Bob\\'s \"shop\"' MERGE (p)-[:PART_OF]->(d);"
+        );
+        assert!(lines[6].contains(
+            "SET e.kind = 'price', e.value = '49 EUR/month' MERGE (p)-[:MENTIONS]->(e) \
+             MERGE (e)-[r:EXTRACTED_FROM]->(p) SET r.text = '€49/mo', r.confidence = 0.9;"
+        ));
+        // Links between crawled pages only, without fragments or self-links
+        assert_eq!(
+            &lines[7..],
+            &[
+                "MATCH (a:Page {url: 'https://example.com/'}), (b:Page {url: 'https://example.com/pricing'}) MERGE (a)-[:LINKS_TO]->(b);",
+                "MATCH (a:Page {url: 'https://example.com/pricing'}), (b:Page {url: 'https://example.com/'}) MERGE (a)-[:LINKS_TO]->(b);",
+            ]
+        );
+    }
+}
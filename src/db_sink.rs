@@ -0,0 +1,349 @@
+use crate::entities::{Entity, EntityKind};
+use crate::results::ScrapedWebPage;
+use crate::scheduler::{QueuedDomain, SharedDomainQueue};
+use sqlx::any::{install_default_drivers, AnyPoolOptions};
+use sqlx::{AnyPool, Executor, Row};
+use std::time::Duration;
+use tracing::{info, warn};
+
+async fn connect_pool(url: &str) -> Result<AnyPool, String> {
+    install_default_drivers();
+    AnyPoolOptions::new()
+        .max_connections(1)
+        .connect(url)
+        .await
+        .map_err(|e| format!("Failed to connect to the database: {e}"))
+}
+
+/// Table of the entities of a kind, e.g. `price_entities`
+fn entity_table(kind: EntityKind) -> String {
+    format!("{}_entities", kind.as_str())
+}
+
+/// Upserts extracted entities into a SQLite or Postgres database: one table
+/// per entity kind keyed by entity ID, and `entity_provenance` with the
+/// pages each entity was found on
+pub struct EntitySink {
+    pool: AnyPool,
+}
+
+impl EntitySink {
+    /// Connect to `url` (`sqlite://entities.db?mode=rwc`,
+    /// `postgres://user@host/db`) and create the tables that don't exist
+    pub async fn connect(url: &str) -> Result<Self, String> {
+        let pool = connect_pool(url).await?;
+        for kind in EntityKind::ALL {
+            pool.execute(
+                format!(
+                    "CREATE TABLE IF NOT EXISTS {} (id TEXT PRIMARY KEY, value TEXT NOT NULL, \
+                     text TEXT NOT NULL, confidence DOUBLE PRECISION NOT NULL)",
+                    entity_table(kind)
+                )
+                .as_str(),
+            )
+            .await
+            .map_err(|e| format!("Failed to create {}: {e}", entity_table(kind)))?;
+        }
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS entity_provenance (entity_id TEXT NOT NULL, \
+             kind TEXT NOT NULL, url TEXT NOT NULL, path TEXT, snippet TEXT, extracted_at TEXT, \
+             PRIMARY KEY (entity_id, url))",
+        )
+        .await
+        .map_err(|e| format!("Failed to create entity_provenance: {e}"))?;
+        Ok(EntitySink { pool })
+    }
+
+    /// Upsert the entities of `pages` in one transaction, returning how many
+    /// were written. An entity found again replaces its text, confidence
+    /// and the provenance of the page it was found on.
+    pub async fn write_pages(&self, pages: &[ScrapedWebPage]) -> Result<usize, String> {
+        let error = |e: sqlx::Error| format!("Failed to write entities: {e}");
+        let mut tx = self.pool.begin().await.map_err(error)?;
+        let mut written = 0;
+        for page in pages {
+            for entity in &page.entities {
+                upsert_entity(&mut tx, &page.url, entity)
+                    .await
+                    .map_err(error)?;
+                written += 1;
+            }
+        }
+        tx.commit().await.map_err(error)?;
+        Ok(written)
+    }
+}
+
+/// Domains shared by workers crawling into the same database, in
+/// `crawl_queue`. Each domain is queued once and claimed by one worker;
+/// its status goes from `pending` to `running` and then `done` or `failed`.
+#[derive(Clone)]
+pub struct CrawlQueue {
+    pool: AnyPool,
+    worker: String,
+}
+
+impl CrawlQueue {
+    /// Connect to `url` as `worker`, the name recorded on the domains it
+    /// claims, and create `crawl_queue` if it doesn't exist
+    pub async fn connect(url: &str, worker: &str) -> Result<Self, String> {
+        let pool = connect_pool(url).await?;
+        pool.execute(
+            "CREATE TABLE IF NOT EXISTS crawl_queue (domain TEXT PRIMARY KEY, \
+             priority INTEGER NOT NULL, status TEXT NOT NULL, worker TEXT, error TEXT)",
+        )
+        .await
+        .map_err(|e| format!("Failed to create crawl_queue: {e}"))?;
+        Ok(CrawlQueue {
+            pool,
+            worker: worker.to_string(),
+        })
+    }
+
+    /// Queue a domain, returning false if any worker queued it before
+    pub async fn push(&self, domain: &str, priority: i32) -> Result<bool, String> {
+        let queued = sqlx::query(
+            "INSERT INTO crawl_queue (domain, priority, status) VALUES ($1, $2, 'pending') \
+             ON CONFLICT (domain) DO NOTHING",
+        )
+        .bind(domain)
+        .bind(priority)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to queue {domain}: {e}"))?;
+        Ok(queued.rows_affected() == 1)
+    }
+
+    /// Claim the pending domain with the highest priority, or None if no
+    /// domain is pending. Another worker may claim the same domain between
+    /// the select and the update, in which case the next one is tried.
+    pub async fn claim(&self) -> Result<Option<QueuedDomain>, String> {
+        let error = |e: sqlx::Error| format!("Failed to claim a domain: {e}");
+        loop {
+            let Some(row) = sqlx::query(
+                "SELECT domain, priority FROM crawl_queue WHERE status = 'pending' \
+                 ORDER BY priority DESC, domain LIMIT 1",
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(error)?
+            else {
+                return Ok(None);
+            };
+            let domain: String = row.try_get(0).map_err(error)?;
+            let priority: i64 = row.try_get(1).map_err(error)?;
+            let claimed = sqlx::query(
+                "UPDATE crawl_queue SET status = 'running', worker = $1 \
+                 WHERE domain = $2 AND status = 'pending'",
+            )
+            .bind(&self.worker)
+            .bind(&domain)
+            .execute(&self.pool)
+            .await
+            .map_err(error)?;
+            if claimed.rows_affected() == 1 {
+                return Ok(Some(QueuedDomain {
+                    domain,
+                    priority: priority as i32,
+                }));
+            }
+        }
+    }
+
+    /// Mark a claimed domain as done, or failed with `error`
+    pub async fn finish(&self, domain: &str, error: Option<&str>) -> Result<(), String> {
+        sqlx::query("UPDATE crawl_queue SET status = $1, error = $2 WHERE domain = $3")
+            .bind(if error.is_some() { "failed" } else { "done" })
+            .bind(error)
+            .bind(domain)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to finish {domain}: {e}"))?;
+        Ok(())
+    }
+
+    /// Put the domains this worker claimed but didn't finish back in the
+    /// queue, returning how many there were
+    pub async fn release(&self) -> Result<u64, String> {
+        let released = sqlx::query(
+            "UPDATE crawl_queue SET status = 'pending', worker = NULL \
+             WHERE status = 'running' AND worker = $1",
+        )
+        .bind(&self.worker)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| format!("Failed to release claimed domains: {e}"))?;
+        Ok(released.rows_affected())
+    }
+
+    /// Claim a domain into `queue` whenever it runs empty, checking the
+    /// database at every interval. Runs until cancelled.
+    pub async fn feed(&self, queue: SharedDomainQueue, interval: Duration) {
+        loop {
+            let idle = queue.lock().is_ok_and(|queue| queue.is_empty());
+            if idle {
+                match self.claim().await {
+                    Ok(Some(next)) => {
+                        info!("Claimed {} from the shared queue", next.domain);
+                        if let Ok(mut queue) = queue.lock() {
+                            queue.push(next.domain, next.priority);
+                        }
+                        continue;
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!("{}", e),
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+}
+
+async fn upsert_entity(
+    tx: &mut sqlx::Transaction<'_, sqlx::Any>,
+    url: &str,
+    entity: &Entity,
+) -> Result<(), sqlx::Error> {
+    let sql = format!(
+        "INSERT INTO {} (id, value, text, confidence) VALUES ($1, $2, $3, $4) \
+         ON CONFLICT (id) DO UPDATE SET text = excluded.text, confidence = excluded.confidence",
+        entity_table(entity.kind)
+    );
+    sqlx::query(&sql)
+        .bind(&entity.id)
+        .bind(&entity.value)
+        .bind(&entity.text)
+        .bind(f64::from(entity.confidence))
+        .execute(&mut **tx)
+        .await?;
+
+    let provenance = entity.provenance.as_ref();
+    sqlx::query(
+        "INSERT INTO entity_provenance (entity_id, kind, url, path, snippet, extracted_at) \
+         VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT (entity_id, url) DO UPDATE SET \
+         path = excluded.path, snippet = excluded.snippet, extracted_at = excluded.extracted_at",
+    )
+    .bind(&entity.id)
+    .bind(entity.kind.as_str())
+    .bind(url)
+    .bind(provenance.and_then(|p| p.path.clone()))
+    .bind(provenance.map(|p| p.snippet.clone()))
+    .bind(
+        provenance
+            .and_then(|p| p.extracted_at)
+            .map(|at| at.to_rfc3339()),
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+    use serde_json::json;
+
+    fn page(url: &str, text: &str) -> ScrapedWebPage {
+        let mut page: ScrapedWebPage = serde_json::from_value(json!({
+            "url": url,
+            "domain": "example.com",
+        }))
+        .unwrap();
+        page.entities = EntityExtractor::new().extract(text);
+        page
+    }
+
+    #[tokio::test]
+    async fn test_upsert_entities() {
+        let sink = EntitySink::connect("sqlite::memory:").await.unwrap();
+        let pages = [
+            page("https://example.com/", "Pro at $49. sales@example.com"),
+            page("https://example.com/team", "sales@example.com"),
+        ];
+        assert_eq!(sink.write_pages(&pages).await.unwrap(), 3);
+        // Writing the same pages again updates the rows
+        assert_eq!(sink.write_pages(&pages).await.unwrap(), 3);
+
+        let rows = sqlx::query("SELECT value FROM email_entities")
+            .fetch_all(&sink.pool)
+            .await
+            .unwrap();
+        let values: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+        assert_eq!(values, vec!["sales@example.com"]);
+
+        let rows =
+            sqlx::query("SELECT kind, url, snippet FROM entity_provenance ORDER BY kind, url")
+                .fetch_all(&sink.pool)
+                .await
+                .unwrap();
+        let provenance: Vec<(String, String, String)> = rows
+            .iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2)))
+            .collect();
+        assert_eq!(
+            provenance,
+            vec![
+                (
+                    "email".to_string(),
+                    "https://example.com/".to_string(),
+                    "Pro at $49. sales@example.com".to_string()
+                ),
+                (
+                    "email".to_string(),
+                    "https://example.com/team".to_string(),
+                    "sales@example.com".to_string()
+                ),
+                (
+                    "price".to_string(),
+                    "https://example.com/".to_string(),
+                    "Pro at $49. sales@example.com".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workers_share_the_crawl_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!(
+            "sqlite://{}?mode=rwc",
+            dir.path().join("queue.db").display()
+        );
+        let first = CrawlQueue::connect(&url, "first").await.unwrap();
+        let second = CrawlQueue::connect(&url, "second").await.unwrap();
+        assert!(first.push("a.com", 0).await.unwrap());
+        assert!(first.push("b.com", 5).await.unwrap());
+        assert!(first.push("c.com", 0).await.unwrap());
+        // Queued by another worker already
+        assert!(!second.push("b.com", 9).await.unwrap());
+
+        let claimed = |next: Option<QueuedDomain>| next.map(|next| next.domain);
+        assert_eq!(claimed(first.claim().await.unwrap()), Some("b.com".into()));
+        assert_eq!(claimed(second.claim().await.unwrap()), Some("a.com".into()));
+        first.finish("b.com", None).await.unwrap();
+        // A worker stopping hands its unfinished domains back
+        assert_eq!(second.release().await.unwrap(), 1);
+        assert_eq!(claimed(first.claim().await.unwrap()), Some("a.com".into()));
+        assert_eq!(claimed(second.claim().await.unwrap()), Some("c.com".into()));
+        assert_eq!(claimed(first.claim().await.unwrap()), None);
+        second.finish("c.com", Some("timed out")).await.unwrap();
+
+        let rows = sqlx::query("SELECT domain, status, worker FROM crawl_queue ORDER BY domain")
+            .fetch_all(&first.pool)
+            .await
+            .unwrap();
+        let statuses: Vec<(String, String, String)> = rows
+            .iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2)))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("a.com".into(), "running".into(), "first".into()),
+                ("b.com".into(), "done".into(), "first".into()),
+                ("c.com".into(), "failed".into(), "second".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use crate::entities::{Entity, EntityKind};
+use crate::results::ScrapedWebPage;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An entity that appeared, disappeared or changed value between two runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityDiff {
+    pub url: String,
+    pub kind: EntityKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+/// A page found in both runs whose title or objective matches changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageChange {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_before: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_after: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objective_matches_added: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objective_matches_removed: Vec<String>,
+}
+
+/// Differences between two pages files, for monitoring a site over time
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunDiff {
+    pub pages_added: Vec<String>,
+    pub pages_removed: Vec<String>,
+    pub pages_changed: Vec<PageChange>,
+    pub entities_added: Vec<EntityDiff>,
+    pub entities_removed: Vec<EntityDiff>,
+    pub entities_changed: Vec<EntityDiff>,
+}
+
+impl RunDiff {
+    pub fn is_empty(&self) -> bool {
+        self.pages_added.is_empty()
+            && self.pages_removed.is_empty()
+            && self.pages_changed.is_empty()
+            && self.entities_added.is_empty()
+            && self.entities_removed.is_empty()
+            && self.entities_changed.is_empty()
+    }
+}
+
+/// Compare two runs page by page (matched by URL). On a page, entities of a
+/// kind that disappeared and appeared are paired in page order as changes,
+/// so a price going from $20 to $25 is one change rather than a removal and
+/// an addition.
+pub fn diff_runs(before: &[ScrapedWebPage], after: &[ScrapedWebPage]) -> RunDiff {
+    let mut diff = RunDiff::default();
+    for page in after {
+        if !before.iter().any(|old| old.url == page.url) {
+            diff.pages_added.push(page.url.clone());
+            diff.entities_added
+                .extend(page.entities.iter().map(|entity| EntityDiff {
+                    url: page.url.clone(),
+                    kind: entity.kind,
+                    before: None,
+                    after: Some(entity.value.clone()),
+                }));
+        }
+    }
+    for old in before {
+        let Some(page) = after.iter().find(|page| page.url == old.url) else {
+            diff.pages_removed.push(old.url.clone());
+            diff.entities_removed
+                .extend(old.entities.iter().map(|entity| EntityDiff {
+                    url: old.url.clone(),
+                    kind: entity.kind,
+                    before: Some(entity.value.clone()),
+                    after: None,
+                }));
+            continue;
+        };
+        if let Some(change) = page_change(old, page) {
+            diff.pages_changed.push(change);
+        }
+        diff_entities(&old.url, &old.entities, &page.entities, &mut diff);
+    }
+    diff
+}
+
+fn page_change(old: &ScrapedWebPage, page: &ScrapedWebPage) -> Option<PageChange> {
+    let added = missing_from(&page.objective_matches, &old.objective_matches);
+    let removed = missing_from(&old.objective_matches, &page.objective_matches);
+    if old.title == page.title && added.is_empty() && removed.is_empty() {
+        return None;
+    }
+    let title_changed = old.title != page.title;
+    Some(PageChange {
+        url: page.url.clone(),
+        title_before: old.title.clone().filter(|_| title_changed),
+        title_after: page.title.clone().filter(|_| title_changed),
+        objective_matches_added: added,
+        objective_matches_removed: removed,
+    })
+}
+
+fn missing_from(texts: &[String], other: &[String]) -> Vec<String> {
+    texts
+        .iter()
+        .filter(|text| !other.contains(text))
+        .cloned()
+        .collect()
+}
+
+fn diff_entities(url: &str, before: &[Entity], after: &[Entity], diff: &mut RunDiff) {
+    let has = |entities: &[Entity], entity: &Entity| {
+        entities
+            .iter()
+            .any(|other| other.kind == entity.kind && other.value == entity.value)
+    };
+    let removed: Vec<&Entity> = before.iter().filter(|e| !has(after, e)).collect();
+    let mut added: Vec<&Entity> = after.iter().filter(|e| !has(before, e)).collect();
+
+    for old in removed {
+        match added.iter().position(|new| new.kind == old.kind) {
+            Some(index) => diff.entities_changed.push(EntityDiff {
+                url: url.to_string(),
+                kind: old.kind,
+                before: Some(old.value.clone()),
+                after: Some(added.remove(index).value.clone()),
+            }),
+            None => diff.entities_removed.push(EntityDiff {
+                url: url.to_string(),
+                kind: old.kind,
+                before: Some(old.value.clone()),
+                after: None,
+            }),
+        }
+    }
+    diff.entities_added
+        .extend(added.into_iter().map(|new| EntityDiff {
+            url: url.to_string(),
+            kind: new.kind,
+            before: None,
+            after: Some(new.value.clone()),
+        }));
+}
+
+impl fmt::Display for RunDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+        for url in &self.pages_added {
+            writeln!(f, "+ page {url}")?;
+        }
+        for url in &self.pages_removed {
+            writeln!(f, "- page {url}")?;
+        }
+        for change in &self.pages_changed {
+            writeln!(f, "~ page {}", change.url)?;
+            if change.title_before.is_some() || change.title_after.is_some() {
+                writeln!(
+                    f,
+                    "    title: {} -> {}",
+                    change.title_before.as_deref().unwrap_or("(none)"),
+                    change.title_after.as_deref().unwrap_or("(none)")
+                )?;
+            }
+            for text in &change.objective_matches_added {
+                writeln!(f, "    + {text}")?;
+            }
+            for text in &change.objective_matches_removed {
+                writeln!(f, "    - {text}")?;
+            }
+        }
+        for entity in &self.entities_added {
+            writeln!(
+                f,
+                "+ {:?} {} ({})",
+                entity.kind,
+                entity.after.as_deref().unwrap_or_default(),
+                entity.url
+            )?;
+        }
+        for entity in &self.entities_removed {
+            writeln!(
+                f,
+                "- {:?} {} ({})",
+                entity.kind,
+                entity.before.as_deref().unwrap_or_default(),
+                entity.url
+            )?;
+        }
+        for entity in &self.entities_changed {
+            writeln!(
+                f,
+                "~ {:?} {} -> {} ({})",
+                entity.kind,
+                entity.before.as_deref().unwrap_or_default(),
+                entity.after.as_deref().unwrap_or_default(),
+                entity.url
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::entity_id;
+
+    fn page(
+        url: &str,
+        title: &str,
+        matches: &[&str],
+        entities: &[(EntityKind, &str)],
+    ) -> ScrapedWebPage {
+        ScrapedWebPage {
+            url: url.to_string(),
+            domain: "example.com".to_string(),
+            title: Some(title.to_string()),
+            content_type: None,
+            encoding: None,
+            language: None,
+            detected_language: None,
+            structure: None,
+            page_type: None,
+            archived: None,
+            brand_assets: Vec::new(),
+            content: None,
+            filtered_content: None,
+            text_blocks: Vec::new(),
+            api_responses: Vec::new(),
+            blocked_requests: None,
+            structured_content: None,
+            content_hash: None,
+            duplicate_of: None,
+            objective_matches: matches.iter().map(|text| text.to_string()).collect(),
+            entities: entities
+                .iter()
+                .map(|(kind, value)| Entity {
+                    id: entity_id(*kind, value),
+                    kind: *kind,
+                    value: value.to_string(),
+                    text: value.to_string(),
+                    confidence: 0.9,
+                    flags: Vec::new(),
+                    provenance: None,
+                    status: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_runs() {
+        let before = vec![
+            page(
+                "https://example.com/pricing",
+                "Pricing",
+                &["Pro plan"],
+                &[
+                    (EntityKind::Price, "20 USD"),
+                    (EntityKind::Email, "sales@example.com"),
+                ],
+            ),
+            page("https://example.com/old", "Old", &[], &[]),
+        ];
+        let after = vec![
+            page(
+                "https://example.com/pricing",
+                "Pricing",
+                &["Pro plan", "Team plan"],
+                &[
+                    (EntityKind::Price, "25 USD"),
+                    (EntityKind::Email, "sales@example.com"),
+                    (EntityKind::Phone, "5550104000"),
+                ],
+            ),
+            page(
+                "https://example.com/new",
+                "New",
+                &[],
+                &[(EntityKind::Date, "2025-03-14")],
+            ),
+        ];
+
+        let diff = diff_runs(&before, &after);
+        assert_eq!(diff.pages_added, vec!["https://example.com/new"]);
+        assert_eq!(diff.pages_removed, vec!["https://example.com/old"]);
+        assert_eq!(diff.pages_changed.len(), 1);
+        assert_eq!(
+            diff.pages_changed[0].objective_matches_added,
+            vec!["Team plan"]
+        );
+        assert_eq!(diff.pages_changed[0].title_before, None);
+        assert_eq!(
+            diff.entities_changed,
+            vec![EntityDiff {
+                url: "https://example.com/pricing".to_string(),
+                kind: EntityKind::Price,
+                before: Some("20 USD".to_string()),
+                after: Some("25 USD".to_string()),
+            }]
+        );
+        let added: Vec<&str> = diff
+            .entities_added
+            .iter()
+            .map(|entity| entity.after.as_deref().unwrap())
+            .collect();
+        assert_eq!(added, vec!["2025-03-14", "5550104000"]);
+        assert!(diff.entities_removed.is_empty());
+
+        let text = diff.to_string();
+        assert!(text.contains("~ Price 20 USD -> 25 USD (https://example.com/pricing)"));
+        assert!(text.contains("    + Team plan"));
+
+        assert!(diff_runs(&after, &after).is_empty());
+        assert_eq!(diff_runs(&after, &after).to_string(), "No changes\n");
+    }
+}
@@ -0,0 +1,217 @@
+use crate::brand_assets::BrandAssetKind;
+use crate::contact::{merge_contact_info, ContactInfo};
+use crate::entities::EntityKind;
+use crate::page_type::PageType;
+use crate::results::ScrapedWebPage;
+use serde::{Deserialize, Serialize};
+
+/// Separators between a page's own title and the site name, e.g.
+/// `Pricing | Acme`
+const TITLE_SEPARATORS: &[&str] = &[" | ", " - ", " – ", " — ", " · ", " :: "];
+
+/// An entity of a domain, merged across the pages it was found on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProfileEntity {
+    pub id: String,
+    pub kind: EntityKind,
+    pub value: String,
+    /// Highest confidence of the entity on any page
+    pub confidence: f32,
+    /// Pages the entity was found on
+    pub pages: Vec<String>,
+}
+
+/// What a crawl found about a domain as one record, each value once
+/// however many pages repeat it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainProfile {
+    pub domain: String,
+    /// Site name: the part of the page titles most of them share (`Acme`
+    /// of `Pricing | Acme` and `Team | Acme`), else the first part of the
+    /// homepage title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// First logo found on the site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo: Option<String>,
+    /// Emails, phone numbers and addresses, contact pages first
+    pub contact: ContactInfo,
+    /// Prices, dates and measurements, those found on the most pages first
+    pub entities: Vec<ProfileEntity>,
+    /// Pages the profile was built from, without duplicates
+    pub page_count: usize,
+}
+
+impl DomainProfile {
+    /// Merge the pages crawled for `domain`; duplicate pages are left out
+    pub fn from_pages(domain: &str, pages: &[ScrapedWebPage]) -> Self {
+        let pages: Vec<ScrapedWebPage> = pages
+            .iter()
+            .filter(|page| page.duplicate_of.is_none())
+            .cloned()
+            .collect();
+        let logo = pages
+            .iter()
+            .flat_map(|page| &page.brand_assets)
+            .find(|asset| asset.kind == BrandAssetKind::Logo)
+            .map(|asset| asset.url.clone());
+
+        let mut entities: Vec<ProfileEntity> = Vec::new();
+        for page in &pages {
+            for entity in &page.entities {
+                if matches!(
+                    entity.kind,
+                    EntityKind::Email | EntityKind::Phone | EntityKind::Address
+                ) {
+                    continue;
+                }
+                match entities.iter_mut().find(|known| known.id == entity.id) {
+                    Some(known) => {
+                        known.confidence = known.confidence.max(entity.confidence);
+                        if !known.pages.contains(&page.url) {
+                            known.pages.push(page.url.clone());
+                        }
+                    }
+                    None => entities.push(ProfileEntity {
+                        id: entity.id.clone(),
+                        kind: entity.kind,
+                        value: entity.value.clone(),
+                        confidence: entity.confidence,
+                        pages: vec![page.url.clone()],
+                    }),
+                }
+            }
+        }
+        entities.sort_by_key(|entity| std::cmp::Reverse(entity.pages.len()));
+
+        DomainProfile {
+            domain: domain.to_string(),
+            name: site_name(&pages),
+            logo,
+            contact: merge_contact_info(&pages),
+            entities,
+            page_count: pages.len(),
+        }
+    }
+}
+
+fn title_segments(title: &str) -> Vec<String> {
+    let mut segments = vec![title.trim().to_string()];
+    for separator in TITLE_SEPARATORS {
+        segments = segments
+            .iter()
+            .flat_map(|segment| segment.split(separator))
+            .map(|segment| segment.trim().to_string())
+            .filter(|segment| !segment.is_empty())
+            .collect();
+    }
+    let mut unique = Vec::new();
+    for segment in segments {
+        if !unique.contains(&segment) {
+            unique.push(segment);
+        }
+    }
+    unique
+}
+
+fn site_name(pages: &[ScrapedWebPage]) -> Option<String> {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+    for title in pages.iter().filter_map(|page| page.title.as_deref()) {
+        for segment in title_segments(title) {
+            match counts.iter_mut().find(|(known, _)| *known == segment) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((segment, 1)),
+            }
+        }
+    }
+    let mut shared: Option<&(String, usize)> = None;
+    for candidate in counts.iter().filter(|(_, count)| *count > 1) {
+        if shared.is_none_or(|(_, best)| candidate.1 > *best) {
+            shared = Some(candidate);
+        }
+    }
+    if let Some((name, _)) = shared {
+        return Some(name.clone());
+    }
+    pages
+        .iter()
+        .find(|page| page.page_type == Some(PageType::Home))
+        .or(pages.first())
+        .and_then(|page| page.title.as_deref())
+        .and_then(|title| title_segments(title).into_iter().next())
+}
+
+/// Write domain profiles as a JSON array
+pub fn write_profiles(path: &str, profiles: &[DomainProfile]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(profiles)
+        .map_err(|e| format!("Failed to serialize profiles: {e}"))?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+    use serde_json::json;
+
+    fn page(url: &str, title: &str, text: &str) -> ScrapedWebPage {
+        let mut page: ScrapedWebPage = serde_json::from_value(json!({
+            "url": url,
+            "domain": "example.com",
+            "title": title,
+        }))
+        .unwrap();
+        page.entities = EntityExtractor::new().extract(text);
+        page
+    }
+
+    #[test]
+    fn test_domain_profile() {
+        let mut pricing = page(
+            "https://example.com/pricing",
+            "Pricing | Acme",
+            "Pro €49/mo, Team €99/mo. sales@example.com",
+        );
+        pricing.brand_assets = serde_json::from_value(json!([
+            {"kind": "favicon", "url": "https://example.com/favicon.ico"},
+            {"kind": "logo", "url": "https://example.com/logo.svg"}
+        ]))
+        .unwrap();
+        let pages = vec![
+            page(
+                "https://example.com/",
+                "Acme - Widgets for everyone",
+                "Team €99/mo. Call 555-010-4000",
+            ),
+            pricing,
+            page(
+                "https://example.com/team",
+                "Team | Acme",
+                "sales@example.com",
+            ),
+        ];
+
+        let profile = DomainProfile::from_pages("example.com", &pages);
+        assert_eq!(profile.name.as_deref(), Some("Acme"));
+        assert_eq!(
+            profile.logo.as_deref(),
+            Some("https://example.com/logo.svg")
+        );
+        assert_eq!(profile.contact.emails, vec!["sales@example.com"]);
+        assert_eq!(profile.contact.phones, vec!["5550104000"]);
+        // The price on two pages first, contact details only once, in contact
+        let entities: Vec<(&str, usize)> = profile
+            .entities
+            .iter()
+            .map(|entity| (entity.value.as_str(), entity.pages.len()))
+            .collect();
+        assert_eq!(entities, vec![("99 EUR/month", 2), ("49 EUR/month", 1)]);
+        assert_eq!(profile.page_count, 3);
+
+        let single = DomainProfile::from_pages(
+            "example.com",
+            &[page("https://example.com/", "Acme — Widgets", "")],
+        );
+        assert_eq!(single.name.as_deref(), Some("Acme"));
+    }
+}
@@ -0,0 +1,112 @@
+use encoding_rs::{Encoding, UTF_8, WINDOWS_1252};
+use regex::bytes::Regex;
+
+/// Script returning the encoding the browser decoded the current document with
+pub const DOCUMENT_CHARSET_SCRIPT: &str = "return document.characterSet || '';";
+
+/// Bytes searched for a `<meta charset>` or XML declaration, as browsers do
+const SNIFF_BYTES: usize = 1024;
+
+/// The `charset` parameter of a Content-Type header
+pub fn charset_from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case("charset")
+            .then(|| value.trim().trim_matches(['"', '\'']))
+            .filter(|value| !value.is_empty())
+    })
+}
+
+/// The charset declared by a `<meta>` tag or an XML declaration near the
+/// start of a document
+pub fn sniff_charset(bytes: &[u8]) -> Option<String> {
+    let head = &bytes[..bytes.len().min(SNIFF_BYTES)];
+    let declaration = Regex::new(
+        r#"(?i)(?:<meta[^>]+charset\s*=\s*["']?|<\?xml[^>]+encoding\s*=\s*["'])([A-Za-z0-9_.:-]+)"#,
+    )
+    .unwrap();
+    let captures = declaration.captures(head)?;
+    Some(String::from_utf8_lossy(&captures[1]).into_owned())
+}
+
+/// Decode a response body: by its byte order mark, else the Content-Type
+/// charset, else a charset declared in the document, else UTF-8 (or
+/// windows-1252 when the body isn't valid UTF-8). Returns the text and the
+/// name of the encoding used.
+pub fn decode_body(bytes: &[u8], content_type: &str) -> (String, &'static str) {
+    let declared = charset_from_content_type(content_type)
+        .map(str::to_string)
+        .or_else(|| sniff_charset(bytes))
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()));
+    let encoding = match declared {
+        Some(encoding) => encoding,
+        None if std::str::from_utf8(bytes).is_ok() => UTF_8,
+        None => WINDOWS_1252,
+    };
+    // A byte order mark overrides the declared encoding
+    let (text, used, _) = encoding.decode(bytes);
+    (text.into_owned(), used.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charset_detection() {
+        assert_eq!(
+            charset_from_content_type("text/html; charset=\"Shift_JIS\""),
+            Some("Shift_JIS")
+        );
+        assert_eq!(charset_from_content_type("application/json"), None);
+        assert_eq!(
+            sniff_charset(br#"<html><head><meta charset="windows-1251"></head>"#).as_deref(),
+            Some("windows-1251")
+        );
+        assert_eq!(
+            sniff_charset(
+                br#"<meta http-equiv="Content-Type" content="text/html; charset=koi8-r">"#
+            )
+            .as_deref(),
+            Some("koi8-r")
+        );
+        assert_eq!(
+            sniff_charset(br#"<?xml version="1.0" encoding="ISO-8859-1"?><urlset>"#).as_deref(),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(sniff_charset(b"<html><body>plain</body></html>"), None);
+    }
+
+    #[test]
+    fn test_decode_body() {
+        // "Привет" in windows-1251
+        let cyrillic = [0xcf, 0xf0, 0xe8, 0xe2, 0xe5, 0xf2];
+        assert_eq!(
+            decode_body(&cyrillic, "text/csv; charset=windows-1251"),
+            ("Привет".to_string(), "windows-1251")
+        );
+
+        // "日本" in Shift_JIS, declared in the document
+        let mut html = br#"<meta charset="shift_jis">"#.to_vec();
+        html.extend([0x93, 0xfa, 0x96, 0x7b]);
+        let (text, encoding) = decode_body(&html, "text/html");
+        assert!(text.ends_with("日本"));
+        assert_eq!(encoding, "Shift_JIS");
+
+        assert_eq!(
+            decode_body("naïve".as_bytes(), ""),
+            ("naïve".to_string(), "UTF-8")
+        );
+        assert_eq!(
+            decode_body(&[0x6e, 0x61, 0xef, 0x76, 0x65], ""),
+            ("naïve".to_string(), "windows-1252")
+        );
+        let mut bom = vec![0xef, 0xbb, 0xbf];
+        bom.extend(b"ok");
+        assert_eq!(
+            decode_body(&bom, "text/csv; charset=latin1"),
+            ("ok".to_string(), "UTF-8")
+        );
+    }
+}
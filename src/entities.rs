@@ -0,0 +1,665 @@
+use crate::html_parser::HtmlNode;
+use crate::known_entities::EntityStatus;
+use crate::text_normalize::normalize_text;
+use crate::units::{normalize_measure, normalize_price, Dimension};
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Most entities kept per page
+pub const MAX_ENTITIES: usize = 100;
+
+/// Classes of microformat address elements (h-card `adr`, h-adr)
+const ADDRESS_CLASSES: &[&str] = &["adr", "h-adr", "p-adr"];
+
+/// Characters of page text kept on each side of an entity in its snippet
+const SNIPPET_CONTEXT: usize = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    Email,
+    Phone,
+    Price,
+    Date,
+    Address,
+    Area,
+    Weight,
+    Duration,
+}
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 8] = [
+        EntityKind::Email,
+        EntityKind::Phone,
+        EntityKind::Price,
+        EntityKind::Date,
+        EntityKind::Address,
+        EntityKind::Area,
+        EntityKind::Weight,
+        EntityKind::Duration,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityKind::Email => "email",
+            EntityKind::Phone => "phone",
+            EntityKind::Price => "price",
+            EntityKind::Date => "date",
+            EntityKind::Address => "address",
+            EntityKind::Area => "area",
+            EntityKind::Weight => "weight",
+            EntityKind::Duration => "duration",
+        }
+    }
+}
+
+impl FromStr for EntityKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "email" => Ok(EntityKind::Email),
+            "phone" => Ok(EntityKind::Phone),
+            "price" => Ok(EntityKind::Price),
+            "date" => Ok(EntityKind::Date),
+            "address" => Ok(EntityKind::Address),
+            "area" => Ok(EntityKind::Area),
+            "weight" => Ok(EntityKind::Weight),
+            "duration" => Ok(EntityKind::Duration),
+            _ => Err(format!(
+                "Unknown entity type: {s} (expected email, phone, price, date, address, area, weight or duration)"
+            )),
+        }
+    }
+}
+
+/// Comma separated entity types, e.g. `price,date`
+pub fn parse_entity_kinds(list: &str) -> Result<Vec<EntityKind>, String> {
+    let mut kinds = Vec::new();
+    for kind in list.split(',').filter(|kind| !kind.trim().is_empty()) {
+        let kind = kind.parse()?;
+        if !kinds.contains(&kind) {
+            kinds.push(kind);
+        }
+    }
+    Ok(kinds)
+}
+
+/// Stable ID of an entity: a hex FNV-1a hash of its kind and normalized
+/// value, the same across pages, runs and builds
+pub fn entity_id(kind: EntityKind, value: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in kind.as_str().bytes().chain([0]).chain(value.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// A value recognized in page text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    /// `entity_id` of the kind and value, for joining runs and datasets
+    #[serde(default)]
+    pub id: String,
+    pub kind: EntityKind,
+    /// Normalized value: lowercased email, phone digits (with `+` when
+    /// international), `1299.00 USD` or `49 EUR/month`, ISO date when
+    /// unambiguous, areas in `m2`, weights in `kg` and durations in `s`
+    pub value: String,
+    /// The text as it appears on the page
+    pub text: String,
+    /// How likely the value is what its kind says, from 0 to 1: links and
+    /// unambiguous formats score high, loose patterns such as street
+    /// addresses or numeric dates low
+    pub confidence: f32,
+    /// Validation rules the entity failed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    /// Where the entity was found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// How the entity compares to `--known-entities`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<EntityStatus>,
+}
+
+/// Where an entity was found, so it can be audited and checked again
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Page the entity was found on, empty for entities of a bare text
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    /// `HtmlNode::find_by_path` path of the element it was read from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Text around the entity, or the link text of `mailto:`/`tel:` links
+    pub snippet: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extracted_at: Option<DateTime<Utc>>,
+}
+
+/// Confidence of the entities of one kind, for deciding what needs review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceSummary {
+    pub kind: EntityKind,
+    pub count: usize,
+    pub mean: f32,
+    pub min: f32,
+}
+
+/// Confidence per entity kind, in order of each kind's first entity
+pub fn summarize_confidence(entities: &[Entity]) -> Vec<ConfidenceSummary> {
+    let mut summaries: Vec<ConfidenceSummary> = Vec::new();
+    for entity in entities {
+        match summaries
+            .iter_mut()
+            .find(|summary| summary.kind == entity.kind)
+        {
+            Some(summary) => {
+                summary.mean = (summary.mean * summary.count as f32 + entity.confidence)
+                    / (summary.count + 1) as f32;
+                summary.count += 1;
+                summary.min = summary.min.min(entity.confidence);
+            }
+            None => summaries.push(ConfidenceSummary {
+                kind: entity.kind,
+                count: 1,
+                mean: entity.confidence,
+                min: entity.confidence,
+            }),
+        }
+    }
+    summaries
+}
+
+/// Entities below `threshold`, which should be checked by hand
+pub fn needs_review(entities: &[Entity], threshold: f32) -> Vec<&Entity> {
+    entities
+        .iter()
+        .filter(|entity| entity.confidence < threshold)
+        .collect()
+}
+
+/// Kind, normalized value and confidence of a match
+type Recognized = (EntityKind, String, f32);
+
+/// Finds emails, phone numbers, prices, dates, street addresses, areas,
+/// weights and durations with
+/// regular expressions, plus `mailto:` and `tel:` links and microformat
+/// addresses. Deterministic and cheap, so it runs on every page.
+pub struct EntityExtractor {
+    /// Kinds extracted, every kind when empty
+    kinds: Vec<EntityKind>,
+    email_regex: Regex,
+    phone_regex: Regex,
+    price_regex: Regex,
+    date_regex: Regex,
+    address_regex: Regex,
+    measure_regex: Regex,
+}
+
+impl EntityExtractor {
+    pub fn new() -> Self {
+        EntityExtractor {
+            kinds: Vec::new(),
+            email_regex: Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap(),
+            phone_regex: Regex::new(
+                r"\+\d{1,3}[\s.-]?(?:\(\d{1,4}\)[\s.-]?)?\d{1,4}(?:[\s.-]?\d{2,4}){2,4}|\(\d{3}\)\s?\d{3}[\s.-]\d{4}|\b\d{3}[.-]\d{3}[.-]\d{4}\b",
+            )
+            .unwrap(),
+            price_regex: Regex::new(
+                r"(?:[$€£¥₹]\s?\d[\d,.']*\d|[$€£¥₹]\s?\d|\b(?:USD|EUR|GBP|JPY|CHF|CAD|AUD|INR|CNY)\s?\d(?:[\d,.']*\d)?|\b\d[\d,.']*\s?(?:[$€£¥₹]|\b(?:USD|EUR|GBP|JPY|CHF|CAD|AUD|INR|CNY)\b))(?:\s?/\s?(?i:month|mo|year|yr|week|wk|day|hour|hr)\b|\s(?i:per|an?)\s(?i:month|year|annum|week|day|hour)\b)?",
+            )
+            .unwrap(),
+            date_regex: Regex::new(
+                r"(?i)\b(?:\d{4}-\d{2}-\d{2}|\d{1,2}[/.]\d{1,2}[/.]\d{2,4}|(?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.? \d{1,2},? \d{4}|\d{1,2} (?:jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.? \d{4})\b",
+            )
+            .unwrap(),
+            address_regex: Regex::new(
+                r"\b\d{1,5}(?:\s+[A-Z][A-Za-z]*\.?){1,4}\s+(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Lane|Ln|Drive|Dr|Way|Court|Ct|Place|Pl|Square|Sq)\b\.?(?:,?\s+(?:Suite|Ste|Floor|Fl)\.?\s*\w+)?(?:,\s*[A-Z][A-Za-z]+(?:\s[A-Z][A-Za-z]+)*)?(?:,\s*[A-Z]{2})?(?:\s+\d{5}(?:-\d{4})?)?",
+            )
+            .unwrap(),
+            measure_regex: Regex::new(
+                r"\b\d(?:[\d,.']*\d)?\s?(?:(?:km²|m²|ft²)|(?i:square (?:feet|foot|meters?|metres?)|sq\.? ?(?:ft|m)|km2|m2|ft2|hectares?|ha|acres?|kilograms?|kgs?|grams?|mg|lbs?|ounces?|oz|tonnes?|hours?|hrs?|h|minutes?|mins?|seconds?|secs?|days?|weeks?)\b|g\b)",
+            )
+            .unwrap(),
+        }
+    }
+
+    /// Only extract entities of these kinds; PII is still redacted whatever
+    /// the kinds
+    pub fn kinds(mut self, kinds: &[EntityKind]) -> Self {
+        self.kinds = kinds.to_vec();
+        self
+    }
+
+    /// Whether entities of `kind` are extracted
+    pub fn extracts(&self, kind: EntityKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    /// Entities in a text, in order of appearance and without duplicates
+    pub fn extract(&self, text: &str) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.extract_into(text, None, &mut entities);
+        entities
+    }
+
+    /// Entities in the text and `mailto:`/`tel:` links of a parsed page, in
+    /// document order, without duplicates and capped at `MAX_ENTITIES`
+    pub fn extract_from_tree(&self, tree: &HtmlNode) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        self.collect(tree, &mut Vec::new(), &mut entities);
+        entities.truncate(MAX_ENTITIES);
+        entities
+    }
+
+    /// `path` holds the path parts of the node's ancestors
+    fn collect(&self, node: &HtmlNode, path: &mut Vec<String>, entities: &mut Vec<Entity>) {
+        if entities.len() >= MAX_ENTITIES {
+            return;
+        }
+        path.push(if node.classes.is_empty() {
+            node.tag.clone()
+        } else {
+            format!("{}.{}", node.tag, node.classes.join("."))
+        });
+        let node_path = path.join(" ");
+        self.collect_node(node, &node_path, path, entities);
+        path.pop();
+    }
+
+    fn collect_node(
+        &self,
+        node: &HtmlNode,
+        node_path: &str,
+        path: &mut Vec<String>,
+        entities: &mut Vec<Entity>,
+    ) {
+        if let Some(href) = &node.href {
+            let link_text = normalize_text(&node.text_content());
+            let entity = if let Some(email) = href.strip_prefix("mailto:") {
+                let email = email.split('?').next().unwrap_or_default();
+                self.email_regex.is_match(email).then(|| {
+                    let value = email.to_lowercase();
+                    Self::entity(EntityKind::Email, value, email, 1.0, &link_text)
+                })
+            } else if let Some(phone) = href.strip_prefix("tel:") {
+                normalize_phone(phone).map(|(value, _)| {
+                    Self::entity(EntityKind::Phone, value, phone, 1.0, &link_text)
+                })
+            } else {
+                None
+            };
+            let entity = entity.map(|entity| Self::located(entity, node_path));
+            if let Some(entity) = entity.filter(|entity| self.extracts(entity.kind)) {
+                push_unique(entities, entity);
+            }
+        }
+        if node
+            .classes
+            .iter()
+            .any(|class| ADDRESS_CLASSES.contains(&class.as_str()))
+        {
+            return self.collect_address(node, node_path, path, entities);
+        }
+        self.extract_into(&node.content, Some(node_path), entities);
+        for child in &node.children {
+            self.collect(child, path, entities);
+        }
+    }
+
+    /// A microformat address is one entity, so the street address pattern
+    /// matching part of it isn't added again
+    fn collect_address(
+        &self,
+        node: &HtmlNode,
+        node_path: &str,
+        path: &mut Vec<String>,
+        entities: &mut Vec<Entity>,
+    ) {
+        let address = normalize_text(&node.text_content());
+        if address.is_empty() {
+            return;
+        }
+        let mut inner = Vec::new();
+        self.extract_into(&node.content, Some(node_path), &mut inner);
+        for child in &node.children {
+            self.collect(child, path, &mut inner);
+        }
+        if self.extracts(EntityKind::Address) {
+            let entity = Self::entity(
+                EntityKind::Address,
+                address.clone(),
+                &address,
+                0.9,
+                &address,
+            );
+            push_unique(entities, Self::located(entity, node_path));
+        }
+        for entity in inner {
+            if !(entity.kind == EntityKind::Address && address.contains(&entity.value)) {
+                push_unique(entities, entity);
+            }
+        }
+    }
+
+    /// Entities of the text of the element at `path`, if any
+    fn extract_into(&self, text: &str, path: Option<&str>, entities: &mut Vec<Entity>) {
+        if text.trim().is_empty() {
+            return;
+        }
+        let mut found: Vec<(usize, Entity)> = Vec::new();
+        let mut add = |regex: &Regex, normalize: &dyn Fn(&str) -> Option<Recognized>| {
+            for m in regex.find_iter(text) {
+                if let Some((kind, value, confidence)) = normalize(m.as_str()) {
+                    if self.extracts(kind) {
+                        let snippet = snippet(text, m.start(), m.end());
+                        let mut entity =
+                            Self::entity(kind, value, m.as_str(), confidence, &snippet);
+                        if let Some(path) = path {
+                            entity = Self::located(entity, path);
+                        }
+                        found.push((m.start(), entity));
+                    }
+                }
+            }
+        };
+        add(&self.email_regex, &|text| {
+            Some((EntityKind::Email, text.to_lowercase(), 0.9))
+        });
+        add(&self.phone_regex, &|text| {
+            normalize_phone(text).map(|(value, confidence)| (EntityKind::Phone, value, confidence))
+        });
+        add(&self.price_regex, &|text| {
+            // Amounts with a currency code could as well be quantities in a sentence
+            let confidence = if text.starts_with(['$', '€', '£', '¥', '₹']) {
+                0.9
+            } else {
+                0.8
+            };
+            normalize_price(text).map(|value| (EntityKind::Price, value, confidence))
+        });
+        add(&self.date_regex, &|text| {
+            let (value, confidence) = normalize_date(text);
+            Some((EntityKind::Date, value, confidence))
+        });
+        add(&self.address_regex, &|text| {
+            // A postcode at the end makes a false positive much less likely
+            let has_postcode = text.ends_with(|c: char| c.is_ascii_digit());
+            let confidence = if has_postcode { 0.8 } else { 0.6 };
+            Some((EntityKind::Address, text.to_string(), confidence))
+        });
+        add(&self.measure_regex, &|text| {
+            normalize_measure(text).map(|(dimension, value)| {
+                let kind = match dimension {
+                    Dimension::Area => EntityKind::Area,
+                    Dimension::Weight => EntityKind::Weight,
+                    Dimension::Duration => EntityKind::Duration,
+                };
+                (kind, value, 0.8)
+            })
+        });
+
+        found.sort_by_key(|(start, _)| *start);
+        for (_, entity) in found {
+            push_unique(entities, entity);
+        }
+    }
+
+    /// The text with emails, phone numbers and street addresses replaced by
+    /// `[email]`, `[phone]` and `[address]`
+    pub fn redact_pii(&self, text: &str) -> String {
+        let text = self.email_regex.replace_all(text, "[email]");
+        let text = self.phone_regex.replace_all(&text, "[phone]");
+        self.address_regex
+            .replace_all(&text, "[address]")
+            .into_owned()
+    }
+
+    fn entity(
+        kind: EntityKind,
+        value: String,
+        text: &str,
+        confidence: f32,
+        snippet: &str,
+    ) -> Entity {
+        Entity {
+            id: entity_id(kind, &value),
+            kind,
+            value,
+            text: text.trim().to_string(),
+            confidence,
+            flags: Vec::new(),
+            provenance: Some(Provenance {
+                url: String::new(),
+                path: None,
+                snippet: snippet.to_string(),
+                extracted_at: None,
+            }),
+            status: None,
+        }
+    }
+
+    fn located(mut entity: Entity, path: &str) -> Entity {
+        if let Some(provenance) = &mut entity.provenance {
+            provenance.path = Some(path.to_string());
+        }
+        entity
+    }
+}
+
+impl Default for EntityExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Add an entity unless it's known, keeping the higher confidence when it is
+fn push_unique(entities: &mut Vec<Entity>, entity: Entity) {
+    match entities
+        .iter_mut()
+        .find(|known| known.kind == entity.kind && known.value == entity.value)
+    {
+        Some(known) => known.confidence = known.confidence.max(entity.confidence),
+        None => entities.push(entity),
+    }
+}
+
+/// The text from `SNIPPET_CONTEXT` characters before `start` to as many after
+/// `end`, with whitespace collapsed
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let before: String = {
+        let chars: Vec<char> = text[..start].chars().rev().take(SNIPPET_CONTEXT).collect();
+        chars.into_iter().rev().collect()
+    };
+    let after: String = text[end..].chars().take(SNIPPET_CONTEXT).collect();
+    normalize_text(&format!("{before}{}{after}", &text[start..end]))
+}
+
+/// Digits of a phone number, with a leading `+` for international numbers
+fn normalize_phone(text: &str) -> Option<(String, f32)> {
+    let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+    if !(7..=15).contains(&digits.len()) {
+        return None;
+    }
+    if text.trim().starts_with('+') {
+        Some((format!("+{digits}"), 0.8))
+    } else {
+        Some((digits, 0.7))
+    }
+}
+
+/// ISO date when the text is unambiguous, the text itself otherwise
+/// (e.g. `03/04/2025`, which is read differently in the US and Europe)
+fn normalize_date(text: &str) -> (String, f32) {
+    let cleaned = text.replace(['.', ','], "");
+    ["%Y-%m-%d", "%B %d %Y", "%b %d %Y", "%d %B %Y", "%d %b %Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(&cleaned, format).ok())
+        .map(|date| (date.format("%Y-%m-%d").to_string(), 0.9))
+        .unwrap_or_else(|| (text.to_string(), 0.5))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::HtmlParser;
+
+    fn values(entities: &[Entity], kind: EntityKind) -> Vec<&str> {
+        entities
+            .iter()
+            .filter(|entity| entity.kind == kind)
+            .map(|entity| entity.value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_entity_ids() {
+        let extractor = EntityExtractor::new();
+        let first = extractor.extract("Mail SALES@example.com");
+        let second = extractor.extract("Or sales@example.com, for orders");
+        assert_eq!(first[0].id, second[0].id);
+        assert_eq!(
+            first[0].id,
+            entity_id(EntityKind::Email, "sales@example.com")
+        );
+        // Pinned, so a change of hash shows up before it breaks joins
+        assert_eq!(first[0].id, "755fe7e5884f8776");
+        assert_ne!(
+            entity_id(EntityKind::Email, "sales@example.com"),
+            entity_id(EntityKind::Phone, "sales@example.com")
+        );
+    }
+
+    #[test]
+    fn test_redact_pii() {
+        let extractor = EntityExtractor::new();
+        assert_eq!(
+            extractor.redact_pii(
+                "Write to sales@example.com, call 555-010-4000 or visit 221 Baker Street, London. From $20."
+            ),
+            "Write to [email], call [phone] or visit [address]. From $20."
+        );
+        assert_eq!(extractor.redact_pii("Nothing to hide"), "Nothing to hide");
+    }
+
+    #[test]
+    fn test_extract_entities_from_text() {
+        let extractor = EntityExtractor::new();
+        let entities = extractor.extract(
+            "Write to Sales@Example.com or call +1 (555) 010-2030 / 555-010-4000. \
+             Plans from $1,299.00 or 19,99 € per month, starting March 14, 2025 (or 2025-04-01, 03/04/2025). \
+             Visit us at 221 Baker Street, London. Ships in 2 days, weighs 1.5 kg (3.3 lbs), needs 12 m² of floor.",
+        );
+
+        assert_eq!(
+            values(&entities, EntityKind::Email),
+            vec!["sales@example.com"]
+        );
+        assert_eq!(
+            values(&entities, EntityKind::Phone),
+            vec!["+15550102030", "5550104000"]
+        );
+        assert_eq!(
+            values(&entities, EntityKind::Price),
+            vec!["1299.00 USD", "19.99 EUR/month"]
+        );
+        assert_eq!(
+            values(&entities, EntityKind::Date),
+            vec!["2025-03-14", "2025-04-01", "03/04/2025"]
+        );
+        assert_eq!(
+            values(&entities, EntityKind::Address),
+            vec!["221 Baker Street, London"]
+        );
+        assert_eq!(values(&entities, EntityKind::Duration), vec!["172800 s"]);
+        assert_eq!(
+            values(&entities, EntityKind::Weight),
+            vec!["1.5 kg", "1.497 kg"]
+        );
+        assert_eq!(values(&entities, EntityKind::Area), vec!["12 m2"]);
+        assert_eq!(entities[0].text, "Sales@Example.com");
+    }
+
+    #[test]
+    fn test_extract_entities_from_tree() {
+        let parser = HtmlParser::new();
+        let tree = parser.parse(
+            r#"<html><body>
+                <a href="mailto:jobs@example.com?subject=Hi">Email us</a>
+                <a href="tel:+44 20 7946 0018">Call</a>
+                <p>jobs@example.com</p>
+                <p>Open since 12 Jan 2010</p>
+            </body></html>"#,
+        );
+
+        let entities = EntityExtractor::new().extract_from_tree(&tree);
+        assert_eq!(
+            entities
+                .iter()
+                .map(|entity| (entity.kind, entity.value.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (EntityKind::Email, "jobs@example.com"),
+                (EntityKind::Phone, "+442079460018"),
+                (EntityKind::Date, "2010-01-12"),
+            ]
+        );
+        // The link makes the email certain even though the text repeats it
+        assert_eq!(entities[0].confidence, 1.0);
+        let provenance = entities[0].provenance.as_ref().unwrap();
+        assert_eq!(provenance.path.as_deref(), Some("html body a"));
+        assert_eq!(provenance.snippet, "Email us");
+        let provenance = entities[2].provenance.as_ref().unwrap();
+        assert_eq!(provenance.path.as_deref(), Some("html body p"));
+        assert_eq!(provenance.snippet, "Open since 12 Jan 2010");
+        assert_eq!(
+            tree.find_by_path("html body p")[1].content.trim(),
+            "Open since 12 Jan 2010"
+        );
+    }
+
+    #[test]
+    fn test_extract_only_some_kinds() {
+        let extractor =
+            EntityExtractor::new().kinds(&parse_entity_kinds("date, email,date").unwrap());
+        let tree = HtmlParser::new().parse(
+            r#"<a href="tel:+44 20 7946 0018">Call</a><a href="mailto:jobs@example.com">Email</a>
+               <p>From $5 on 2025-03-14, 2 kg</p>"#,
+        );
+        let kinds: Vec<EntityKind> = extractor
+            .extract_from_tree(&tree)
+            .iter()
+            .map(|entity| entity.kind)
+            .collect();
+        assert_eq!(kinds, vec![EntityKind::Email, EntityKind::Date]);
+        // Redaction doesn't depend on the kinds extracted
+        assert_eq!(extractor.redact_pii("Call 555-010-4000"), "Call [phone]");
+
+        assert!(parse_entity_kinds("price,person").is_err());
+        assert_eq!(parse_entity_kinds("").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_confidence_summary() {
+        let entities = EntityExtractor::new()
+            .extract("On 2025-03-14 or 03/04/2025 at 10 Downing Street, for $5");
+
+        let summaries = summarize_confidence(&entities);
+        let dates = &summaries[0];
+        assert_eq!((dates.kind, dates.count), (EntityKind::Date, 2));
+        assert!((dates.mean - 0.7).abs() < 1e-6);
+        assert_eq!(dates.min, 0.5);
+        assert_eq!(summaries.len(), 3);
+
+        let review: Vec<&str> = needs_review(&entities, 0.7)
+            .iter()
+            .map(|entity| entity.text.as_str())
+            .collect();
+        assert_eq!(review, vec!["03/04/2025", "10 Downing Street"]);
+    }
+}
@@ -0,0 +1,267 @@
+use crate::network_guard::http_client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::debug;
+
+/// Largest response fetched by default, 50 MB
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Content types fetched by default: pages, text and the JSON, CSV and XML
+/// documents read as structured content. `type/*` matches any subtype and
+/// `*+suffix` any type with that suffix.
+pub const DEFAULT_CONTENT_TYPES: &[&str] = &[
+    "text/*",
+    "application/xhtml+xml",
+    "application/xml",
+    "application/json",
+    "application/csv",
+    "*+json",
+    "*+xml",
+];
+
+/// Extensions of archives, executables, disk images and media, skipped
+/// without asking the server
+const BINARY_EXTENSIONS: &[&str] = &[
+    "7z", "apk", "avi", "bin", "bz2", "dmg", "exe", "flac", "gz", "iso", "jar", "m4a", "mkv",
+    "mov", "mp3", "mp4", "msi", "ogg", "rar", "tar", "tgz", "wav", "webm", "xz", "zip",
+];
+
+/// Longest the HEAD request before a fetch may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the crawler fetches, checked before a page is downloaded so a stray
+/// link to a large dataset or a binary doesn't stall the crawl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FetchLimits {
+    /// Responses announcing a larger Content-Length are skipped, and bodies
+    /// read without the browser stop at this size; None for no limit
+    pub max_file_size: Option<u64>,
+    /// Content types fetched, others are skipped
+    pub content_types: Vec<String>,
+}
+
+impl Default for FetchLimits {
+    fn default() -> Self {
+        FetchLimits {
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+            content_types: DEFAULT_CONTENT_TYPES
+                .iter()
+                .map(|pattern| pattern.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl FetchLimits {
+    /// Whether a content type such as `text/html; charset=utf-8` is allowed
+    pub fn allows_content_type(&self, content_type: &str) -> bool {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        self.content_types.iter().any(|pattern| {
+            let pattern = pattern.trim().to_lowercase();
+            if let Some(suffix) = pattern.strip_prefix('*') {
+                mime.ends_with(suffix)
+            } else if let Some(kind) = pattern.strip_suffix("/*") {
+                mime.split('/').next() == Some(kind)
+            } else {
+                mime == pattern
+            }
+        })
+    }
+
+    /// Why a response with these headers isn't fetched, None when it is.
+    /// Missing headers pass.
+    pub fn skip_reason(
+        &self,
+        content_type: Option<&str>,
+        content_length: Option<u64>,
+    ) -> Option<String> {
+        if let (Some(max), Some(length)) = (self.max_file_size, content_length) {
+            if length > max {
+                return Some(format!("{length} bytes, over the {max} byte limit"));
+            }
+        }
+        match content_type {
+            Some(content_type) if !self.allows_content_type(content_type) => {
+                Some(format!("content type {content_type} isn't fetched"))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Why `url` isn't fetched: a binary file extension, or a HEAD response
+/// over the size limit or of a content type that isn't allowed. Servers
+/// that don't answer HEAD let the page through to the browser. Unless
+/// `allow_private`, the HEAD request isn't redirected to private networks;
+/// `url` itself is checked by the caller.
+pub async fn check_fetch(url: &str, limits: &FetchLimits, allow_private: bool) -> Option<String> {
+    let extension = url::Url::parse(url)
+        .ok()?
+        .path()
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase());
+    if let Some(extension) = extension.filter(|e| BINARY_EXTENSIONS.contains(&e.as_str())) {
+        return Some(format!(".{extension} file"));
+    }
+
+    let client = http_client(CHECK_TIMEOUT, allow_private);
+    let response = match client.head(url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            debug!(
+                "HEAD {} returned {}, fetching it anyway",
+                url,
+                response.status()
+            );
+            return None;
+        }
+        Err(e) => {
+            debug!("HEAD {} failed, fetching it anyway: {}", url, e);
+            return None;
+        }
+    };
+    let headers = response.headers();
+    let content_type = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    let content_length = headers
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    limits.skip_reason(content_type, content_length)
+}
+
+/// Read a response body chunk by chunk, failing as soon as it's over
+/// `max_bytes`: chunked responses and those without or with a wrong
+/// Content-Length are capped as well as the others
+pub async fn read_body(
+    mut response: reqwest::Response,
+    max_bytes: Option<u64>,
+) -> Result<Vec<u8>, String> {
+    let max = max_bytes.unwrap_or(u64::MAX);
+    if let Some(length) = response.content_length().filter(|&length| length > max) {
+        return Err(format!("{length} bytes, over the {max} byte limit"));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+        if (body.len() + chunk.len()) as u64 > max {
+            return Err(format!("over the {max} byte limit"));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_skip_reason() {
+        let limits = FetchLimits::default();
+        assert_eq!(
+            limits.skip_reason(Some("text/html; charset=UTF-8"), Some(2048)),
+            None
+        );
+        assert_eq!(limits.skip_reason(Some("application/ld+json"), None), None);
+        assert_eq!(limits.skip_reason(None, None), None);
+        assert_eq!(
+            limits
+                .skip_reason(Some("application/zip"), Some(100))
+                .as_deref(),
+            Some("content type application/zip isn't fetched")
+        );
+        assert!(limits
+            .skip_reason(Some("text/csv"), Some(2 * 1024 * 1024 * 1024))
+            .unwrap()
+            .contains("over the 52428800 byte limit"));
+
+        let limits = FetchLimits {
+            max_file_size: None,
+            content_types: vec!["text/html".to_string(), "application/pdf".to_string()],
+        };
+        assert_eq!(
+            limits.skip_reason(Some("application/pdf"), Some(u64::MAX)),
+            None
+        );
+        assert!(limits.skip_reason(Some("text/plain"), None).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_check_fetch() {
+        // /data is a large CSV, /report a PDF and everything else a page
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let headers = match request.split_whitespace().nth(1) {
+                    Some("/data") => "Content-Type: text/csv\r\nContent-Length: 2147483648",
+                    Some("/report") => "Content-Type: application/pdf\r\nContent-Length: 1024",
+                    _ => "Content-Type: text/html\r\nContent-Length: 512",
+                };
+                let response = format!("HTTP/1.1 200 OK\r\n{headers}\r\nConnection: close\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.ok();
+            }
+        });
+
+        let limits = FetchLimits::default();
+        let url = |path: &str| format!("http://127.0.0.1:{port}{path}");
+        assert_eq!(check_fetch(&url("/about"), &limits, true).await, None);
+        assert!(check_fetch(&url("/data"), &limits, true)
+            .await
+            .unwrap()
+            .contains("2147483648 bytes"));
+        assert!(check_fetch(&url("/report"), &limits, true).await.is_some());
+        assert_eq!(
+            check_fetch("https://example.com/downloads/Setup.EXE", &limits, false)
+                .await
+                .as_deref(),
+            Some(".exe file")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_body_caps_chunked_responses() {
+        // Five 1 KB chunks without a Content-Length
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let _ = stream.read(&mut request).await;
+                let mut response = String::from(
+                    "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+                );
+                for _ in 0..5 {
+                    response.push_str(&format!("400\r\n{}\r\n", "x".repeat(1024)));
+                }
+                response.push_str("0\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.ok();
+            }
+        });
+
+        let client = http_client(Duration::from_secs(5), true);
+        let url = format!("http://127.0.0.1:{port}/data");
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.content_length(), None);
+        assert_eq!(
+            read_body(response, Some(4096)).await.unwrap_err(),
+            "over the 4096 byte limit"
+        );
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(read_body(response, Some(8192)).await.unwrap().len(), 5120);
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(read_body(response, None).await.unwrap().len(), 5120);
+    }
+}
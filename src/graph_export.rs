@@ -0,0 +1,417 @@
+use crate::contact::merge_contact_info;
+use crate::cypher_export::render_cypher;
+use crate::entities::{Entity, EntityKind};
+use crate::results::ScrapedWebPage;
+use crate::utils::construct_root_url;
+use serde_json::{json, Map, Value};
+use std::fmt::Write;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Vocabulary of the exported graph
+pub const SCHEMA_ORG: &str = "https://schema.org/";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    JsonLd,
+    Turtle,
+    /// Cypher statements building a property graph, see `render_cypher`
+    Cypher,
+}
+
+impl GraphFormat {
+    /// Turtle for `.ttl` files, Cypher for `.cypher` and `.cql`, JSON-LD
+    /// otherwise
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("ttl") => GraphFormat::Turtle,
+            Some("cypher" | "cql") => GraphFormat::Cypher,
+            _ => GraphFormat::JsonLd,
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonld" => Ok(GraphFormat::JsonLd),
+            "ttl" => Ok(GraphFormat::Turtle),
+            "cypher" => Ok(GraphFormat::Cypher),
+            other => Err(format!(
+                "Invalid graph format '{other}', expected jsonld, ttl or cypher"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GraphValue {
+    Text(String),
+    /// A decimal number, written as found in the normalized entity value
+    Number(String),
+    /// Another node or a web page, by IRI
+    Node(String),
+}
+
+/// A schema.org resource and its properties, in the order they are written
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub id: String,
+    /// schema.org type, e.g. `WebPage`
+    pub kind: &'static str,
+    pub properties: Vec<(&'static str, GraphValue)>,
+}
+
+impl GraphNode {
+    fn new(id: String, kind: &'static str) -> Self {
+        GraphNode {
+            id,
+            kind,
+            properties: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, property: &'static str, value: GraphValue) {
+        self.properties.push((property, value));
+    }
+}
+
+/// IRI of an entity, from its `entity_id`, so the same value on several
+/// pages, runs or datasets is one node
+pub fn entity_iri(entity: &Entity) -> String {
+    format!("urn:smart-crawler:entity:{}", entity.id)
+}
+
+/// schema.org node of an entity: a `ContactPoint` for emails and phones, a
+/// `PostalAddress`, a `PriceSpecification` (`UnitPriceSpecification` with a
+/// billing period) or a `QuantitativeValue` with a UN/CEFACT unit code.
+/// Dates, which schema.org has no node for, and values that don't parse
+/// (e.g. a reviewer's free-form correction) have none.
+fn entity_node(entity: &Entity) -> Option<GraphNode> {
+    let text = |value: &str| GraphValue::Text(value.to_string());
+    let number = |value: &str| {
+        value
+            .parse::<f64>()
+            .is_ok()
+            .then(|| GraphValue::Number(value.to_string()))
+    };
+    let iri = entity_iri(entity);
+    let node = match entity.kind {
+        EntityKind::Email => {
+            let mut node = GraphNode::new(iri, "ContactPoint");
+            node.add("email", text(&entity.value));
+            node
+        }
+        EntityKind::Phone => {
+            let mut node = GraphNode::new(iri, "ContactPoint");
+            node.add("telephone", text(&entity.value));
+            node
+        }
+        EntityKind::Address => {
+            let mut node = GraphNode::new(iri, "PostalAddress");
+            node.add("name", text(&entity.value));
+            node
+        }
+        EntityKind::Price => {
+            let (amount, currency) = entity.value.split_once(' ')?;
+            let (currency, period) = match currency.split_once('/') {
+                Some((currency, period)) => (currency, Some(period)),
+                None => (currency, None),
+            };
+            let kind = match period {
+                Some(_) => "UnitPriceSpecification",
+                None => "PriceSpecification",
+            };
+            let mut node = GraphNode::new(iri, kind);
+            node.add("price", number(amount)?);
+            node.add("priceCurrency", text(currency));
+            if let Some(period) = period {
+                node.add("unitText", text(period));
+            }
+            node
+        }
+        EntityKind::Area | EntityKind::Weight | EntityKind::Duration => {
+            let (amount, _) = entity.value.split_once(' ')?;
+            let unit_code = match entity.kind {
+                EntityKind::Area => "MTK",
+                EntityKind::Weight => "KGM",
+                _ => "SEC",
+            };
+            let mut node = GraphNode::new(iri, "QuantitativeValue");
+            node.add("value", number(amount)?);
+            node.add("unitCode", text(unit_code));
+            node
+        }
+        EntityKind::Date => return None,
+    };
+    Some(node)
+}
+
+/// The crawl as schema.org nodes: per domain an `Organization` with the
+/// contact details merged across its pages, a `WebPage` published by it for
+/// each page, and the entities the pages mention. Duplicate pages are left
+/// out, and an entity found on several pages is one node.
+pub fn build_graph(pages: &[ScrapedWebPage]) -> Vec<GraphNode> {
+    let pages: Vec<&ScrapedWebPage> = pages
+        .iter()
+        .filter(|page| page.duplicate_of.is_none())
+        .collect();
+    let mut domains: Vec<&str> = Vec::new();
+    for page in &pages {
+        if !domains.contains(&page.domain.as_str()) {
+            domains.push(&page.domain);
+        }
+    }
+
+    let mut nodes = Vec::new();
+    let mut entity_nodes: Vec<GraphNode> = Vec::new();
+    for domain in domains {
+        let domain_pages: Vec<ScrapedWebPage> = pages
+            .iter()
+            .filter(|page| page.domain == domain)
+            .map(|page| (*page).clone())
+            .collect();
+        let root_url = construct_root_url(domain);
+        let organization_iri = format!("{root_url}#organization");
+        let mut organization = GraphNode::new(organization_iri.clone(), "Organization");
+        organization.add("url", GraphValue::Node(root_url));
+        let contact = merge_contact_info(&domain_pages);
+        for email in contact.emails {
+            organization.add("email", GraphValue::Text(email));
+        }
+        for phone in contact.phones {
+            organization.add("telephone", GraphValue::Text(phone));
+        }
+        for address in contact.addresses {
+            organization.add("address", GraphValue::Text(address));
+        }
+        nodes.push(organization);
+
+        for page in &domain_pages {
+            let mut node = GraphNode::new(page.url.clone(), "WebPage");
+            node.add("url", GraphValue::Node(page.url.clone()));
+            if let Some(title) = &page.title {
+                node.add("name", GraphValue::Text(title.clone()));
+            }
+            node.add("publisher", GraphValue::Node(organization_iri.clone()));
+            for entity in &page.entities {
+                let Some(entity_node) = entity_node(entity) else {
+                    continue;
+                };
+                node.add("mentions", GraphValue::Node(entity_node.id.clone()));
+                if !entity_nodes.iter().any(|known| known.id == entity_node.id) {
+                    entity_nodes.push(entity_node);
+                }
+            }
+            nodes.push(node);
+        }
+    }
+    nodes.extend(entity_nodes);
+    nodes
+}
+
+/// JSON-LD document with the schema.org context and the nodes in `@graph`.
+/// Properties with several values are arrays.
+fn render_json_ld(nodes: &[GraphNode]) -> String {
+    let graph: Vec<Value> = nodes
+        .iter()
+        .map(|node| {
+            let mut object = Map::new();
+            object.insert("@id".to_string(), json!(node.id));
+            object.insert("@type".to_string(), json!(node.kind));
+            for (property, value) in &node.properties {
+                let value = match value {
+                    GraphValue::Text(text) => json!(text),
+                    GraphValue::Number(number) => {
+                        number.parse::<Value>().unwrap_or_else(|_| json!(number))
+                    }
+                    GraphValue::Node(iri) => json!({"@id": iri}),
+                };
+                match object.get_mut(*property) {
+                    Some(Value::Array(values)) => values.push(value),
+                    Some(first) => *first = json!([first.take(), value]),
+                    None => {
+                        object.insert(property.to_string(), value);
+                    }
+                }
+            }
+            Value::Object(object)
+        })
+        .collect();
+    let document = json!({"@context": SCHEMA_ORG, "@graph": graph});
+    serde_json::to_string_pretty(&document).unwrap_or_default()
+}
+
+/// An IRI between `<>`, with the characters Turtle doesn't allow in one
+/// percent-encoded
+fn turtle_iri(iri: &str) -> String {
+    let mut out = String::from("<");
+    for c in iri.chars() {
+        if c <= ' ' || "<>\"{}|^`\\".contains(c) {
+            let _ = write!(out, "%{:02X}", c as u32);
+        } else {
+            out.push(c);
+        }
+    }
+    out.push('>');
+    out
+}
+
+fn turtle_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Turtle with the `schema:` prefix, one block of statements per node
+fn render_turtle(nodes: &[GraphNode]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "@prefix schema: <{SCHEMA_ORG}> .");
+    for node in nodes {
+        let _ = write!(out, "\n{} a schema:{}", turtle_iri(&node.id), node.kind);
+        for (property, value) in &node.properties {
+            let value = match value {
+                GraphValue::Text(text) => turtle_string(text),
+                GraphValue::Number(number) => number.clone(),
+                GraphValue::Node(iri) => turtle_iri(iri),
+            };
+            let _ = write!(out, " ;\n    schema:{property} {value}");
+        }
+        out.push_str(" .\n");
+    }
+    out
+}
+
+/// The crawled pages and their entities in `format`: the schema.org graph
+/// of `build_graph` as JSON-LD or Turtle, or the property graph of
+/// `render_cypher`
+pub fn render_graph(pages: &[ScrapedWebPage], format: GraphFormat) -> String {
+    match format {
+        GraphFormat::JsonLd => render_json_ld(&build_graph(pages)),
+        GraphFormat::Turtle => render_turtle(&build_graph(pages)),
+        GraphFormat::Cypher => render_cypher(pages),
+    }
+}
+
+pub fn write_graph(
+    path: &str,
+    pages: &[ScrapedWebPage],
+    format: GraphFormat,
+) -> Result<(), String> {
+    std::fs::write(path, render_graph(pages, format))
+        .map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+
+    fn page(url: &str, title: &str, text: &str) -> ScrapedWebPage {
+        let mut page: ScrapedWebPage = serde_json::from_value(json!({
+            "url": url,
+            "domain": "example.com",
+            "title": title,
+        }))
+        .unwrap();
+        page.entities = EntityExtractor::new().extract(text);
+        page
+    }
+
+    #[test]
+    fn test_build_graph() {
+        let pages = vec![
+            page(
+                "https://example.com/pricing",
+                "Pricing",
+                "Pro plan €49/mo, on 2025-03-14. Write to sales@example.com",
+            ),
+            page(
+                "https://example.com/contact",
+                "Contact \"us\"",
+                "sales@example.com or +44 20 7946 0018",
+            ),
+        ];
+        let nodes = build_graph(&pages);
+        let kinds: Vec<&str> = nodes.iter().map(|node| node.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "Organization",
+                "WebPage",
+                "WebPage",
+                "UnitPriceSpecification",
+                "ContactPoint",
+                "ContactPoint",
+            ]
+        );
+        assert_eq!(nodes[0].id, "https://example.com/#organization");
+        assert!(nodes[0]
+            .properties
+            .contains(&("telephone", GraphValue::Text("+442079460018".to_string()))));
+        // The email on both pages is one node, mentioned by each
+        let email = &nodes[4].id;
+        for page in &nodes[1..3] {
+            assert!(page
+                .properties
+                .contains(&("mentions", GraphValue::Node(email.clone()))));
+        }
+    }
+
+    #[test]
+    fn test_render_graph() {
+        let pages = vec![page(
+            "https://example.com/pricing",
+            "Plans \"2025\"",
+            "From €49/mo or 1,200 sq ft",
+        )];
+        let document: Value =
+            serde_json::from_str(&render_graph(&pages, GraphFormat::JsonLd)).unwrap();
+        assert_eq!(document["@context"], SCHEMA_ORG);
+        let graph = document["@graph"].as_array().unwrap();
+        let page = &graph[1];
+        assert_eq!(page["@type"], "WebPage");
+        assert_eq!(
+            page["publisher"]["@id"],
+            "https://example.com/#organization"
+        );
+        assert_eq!(page["mentions"].as_array().unwrap().len(), 2);
+        assert_eq!(graph[2]["price"], 49);
+        assert_eq!(graph[2]["priceCurrency"], "EUR");
+        assert_eq!(graph[3]["unitCode"], "MTK");
+
+        let turtle = render_graph(&pages, GraphFormat::Turtle);
+        assert!(turtle.starts_with("@prefix schema: <https://schema.org/> .\n"));
+        assert!(turtle.contains(
+            "<https://example.com/pricing> a schema:WebPage ;\n    schema:url <https://example.com/pricing> ;\n    schema:name \"Plans \\\"2025\\\"\" ;"
+        ));
+        assert!(turtle.contains("schema:price 49 ;\n    schema:priceCurrency \"EUR\" ;\n    schema:unitText \"month\" ."));
+        assert!(turtle.contains("schema:value 111.484 ;"));
+    }
+
+    #[test]
+    fn test_graph_format() {
+        assert_eq!(GraphFormat::from_path("graph.TTL"), GraphFormat::Turtle);
+        assert_eq!(GraphFormat::from_path("graph.json"), GraphFormat::JsonLd);
+        assert_eq!(GraphFormat::from_path("graph.cql"), GraphFormat::Cypher);
+        assert_eq!("ttl".parse(), Ok(GraphFormat::Turtle));
+        assert!("rdf".parse::<GraphFormat>().is_err());
+    }
+}
@@ -1,10 +1,20 @@
+use crate::bounding_box::{BoundingBox, RenderedStyle, BOX_ATTRIBUTE, STYLE_ATTRIBUTE};
+use crate::language::{normalize_language_tag, HreflangAlternate};
 use crate::storage::{DomainDuplicates, NodeSignature};
-use crate::utils::trim_and_clean_text;
+use crate::text_normalize::normalize_text;
+use crate::utils::site_host;
 use scraper::{ElementRef, Html, Selector};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
 use url::Url;
 
+/// Content `filter_domain_duplicates` gives nodes that repeat across the
+/// domain's pages
+pub const FILTERED_DUPLICATE: &str = "[FILTERED DUPLICATE]";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HtmlNode {
     pub tag: String,
@@ -12,6 +22,15 @@ pub struct HtmlNode {
     pub id: Option<String>,
     pub content: String,
     pub children: Vec<HtmlNode>,
+    /// Link target of `<a>` elements, as written in the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub href: Option<String>,
+    /// Rendered position of the element, present when boxes were captured in the browser
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounding_box: Option<BoundingBox>,
+    /// Computed font style, present for elements with direct text when boxes were captured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rendered_style: Option<RenderedStyle>,
 }
 
 impl HtmlNode {
@@ -22,6 +41,9 @@ impl HtmlNode {
             id,
             content,
             children: Vec::new(),
+            href: None,
+            bounding_box: None,
+            rendered_style: None,
         }
     }
 
@@ -29,6 +51,60 @@ impl HtmlNode {
         self.children.push(child);
     }
 
+    /// Rough heap and inline size of this node and its subtree, to keep
+    /// memory use of stored pages in check
+    pub fn estimated_bytes(&self) -> usize {
+        std::mem::size_of::<HtmlNode>()
+            + self.tag.len()
+            + self.classes.iter().map(String::len).sum::<usize>()
+            + self.id.as_ref().map_or(0, String::len)
+            + self.content.len()
+            + self.href.as_ref().map_or(0, String::len)
+            + self
+                .children
+                .iter()
+                .map(HtmlNode::estimated_bytes)
+                .sum::<usize>()
+    }
+
+    /// Hash of the complete structure: tag, classes, id, content and children.
+    /// Rehashes the whole subtree; see `StructureHashes` for hashing every
+    /// node of a tree.
+    pub fn structure_hash(&self) -> u64 {
+        let child_hashes: Vec<u64> = self
+            .children
+            .iter()
+            .map(|child| child.structure_hash())
+            .collect();
+        self.hash_with_children(&child_hashes)
+    }
+
+    fn hash_with_children(&self, child_hashes: &[u64]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.tag.hash(&mut hasher);
+        self.classes.hash(&mut hasher);
+        self.id.hash(&mut hasher);
+        self.content.hash(&mut hasher);
+        child_hashes.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// All text content of this node and its descendants, space separated
+    pub fn text_content(&self) -> String {
+        let mut parts = Vec::new();
+        self.collect_text(&mut parts);
+        parts.join(" ")
+    }
+
+    fn collect_text<'a>(&'a self, parts: &mut Vec<&'a str>) {
+        if !self.content.is_empty() {
+            parts.push(&self.content);
+        }
+        for child in &self.children {
+            child.collect_text(parts);
+        }
+    }
+
     pub fn find_title(&self) -> Option<String> {
         if self.tag == "title" && !self.content.is_empty() {
             return Some(self.content.clone());
@@ -106,6 +182,45 @@ impl HtmlNode {
     }
 }
 
+/// Structure hashes of every node of a tree, computed bottom-up in one pass
+/// so looking up a node doesn't rehash its subtree. The table borrows the
+/// tree, which can't change while it's in use; build a new one after edits.
+pub struct StructureHashes<'a> {
+    /// By node address, stable while the tree is borrowed
+    hashes: HashMap<usize, u64>,
+    tree: PhantomData<&'a HtmlNode>,
+}
+
+impl<'a> StructureHashes<'a> {
+    pub fn new(tree: &'a HtmlNode) -> Self {
+        let mut hashes = HashMap::new();
+        Self::insert(tree, &mut hashes);
+        StructureHashes {
+            hashes,
+            tree: PhantomData,
+        }
+    }
+
+    fn insert(node: &HtmlNode, hashes: &mut HashMap<usize, u64>) -> u64 {
+        let child_hashes: Vec<u64> = node
+            .children
+            .iter()
+            .map(|child| Self::insert(child, hashes))
+            .collect();
+        let hash = node.hash_with_children(&child_hashes);
+        hashes.insert(node as *const HtmlNode as usize, hash);
+        hash
+    }
+
+    /// `node.structure_hash()`, hashed anew for nodes outside the tree
+    pub fn get(&self, node: &'a HtmlNode) -> u64 {
+        self.hashes
+            .get(&(node as *const HtmlNode as usize))
+            .copied()
+            .unwrap_or_else(|| node.structure_hash())
+    }
+}
+
 pub struct HtmlParser {
     ignored_tags: HashSet<String>,
 }
@@ -164,13 +279,28 @@ impl HtmlParser {
         }
 
         let content = if children.is_empty() {
-            trim_and_clean_text(&self.extract_text_content(element))
+            normalize_text(&self.extract_text_content(element))
         } else {
             String::new()
         };
 
         let mut node = HtmlNode::new(tag, classes, id, content);
         node.children = children;
+        if node.tag == "a" {
+            node.href = element
+                .value()
+                .attr("href")
+                .map(|href| href.trim().to_string())
+                .filter(|href| !href.is_empty());
+        }
+        node.bounding_box = element
+            .value()
+            .attr(BOX_ATTRIBUTE)
+            .and_then(BoundingBox::from_attribute);
+        node.rendered_style = element
+            .value()
+            .attr(STYLE_ATTRIBUTE)
+            .and_then(RenderedStyle::from_attribute);
         node
     }
 
@@ -205,7 +335,15 @@ impl HtmlParser {
         node: &HtmlNode,
         domain_duplicates: &DomainDuplicates,
     ) -> HtmlNode {
-        let signature = NodeSignature::from_html_node(node);
+        Self::filter_duplicate_nodes(node, &StructureHashes::new(node), domain_duplicates)
+    }
+
+    fn filter_duplicate_nodes<'a>(
+        node: &'a HtmlNode,
+        hashes: &StructureHashes<'a>,
+        domain_duplicates: &DomainDuplicates,
+    ) -> HtmlNode {
+        let signature = NodeSignature::with_hash(node, hashes.get(node));
 
         // Create the filtered node structure
         let mut filtered_node = HtmlNode::new(
@@ -213,52 +351,143 @@ impl HtmlParser {
             node.classes.clone(),
             node.id.clone(),
             if domain_duplicates.is_duplicate(&signature) {
-                "[FILTERED DUPLICATE]".to_string()
+                FILTERED_DUPLICATE.to_string()
             } else {
                 node.content.clone()
             },
         );
 
+        filtered_node.href = node.href.clone();
+        filtered_node.bounding_box = node.bounding_box;
+        filtered_node.rendered_style = node.rendered_style;
+
         // Always process children to maintain structure
         for child in &node.children {
-            let filtered_child = Self::filter_domain_duplicates(child, domain_duplicates);
+            let filtered_child = Self::filter_duplicate_nodes(child, hashes, domain_duplicates);
             filtered_node.add_child(filtered_child);
         }
 
         filtered_node
     }
 
+    /// Links to `base_domain` and its subdomains; relative links resolve to
+    /// https, or http when the site is given as `http://domain`
     pub fn extract_links(&self, html: &str, base_domain: &str) -> Vec<String> {
+        self.collect_links(html, base_domain, false)
+    }
+
+    /// Like `extract_links`, without `rel="nofollow"` links
+    pub fn extract_followed_links(&self, html: &str, base_domain: &str) -> Vec<String> {
+        self.collect_links(html, base_domain, true)
+    }
+
+    fn collect_links(&self, html: &str, base_domain: &str, skip_nofollow: bool) -> Vec<String> {
         let document = Html::parse_document(html);
         let link_selector = Selector::parse("a[href]").unwrap();
-        let mut links = HashSet::new();
+        let mut seen = HashSet::new();
+        let mut links = Vec::new();
 
         for element in document.select(&link_selector) {
+            let nofollow = element.value().attr("rel").is_some_and(|rel| {
+                rel.split_whitespace()
+                    .any(|r| r.eq_ignore_ascii_case("nofollow"))
+            });
+            if skip_nofollow && nofollow {
+                continue;
+            }
             if let Some(href) = element.value().attr("href") {
                 if let Ok(url) = self.resolve_url(href, base_domain) {
-                    if self.is_same_domain(&url, base_domain) {
-                        links.insert(url);
+                    if self.is_same_domain(&url, base_domain) && seen.insert(url.clone()) {
+                        links.push(url);
+                    }
+                }
+            }
+        }
+
+        links
+    }
+
+    /// Language declared on the `<html lang>` attribute
+    pub fn detect_language(&self, html: &str) -> Option<String> {
+        let document = Html::parse_document(html);
+        let html_selector = Selector::parse("html[lang]").unwrap();
+
+        document
+            .select(&html_selector)
+            .next()
+            .and_then(|element| element.value().attr("lang"))
+            .map(normalize_language_tag)
+            .filter(|lang| !lang.is_empty())
+    }
+
+    /// Directives of `<meta name="robots">` tags; `none` means both
+    pub fn meta_robots(&self, html: &str) -> RobotsDirectives {
+        let document = Html::parse_document(html);
+        let meta_selector = Selector::parse("meta[name][content]").unwrap();
+        let mut directives = RobotsDirectives::default();
+
+        for element in document.select(&meta_selector) {
+            let name = element.value().attr("name").unwrap_or_default();
+            if !name.eq_ignore_ascii_case("robots") {
+                continue;
+            }
+            let content = element.value().attr("content").unwrap_or_default();
+            for directive in content.split(',').map(|d| d.trim().to_lowercase()) {
+                match directive.as_str() {
+                    "noindex" => directives.noindex = true,
+                    "nofollow" => directives.nofollow = true,
+                    "none" => {
+                        directives.noindex = true;
+                        directives.nofollow = true;
                     }
+                    _ => {}
                 }
             }
         }
+        directives
+    }
 
-        links.into_iter().collect()
+    /// Alternate-language versions of the page declared with `hreflang`
+    pub fn extract_hreflang_alternates(
+        &self,
+        html: &str,
+        base_domain: &str,
+    ) -> Vec<HreflangAlternate> {
+        let document = Html::parse_document(html);
+        let alternate_selector = Selector::parse("link[rel~=alternate][hreflang][href]").unwrap();
+
+        document
+            .select(&alternate_selector)
+            .filter_map(|element| {
+                let lang = normalize_language_tag(element.value().attr("hreflang")?);
+                let url = self
+                    .resolve_url(element.value().attr("href")?, base_domain)
+                    .ok()?;
+                Some(HreflangAlternate { lang, url })
+            })
+            .collect()
     }
 
     fn resolve_url(&self, href: &str, base_domain: &str) -> Result<String, String> {
+        let scheme = if base_domain.starts_with("http://") {
+            "http"
+        } else {
+            "https"
+        };
+        let base_domain = site_host(base_domain);
         if href.starts_with("http://") || href.starts_with("https://") {
             Ok(href.to_string())
         } else if href.starts_with('/') {
-            Ok(format!("https://{base_domain}{href}"))
+            Ok(format!("{scheme}://{base_domain}{href}"))
         } else if href.starts_with("//") {
-            Ok(format!("https:{href}"))
+            Ok(format!("{scheme}:{href}"))
         } else {
-            Ok(format!("https://{base_domain}/{href}"))
+            Ok(format!("{scheme}://{base_domain}/{href}"))
         }
     }
 
     fn is_same_domain(&self, url: &str, base_domain: &str) -> bool {
+        let base_domain = site_host(base_domain);
         if let Ok(parsed_url) = Url::parse(url) {
             if let Some(host) = parsed_url.host_str() {
                 return host == base_domain || host.ends_with(&format!(".{base_domain}"));
@@ -268,6 +497,69 @@ impl HtmlParser {
     }
 }
 
+/// What a page's `<meta name="robots">` asks crawlers not to do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RobotsDirectives {
+    /// Leave the page out of results
+    pub noindex: bool,
+    /// Don't follow the page's links
+    pub nofollow: bool,
+}
+
+/// A link from a page to another site
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExternalLink {
+    pub url: String,
+    /// Host of the link without a leading `www.`
+    pub domain: String,
+    pub text: String,
+}
+
+impl HtmlParser {
+    /// Links in a parsed page that lead off the page's own site (subdomains
+    /// count as the same site), resolved against the page URL
+    pub fn extract_external_links(&self, tree: &HtmlNode, page_url: &str) -> Vec<ExternalLink> {
+        let Ok(base) = Url::parse(page_url) else {
+            return Vec::new();
+        };
+        let own_domain = base
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches("www.")
+            .to_string();
+        let mut links = Vec::new();
+        Self::collect_external_links(tree, &base, &own_domain, &mut links);
+        links
+    }
+
+    fn collect_external_links(
+        node: &HtmlNode,
+        base: &Url,
+        own_domain: &str,
+        links: &mut Vec<ExternalLink>,
+    ) {
+        if let Some(url) = node.href.as_deref().and_then(|href| base.join(href).ok()) {
+            let domain = url
+                .host_str()
+                .unwrap_or_default()
+                .trim_start_matches("www.");
+            let related = domain == own_domain
+                || domain.ends_with(&format!(".{own_domain}"))
+                || own_domain.ends_with(&format!(".{domain}"));
+            if matches!(url.scheme(), "http" | "https") && !domain.is_empty() && !related {
+                links.push(ExternalLink {
+                    url: url.to_string(),
+                    domain: domain.to_string(),
+                    text: node.text_content().trim().to_string(),
+                });
+            }
+        }
+        for child in &node.children {
+            Self::collect_external_links(child, base, own_domain, links);
+        }
+    }
+}
+
 impl Default for HtmlParser {
     fn default() -> Self {
         Self::new()
@@ -278,6 +570,33 @@ impl Default for HtmlParser {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extract_external_links() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body>
+            <a href="/about">About</a>
+            <a href="https://blog.example.com/post">Blog</a>
+            <a href="https://www.rustconf.com/">RustConf 2025</a>
+            <a href="//eurorust.eu/tickets">EuroRust</a>
+            <a href="mailto:hi@example.com">Mail</a>
+        </body></html>"#;
+        let tree = parser.parse(html);
+
+        let links = parser.extract_external_links(&tree, "https://www.example.com/events");
+        let found: Vec<(&str, &str)> = links
+            .iter()
+            .map(|link| (link.domain.as_str(), link.text.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("rustconf.com", "RustConf 2025"),
+                ("eurorust.eu", "EuroRust")
+            ]
+        );
+        assert_eq!(links[1].url, "https://eurorust.eu/tickets");
+    }
+
     #[test]
     fn test_html_parser_basic() {
         let parser = HtmlParser::new();
@@ -344,6 +663,48 @@ mod tests {
         assert_eq!(body.children.len(), 3); // p, p, div
     }
 
+    #[test]
+    fn test_text_content() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body><h1>Title</h1><div><p>One</p><p>Two</p></div></body></html>"#;
+        assert_eq!(parser.parse(html).text_content(), "Title One Two");
+    }
+
+    #[test]
+    fn test_parse_link_href() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body><a href=" /item?id=1 ">Item</a><a>No target</a></body></html>"#;
+        let tree = parser.parse(html);
+
+        let body = &tree.children[0];
+        assert_eq!(body.children[0].href.as_deref(), Some("/item?id=1"));
+        assert!(body.children[1].href.is_none());
+    }
+
+    #[test]
+    fn test_parse_bounding_boxes() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body data-sc-box="0,0,1280,2000"><h1 data-sc-box="20,40,600,48">Hero</h1><p>No box</p></body></html>"#;
+        let tree = parser.parse(html);
+
+        let body = &tree.children[0];
+        assert_eq!(body.bounding_box.unwrap().height, 2000.0);
+        assert_eq!(
+            body.children[0].bounding_box,
+            Some(BoundingBox {
+                x: 20.0,
+                y: 40.0,
+                width: 600.0,
+                height: 48.0
+            })
+        );
+        assert!(body.children[1].bounding_box.is_none());
+
+        // Positions differ between pages and must not affect duplicate detection
+        let plain = parser.parse(r#"<html><body><h1>Hero</h1><p>No box</p></body></html>"#);
+        assert_eq!(tree.structure_hash(), plain.structure_hash());
+    }
+
     #[test]
     fn test_find_title() {
         let parser = HtmlParser::new();
@@ -382,6 +743,70 @@ mod tests {
         // Protocol-relative URLs are handled correctly
         assert!(links.iter().any(|link| link.contains("page4")));
         assert!(!links.iter().any(|link| link.contains("other.com")));
+
+        let links = parser.extract_links(html, "http://example.com");
+        assert!(links.contains(&"http://example.com/page1".to_string()));
+        assert!(links.contains(&"https://example.com/page2".to_string()));
+    }
+
+    #[test]
+    fn test_robots_directives() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><head><meta name="ROBOTS" content="noindex, follow"></head><body>
+            <a href="/about">About</a>
+            <a href="/login" rel="nofollow noopener">Login</a>
+        </body></html>"#;
+
+        assert_eq!(
+            parser.meta_robots(html),
+            RobotsDirectives {
+                noindex: true,
+                nofollow: false
+            }
+        );
+        assert_eq!(
+            parser.extract_followed_links(html, "example.com"),
+            vec!["https://example.com/about"]
+        );
+        assert_eq!(
+            parser.extract_links(html, "example.com"),
+            vec!["https://example.com/about", "https://example.com/login"]
+        );
+
+        let none = r#"<html><head><meta name="robots" content="none"></head></html>"#;
+        assert!(parser.meta_robots(none).noindex && parser.meta_robots(none).nofollow);
+        assert_eq!(
+            parser.meta_robots("<html></html>"),
+            RobotsDirectives::default()
+        );
+    }
+
+    #[test]
+    fn test_detect_language_and_hreflang() {
+        let parser = HtmlParser::new();
+        let html = r#"<html lang="en-US"><head>
+            <link rel="alternate" hreflang="de" href="/de/">
+            <link rel="alternate" hreflang="fr_FR" href="https://fr.example.com/">
+            <link rel="canonical" href="https://example.com/">
+        </head><body>Hi</body></html>"#;
+
+        assert_eq!(parser.detect_language(html), Some("en-us".to_string()));
+        assert_eq!(parser.detect_language("<html><body>Hi</body></html>"), None);
+
+        let alternates = parser.extract_hreflang_alternates(html, "example.com");
+        assert_eq!(
+            alternates,
+            vec![
+                HreflangAlternate {
+                    lang: "de".to_string(),
+                    url: "https://example.com/de/".to_string()
+                },
+                HreflangAlternate {
+                    lang: "fr-fr".to_string(),
+                    url: "https://fr.example.com/".to_string()
+                },
+            ]
+        );
     }
 
     #[test]
@@ -470,6 +895,30 @@ mod tests {
         assert_eq!(empty_results.len(), 0);
     }
 
+    #[test]
+    fn test_structure_hashes_match_computed() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body><div class="a"><p>One</p><p>Two</p></div></body></html>"#;
+        let mut tree = parser.parse(html);
+        let before = tree.structure_hash();
+        {
+            let hashes = StructureHashes::new(&tree);
+            assert_eq!(hashes.get(&tree), before);
+            let div = &tree.children[0].children[0];
+            assert_eq!(hashes.get(div), div.structure_hash());
+        }
+
+        // Edits in place, as redaction makes, change the hash of the tree
+        tree.children[0].children[0].children[1].content = "[REDACTED]".to_string();
+        let after = StructureHashes::new(&tree).get(&tree);
+        assert_ne!(after, before);
+        assert_eq!(after, tree.structure_hash());
+        assert_eq!(tree.clone().structure_hash(), after);
+
+        let other = parser.parse(r#"<html><body><div class="a"><p>One</p></div></body></html>"#);
+        assert_ne!(other.structure_hash(), before);
+    }
+
     #[test]
     fn test_matches_path_part() {
         let node = HtmlNode::new(
@@ -0,0 +1,168 @@
+use crate::wait::WaitStrategy;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A small page interaction performed after navigation and before the HTML is
+/// captured, e.g. dismissing a cookie banner or switching a currency selector.
+/// Elements are addressed with the path syntax of `HtmlNode::find_by_path`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Interaction {
+    /// Click the first element matching the path
+    Click { path: String },
+    /// Replace the value of the first matching input with text
+    Type { path: String, text: String },
+    /// Choose an option of the first matching `select`, by value or visible label
+    Select { path: String, value: String },
+    /// Wait for the page to settle, e.g. after a click that loads more content
+    Wait { strategy: WaitStrategy },
+}
+
+impl Interaction {
+    /// Path of the element the interaction acts on, `None` for waits
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Interaction::Click { path }
+            | Interaction::Type { path, .. }
+            | Interaction::Select { path, .. } => Some(path),
+            Interaction::Wait { .. } => None,
+        }
+    }
+}
+
+impl FromStr for Interaction {
+    type Err = String;
+
+    /// Parse `click:<path>`, `type:<path>=<text>`, `select:<path>=<value>` or
+    /// `wait:<strategy>` (see `WaitStrategy`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (action, target) = s
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid interaction '{s}', expected ACTION:PATH"))?;
+
+        let split_value = |target: &str| {
+            target
+                .split_once('=')
+                .map(|(path, value)| (path.trim().to_string(), value.to_string()))
+                .ok_or_else(|| format!("Invalid interaction '{s}', expected {action}:PATH=VALUE"))
+        };
+
+        let interaction = match action.trim().to_lowercase().as_str() {
+            "click" => Interaction::Click {
+                path: target.trim().to_string(),
+            },
+            "type" => {
+                let (path, text) = split_value(target)?;
+                Interaction::Type { path, text }
+            }
+            "select" => {
+                let (path, value) = split_value(target)?;
+                Interaction::Select { path, value }
+            }
+            "wait" => Interaction::Wait {
+                strategy: target.parse()?,
+            },
+            other => {
+                return Err(format!(
+                    "Unknown interaction '{other}', expected click, type, select or wait"
+                ))
+            }
+        };
+
+        if interaction.path() == Some("") {
+            return Err(format!("Invalid interaction '{s}', the path is empty"));
+        }
+        Ok(interaction)
+    }
+}
+
+/// Convert a `find_by_path` path such as `div.cookie-banner button.accept`
+/// into the equivalent CSS descendant selector
+pub fn path_to_css_selector(path: &str) -> String {
+    path.split_whitespace()
+        .map(|part| {
+            let mut pieces = part.split('.');
+            let tag = pieces.next().unwrap_or_default();
+            let mut selector = if tag.is_empty() {
+                String::new()
+            } else {
+                escape_css_identifier(tag)
+            };
+            for class in pieces.filter(|class| !class.is_empty()) {
+                selector.push('.');
+                selector.push_str(&escape_css_identifier(class));
+            }
+            selector
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Escape characters that are not valid in a CSS identifier, such as the `:`
+/// and `/` used by utility CSS frameworks (`md:flex`, `w-1/2`)
+fn escape_css_identifier(identifier: &str) -> String {
+    let mut escaped = String::with_capacity(identifier.len());
+    for (index, c) in identifier.chars().enumerate() {
+        let valid = c.is_ascii_alphanumeric() || c == '-' || c == '_' || !c.is_ascii();
+        if !valid || (index == 0 && c.is_ascii_digit()) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interactions() {
+        assert_eq!(
+            "click:div.cookie-banner button.accept".parse::<Interaction>(),
+            Ok(Interaction::Click {
+                path: "div.cookie-banner button.accept".to_string()
+            })
+        );
+        assert_eq!(
+            "type:form input.search=rust crawler".parse::<Interaction>(),
+            Ok(Interaction::Type {
+                path: "form input.search".to_string(),
+                text: "rust crawler".to_string()
+            })
+        );
+        assert_eq!(
+            "select:select.currency=EUR".parse::<Interaction>(),
+            Ok(Interaction::Select {
+                path: "select.currency".to_string(),
+                value: "EUR".to_string()
+            })
+        );
+
+        assert_eq!(
+            "wait:selector:.results".parse::<Interaction>(),
+            Ok(Interaction::Wait {
+                strategy: WaitStrategy::Selector {
+                    selector: ".results".to_string()
+                }
+            })
+        );
+
+        assert!("hover:div".parse::<Interaction>().is_err());
+        assert!("type:input".parse::<Interaction>().is_err());
+        assert!("click:".parse::<Interaction>().is_err());
+        assert!("button.accept".parse::<Interaction>().is_err());
+    }
+
+    #[test]
+    fn test_path_to_css_selector() {
+        assert_eq!(
+            path_to_css_selector("html body tr.athing.submission td.title"),
+            "html body tr.athing.submission td.title"
+        );
+        assert_eq!(
+            path_to_css_selector("div.md:flex span.w-1/2"),
+            "div.md\\:flex span.w-1\\/2"
+        );
+    }
+}
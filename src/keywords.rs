@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Words that carry no meaning in an objective ("find the pricing of ...")
+pub const STOP_WORDS: &[&str] = &[
+    "about", "after", "all", "also", "and", "any", "are", "but", "can", "could", "did", "does",
+    "each", "find", "for", "from", "get", "had", "has", "have", "her", "his", "how", "into", "its",
+    "list", "more", "most", "not", "now", "off", "only", "other", "our", "out", "over", "show",
+    "some", "such", "than", "that", "the", "their", "them", "then", "there", "these", "they",
+    "this", "those", "through", "was", "were", "what", "when", "where", "which", "while", "who",
+    "whom", "why", "will", "with", "would", "you", "your",
+];
+
+/// Small built-in synonym groups for common crawl objectives; a keyword in a
+/// group also matches the other members
+const SYNONYM_GROUPS: &[&[&str]] = &[
+    &[
+        "team",
+        "staff",
+        "people",
+        "leadership",
+        "founders",
+        "employees",
+    ],
+    &["price", "pricing", "cost", "plans", "fees"],
+    &["contact", "email", "phone", "address", "reach"],
+    &["job", "career", "hiring", "vacancy", "position", "openings"],
+    &["product", "service", "solution", "offering"],
+    &["news", "blog", "article", "press", "announcement"],
+    &["event", "conference", "meetup", "webinar"],
+    &["location", "office", "headquarters", "store"],
+];
+
+pub fn is_stop_word(word: &str) -> bool {
+    STOP_WORDS.contains(&word)
+}
+
+/// Lowercase alphanumeric words of at least two characters
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 1)
+        .map(|word| word.to_lowercase())
+}
+
+/// Light suffix-stripping stemmer so inflections of a keyword match each
+/// other: "pricing", "prices" and "priced" all stem to "pric"
+pub fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    if word.chars().count() <= 3 || !word.is_ascii() {
+        return word;
+    }
+
+    let mut stem = if let Some(base) = word.strip_suffix("ies") {
+        format!("{base}y")
+    } else if let Some(base) = word.strip_suffix("sses") {
+        format!("{base}ss")
+    } else if let Some(base) = word.strip_suffix("ing").filter(|b| b.len() > 2) {
+        undouble(base)
+    } else if let Some(base) = word.strip_suffix("ed").filter(|b| b.len() > 2) {
+        undouble(base)
+    } else if let Some(base) = word.strip_suffix("ly").filter(|b| b.len() > 3) {
+        base.to_string()
+    } else if let Some(base) = word.strip_suffix("es").filter(|b| {
+        ["s", "x", "z", "ch", "sh"]
+            .iter()
+            .any(|end| b.ends_with(end))
+    }) {
+        base.to_string()
+    } else if let Some(base) = word.strip_suffix('s').filter(|b| !b.ends_with('s')) {
+        base.to_string()
+    } else {
+        word
+    };
+
+    if stem.len() > 3 && stem.ends_with('e') {
+        stem.pop();
+    }
+    stem
+}
+
+/// "runn" -> "run", but keep "ll", "ss" and "zz" ("billing" -> "bill")
+fn undouble(base: &str) -> String {
+    let bytes = base.as_bytes();
+    let n = bytes.len();
+    if n > 2 && bytes[n - 1] == bytes[n - 2] && !matches!(bytes[n - 1], b'l' | b's' | b'z') {
+        base[..n - 1].to_string()
+    } else {
+        base.to_string()
+    }
+}
+
+/// An objective keyword and the stems it matches, including synonyms
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeywordTerm {
+    pub keyword: String,
+    pub stems: Vec<String>,
+}
+
+/// Matches objective keywords against page text and URLs, ignoring stop words
+/// and inflection and expanding synonyms
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeywordMatcher {
+    terms: Vec<KeywordTerm>,
+}
+
+impl KeywordMatcher {
+    pub fn new<S: AsRef<str>>(keywords: &[S]) -> Self {
+        let mut matcher = KeywordMatcher::default();
+        matcher.add_keywords(keywords);
+        matcher
+    }
+
+    /// Keywords of a free-text objective, with stop words removed
+    pub fn from_objective(objective: &str) -> Self {
+        Self::new(&crate::utils::extract_objective_keywords(objective))
+    }
+
+    /// Add more keywords, skipping ones already present
+    pub fn add_keywords<S: AsRef<str>>(&mut self, keywords: &[S]) {
+        for keyword in keywords {
+            self.add_keyword(keyword.as_ref());
+        }
+    }
+
+    fn add_keyword(&mut self, keyword: &str) {
+        let keyword = keyword.trim().to_lowercase();
+        if keyword.is_empty() || self.terms.iter().any(|term| term.keyword == keyword) {
+            return;
+        }
+
+        let stemmed = stem(&keyword);
+        let mut stems = vec![stemmed.clone()];
+        for group in SYNONYM_GROUPS {
+            if group.iter().any(|word| stem(word) == stemmed) {
+                for word in group.iter() {
+                    let synonym = stem(word);
+                    if !stems.contains(&synonym) {
+                        stems.push(synonym);
+                    }
+                }
+            }
+        }
+        self.terms.push(KeywordTerm { keyword, stems });
+    }
+
+    /// Add extra synonyms per keyword, e.g. loaded from a `--synonyms` file.
+    /// Entries for words that aren't keywords are ignored.
+    pub fn add_synonyms(&mut self, synonyms: &HashMap<String, Vec<String>>) {
+        for term in &mut self.terms {
+            let Some(extra) = synonyms.get(&term.keyword) else {
+                continue;
+            };
+            for word in extra {
+                let synonym = stem(word);
+                if !synonym.is_empty() && !term.stems.contains(&synonym) {
+                    term.stems.push(synonym);
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn terms(&self) -> &[KeywordTerm] {
+        &self.terms
+    }
+
+    /// Number of distinct keywords mentioned in the text
+    pub fn match_count(&self, text: &str) -> usize {
+        self.matched_keywords(text).len()
+    }
+
+    /// Keywords mentioned in the text, in keyword order
+    pub fn matched_keywords(&self, text: &str) -> Vec<&str> {
+        if self.terms.is_empty() {
+            return Vec::new();
+        }
+        let stems: Vec<String> = tokenize(text).map(|word| stem(&word)).collect();
+        self.terms
+            .iter()
+            .filter(|term| term.stems.iter().any(|s| stems.contains(s)))
+            .map(|term| term.keyword.as_str())
+            .collect()
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        self.match_count(text) > 0
+    }
+
+    /// Relevance of a URL to the objective: keywords found in its path and query
+    pub fn score_url(&self, url: &str) -> usize {
+        self.url_keywords(url).len()
+    }
+
+    /// Keywords found in the path and query of a URL
+    pub fn url_keywords(&self, url: &str) -> Vec<&str> {
+        let target = url::Url::parse(url)
+            .map(|parsed| format!("{} {}", parsed.path(), parsed.query().unwrap_or_default()))
+            .unwrap_or_else(|_| url.to_string());
+        self.matched_keywords(&target)
+    }
+}
+
+/// Read a JSON object mapping keywords to extra synonyms
+pub fn load_synonyms(path: &str) -> Result<HashMap<String, Vec<String>>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let synonyms: HashMap<String, Vec<String>> =
+        serde_json::from_str(&content).map_err(|e| format!("Invalid synonyms file {path}: {e}"))?;
+    Ok(synonyms
+        .into_iter()
+        .map(|(keyword, words)| (keyword.to_lowercase(), words))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem() {
+        assert_eq!(stem("pricing"), "pric");
+        assert_eq!(stem("prices"), "pric");
+        assert_eq!(stem("priced"), "pric");
+        assert_eq!(stem("companies"), "company");
+        assert_eq!(stem("running"), "run");
+        assert_eq!(stem("billing"), "bill");
+        assert_eq!(stem("careers"), "career");
+        assert_eq!(stem("business"), "business");
+        assert_eq!(stem("boxes"), "box");
+        assert_eq!(stem("team"), "team");
+    }
+
+    #[test]
+    fn test_matcher_uses_stems_and_synonyms() {
+        let matcher = KeywordMatcher::from_objective("Find the pricing and the team");
+        assert_eq!(
+            matcher
+                .terms()
+                .iter()
+                .map(|term| term.keyword.as_str())
+                .collect::<Vec<_>>(),
+            vec!["pricing", "team"]
+        );
+
+        assert!(matcher.matches("Compare our Prices"));
+        assert!(matcher.matches("Meet our leadership"));
+        assert!(matcher.matches("Plans start at $10"));
+        assert_eq!(matcher.match_count("Pricing for staff seats"), 2);
+        // No substring matches inside unrelated words
+        assert!(!matcher.matches("Steamboat tours"));
+        assert!(!KeywordMatcher::default().matches("pricing"));
+    }
+
+    #[test]
+    fn test_add_synonyms() {
+        let mut matcher = KeywordMatcher::new(&["menu"]);
+        assert!(!matcher.matches("Lunch specials"));
+
+        let synonyms = HashMap::from([(
+            "menu".to_string(),
+            vec!["specials".to_string(), "dishes".to_string()],
+        )]);
+        matcher.add_synonyms(&synonyms);
+        assert!(matcher.matches("Lunch specials"));
+    }
+
+    #[test]
+    fn test_score_url() {
+        let matcher = KeywordMatcher::from_objective("team members and careers");
+        assert_eq!(matcher.score_url("https://example.com/about/our-team"), 1);
+        assert_eq!(matcher.score_url("https://example.com/jobs?team=eng"), 2);
+        assert_eq!(matcher.score_url("https://team.example.com/blog"), 0);
+        assert_eq!(
+            matcher.url_keywords("https://example.com/careers/team"),
+            vec!["team", "careers"]
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use crate::entities::{entity_id, Entity, EntityKind};
+use crate::results::ScrapedWebPage;
+use crate::utils::read_output_file;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// How an extracted entity compares to the entities of earlier runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityStatus {
+    /// Not known, and no entity of its kind was known on its page
+    New,
+    /// Not known, but replaces another value of its kind known on its page,
+    /// e.g. a changed price
+    Updated,
+    /// Already known
+    Duplicate,
+}
+
+/// How many entities were marked with each status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatusCounts {
+    pub new: usize,
+    pub updated: usize,
+    pub duplicate: usize,
+}
+
+/// Entities collected by earlier runs, to mark extracted entities against
+#[derive(Debug, Clone, Default)]
+pub struct KnownEntities {
+    ids: HashSet<String>,
+    /// Kinds known on each page, from the entities' provenance
+    page_kinds: HashSet<(String, EntityKind)>,
+}
+
+impl KnownEntities {
+    pub fn new(entities: &[Entity]) -> Self {
+        let mut known = KnownEntities::default();
+        for entity in entities {
+            let id = if entity.id.is_empty() {
+                entity_id(entity.kind, &entity.value)
+            } else {
+                entity.id.clone()
+            };
+            known.ids.insert(id);
+            if let Some(provenance) = entity.provenance.as_ref().filter(|p| !p.url.is_empty()) {
+                known
+                    .page_kinds
+                    .insert((provenance.url.clone(), entity.kind));
+            }
+        }
+        known
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Status of an entity found on `url`
+    pub fn status(&self, url: &str, entity: &Entity) -> EntityStatus {
+        if self.ids.contains(&entity.id) {
+            EntityStatus::Duplicate
+        } else if self.page_kinds.contains(&(url.to_string(), entity.kind)) {
+            EntityStatus::Updated
+        } else {
+            EntityStatus::New
+        }
+    }
+
+    /// Set the `status` of the entities of `pages`
+    pub fn mark_pages(&self, pages: &mut [ScrapedWebPage]) -> StatusCounts {
+        let mut counts = StatusCounts::default();
+        for page in pages {
+            for entity in &mut page.entities {
+                let status = self.status(&page.url, entity);
+                match status {
+                    EntityStatus::New => counts.new += 1,
+                    EntityStatus::Updated => counts.updated += 1,
+                    EntityStatus::Duplicate => counts.duplicate += 1,
+                }
+                entity.status = Some(status);
+            }
+        }
+        counts
+    }
+}
+
+/// Entities from a JSON lines file, one entity per line as in the
+/// `entities` of a pages file, e.g. made with
+/// `jq -c '.pages[].entities[]' pages.json`. Lines without an `id` get the
+/// one of their kind and value.
+pub fn load_known_entities(path: &str) -> Result<KnownEntities, String> {
+    let content = read_output_file(path)?;
+    let entities: Vec<Entity> = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Invalid entity on line {} of {path}: {e}", number + 1))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(KnownEntities::new(&entities))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+    use serde_json::json;
+
+    fn page(url: &str, text: &str) -> ScrapedWebPage {
+        let mut page: ScrapedWebPage = serde_json::from_value(json!({
+            "url": url,
+            "domain": "example.com",
+        }))
+        .unwrap();
+        page.entities = EntityExtractor::new().extract(text);
+        for entity in &mut page.entities {
+            if let Some(provenance) = &mut entity.provenance {
+                provenance.url = url.to_string();
+            }
+        }
+        page
+    }
+
+    #[test]
+    fn test_mark_against_known_entities() {
+        let earlier = [
+            page(
+                "https://example.com/pricing",
+                "Pro at $49. sales@example.com",
+            ),
+            page("https://example.com/team", "jobs@example.com"),
+        ];
+        let lines: Vec<String> = earlier
+            .iter()
+            .flat_map(|page| &page.entities)
+            .map(|entity| serde_json::to_string(entity).unwrap())
+            .chain([
+                r#"{"kind": "phone", "value": "5550104000", "text": "", "confidence": 1}"#
+                    .to_string(),
+            ])
+            .collect();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("known.jsonl");
+        std::fs::write(&path, lines.join("\n")).unwrap();
+        let known = load_known_entities(&path.to_string_lossy()).unwrap();
+        assert_eq!(known.len(), 4);
+
+        let mut pages = [
+            page(
+                "https://example.com/pricing",
+                "Pro at $59. sales@example.com, call 555-010-4000",
+            ),
+            page("https://example.com/about", "Since 2010-01-12"),
+        ];
+        let counts = known.mark_pages(&mut pages);
+        let statuses: Vec<(&str, Option<EntityStatus>)> = pages
+            .iter()
+            .flat_map(|page| &page.entities)
+            .map(|entity| (entity.value.as_str(), entity.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("59 USD", Some(EntityStatus::Updated)),
+                ("sales@example.com", Some(EntityStatus::Duplicate)),
+                ("5550104000", Some(EntityStatus::Duplicate)),
+                ("2010-01-12", Some(EntityStatus::New)),
+            ]
+        );
+        assert_eq!(
+            counts,
+            StatusCounts {
+                new: 1,
+                updated: 1,
+                duplicate: 2
+            }
+        );
+    }
+}
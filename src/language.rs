@@ -0,0 +1,214 @@
+use serde::{Deserialize, Serialize};
+
+/// An alternate-language version of a page declared via `<link rel="alternate" hreflang>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HreflangAlternate {
+    pub lang: String,
+    pub url: String,
+}
+
+/// Language of a page's text as detected from its content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DetectedLanguage {
+    /// ISO 639-3 code, e.g. "eng"
+    pub code: String,
+    pub confidence: f64,
+    pub reliable: bool,
+}
+
+/// Detect the language of a block of text, `None` when there is too little to tell
+pub fn detect_text_language(text: &str) -> Option<DetectedLanguage> {
+    let info = whatlang::detect(text)?;
+    Some(DetectedLanguage {
+        code: info.lang().code().to_string(),
+        confidence: info.confidence(),
+        reliable: info.is_reliable(),
+    })
+}
+
+/// Restricts a crawl to pages in a set of languages
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<String>", into = "Vec<String>")]
+pub struct LanguageFilter {
+    languages: Vec<String>,
+}
+
+impl From<Vec<String>> for LanguageFilter {
+    fn from(languages: Vec<String>) -> Self {
+        LanguageFilter::new(languages)
+    }
+}
+
+impl From<LanguageFilter> for Vec<String> {
+    fn from(filter: LanguageFilter) -> Self {
+        filter.languages
+    }
+}
+
+impl LanguageFilter {
+    pub fn new(languages: Vec<String>) -> Self {
+        LanguageFilter {
+            languages: languages
+                .into_iter()
+                .map(|lang| normalize_language_tag(&lang))
+                .filter(|lang| !lang.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Parse a comma separated list such as "en,de"
+    pub fn parse(list: &str) -> Self {
+        Self::new(list.split(',').map(|lang| lang.to_string()).collect())
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.languages.is_empty()
+    }
+
+    /// Whether a language tag is wanted. "en" accepts "en-US", while "en-gb"
+    /// only accepts British English. `x-default` is always accepted.
+    pub fn allows(&self, lang: &str) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+
+        let lang = normalize_language_tag(lang);
+        if lang == "x-default" {
+            return true;
+        }
+
+        self.languages.iter().any(|wanted| {
+            lang == *wanted
+                || (!wanted.contains('-') && lang.split('-').next() == Some(wanted.as_str()))
+        })
+    }
+
+    /// URL prefixes of alternates in unwanted languages; any URL under one of them
+    /// is another-language copy of content available in a wanted language
+    pub fn excluded_prefixes(&self, alternates: &[HreflangAlternate]) -> Vec<String> {
+        if !self.is_active() || !alternates.iter().any(|alt| self.allows(&alt.lang)) {
+            // Without a wanted-language version there is nothing to deduplicate against
+            return Vec::new();
+        }
+
+        let allowed_urls: Vec<&str> = alternates
+            .iter()
+            .filter(|alt| self.allows(&alt.lang))
+            .map(|alt| alt.url.as_str())
+            .collect();
+
+        let mut prefixes: Vec<String> = alternates
+            .iter()
+            .filter(|alt| !self.allows(&alt.lang))
+            .map(|alt| alt.url.clone())
+            // Never exclude a prefix that also covers a wanted-language page
+            .filter(|prefix| {
+                !allowed_urls
+                    .iter()
+                    .any(|url| url.starts_with(prefix.as_str()))
+            })
+            .collect();
+        prefixes.sort();
+        prefixes.dedup();
+        prefixes
+    }
+
+    /// Drop URLs that fall under any excluded alternate-language prefix
+    pub fn filter_urls(&self, urls: Vec<String>, excluded_prefixes: &[String]) -> Vec<String> {
+        urls.into_iter()
+            .filter(|url| !is_excluded(url, excluded_prefixes))
+            .collect()
+    }
+}
+
+pub fn is_excluded(url: &str, excluded_prefixes: &[String]) -> bool {
+    excluded_prefixes
+        .iter()
+        .any(|prefix| url.starts_with(prefix.as_str()))
+}
+
+/// Lowercase a BCP 47 tag and use `-` as separator ("en_US" -> "en-us")
+pub fn normalize_language_tag(lang: &str) -> String {
+    lang.trim().replace('_', "-").to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alternate(lang: &str, url: &str) -> HreflangAlternate {
+        HreflangAlternate {
+            lang: lang.to_string(),
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_detect_text_language() {
+        let english = detect_text_language(
+            "The quick brown fox jumps over the lazy dog while the farmer watches from the porch.",
+        )
+        .unwrap();
+        assert_eq!(english.code, "eng");
+
+        let german = detect_text_language(
+            "Der schnelle braune Fuchs springt über den faulen Hund, während der Bauer zusieht.",
+        )
+        .unwrap();
+        assert_eq!(german.code, "deu");
+
+        assert!(detect_text_language("").is_none());
+    }
+
+    #[test]
+    fn test_language_filter_allows() {
+        let filter = LanguageFilter::parse("en, de-AT");
+        assert!(filter.allows("en"));
+        assert!(filter.allows("en-US"));
+        assert!(filter.allows("EN_gb"));
+        assert!(filter.allows("de-at"));
+        assert!(!filter.allows("de"));
+        assert!(!filter.allows("fr"));
+        assert!(filter.allows("x-default"));
+
+        let inactive = LanguageFilter::default();
+        assert!(inactive.allows("fr"));
+    }
+
+    #[test]
+    fn test_excluded_prefixes_and_filtering() {
+        let filter = LanguageFilter::parse("en");
+        let alternates = vec![
+            alternate("en", "https://example.com/"),
+            alternate("de", "https://example.com/de/"),
+            alternate("fr", "https://fr.example.com/"),
+            alternate("x-default", "https://example.com/"),
+        ];
+
+        let prefixes = filter.excluded_prefixes(&alternates);
+        assert_eq!(
+            prefixes,
+            vec!["https://example.com/de/", "https://fr.example.com/"]
+        );
+
+        let urls = vec![
+            "https://example.com/about".to_string(),
+            "https://example.com/de/about".to_string(),
+            "https://fr.example.com/about".to_string(),
+        ];
+        assert_eq!(
+            filter.filter_urls(urls, &prefixes),
+            vec!["https://example.com/about"]
+        );
+    }
+
+    #[test]
+    fn test_no_exclusions_without_wanted_alternate() {
+        let filter = LanguageFilter::parse("ja");
+        let alternates = vec![
+            alternate("en", "https://example.com/"),
+            alternate("de", "https://example.com/de/"),
+        ];
+        assert!(filter.excluded_prefixes(&alternates).is_empty());
+    }
+}
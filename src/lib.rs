@@ -1,13 +1,128 @@
+//! High-level crawl pipeline for embedding SmartCrawler in other programs.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), smart_crawler::CrawlError> {
+//! use smart_crawler::{ContentMode, SmartCrawler};
+//!
+//! let crawler = SmartCrawler::builder()
+//!     .domain("example.com")
+//!     .objective("pricing plans")
+//!     .build()?;
+//! let result = crawler.run().await?;
+//! for page in result.pages(ContentMode::Filtered) {
+//!     println!("{} {:?}", page.url, page.title);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+// The crate's API is what this file re-exports by name, plus the modules
+// declared `pub` here. Types reachable from that API must be re-exported
+// too, which `unnameable_types` checks.
+#![warn(unnameable_types)]
+
+pub(crate) mod api_capture;
+pub(crate) mod bounding_box;
+pub(crate) mod brand_assets;
 pub mod browser;
+#[cfg(feature = "cdp")]
+pub(crate) mod cdp;
 pub mod cli;
+pub(crate) mod contact;
+pub(crate) mod crawler;
+pub(crate) mod cypher_export;
+#[cfg(feature = "db")]
+pub mod db_sink;
+pub mod diff;
+pub mod domain_profile;
+pub(crate) mod encoding;
+pub(crate) mod entities;
+pub(crate) mod fetch_limits;
+pub mod graph_export;
 pub mod html_parser;
+pub(crate) mod interaction;
+pub mod keywords;
+pub(crate) mod known_entities;
+pub(crate) mod language;
+pub mod list_extraction;
+pub mod monitor;
+pub(crate) mod network_guard;
+pub(crate) mod observer;
+pub(crate) mod page_structure;
+pub(crate) mod page_type;
+pub(crate) mod presets;
+pub(crate) mod probe;
+pub(crate) mod profiles;
+pub(crate) mod prominence;
+pub(crate) mod redaction;
+pub mod report;
+pub(crate) mod request_blocking;
+pub mod results;
+pub mod review;
+pub mod scheduler;
+pub mod search_seed;
+pub(crate) mod shadow_dom;
+pub(crate) mod site_search;
+pub mod sitemap;
+pub(crate) mod spill;
 pub mod storage;
+pub(crate) mod structured_content;
+pub(crate) mod survey;
 pub mod template_detection;
+pub(crate) mod text_normalize;
+pub(crate) mod units;
+pub(crate) mod url_selection;
 pub mod utils;
+pub(crate) mod validation;
+pub(crate) mod wait;
+pub(crate) mod wayback;
+#[cfg(test)]
+mod webdriver_mock;
 
-pub use browser::*;
-pub use cli::*;
-pub use html_parser::*;
-pub use storage::*;
-pub use template_detection::*;
-pub use utils::*;
+pub use api_capture::ApiResponse;
+pub use bounding_box::{BoundingBox, BoundingBoxReport, RenderedStyle};
+pub use brand_assets::{BrandAsset, BrandAssetKind};
+pub use browser::{Browser, BrowserEngine, BrowserError, Geolocation, LocaleOptions, PageTimeouts};
+pub use contact::ContactInfo;
+pub use crawler::{
+    CrawlConfig, CrawlError, CrawlEvent, CrawlFailure, CrawlPhase, CrawlPlan, CrawlResult,
+    CrawlSession, DiscoverySource, SmartCrawler, SmartCrawlerBuilder,
+};
+pub use entities::{
+    entity_id, needs_review, parse_entity_kinds, summarize_confidence, ConfidenceSummary, Entity,
+    EntityExtractor, EntityKind, Provenance,
+};
+pub use fetch_limits::FetchLimits;
+pub use html_parser::{ExternalLink, HtmlNode, HtmlParser, RobotsDirectives};
+pub use interaction::Interaction;
+pub use known_entities::{load_known_entities, EntityStatus, KnownEntities, StatusCounts};
+pub use language::{DetectedLanguage, HreflangAlternate, LanguageFilter};
+pub use list_extraction::ListExtractor;
+pub use observer::CrawlObserver;
+pub use page_structure::{Breadcrumb, Heading, PageStructure};
+pub use page_type::PageType;
+pub use presets::ObjectivePreset;
+pub use profiles::CrawlProfile;
+pub use prominence::{ProminenceScorer, TextBlock};
+pub use request_blocking::{BlockCategory, BlockRules};
+pub use results::{ContentMode, RunManifest, ScrapedWebPage};
+pub use scheduler::{DomainQueue, DomainScheduler};
+pub use spill::SpillConfig;
+pub use storage::{
+    DedupConfig, DomainDuplicates, FetchStatus, HtmlRetention, NodeSignature, RetentionConfig,
+    StoredHtml, UrlData, UrlStorage,
+};
+pub use structured_content::{StructuredKind, StructuredPayload};
+pub use survey::SurveyConfig;
+pub use template_detection::{
+    ElementPath, ElementPathComponent, Template, TemplateDetector, TemplatePathStore, VariableType,
+};
+pub use url_selection::{UrlSelection, UrlSelectionMethod, UrlSelectionStrategy};
+pub use utils::{
+    construct_root_url, extract_domain_from_url, is_root_url, normalize_domain, site_host,
+};
+pub use validation::{
+    load_validation_rules, EntityValidator, RuleAction, RuleStats, ValidationRule,
+};
+pub use wait::WaitStrategy;
+pub use wayback::{ArchivedSnapshot, WaybackConfig};
@@ -0,0 +1,546 @@
+use crate::bounding_box::BoundingBox;
+use crate::html_parser::HtmlNode;
+use crate::template_detection::TemplateDetector;
+use crate::utils::{strip_compression_extension, write_output_file};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Records extracted from one group of repeated sibling elements on a page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedList {
+    /// Page the list was found on
+    pub url: String,
+    /// CSS-like path of the repeated items, usable with `HtmlNode::find_by_path`
+    pub path: String,
+    /// Inferred column names in display order
+    pub columns: Vec<String>,
+    pub records: Vec<BTreeMap<String, String>>,
+    /// Rendered boxes of the repeated items, present when bounding boxes were captured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub item_boxes: Vec<BoundingBox>,
+}
+
+/// A leaf value inside a list item, keyed by its path relative to the item
+struct ItemField {
+    path: String,
+    text: String,
+    href: Option<String>,
+}
+
+/// Column inferred from the values found at the same relative path in every item
+struct Column {
+    path: String,
+    name: String,
+    kind: ColumnKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Link,
+    Count,
+    Text,
+}
+
+/// Turns repeated sibling elements (result rows, product cards, team members)
+/// into records with inferred columns such as title, link, number and date
+pub struct ListExtractor {
+    /// Minimum number of siblings sharing a tag and classes to count as a list
+    pub min_items: usize,
+    template_detector: TemplateDetector,
+    date_regex: Regex,
+    number_regex: Regex,
+    price_regex: Regex,
+}
+
+impl ListExtractor {
+    pub fn new() -> Self {
+        ListExtractor {
+            min_items: 3,
+            template_detector: TemplateDetector::new(),
+            date_regex: Regex::new(
+                r"(?i)^(\d{4}-\d{2}-\d{2}|\d{1,2}[/.]\d{1,2}[/.]\d{2,4}|(jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]*\.? \d{1,2},? \d{4}|\d{1,2} (jan|feb|mar|apr|may|jun|jul|aug|sep|oct|nov|dec)[a-z]* \d{4})$",
+            )
+            .unwrap(),
+            number_regex: Regex::new(r"\d+(?:[.,]\d+)*").unwrap(),
+            price_regex: Regex::new(r"^(?:[$€£¥]\s?\d[\d.,]*|\d[\d.,]*\s?(?:[$€£¥]|USD|EUR|GBP))$")
+                .unwrap(),
+        }
+    }
+
+    /// Find every sibling group in the tree and convert it into records
+    pub fn extract(&self, root: &HtmlNode, page_url: &str) -> Vec<ExtractedList> {
+        let mut groups = Vec::new();
+        let mut path = Vec::new();
+        self.find_sibling_groups(root, &mut path, &mut groups);
+
+        groups
+            .into_iter()
+            .filter_map(|(path, items)| self.group_to_list(page_url, path, &items))
+            .collect()
+    }
+
+    fn find_sibling_groups<'a>(
+        &self,
+        node: &'a HtmlNode,
+        path: &mut Vec<String>,
+        groups: &mut Vec<(String, Vec<&'a HtmlNode>)>,
+    ) {
+        // Menus and footers repeat links on every page but aren't data
+        if matches!(node.tag.as_str(), "nav" | "footer" | "header" | "head") {
+            return;
+        }
+        path.push(Self::path_part(node));
+
+        // Group child indices by tag and classes, keeping first-seen order
+        let mut by_signature: Vec<(String, Vec<usize>)> = Vec::new();
+        for (index, child) in node.children.iter().enumerate() {
+            let part = Self::path_part(child);
+            match by_signature.iter_mut().find(|(sig, _)| *sig == part) {
+                Some((_, indices)) => indices.push(index),
+                None => by_signature.push((part, vec![index])),
+            }
+        }
+
+        let mut qualifying: Vec<(String, Vec<usize>)> = Vec::new();
+        for (part, indices) in by_signature {
+            let has_text = indices
+                .iter()
+                .all(|&i| !node.children[i].text_content().is_empty());
+            if indices.len() >= self.min_items && has_text {
+                qualifying.push((part, indices));
+            }
+        }
+
+        // Walk children in document order so groups come out in page order. Items
+        // of a group are records; lists nested inside them are part of the record.
+        for (index, child) in node.children.iter().enumerate() {
+            match qualifying
+                .iter()
+                .find(|(_, indices)| indices.contains(&index))
+            {
+                Some((part, indices)) if indices[0] == index => {
+                    let items = indices.iter().map(|&i| &node.children[i]).collect();
+                    groups.push((format!("{} {}", path.join(" "), part), items));
+                }
+                Some(_) => {}
+                None => self.find_sibling_groups(child, path, groups),
+            }
+        }
+
+        path.pop();
+    }
+
+    fn path_part(node: &HtmlNode) -> String {
+        if node.classes.is_empty() {
+            node.tag.clone()
+        } else {
+            format!("{}.{}", node.tag, node.classes.join("."))
+        }
+    }
+
+    fn group_to_list(
+        &self,
+        page_url: &str,
+        path: String,
+        items: &[&HtmlNode],
+    ) -> Option<ExtractedList> {
+        let item_fields: Vec<Vec<ItemField>> = items
+            .iter()
+            .map(|item| {
+                let mut fields = Vec::new();
+                Self::collect_fields(item, String::new(), None, &mut fields);
+                Self::disambiguate_paths(&mut fields);
+                fields
+            })
+            .collect();
+
+        let columns = self.infer_columns(&item_fields);
+        if columns.is_empty() {
+            return None;
+        }
+
+        let mut column_names = Vec::new();
+        for column in &columns {
+            column_names.push(column.name.clone());
+            if column.kind == ColumnKind::Link {
+                column_names.push(Self::link_column_name(&column.name));
+            }
+        }
+
+        let records = item_fields
+            .iter()
+            .map(|fields| {
+                let mut record = BTreeMap::new();
+                for column in &columns {
+                    let Some(field) = fields.iter().find(|f| f.path == column.path) else {
+                        continue;
+                    };
+                    let value = match column.kind {
+                        ColumnKind::Count => self
+                            .number_regex
+                            .find(&field.text)
+                            .map(|m| m.as_str().to_string())
+                            .unwrap_or_else(|| field.text.clone()),
+                        _ => field.text.clone(),
+                    };
+                    record.insert(column.name.clone(), value);
+                    if column.kind == ColumnKind::Link {
+                        if let Some(href) = &field.href {
+                            record.insert(
+                                Self::link_column_name(&column.name),
+                                resolve_href(page_url, href),
+                            );
+                        }
+                    }
+                }
+                record
+            })
+            .filter(|record| !record.is_empty())
+            .collect();
+
+        Some(ExtractedList {
+            url: page_url.to_string(),
+            path,
+            columns: column_names,
+            item_boxes: items.iter().filter_map(|item| item.bounding_box).collect(),
+            records,
+        })
+    }
+
+    fn link_column_name(name: &str) -> String {
+        if name == "title" {
+            "link".to_string()
+        } else {
+            format!("{name}_link")
+        }
+    }
+
+    fn collect_fields(
+        node: &HtmlNode,
+        path: String,
+        href: Option<&str>,
+        fields: &mut Vec<ItemField>,
+    ) {
+        let href = node.href.as_deref().or(href);
+        if !node.content.is_empty() {
+            fields.push(ItemField {
+                path: path.clone(),
+                text: node.content.clone(),
+                href: href.map(|href| href.to_string()),
+            });
+        }
+
+        for child in &node.children {
+            let child_path = if path.is_empty() {
+                Self::path_part(child)
+            } else {
+                format!("{path} {}", Self::path_part(child))
+            };
+            Self::collect_fields(child, child_path, href, fields);
+        }
+    }
+
+    /// Give repeated paths within one item distinct keys ("td", "td#2", ...)
+    fn disambiguate_paths(fields: &mut [ItemField]) {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for field in fields.iter_mut() {
+            let count = seen.entry(field.path.clone()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                field.path = format!("{}#{}", field.path, count);
+            }
+        }
+    }
+
+    fn infer_columns(&self, item_fields: &[Vec<ItemField>]) -> Vec<Column> {
+        // Keep paths present in at least half of the items, in first-seen order
+        let mut paths: Vec<(String, usize)> = Vec::new();
+        for fields in item_fields {
+            for field in fields {
+                match paths.iter_mut().find(|(path, _)| *path == field.path) {
+                    Some((_, count)) => *count += 1,
+                    None => paths.push((field.path.clone(), 1)),
+                }
+            }
+        }
+        let min_count = item_fields.len().div_ceil(2);
+
+        let mut columns: Vec<Column> = Vec::new();
+        for (path, count) in paths {
+            if count < min_count {
+                continue;
+            }
+            let values: Vec<&ItemField> = item_fields
+                .iter()
+                .filter_map(|fields| fields.iter().find(|f| f.path == path))
+                .collect();
+
+            let (base_name, kind) = self.infer_column_name(&values, &columns);
+            let mut name = base_name.clone();
+            let mut suffix = 2;
+            while columns.iter().any(|column| column.name == name) {
+                name = format!("{base_name}_{suffix}");
+                suffix += 1;
+            }
+            columns.push(Column { path, name, kind });
+        }
+        columns
+    }
+
+    fn infer_column_name(
+        &self,
+        values: &[&ItemField],
+        existing: &[Column],
+    ) -> (String, ColumnKind) {
+        let majority = |predicate: &dyn Fn(&ItemField) -> bool| {
+            values.iter().filter(|field| predicate(field)).count() * 2 > values.len()
+        };
+
+        if majority(&|field| self.is_date(&field.text)) {
+            return ("date".to_string(), ColumnKind::Text);
+        }
+        if majority(&|field| self.price_regex.is_match(&field.text)) {
+            return ("price".to_string(), ColumnKind::Text);
+        }
+        if let Some(descriptor) = self.count_descriptor(values) {
+            return (descriptor, ColumnKind::Count);
+        }
+        if majority(&|field| field.href.is_some()) {
+            let has_title = existing
+                .iter()
+                .any(|column| column.kind == ColumnKind::Link);
+            let name = if has_title { "link_text" } else { "title" };
+            return (name.to_string(), ColumnKind::Link);
+        }
+        if majority(&|field| self.is_number(&field.text)) {
+            return ("number".to_string(), ColumnKind::Text);
+        }
+        ("text".to_string(), ColumnKind::Text)
+    }
+
+    fn is_date(&self, text: &str) -> bool {
+        if self.date_regex.is_match(text.trim()) {
+            return true;
+        }
+        // Relative times like "3 hours ago" are templated as "{time} hours ago"
+        self.template_detector
+            .detect_template(text)
+            .is_some_and(|template| template.variables.iter().any(|(name, _)| name == "time"))
+    }
+
+    fn is_number(&self, text: &str) -> bool {
+        let text = text.trim();
+        self.number_regex
+            .find(text)
+            .is_some_and(|m| m.start() == 0 && m.end() == text.len())
+    }
+
+    /// Name a column of templated counts after its descriptor, e.g. "42 comments" -> "comments"
+    fn count_descriptor(&self, values: &[&ItemField]) -> Option<String> {
+        let mut descriptors: HashMap<String, usize> = HashMap::new();
+        for field in values {
+            let Some(template) = self.template_detector.detect_template(&field.text) else {
+                continue;
+            };
+            if !template.variables.iter().any(|(name, _)| name == "count") {
+                continue;
+            }
+            let descriptor = template
+                .pattern
+                .split_whitespace()
+                .find(|word| !word.starts_with('{'))
+                .map(|word| {
+                    word.trim_matches(|c: char| !c.is_alphanumeric())
+                        .to_lowercase()
+                })?;
+            *descriptors.entry(descriptor).or_insert(0) += 1;
+        }
+
+        descriptors
+            .into_iter()
+            .filter(|(_, count)| count * 2 > values.len())
+            .max_by_key(|(_, count)| *count)
+            .map(|(descriptor, _)| descriptor)
+    }
+}
+
+impl Default for ListExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn resolve_href(page_url: &str, href: &str) -> String {
+    url::Url::parse(page_url)
+        .and_then(|base| base.join(href))
+        .map(|url| url.to_string())
+        .unwrap_or_else(|_| href.to_string())
+}
+
+/// Argument for `OVERLAY_GROUPS_SCRIPT`: one entry per list that has item
+/// boxes, labelled with its path and record count
+pub fn overlay_groups(lists: &[ExtractedList]) -> serde_json::Value {
+    let groups: Vec<serde_json::Value> = lists
+        .iter()
+        .filter(|list| !list.item_boxes.is_empty())
+        .map(|list| {
+            let boxes: Vec<[f64; 4]> = list.item_boxes.iter().map(BoundingBox::as_array).collect();
+            serde_json::json!({
+                "label": format!("{} ({} items)", list.path.trim(), list.records.len()),
+                "boxes": boxes,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(groups)
+}
+
+/// Write extracted lists to a file: CSV when the path ends in `.csv`
+/// (one row per record with `url` and `path` columns), JSON otherwise.
+/// A further `.gz`/`.zst` extension compresses the file.
+pub fn write_lists(path: &str, lists: &[ExtractedList]) -> Result<(), String> {
+    if strip_compression_extension(path)
+        .to_lowercase()
+        .ends_with(".csv")
+    {
+        let mut headers: Vec<String> = vec!["url".to_string(), "path".to_string()];
+        for list in lists {
+            for column in &list.columns {
+                if !headers.contains(column) {
+                    headers.push(column.clone());
+                }
+            }
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record(&headers)
+            .map_err(|e| format!("Failed to write {path}: {e}"))?;
+        for list in lists {
+            for record in &list.records {
+                let row = headers.iter().map(|header| match header.as_str() {
+                    "url" => list.url.as_str(),
+                    "path" => list.path.as_str(),
+                    column => record.get(column).map(String::as_str).unwrap_or(""),
+                });
+                writer
+                    .write_record(row)
+                    .map_err(|e| format!("Failed to write {path}: {e}"))?;
+            }
+        }
+        let csv = writer
+            .into_inner()
+            .map_err(|e| format!("Failed to write {path}: {e}"))?;
+        write_output_file(path, &csv)
+    } else {
+        let json = serde_json::to_string_pretty(lists)
+            .map_err(|e| format!("Failed to serialize lists: {e}"))?;
+        write_output_file(path, json.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::HtmlParser;
+
+    const LISTING: &str = r#"<html><body>
+        <nav><ul><li><a href="/">Home</a></li><li><a href="/new">New</a></li><li><a href="/ask">Ask</a></li></ul></nav>
+        <table><tbody>
+            <tr class="athing"><td class="title"><a href="item?id=1">First story</a></td><td class="meta"><span class="score">120 points</span><span class="age">3 hours ago</span></td></tr>
+            <tr class="athing"><td class="title"><a href="item?id=2">Second story</a></td><td class="meta"><span class="score">45 points</span><span class="age">5 hours ago</span></td></tr>
+            <tr class="athing"><td class="title"><a href="item?id=3">Third story</a></td><td class="meta"><span class="score">7 points</span><span class="age">1 day ago</span></td></tr>
+        </tbody></table>
+        <div class="card"><h3>Widget</h3><span class="price">$49.00</span><span class="date">2024-03-01</span></div>
+        <div class="card"><h3>Gadget</h3><span class="price">$19.99</span><span class="date">2024-04-12</span></div>
+        <div class="card"><h3>Gizmo</h3><span class="price">$5</span><span class="date">2024-05-30</span></div>
+    </body></html>"#;
+
+    #[test]
+    fn test_extract_rows_with_inferred_columns() {
+        let tree = HtmlParser::new().parse(LISTING);
+        let lists = ListExtractor::new().extract(&tree, "https://news.example.com/");
+
+        // The navigation menu is not treated as data
+        assert_eq!(lists.len(), 2);
+
+        let stories = &lists[0];
+        assert_eq!(stories.path, "html body table tbody tr.athing");
+        assert_eq!(stories.columns, vec!["title", "link", "points", "date"]);
+        assert_eq!(stories.records.len(), 3);
+        assert_eq!(stories.records[0]["title"], "First story");
+        assert_eq!(
+            stories.records[0]["link"],
+            "https://news.example.com/item?id=1"
+        );
+        assert_eq!(stories.records[0]["points"], "120");
+        assert_eq!(stories.records[2]["date"], "1 day ago");
+
+        // The path finds the same items in the tree
+        assert_eq!(tree.find_by_path(&stories.path).len(), 3);
+    }
+
+    #[test]
+    fn test_extract_cards_with_price_and_date() {
+        let tree = HtmlParser::new().parse(LISTING);
+        let lists = ListExtractor::new().extract(&tree, "https://shop.example.com/");
+
+        let cards = &lists[1];
+        assert_eq!(cards.path, "html body div.card");
+        assert_eq!(cards.columns, vec!["text", "price", "date"]);
+        assert_eq!(cards.records[1]["text"], "Gadget");
+        assert_eq!(cards.records[1]["price"], "$19.99");
+        assert_eq!(cards.records[1]["date"], "2024-04-12");
+    }
+
+    #[test]
+    fn test_small_groups_are_ignored() {
+        let html = r#"<html><body><p>One</p><p>Two</p><div>Other</div></body></html>"#;
+        let tree = HtmlParser::new().parse(html);
+        assert!(ListExtractor::new()
+            .extract(&tree, "https://example.com/")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_write_lists_csv() {
+        let tree = HtmlParser::new().parse(LISTING);
+        let lists = ListExtractor::new().extract(&tree, "https://example.com/");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lists.csv");
+        write_lists(path.to_str().unwrap(), &lists).unwrap();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            "url,path,title,link,points,date,text,price"
+        );
+        assert_eq!(csv.lines().count(), 7);
+        assert!(csv.contains("Gizmo,$5"));
+    }
+
+    #[test]
+    fn test_overlay_groups_uses_item_boxes() {
+        let html = r#"<html><body>
+            <div class="card" data-sc-box="0,0,100,50"><h3>One</h3></div>
+            <div class="card" data-sc-box="0,60,100,50"><h3>Two</h3></div>
+            <div class="card" data-sc-box="0,120,100,50"><h3>Three</h3></div>
+        </body></html>"#;
+        let tree = HtmlParser::new().parse(html);
+        let mut lists = ListExtractor::new().extract(&tree, "https://example.com/");
+        assert_eq!(lists[0].item_boxes.len(), 3);
+
+        let overlay = overlay_groups(&lists);
+        assert_eq!(overlay[0]["label"], "html body div.card (3 items)");
+        assert_eq!(
+            overlay[0]["boxes"][1],
+            serde_json::json!([0.0, 60.0, 100.0, 50.0])
+        );
+
+        // Lists captured without boxes have nothing to draw
+        lists[0].item_boxes.clear();
+        assert_eq!(overlay_groups(&lists), serde_json::json!([]));
+    }
+}
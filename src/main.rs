@@ -1,8 +1,35 @@
+use chrono::Utc;
+use smart_crawler::cli::{CliArgs, CliCommand};
+use smart_crawler::diff::diff_runs;
+use smart_crawler::domain_profile::write_profiles;
+use smart_crawler::graph_export::write_graph;
+use smart_crawler::monitor::{
+    append_history, check_freshness, last_observation, load_history, load_monitor_config,
+    send_webhook, Freshness, Observation, Validators,
+};
+use smart_crawler::report::{write_report, DomainReport};
+use smart_crawler::review::{load_review_file, merge_reviews, write_review_file};
+use smart_crawler::scheduler::watch_domains_file;
+#[cfg(feature = "db")]
+use smart_crawler::scheduler::SharedDomainQueue;
+use smart_crawler::search_seed::{SearchSeeder, SEARCH_API_KEY_ENV};
+use smart_crawler::sitemap::{
+    format_sitemap_urls, SitemapConfig, SitemapFormat, SitemapParser, DETERMINISTIC_SEED,
+};
+use smart_crawler::utils::normalize_domain;
 use smart_crawler::{
-    Browser, CliArgs, FetchStatus, HtmlParser, TemplateDetector, TemplatePathStore, UrlStorage,
+    site_host, BrowserError, ContentMode, CrawlConfig, CrawlError, CrawlEvent, CrawlObserver,
+    CrawlPlan, CrawlResult, DiscoverySource, DomainQueue, DomainScheduler, EntityExtractor,
+    ListExtractor, RuleAction, RunManifest, ScrapedWebPage, SmartCrawler, SmartCrawlerBuilder,
+    UrlData,
 };
-use std::collections::{HashMap, HashSet};
-use tracing::{debug, error, info};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// How often `--watch` re-reads the domains file
+const DOMAINS_FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() {
@@ -11,34 +38,352 @@ async fn main() {
         .install_default()
         .expect("Failed to install default crypto provider");
 
-    tracing_subscriber::fmt::init();
-
     let args = match CliArgs::parse() {
         Ok(args) => args,
         Err(e) => {
-            error!("Error parsing arguments: {}", e);
+            eprintln!("Error parsing arguments: {e}");
             std::process::exit(1);
         }
     };
 
-    info!("Starting SmartCrawler with domain: {}", args.domain);
+    // Logs go to stderr so results on stdout can be piped
+    tracing_subscriber::fmt()
+        .with_max_level(args.log_level)
+        .with_writer(std::io::stderr)
+        .init();
 
-    let mut storage = UrlStorage::new();
-    let mut domain_urls: HashMap<String, HashSet<String>> = HashMap::new();
+    if let CliCommand::Sitemap { format } = args.command {
+        if let Err(e) = list_sitemap(&args, format).await {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    // Convert domain to initial URL
-    let root_url = smart_crawler::utils::construct_root_url(&args.domain);
-    storage.add_url(root_url.clone());
-    domain_urls
-        .entry(args.domain.clone())
-        .or_default()
-        .insert(root_url);
+    if let CliCommand::MergeReviews { pages, reviews } = &args.command {
+        if let Err(e) = merge_review_file(pages, reviews, args.output.as_deref()) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    let mut browser = Browser::new(4444);
+    if let CliCommand::Diff {
+        before,
+        after,
+        json,
+    } = &args.command
+    {
+        if let Err(e) = diff_pages_files(before, after, *json, args.output.as_deref()) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    match browser.connect().await {
-        Ok(()) => info!("Connected to WebDriver"),
+    let template = match crawler_builder(&args) {
+        Ok(builder) => builder,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let build = |domain: &str| {
+        template.clone().domain(domain).build().unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        })
+    };
+
+    if let CliCommand::Test { url } = &args.command {
+        let crawler = build(&args.domain);
+        match crawler.check_browser(url).await {
+            Ok(title) => println!(
+                "✅ {} rendered {} (title: {})",
+                crawler.config().engine.as_str(),
+                url,
+                title
+            ),
+            Err(e) => exit_with_crawl_error(e),
+        }
+        return;
+    }
+
+    if let CliCommand::Monitor {
+        config,
+        history,
+        conditional,
+    } = &args.command
+    {
+        match run_monitor(&template, config, history, *conditional).await {
+            Ok(0) => {}
+            Ok(_) => std::process::exit(2),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let domains = match seed_domains(&args).await {
+        Ok(domains) => domains,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if args.dry_run {
+        for domain in &domains {
+            match build(domain).plan().await {
+                Ok(plan) => print_plan(&plan),
+                Err(e) => exit_with_crawl_error(e),
+            }
+        }
+        return;
+    }
+
+    if let CliCommand::AnalyzeUrl { url } = &args.command {
+        match build(&args.domain).analyze_url(url).await {
+            Ok(result) => {
+                print_analysis(&args, &result, url);
+                if let Err(e) = write_outputs(&args, template.config(), &[result]).await {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+            Err(e) => exit_with_crawl_error(e),
+        }
+        return;
+    }
+
+    let mut queue = DomainQueue::new();
+    if !args.worker {
+        for domain in &domains {
+            let priority = args.domain_priorities.get(domain).copied().unwrap_or(0);
+            queue.push(domain.clone(), priority);
+        }
+    }
+    let manifest_config = template.config().clone();
+    let mut scheduler = DomainScheduler::new(template, queue.into_shared())
+        .max_concurrent(args.max_concurrent_domains);
+    if let Some(pages) = args.fair_share {
+        scheduler = scheduler.fair_share(pages);
+    }
+    if let (true, Some(path)) = (args.watch, &args.domains_file) {
+        scheduler = scheduler.wait_for_domains(DOMAINS_FILE_POLL_INTERVAL);
+        tokio::spawn(watch_domains_file(
+            path.clone(),
+            scheduler.queue(),
+            DOMAINS_FILE_POLL_INTERVAL,
+            normalize_domain,
+        ));
+        info!("Watching {} for new domains, press Ctrl-C to stop", path);
+    }
+    #[cfg(feature = "db")]
+    let worker = match (&args.db_url, args.worker) {
+        (Some(db_url), true) => {
+            scheduler = scheduler.wait_for_domains(DOMAINS_FILE_POLL_INTERVAL);
+            match start_worker(db_url, &args, &domains, scheduler.queue()).await {
+                Ok(worker) => Some(worker),
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => None,
+    };
+    #[cfg(not(feature = "db"))]
+    let worker: Option<Worker> = None;
+
+    let queue = scheduler.queue();
+    let mut expanded = 0;
+    let mut results = Vec::new();
+    let mut last_error = None;
+    let crawl = scheduler.run(|domain, result| match result {
+        Ok(result) => {
+            print_results(&result);
+            if let Some(worker) = &worker {
+                // Other workers may crawl the linked domains too
+                let related = result.related_domains(args.auto_expand_domains - expanded);
+                expanded += related.len();
+                worker.record(WorkerUpdate {
+                    domain: domain.to_string(),
+                    pages: result.pages(args.content_mode),
+                    error: None,
+                    related,
+                });
+            } else if expanded < args.auto_expand_domains {
+                let mut queue = queue.lock().unwrap_or_else(|e| e.into_inner());
+                for related in result.related_domains(args.auto_expand_domains - expanded) {
+                    if queue.push(related.clone(), 0) {
+                        info!("Queued {} (linked from {})", related, domain);
+                        expanded += 1;
+                    }
+                }
+            }
+            results.push(result);
+        }
         Err(e) => {
+            error!("Crawl of {} failed: {}", domain, e);
+            if let Some(worker) = &worker {
+                worker.record(WorkerUpdate {
+                    domain: domain.to_string(),
+                    pages: Vec::new(),
+                    error: Some(e.to_string()),
+                    related: Vec::new(),
+                });
+            }
+            last_error = Some(e);
+        }
+    });
+    tokio::select! {
+        _ = crawl => {}
+        _ = tokio::signal::ctrl_c(), if args.watch || args.worker => {
+            info!("Interrupted, writing results of finished crawls");
+        }
+    }
+    if let Some(worker) = worker {
+        worker.stop().await;
+    }
+
+    if results.is_empty() {
+        if let Some(e) = last_error {
+            exit_with_crawl_error(e);
+        }
+    }
+    if args.deterministic {
+        // Concurrent crawls finish in any order; domains found while
+        // crawling come after the given ones, by name
+        results.sort_by_key(|result| {
+            let position = domains
+                .iter()
+                .position(|domain| site_host(domain) == result.domain);
+            (position.unwrap_or(usize::MAX), result.domain.clone())
+        });
+    }
+    if let Err(e) = write_outputs(&args, &manifest_config, &results).await {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// A finished crawl of a worker, to record in the shared database. Only
+/// builds with `--features db` can start a worker.
+#[cfg_attr(not(feature = "db"), allow(dead_code))]
+struct WorkerUpdate {
+    domain: String,
+    pages: Vec<ScrapedWebPage>,
+    error: Option<String>,
+    /// Linked domains to queue for any worker
+    related: Vec<String>,
+}
+
+/// Tasks of `--worker`: one claims domains from the shared queue, the
+/// other writes each finished crawl to the database
+struct Worker {
+    updates: tokio::sync::mpsc::UnboundedSender<WorkerUpdate>,
+    feeder: tokio::task::JoinHandle<()>,
+    recorder: tokio::task::JoinHandle<()>,
+}
+
+impl Worker {
+    fn record(&self, update: WorkerUpdate) {
+        if self.updates.send(update).is_err() {
+            error!("Failed to record a crawl in the database");
+        }
+    }
+
+    /// Stop claiming domains, write the crawls recorded so far and hand the
+    /// unfinished ones back to the queue
+    async fn stop(self) {
+        self.feeder.abort();
+        drop(self.updates);
+        if let Err(e) = self.recorder.await {
+            error!("Database task failed: {}", e);
+        }
+    }
+}
+
+/// Queue `domains` in the database at `db_url` and start claiming domains
+/// from it into `queue`
+#[cfg(feature = "db")]
+async fn start_worker(
+    db_url: &str,
+    args: &CliArgs,
+    domains: &[String],
+    queue: SharedDomainQueue,
+) -> Result<Worker, String> {
+    use smart_crawler::db_sink::{CrawlQueue, EntitySink};
+
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".to_string());
+    let name = format!("{}-{}", host, std::process::id());
+    let crawl_queue = CrawlQueue::connect(db_url, &name).await?;
+    let sink = EntitySink::connect(db_url).await?;
+    for domain in domains {
+        let priority = args.domain_priorities.get(domain).copied().unwrap_or(0);
+        if crawl_queue.push(domain, priority).await? {
+            info!("Queued {} in the shared queue", domain);
+        }
+    }
+
+    let feeder = {
+        let crawl_queue = crawl_queue.clone();
+        tokio::spawn(async move { crawl_queue.feed(queue, DOMAINS_FILE_POLL_INTERVAL).await })
+    };
+    let (updates, mut received) = tokio::sync::mpsc::unbounded_channel::<WorkerUpdate>();
+    let recorder = tokio::spawn(async move {
+        while let Some(update) = received.recv().await {
+            match sink.write_pages(&update.pages).await {
+                Ok(count) => info!(
+                    "Upserted {} entities of {} into the database",
+                    count, update.domain
+                ),
+                Err(e) => error!("{}", e),
+            }
+            for related in &update.related {
+                match crawl_queue.push(related, 0).await {
+                    Ok(true) => info!("Queued {} (linked from {})", related, update.domain),
+                    Ok(false) => {}
+                    Err(e) => warn!("{}", e),
+                }
+            }
+            if let Err(e) = crawl_queue
+                .finish(&update.domain, update.error.as_deref())
+                .await
+            {
+                error!("{}", e);
+            }
+        }
+        match crawl_queue.release().await {
+            Ok(0) => {}
+            Ok(count) => info!("Handed {} unfinished domains back to the queue", count),
+            Err(e) => error!("{}", e),
+        }
+    });
+    info!(
+        "Crawling domains from the shared queue as {}, press Ctrl-C to stop",
+        name
+    );
+    Ok(Worker {
+        updates,
+        feeder,
+        recorder,
+    })
+}
+
+fn exit_with_crawl_error(e: CrawlError) -> ! {
+    match e {
+        CrawlError::WebDriver(e @ BrowserError::EngineNotAvailable(_)) => error!("{}", e),
+        #[cfg(feature = "cdp")]
+        CrawlError::WebDriver(e @ BrowserError::CdpError(_)) => {
+            error!("Failed to launch Chrome: {}", e);
+            eprintln!("\n❌ Chrome Launch Failed");
+            eprintln!("📋 The cdp engine needs Chrome or Chromium installed on this machine");
+        }
+        CrawlError::WebDriver(e) => {
             error!("Failed to connect to WebDriver: {}", e);
             eprintln!("\n❌ WebDriver Connection Failed");
             eprintln!("📋 Please ensure a WebDriver server is running on port 4444");
@@ -48,152 +393,361 @@ async fn main() {
             eprintln!("   • Docker: docker run -d -p 4444:4444 selenium/standalone-chrome:latest");
             eprintln!("   • Check status: curl http://localhost:4444/status");
             eprintln!("📖 See CLAUDE.md for detailed setup instructions");
-            std::process::exit(1);
         }
+        e => error!("{}", e),
     }
+    std::process::exit(1);
+}
 
-    let parser = HtmlParser::new();
-
-    // Phase 1: URL Discovery - find additional URLs for each domain
-    info!("Starting URL discovery for domains");
+/// Crawl settings from the command line, without the domain
+fn crawler_builder(args: &CliArgs) -> Result<SmartCrawlerBuilder, String> {
+    let synonyms = match &args.synonyms {
+        Some(path) => smart_crawler::keywords::load_synonyms(path)?,
+        None => Default::default(),
+    };
 
-    let max_urls_per_domain = if args.prep { 10 } else { 3 };
+    let mut builder = args
+        .apply_profiled_options(SmartCrawler::builder().prep(args.prep))
+        .synonyms(synonyms)
+        .sitemap_sampling(args.sitemap_sampling)
+        .site_search(args.site_search)
+        .modified_since(args.modified_since)
+        .languages(args.languages.clone())
+        .locale(args.locale.clone())
+        .bounding_boxes(args.bounding_boxes)
+        .interactions(args.interactions.clone())
+        .capture_api(args.capture_api)
+        .allow_private(args.allow_private)
+        .fetch_limits(args.fetch_limits.clone())
+        .engine(args.engine)
+        .blocked_urls(args.blocked_urls.clone())
+        .block(args.block_categories.clone())
+        .ignore_meta_robots(args.ignore_meta_robots)
+        .redact_pii(args.redact_pii, args.keep_pii_entities)
+        .entity_types(args.entity_types.clone())
+        .entity_rules(args.entity_rules.clone())
+        .html_retention(args.html_retention.clone())
+        .deterministic(args.deterministic)
+        .brand_assets(args.brand_assets, args.assets_dir.as_ref().map(Into::into))
+        .visualize_groups(args.visualize_groups.as_ref().map(Into::into));
+    if let Some(objective) = &args.objective {
+        builder = builder.objective(objective);
+    }
+    if let Some(preset) = args.preset {
+        builder = builder.preset(preset);
+    }
+    if let Some(wayback) = &args.wayback {
+        builder = builder.wayback(wayback.clone());
+    }
+    if let Some(paths) = &args.probe_paths {
+        builder = builder.probe_paths(paths.clone());
+    }
+    if let Some(spill) = &args.spill {
+        builder = builder.spill_to_disk(spill.clone());
+    }
+    if args.progress {
+        builder = builder.observer(ProgressObserver::default());
+    }
+    Ok(builder)
+}
 
-    // Discover additional URLs for the domain
-    let domain = &args.domain;
-    let urls = domain_urls.get_mut(domain).unwrap();
+/// The domains to crawl: those given, then those found with `--search`
+async fn seed_domains(args: &CliArgs) -> Result<Vec<String>, String> {
+    let mut domains = args.domains.clone();
+    let Some(search) = &args.search else {
+        return Ok(domains);
+    };
+    let query = args
+        .objective
+        .as_deref()
+        .or(args.preset.map(|preset| preset.objective))
+        .unwrap_or_default();
+
+    let mut config = search.clone();
+    config.api_key = std::env::var(SEARCH_API_KEY_ENV).ok();
+    let found = SearchSeeder::new(config)
+        .seed_domains(query)
+        .await
+        .map_err(|e| e.to_string())?;
+    for domain in found {
+        if !domains.contains(&domain) {
+            info!("Found {} by searching", domain);
+            domains.push(domain);
+        }
+    }
+    if domains.is_empty() {
+        return Err(format!(
+            "The search for \"{query}\" found no domains to crawl"
+        ));
+    }
+    Ok(domains)
+}
 
-    if urls.len() < max_urls_per_domain {
-        info!(
-            "Domain {} has {} URL(s), searching for more (max: {})...",
-            domain,
-            urls.len(),
-            max_urls_per_domain
-        );
+/// `smart-crawler sitemap`: read the sitemaps without starting a browser
+async fn list_sitemap(args: &CliArgs, format: SitemapFormat) -> Result<(), String> {
+    let defaults = SitemapConfig::default();
+    let parser = SitemapParser::new(SitemapConfig {
+        max_urls: args.max_sitemap_urls.unwrap_or(defaults.max_urls),
+        sampling: args.sitemap_sampling,
+        modified_since: args.modified_since,
+        seed: args.deterministic.then_some(DETERMINISTIC_SEED),
+        allow_private: args.allow_private,
+        ..defaults
+    });
+    let urls = parser
+        .get_all_urls(&args.domain)
+        .await
+        .map_err(|e| format!("Failed to read sitemap for {}: {}", args.domain, e))?;
+    let listing = format_sitemap_urls(&urls, format)?;
+
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, listing).map_err(|e| format!("Failed to write {path}: {e}"))?;
+            info!("Wrote {} sitemap URLs to {}", urls.len(), path);
+        }
+        None => print!("{listing}"),
+    }
+    Ok(())
+}
 
-        // Pick the first URL to extract links from
-        if let Some(first_url) = urls.iter().next() {
-            match process_url(&mut browser, &parser, &mut storage, first_url, true).await {
-                Ok(html_source) => {
-                    let additional_urls = parser.extract_links(&html_source, domain);
-                    let mut added_count = 0;
-
-                    for additional_url in additional_urls {
-                        if urls.len() >= max_urls_per_domain {
-                            break;
-                        }
-                        if urls.insert(additional_url.clone()) {
-                            storage.add_url(additional_url);
-                            added_count += 1;
-                        }
+/// `smart-crawler monitor`: read every tracked value, record it and alert on
+/// changes since the last run. Returns the number of alerts.
+async fn run_monitor(
+    template: &SmartCrawlerBuilder,
+    config_path: &str,
+    history_path: &str,
+    conditional: bool,
+) -> Result<usize, String> {
+    let config = load_monitor_config(config_path)?;
+    let history = load_history(history_path)?;
+    let extractor = EntityExtractor::new();
+
+    let mut observations = Vec::new();
+    let mut alerts = Vec::new();
+    for target in &config.targets {
+        let previous = last_observation(&history, &target.key());
+        let mut validators = Validators::default();
+        if conditional {
+            let known = previous.map_or_else(Validators::default, |observation| {
+                observation.validators.clone()
+            });
+            match check_freshness(&target.url, &known, template.config().allow_private).await {
+                Ok(Freshness::NotModified) => {
+                    if let Some(previous) = previous {
+                        info!("{}: {} (unchanged)", target.key(), previous.value);
+                        observations.push(Observation {
+                            target: target.key(),
+                            value: previous.value.clone(),
+                            observed_at: Utc::now(),
+                            validators: known,
+                            unchanged: true,
+                        });
+                        continue;
                     }
-
-                    info!(
-                        "Found {} additional URLs for domain {}",
-                        added_count, domain
-                    );
                 }
-                Err(e) => {
-                    error!("Failed to extract links from {}: {}", first_url, e);
+                Ok(Freshness::Modified(current)) => validators = current,
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        let domain = normalize_domain(&target.url)?;
+        let crawler = template
+            .clone()
+            .domain(&domain)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let result = match crawler.analyze_url(&target.url).await {
+            Ok(result) => result,
+            Err(e @ CrawlError::WebDriver(_)) => exit_with_crawl_error(e),
+            Err(e) => {
+                error!("Failed to check {}: {}", target.key(), e);
+                continue;
+            }
+        };
+        let value = result
+            .storage
+            .get_completed_urls()
+            .first()
+            .and_then(|url_data| target.read_value(&result.storage.load(url_data), &extractor));
+        let Some(value) = value else {
+            warn!("No value found for {}", target.key());
+            continue;
+        };
+
+        match previous {
+            Some(previous) => {
+                if let Some(alert) = target.check(&previous.value, &value) {
+                    println!("ALERT {alert}");
+                    alerts.push(alert);
+                } else {
+                    info!("{}: {}", target.key(), value);
                 }
             }
+            None => info!("{}: {} (first observation)", target.key(), value),
         }
+        observations.push(Observation {
+            target: target.key(),
+            value,
+            observed_at: Utc::now(),
+            validators,
+            unchanged: false,
+        });
     }
 
-    // Phase 2: Process all discovered URLs
-    info!("Processing all discovered URLs");
+    append_history(history_path, &observations)?;
+    if let (Some(webhook), false) = (&config.webhook, alerts.is_empty()) {
+        send_webhook(webhook, &alerts).await?;
+    }
+    Ok(alerts.len())
+}
 
-    let mut all_urls: Vec<String> = Vec::new();
+/// `smart-crawler merge-reviews`: apply reviewed entities to a pages file
+fn merge_review_file(
+    pages_path: &str,
+    reviews_path: &str,
+    output: Option<&str>,
+) -> Result<(), String> {
+    let (manifest, mut pages) = smart_crawler::results::read_results(pages_path)?;
+    let reviews = load_review_file(reviews_path)?;
+    let summary = merge_reviews(&mut pages, &reviews);
+    info!(
+        "Merged reviews: {} accepted, {} corrected, {} rejected",
+        summary.accepted, summary.corrected, summary.rejected
+    );
+
+    match (output, &manifest) {
+        (Some(path), Some(manifest)) => {
+            smart_crawler::results::write_results(path, manifest, &pages)
+        }
+        (Some(path), None) => smart_crawler::results::write_pages(path, &pages),
+        (None, _) => {
+            let json = serde_json::to_string_pretty(&pages)
+                .map_err(|e| format!("Failed to serialize results: {e}"))?;
+            println!("{json}");
+            Ok(())
+        }
+    }
+}
 
-    // Collect all URLs with root URL prioritized
-    let domain = &args.domain;
-    let urls = domain_urls.get(domain).unwrap();
-    let root_url = smart_crawler::utils::construct_root_url(domain);
+/// `smart-crawler diff`: what changed between two runs
+fn diff_pages_files(
+    before_path: &str,
+    after_path: &str,
+    json: bool,
+    output: Option<&str>,
+) -> Result<(), String> {
+    let before = smart_crawler::results::read_pages(before_path)?;
+    let after = smart_crawler::results::read_pages(after_path)?;
+    let diff = diff_runs(&before, &after);
+    let listing = if json {
+        let mut json = serde_json::to_string_pretty(&diff)
+            .map_err(|e| format!("Failed to serialize differences: {e}"))?;
+        json.push('\n');
+        json
+    } else {
+        diff.to_string()
+    };
 
-    // Add root URL first
-    if urls.contains(&root_url) {
-        all_urls.push(root_url.clone());
-    }
-    // Then add other URLs
-    for url in urls {
-        if url != &root_url {
-            all_urls.push(url.clone());
+    match output {
+        Some(path) => {
+            std::fs::write(path, listing).map_err(|e| format!("Failed to write {path}: {e}"))?;
+            info!("Wrote differences to {}", path);
         }
+        None => print!("{listing}"),
     }
+    Ok(())
+}
 
-    for url in &all_urls {
-        if let Some(url_data) = storage.get_url_data(url) {
-            if matches!(url_data.status, FetchStatus::Success) {
-                continue; // Already processed
-            }
-        }
+/// Prints crawl progress to stderr for `--progress`
+#[derive(Default)]
+struct ProgressObserver {
+    discovered: AtomicUsize,
+    finished: AtomicUsize,
+}
 
-        match process_url(&mut browser, &parser, &mut storage, url, false).await {
-            Ok(_) => info!("Successfully processed {}", url),
-            Err(e) => error!("Failed to process {}: {}", url, e),
-        }
+impl CrawlObserver for ProgressObserver {
+    fn on_url_discovered(&self, url: &str, source: DiscoverySource) {
+        self.discovered.fetch_add(1, Ordering::Relaxed);
+        eprintln!("  + {url} ({source:?})");
     }
 
-    // Phase 3: Template analysis (prep mode) or standard duplicate analysis
-    if args.prep {
-        info!("Running template detection analysis in prep mode");
-        let mut combined_store = TemplatePathStore::new();
-        let template_detector = TemplateDetector::new();
+    fn on_page_scraped(&self, page: &UrlData) {
+        let finished = self.finished.fetch_add(1, Ordering::Relaxed) + 1;
+        let discovered = self.discovered.load(Ordering::Relaxed);
+        eprintln!(
+            "[{finished}/{discovered}] ✓ {} {}",
+            page.url,
+            page.title.as_deref().unwrap_or_default()
+        );
+    }
 
-        // Process each completed URL to extract template paths
-        let completed_urls = storage.get_completed_urls();
-        for url_data in &completed_urls {
-            if let Some(html_tree) = &url_data.html_tree {
-                let url_store = template_detector.extract_templates_with_paths(html_tree);
-                for path in url_store.get_paths() {
-                    combined_store.add_path(path.clone());
-                }
-            }
+    fn on_error(&self, target: &str, error: &str) {
+        // Sitemap errors are reported against the domain, not a page
+        if !target.contains("://") {
+            eprintln!("  ! {target}: {error}");
+            return;
         }
+        let finished = self.finished.fetch_add(1, Ordering::Relaxed) + 1;
+        let discovered = self.discovered.load(Ordering::Relaxed);
+        eprintln!("[{finished}/{discovered}] ✗ {target}: {error}");
+    }
+}
 
-        info!(
-            "Template analysis complete, found {} unique template paths",
-            combined_store.get_paths().len()
-        );
-    } else {
-        info!("Running standard duplicate analysis");
-
-        let domain = &args.domain;
-        storage.analyze_domain_duplicates(domain);
-        if let Some(duplicates) = storage.get_domain_duplicates(domain) {
-            let duplicate_count = duplicates.get_duplicate_count();
-            if duplicate_count > 0 {
-                info!(
-                    "Found {} duplicate node patterns for domain {}",
-                    duplicate_count, domain
-                );
-            } else {
-                info!(
-                    "No duplicate patterns found for domain {} (likely insufficient pages)",
-                    domain
-                );
-            }
+fn print_plan(plan: &CrawlPlan) {
+    println!("\n=== Crawl Plan for {} ===", plan.domain);
+    if !plan.keywords.is_empty() {
+        let keywords: Vec<String> = plan
+            .keywords
+            .iter()
+            .map(|term| format!("{} ({})", term.keyword, term.stems.join(", ")))
+            .collect();
+        println!("Objective keywords: {}", keywords.join("; "));
+    }
+    println!(
+        "Would crawl {} of up to {} pages, in this order:",
+        plan.urls.len(),
+        plan.max_pages
+    );
+    for (index, planned) in plan.urls.iter().enumerate() {
+        let source = match planned.source {
+            DiscoverySource::Root => "root",
+            DiscoverySource::Homepage => "homepage link",
+            DiscoverySource::Sitemap => "sitemap",
+            DiscoverySource::ContactPage => "contact page",
+            DiscoverySource::Probe => "probed path",
+            DiscoverySource::SiteSearch => "site search",
+        };
+        let mut reasons = vec![source.to_string()];
+        if !planned.url_keywords.is_empty() {
+            reasons.push(format!("matches {}", planned.url_keywords.join(", ")));
+        }
+        if planned.preferred_page_type {
+            reasons.push("preferred page type".to_string());
+        }
+        if let Some(score) = planned.survey_score {
+            reasons.push(format!("survey score {score}"));
         }
+        if let Some(priority) = planned.sitemap_priority {
+            reasons.push(format!("sitemap priority {priority}"));
+        }
+        println!("  {}. {} ({})", index + 1, planned.url, reasons.join("; "));
     }
+}
 
-    let _ = browser.close().await;
+fn print_results(result: &CrawlResult) {
+    let completed_urls = result.storage.get_completed_urls();
 
-    if args.prep {
+    if let Some(template_paths) = &result.template_paths {
         // In prep mode, output detected template paths in serialized format
         println!("\n=== Template Path Detection Results ===");
 
-        let mut combined_store = TemplatePathStore::new();
-        let template_detector = TemplateDetector::new();
-
-        // Process each completed URL to extract template paths
-        let completed_urls = storage.get_completed_urls();
         if completed_urls.is_empty() {
             println!("No URLs were successfully processed.");
         } else {
             println!(
                 "Processed {} URLs for domain {}:",
                 completed_urls.len(),
-                args.domain
+                result.domain
             );
             for url_data in &completed_urls {
                 println!(
@@ -201,27 +755,22 @@ async fn main() {
                     url_data.url,
                     url_data.title.as_deref().unwrap_or("No title")
                 );
-
-                if let Some(html_tree) = &url_data.html_tree {
-                    let url_store = template_detector.extract_templates_with_paths(html_tree);
-                    for path in url_store.get_paths() {
-                        combined_store.add_path(path.clone());
-                    }
-                }
             }
 
             println!("\nDetected Template Paths (Rust-serializable format):");
-            println!("{}", combined_store.to_serialized_string());
+            println!("{}", template_paths.to_serialized_string());
         }
     } else {
         // Regular mode - show crawling results
         println!("\n=== Crawling Results ===");
-        let completed_urls = storage.get_completed_urls();
+        if let Some(reason) = &result.aborted {
+            println!("Crawl stopped early, showing the pages fetched before: {reason}");
+        }
 
         if completed_urls.is_empty() {
             println!("No URLs were successfully processed.");
         } else {
-            for url_data in completed_urls {
+            for url_data in result.output_urls() {
                 let title = url_data.title.as_deref().unwrap_or("No title found");
                 println!("URL: {}", url_data.url);
                 println!("Title: {title}");
@@ -229,59 +778,223 @@ async fn main() {
                 println!("---");
             }
         }
+        for (url, reason) in result.skipped_pages() {
+            println!("Skipped: {url} ({reason})");
+        }
+
+        let validation_stats = result.validation_stats();
+        if !validation_stats.is_empty() {
+            println!("\n=== Entity Validation ===");
+            for stats in validation_stats {
+                let action = match stats.action {
+                    RuleAction::Flag => "flagged",
+                    RuleAction::Drop => "dropped",
+                };
+                println!(
+                    "{}: {} checked, {} {}",
+                    stats.rule, stats.checked, stats.failed, action
+                );
+            }
+        }
+
+        let crawled_contact_pages = result.events.iter().any(|event| {
+            matches!(
+                event,
+                CrawlEvent::UrlDiscovered {
+                    source: DiscoverySource::ContactPage,
+                    ..
+                }
+            )
+        });
+        let contact = result.contact_info();
+        if crawled_contact_pages && !contact.is_empty() {
+            println!("\n=== Contact Details ===");
+            for (label, values) in [
+                ("Email", &contact.emails),
+                ("Phone", &contact.phones),
+                ("Address", &contact.addresses),
+            ] {
+                for value in values {
+                    println!("{label}: {value}");
+                }
+            }
+            println!("Found on: {}", contact.sources.join(", "));
+        }
     }
+}
 
-    info!("SmartCrawler finished processing {} URLs", all_urls.len());
+/// `smart-crawler analyze-url`: the page as JSON on stdout, for piping
+fn print_analysis(args: &CliArgs, result: &CrawlResult, url: &str) {
+    let Some(page) = result.pages(args.content_mode).into_iter().next() else {
+        let reason = result
+            .events
+            .iter()
+            .find_map(|event| match event {
+                CrawlEvent::PageFailed { error, .. } => Some(error.as_str()),
+                _ => None,
+            })
+            .or_else(|| result.skipped_pages().first().map(|(_, reason)| *reason))
+            .unwrap_or("no content");
+        error!("Failed to analyze {}: {}", url, reason);
+        std::process::exit(1);
+    };
+    match serde_json::to_string_pretty(&page) {
+        Ok(json) => println!("{json}"),
+        Err(e) => {
+            error!("Failed to serialize {}: {}", url, e);
+            std::process::exit(1);
+        }
+    }
 }
 
-async fn process_url(
-    browser: &mut Browser,
-    parser: &HtmlParser,
-    storage: &mut UrlStorage,
-    url: &str,
-    return_html: bool,
-) -> Result<String, String> {
-    info!("Processing URL: {}", url);
+/// Write every requested output, logging each that fails. Errs when any
+/// did, after trying the rest.
+async fn write_outputs(
+    args: &CliArgs,
+    config: &CrawlConfig,
+    results: &[CrawlResult],
+) -> Result<(), String> {
+    let mut failed = 0;
+    if let Some(output_path) = &args.output {
+        let mut pages: Vec<_> = results
+            .iter()
+            .flat_map(|result| result.pages(args.content_mode))
+            .collect();
+        if args.deterministic {
+            pages
+                .iter_mut()
+                .for_each(ScrapedWebPage::clear_extraction_times);
+        }
+        if let Some(known) = &args.known_entities {
+            let counts = known.mark_pages(&mut pages);
+            info!(
+                "Entities against the {} known: {} new, {} updated, {} duplicate",
+                known.len(),
+                counts.new,
+                counts.updated,
+                counts.duplicate
+            );
+        }
+        let domains = results.iter().map(|result| result.domain.clone()).collect();
+        let manifest = RunManifest::new(config, domains, args.deterministic);
+        match smart_crawler::results::write_results(output_path, &manifest, &pages) {
+            Ok(()) => info!("Wrote {} pages to {}", pages.len(), output_path),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
+            }
+        }
+    }
 
-    if let Some(url_data) = storage.get_url_data_mut(url) {
-        url_data.update_status(FetchStatus::InProgress);
+    if let Some(review_path) = &args.review_file {
+        let screenshot_dir = args.visualize_groups.as_deref().map(Path::new);
+        let items: Vec<_> = results
+            .iter()
+            .flat_map(|result| result.review_items(screenshot_dir))
+            .collect();
+        match write_review_file(review_path, &items) {
+            Ok(()) => info!(
+                "Wrote {} entities to review to {}",
+                items.len(),
+                review_path
+            ),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
+            }
+        }
     }
 
-    match browser.navigate_to(url).await {
-        Ok(()) => {
-            debug!("Successfully navigated to {}", url);
+    if let Some(report_path) = &args.report {
+        let reports: Vec<_> = results.iter().map(DomainReport::from_result).collect();
+        let screenshot_dir = args.visualize_groups.as_deref().map(Path::new);
+        match write_report(report_path, &reports, screenshot_dir) {
+            Ok(()) => info!("Wrote report to {}", report_path),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
+            }
+        }
+    }
 
-            match browser.get_html_source().await {
-                Ok(html_source) => {
-                    let title = browser.get_page_title().await.ok();
-                    let html_tree = parser.parse(&html_source);
+    if let Some(profiles_path) = &args.domain_profiles {
+        let profiles: Vec<_> = results.iter().map(CrawlResult::domain_profile).collect();
+        match write_profiles(profiles_path, &profiles) {
+            Ok(()) => info!(
+                "Wrote {} domain profiles to {}",
+                profiles.len(),
+                profiles_path
+            ),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
+            }
+        }
+    }
 
-                    if let Some(url_data) = storage.get_url_data_mut(url) {
-                        url_data.set_html_data(html_source.clone(), html_tree, title);
-                        url_data.update_status(FetchStatus::Success);
-                    }
+    if let Some(graph_path) = &args.export_graph {
+        // Raw content, so the Cypher link graph includes navigation links
+        let pages: Vec<_> = results
+            .iter()
+            .flat_map(|result| result.pages(ContentMode::Raw))
+            .collect();
+        match write_graph(graph_path, &pages, args.graph_format) {
+            Ok(()) => info!("Wrote graph of {} pages to {}", pages.len(), graph_path),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
+            }
+        }
+    }
 
-                    if return_html {
-                        Ok(html_source)
-                    } else {
-                        Ok(String::new())
-                    }
-                }
-                Err(e) => {
-                    let error_msg = format!("Failed to get HTML source: {e}");
-                    if let Some(url_data) = storage.get_url_data_mut(url) {
-                        url_data.update_status(FetchStatus::Failed(error_msg.clone()));
-                    }
-                    Err(error_msg)
-                }
+    if let Some(lists_path) = &args.extract_lists {
+        let extractor = ListExtractor::new();
+        let lists: Vec<_> = results
+            .iter()
+            .flat_map(|result| {
+                result
+                    .output_urls()
+                    .into_iter()
+                    .map(|url_data| result.storage.load(url_data))
+            })
+            .filter_map(|url_data| {
+                let tree = url_data.html_tree.as_ref()?;
+                Some(extractor.extract(tree, &url_data.url))
+            })
+            .flatten()
+            .collect();
+        match smart_crawler::list_extraction::write_lists(lists_path, &lists) {
+            Ok(()) => info!("Wrote {} lists to {}", lists.len(), lists_path),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
             }
         }
-        Err(e) => {
-            let error_msg = format!("Failed to navigate: {e}");
-            if let Some(url_data) = storage.get_url_data_mut(url) {
-                url_data.update_status(FetchStatus::Failed(error_msg.clone()));
+    }
+
+    // A worker has written each crawl as it finished
+    #[cfg(feature = "db")]
+    if let (Some(db_url), false) = (&args.db_url, args.worker) {
+        let pages: Vec<_> = results
+            .iter()
+            .flat_map(|result| result.pages(args.content_mode))
+            .collect();
+        let written = match smart_crawler::db_sink::EntitySink::connect(db_url).await {
+            Ok(sink) => sink.write_pages(&pages).await,
+            Err(e) => Err(e),
+        };
+        match written {
+            Ok(count) => info!("Upserted {} entities into the database", count),
+            Err(e) => {
+                error!("{}", e);
+                failed += 1;
             }
-            Err(error_msg)
         }
     }
+
+    match failed {
+        0 => Ok(()),
+        1 => Err("An output could not be written".to_string()),
+        count => Err(format!("{count} outputs could not be written")),
+    }
 }
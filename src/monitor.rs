@@ -0,0 +1,473 @@
+use crate::entities::{EntityExtractor, EntityKind};
+use crate::network_guard::{check_url, http_client};
+use crate::storage::UrlData;
+use crate::text_normalize::normalize_text;
+use chrono::{DateTime, Utc};
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::time::Duration;
+
+/// Values to track, read from the `monitor` config file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MonitorConfig {
+    pub targets: Vec<MonitorTarget>,
+    /// URL that alerts are POSTed to as JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertOn {
+    #[default]
+    Change,
+    Drop,
+    Rise,
+}
+
+/// One tracked value: the text of `selector`, or else the first entity of
+/// kind `entity`, on `url`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorTarget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entity: Option<EntityKind>,
+    /// Smallest relative change of a numeric value that alerts, e.g. 0.05 for 5%
+    #[serde(default)]
+    pub threshold: f64,
+    #[serde(default)]
+    pub alert_on: AlertOn,
+}
+
+impl MonitorTarget {
+    /// Name used in alerts and the history file
+    pub fn key(&self) -> String {
+        self.name.clone().unwrap_or_else(|| match &self.selector {
+            Some(selector) => format!("{} {}", self.url, selector),
+            None => self.url.clone(),
+        })
+    }
+
+    /// The tracked value on a scraped page
+    pub fn read_value(&self, url_data: &UrlData, extractor: &EntityExtractor) -> Option<String> {
+        if let Some(selector) = &self.selector {
+            let selector = Selector::parse(selector).ok()?;
+            let document = Html::parse_document(&url_data.html()?);
+            let element = document.select(&selector).next()?;
+            let text = normalize_text(&element.text().collect::<Vec<_>>().join(" "));
+            return Some(text).filter(|text| !text.is_empty());
+        }
+        let kind = self.entity?;
+        extractor
+            .extract_from_tree(url_data.html_tree.as_ref()?)
+            .into_iter()
+            .find(|entity| entity.kind == kind)
+            .map(|entity| entity.value)
+    }
+
+    /// An alert when `current` differs enough from `previous`
+    pub fn check(&self, previous: &str, current: &str) -> Option<MonitorAlert> {
+        if previous == current {
+            return None;
+        }
+        let change = match (leading_number(previous), leading_number(current)) {
+            (Some(before), Some(after)) if before != 0.0 => Some((after - before) / before),
+            _ => None,
+        };
+        let alert = match (change, self.alert_on) {
+            (Some(change), AlertOn::Change) => change.abs() >= self.threshold,
+            (Some(change), AlertOn::Drop) => change < 0.0 && -change >= self.threshold,
+            (Some(change), AlertOn::Rise) => change > 0.0 && change >= self.threshold,
+            // Text that isn't a number can only change
+            (None, _) => true,
+        };
+        alert.then(|| MonitorAlert {
+            target: self.key(),
+            url: self.url.clone(),
+            before: previous.to_string(),
+            after: current.to_string(),
+            change,
+        })
+    }
+}
+
+/// The first number in a value, ignoring thousands separators
+fn leading_number(value: &str) -> Option<f64> {
+    let start = value.find(|c: char| c.is_ascii_digit())?;
+    let number: String = value[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .filter(|c| *c != ',')
+        .collect();
+    number.trim_end_matches('.').parse().ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorAlert {
+    pub target: String,
+    pub url: String,
+    pub before: String,
+    pub after: String,
+    /// Relative change of numeric values, e.g. -0.1 for a 10% drop
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+}
+
+impl std::fmt::Display for MonitorAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {} -> {}", self.target, self.before, self.after)?;
+        if let Some(change) = self.change {
+            write!(f, " ({:+.1}%)", change * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+/// A value read by one monitor run, one JSON line in the history file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub target: String,
+    pub value: String,
+    pub observed_at: DateTime<Utc>,
+    /// Validators of the page, for asking next time whether it changed
+    #[serde(flatten)]
+    pub validators: Validators,
+    /// The server answered 304 Not Modified, so the previous value was
+    /// kept without rendering the page
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unchanged: bool,
+}
+
+/// HTTP validators a server sent with a page
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Answer to a conditional request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Freshness {
+    /// 304: the page is as it was when the validators were recorded
+    NotModified,
+    /// The page changed, or the server doesn't say; its current validators
+    Modified(Validators),
+}
+
+/// Ask with a HEAD request carrying `If-None-Match` and `If-Modified-Since`
+/// whether a page changed since `previous` were recorded. Pages on or
+/// redirecting to private networks are refused unless `allow_private`.
+pub async fn check_freshness(
+    url: &str,
+    previous: &Validators,
+    allow_private: bool,
+) -> Result<Freshness, String> {
+    if !allow_private {
+        check_url(url).await?;
+    }
+    let client = http_client(Duration::from_secs(30), allow_private);
+    let mut request = client.head(url);
+    if let Some(etag) = &previous.etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = &previous.last_modified {
+        request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Conditional request to {url} failed: {e}"))?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED && !previous.is_empty() {
+        return Ok(Freshness::NotModified);
+    }
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    Ok(Freshness::Modified(Validators {
+        etag: header(reqwest::header::ETAG),
+        last_modified: header(reqwest::header::LAST_MODIFIED),
+    }))
+}
+
+pub fn load_monitor_config(path: &str) -> Result<MonitorConfig, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let config: MonitorConfig = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid monitor config {path}: {e}"))?;
+    for target in &config.targets {
+        match &target.selector {
+            Some(selector) => {
+                Selector::parse(selector)
+                    .map_err(|e| format!("Invalid selector for {}: {e}", target.key()))?;
+            }
+            None if target.entity.is_none() => {
+                return Err(format!("{} needs a selector or an entity", target.key()))
+            }
+            None => {}
+        }
+    }
+    Ok(config)
+}
+
+/// Observations in a history file, oldest first; a missing file is an empty history
+pub fn load_history(path: &str) -> Result<Vec<Observation>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {path}: {e}")),
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| format!("Invalid history entry in {path}: {e}"))
+        })
+        .collect()
+}
+
+pub fn append_history(path: &str, observations: &[Observation]) -> Result<(), String> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {path}: {e}"))?;
+    for observation in observations {
+        let line = serde_json::to_string(observation)
+            .map_err(|e| format!("Failed to serialize observation: {e}"))?;
+        writeln!(file, "{line}").map_err(|e| format!("Failed to write {path}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// The latest observation recorded for a target
+pub fn last_observation<'a>(history: &'a [Observation], target: &str) -> Option<&'a Observation> {
+    history
+        .iter()
+        .rev()
+        .find(|observation| observation.target == target)
+}
+
+/// The latest value recorded for a target
+pub fn last_value<'a>(history: &'a [Observation], target: &str) -> Option<&'a str> {
+    last_observation(history, target).map(|observation| observation.value.as_str())
+}
+
+/// POST alerts to a webhook as `{"alerts": [...]}`. The webhook is set by
+/// whoever runs the monitor and may well be on the LAN, so private networks
+/// are allowed.
+pub async fn send_webhook(url: &str, alerts: &[MonitorAlert]) -> Result<(), String> {
+    let client = http_client(Duration::from_secs(30), true);
+    client
+        .post(url)
+        .json(&serde_json::json!({ "alerts": alerts }))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to send alerts to {url}: {e}"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::HtmlParser;
+
+    fn target(selector: Option<&str>, entity: Option<EntityKind>) -> MonitorTarget {
+        MonitorTarget {
+            name: None,
+            url: "https://shop.example.com/kettle".to_string(),
+            selector: selector.map(str::to_string),
+            entity,
+            threshold: 0.0,
+            alert_on: AlertOn::Change,
+        }
+    }
+
+    #[test]
+    fn test_read_value() {
+        let html = r#"<html><body><h1>Kettle</h1><span class="price">$1,299.00</span><p>In stock</p></body></html>"#;
+        let mut url_data = UrlData::new("https://shop.example.com/kettle".to_string());
+        url_data.set_html_data(html.to_string(), HtmlParser::new().parse(html), None);
+        let extractor = EntityExtractor::new();
+
+        let by_selector = target(Some("span.price"), None);
+        assert_eq!(
+            by_selector.read_value(&url_data, &extractor).as_deref(),
+            Some("$1,299.00")
+        );
+        assert_eq!(
+            by_selector.key(),
+            "https://shop.example.com/kettle span.price"
+        );
+
+        let by_entity = target(None, Some(EntityKind::Price));
+        assert_eq!(
+            by_entity.read_value(&url_data, &extractor).as_deref(),
+            Some("1299.00 USD")
+        );
+        assert!(target(Some("span.missing"), None)
+            .read_value(&url_data, &extractor)
+            .is_none());
+    }
+
+    #[test]
+    fn test_check_thresholds() {
+        let mut price = target(None, Some(EntityKind::Price));
+        price.alert_on = AlertOn::Drop;
+        price.threshold = 0.05;
+
+        assert!(price.check("100.00 USD", "100.00 USD").is_none());
+        assert!(price.check("100.00 USD", "97.00 USD").is_none());
+        assert!(price.check("100.00 USD", "120.00 USD").is_none());
+        let alert = price.check("100.00 USD", "90.00 USD").unwrap();
+        assert_eq!(
+            alert.to_string(),
+            format!("{}: 100.00 USD -> 90.00 USD (-10.0%)", price.key())
+        );
+
+        let stock = target(Some("p"), None);
+        assert!(stock.check("In stock", "Sold out").is_some());
+        assert_eq!(stock.check("In stock", "Sold out").unwrap().change, None);
+    }
+
+    #[test]
+    fn test_load_monitor_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("monitor.json");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(
+            &path,
+            r#"{"targets": [{"name": "kettle", "url": "https://shop.example.com/kettle", "entity": "price", "threshold": 0.05, "alert_on": "drop"}], "webhook": "https://hooks.example.com/alerts"}"#,
+        )
+        .unwrap();
+        let config = load_monitor_config(path_str).unwrap();
+        assert_eq!(config.targets[0].entity, Some(EntityKind::Price));
+        assert_eq!(config.targets[0].alert_on, AlertOn::Drop);
+        assert_eq!(
+            config.webhook.as_deref(),
+            Some("https://hooks.example.com/alerts")
+        );
+
+        std::fs::write(
+            &path,
+            r#"{"targets": [{"url": "https://shop.example.com/kettle"}]}"#,
+        )
+        .unwrap();
+        assert!(load_monitor_config(path_str).is_err());
+    }
+
+    #[test]
+    fn test_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        let path = path.to_str().unwrap();
+        assert!(load_history(path).unwrap().is_empty());
+
+        let observe = |target: &str, value: &str| Observation {
+            target: target.to_string(),
+            value: value.to_string(),
+            observed_at: Utc::now(),
+            validators: Validators::default(),
+            unchanged: false,
+        };
+        append_history(
+            path,
+            &[observe("kettle", "20 USD"), observe("toaster", "30 USD")],
+        )
+        .unwrap();
+        append_history(path, &[observe("kettle", "18 USD")]).unwrap();
+
+        let history = load_history(path).unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(last_value(&history, "kettle"), Some("18 USD"));
+        assert_eq!(last_value(&history, "toaster"), Some("30 USD"));
+        assert_eq!(last_value(&history, "blender"), None);
+
+        // Validators and 304s are recorded only when there are some
+        let line = serde_json::to_string(&Observation {
+            validators: Validators {
+                etag: Some("\"v2\"".to_string()),
+                last_modified: None,
+            },
+            unchanged: true,
+            ..observe("kettle", "18 USD")
+        })
+        .unwrap();
+        assert!(line.contains(r#""etag":"\"v2\"","unchanged":true"#));
+        assert!(!serde_json::to_string(&observe("kettle", "18 USD"))
+            .unwrap()
+            .contains("unchanged"));
+    }
+
+    #[tokio::test]
+    async fn test_check_freshness() {
+        // Answers 304 to requests for the current ETag
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/kettle", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 2048];
+                let read = tokio::io::AsyncReadExt::read(&mut stream, &mut request)
+                    .await
+                    .unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_lowercase();
+                let status = if request.contains("if-none-match: \"v1\"") {
+                    "304 Not Modified"
+                } else {
+                    "200 OK"
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nETag: \"v1\"\r\nLast-Modified: Mon, 01 Jan 2024 00:00:00 GMT\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                tokio::io::AsyncWriteExt::write_all(&mut stream, response.as_bytes())
+                    .await
+                    .ok();
+            }
+        });
+
+        let Freshness::Modified(validators) = check_freshness(&url, &Validators::default(), true)
+            .await
+            .unwrap()
+        else {
+            panic!("a first request can't be answered 304");
+        };
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            validators.last_modified.as_deref(),
+            Some("Mon, 01 Jan 2024 00:00:00 GMT")
+        );
+        assert_eq!(
+            check_freshness(&url, &validators, true).await.unwrap(),
+            Freshness::NotModified
+        );
+        let stale = Validators {
+            etag: Some("\"v0\"".to_string()),
+            last_modified: None,
+        };
+        assert!(matches!(
+            check_freshness(&url, &stale, true).await.unwrap(),
+            Freshness::Modified(_)
+        ));
+        assert!(check_freshness(&url, &validators, false)
+            .await
+            .unwrap_err()
+            .contains("private network"));
+    }
+}
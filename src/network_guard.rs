@@ -0,0 +1,291 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+use url::{Host, Url};
+
+/// Redirects followed before a request fails, as reqwest does by default
+const MAX_REDIRECTS: usize = 10;
+
+/// Address ranges that aren't on the public internet: loopback, private
+/// networks, carrier-grade NAT, link-local (cloud metadata endpoints),
+/// benchmarking, multicast and reserved ranges, for IPv4 and IPv6, and the
+/// NAT64 and 6to4 ranges, whose addresses embed an IPv4 address that may be
+/// any of those
+pub const PRIVATE_RANGES: &[&str] = &[
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/127",
+    "64:ff9b::/96",
+    "2002::/16",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// An IP address block in CIDR notation, e.g. `10.0.0.0/8`; a bare address
+/// is a block of one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl IpRange {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 addresses written as IPv6, e.g. ::ffff:127.0.0.1
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid IP range: {s}");
+        let (address, prefix) = match s.trim().split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let network: IpAddr = address.parse().map_err(|_| invalid())?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse().map_err(|_| invalid())?,
+            None => max,
+        };
+        if prefix > max {
+            return Err(invalid());
+        }
+        Ok(IpRange { network, prefix })
+    }
+}
+
+/// Whether an address is in one of `PRIVATE_RANGES`
+pub fn is_private(ip: IpAddr) -> bool {
+    PRIVATE_RANGES.iter().any(|range| {
+        range
+            .parse::<IpRange>()
+            .is_ok_and(|range| range.contains(ip))
+    })
+}
+
+/// Refuse a host that is, or resolves to, a private address. Names that
+/// don't resolve pass, the browser can't reach them either.
+pub async fn check_host(host: &str) -> Result<(), String> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        if is_private(ip) {
+            return Err(format!("{host} is a private network address"));
+        }
+        return Ok(());
+    }
+    let addresses = match tokio::net::lookup_host((host, 0)).await {
+        Ok(addresses) => addresses,
+        Err(e) => {
+            debug!("Could not resolve {}: {}", host, e);
+            return Ok(());
+        }
+    };
+    for address in addresses {
+        if is_private(address.ip()) {
+            return Err(format!(
+                "{host} resolves to the private network address {}",
+                address.ip()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `check_host` for the host of a URL; URLs without one, e.g. `about:blank`,
+/// pass
+pub async fn check_url(url: &str) -> Result<(), String> {
+    match url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+    {
+        Some(host) => check_host(&host).await,
+        None => Ok(()),
+    }
+}
+
+/// Resolves names for `http_client`, failing for names with a private
+/// address so no request reaches one by name, redirects included
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addresses: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if let Some(address) = addresses.iter().find(|address| is_private(address.ip())) {
+                return Err(format!(
+                    "{host} resolves to the private network address {}",
+                    address.ip()
+                )
+                .into());
+            }
+            let addresses: Addrs = Box::new(addresses.into_iter());
+            Ok(addresses)
+        })
+    }
+}
+
+/// Why a URL whose host is a private address, e.g. `http://127.0.0.1/`, is
+/// refused; hosts given by name are left to `PublicResolver`
+fn private_address(url: &Url) -> Option<String> {
+    let ip = match url.host()? {
+        Host::Ipv4(ip) => IpAddr::V4(ip),
+        Host::Ipv6(ip) => IpAddr::V6(ip),
+        Host::Domain(_) => return None,
+    };
+    is_private(ip).then(|| format!("{ip} is a private network address"))
+}
+
+/// HTTP client for the requests made without the browser: sitemaps, probes,
+/// surveys, downloads and the like. Unless `allow_private`, it refuses
+/// hosts that resolve to a private address and redirects to one. A URL
+/// given as a private address isn't resolved, so check it with `check_url`
+/// before requesting it.
+pub fn http_client(timeout: Duration, allow_private: bool) -> reqwest::Client {
+    let mut builder = reqwest::Client::builder()
+        .user_agent(concat!("SmartCrawler/", env!("CARGO_PKG_VERSION")))
+        .timeout(timeout);
+    if !allow_private {
+        builder = builder
+            .dns_resolver(Arc::new(PublicResolver))
+            .redirect(Policy::custom(|attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                match private_address(attempt.url()) {
+                    Some(reason) => attempt.error(reason),
+                    None => attempt.follow(),
+                }
+            }));
+    }
+    // As `reqwest::Client::new`, which panics the same way
+    builder.build().expect("Failed to build HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ip_ranges() {
+        let range: IpRange = "172.16.0.0/12".parse().unwrap();
+        assert!(range.contains("172.31.255.1".parse().unwrap()));
+        assert!(!range.contains("172.32.0.1".parse().unwrap()));
+        assert!(!range.contains("::1".parse().unwrap()));
+        let single: IpRange = "203.0.113.7".parse().unwrap();
+        assert!(single.contains("203.0.113.7".parse().unwrap()));
+        assert!("10.0.0.0/33".parse::<IpRange>().is_err());
+        assert!("intranet/8".parse::<IpRange>().is_err());
+
+        for private in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "192.168.1.10",
+            "169.254.169.254",
+            "100.64.0.1",
+            "::1",
+            "fd12:3456::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+            "64:ff9b::7f00:1",
+            "2002:c0a8:101::1",
+        ] {
+            assert!(is_private(private.parse().unwrap()), "{private}");
+        }
+        for public in ["93.184.216.34", "8.8.8.8", "2606:4700::1111"] {
+            assert!(!is_private(public.parse().unwrap()), "{public}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_check_host() {
+        assert!(check_host("127.0.0.1").await.is_err());
+        assert!(check_host("[::1]").await.is_err());
+        assert!(check_host("localhost")
+            .await
+            .unwrap_err()
+            .contains("resolves to the private network address"));
+        assert!(check_host("8.8.8.8").await.is_ok());
+        assert!(check_url("http://10.0.0.5:8080/admin").await.is_err());
+        assert!(check_url("about:blank").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_http_client_redirects() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A server redirecting /away to its own loopback address
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = vec![0; 1024];
+                let read = socket.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let response = if request.starts_with("GET /away") {
+                    format!(
+                        "HTTP/1.1 302 Found\r\nLocation: http://127.0.0.1:{port}/secret\r\nContent-Length: 0\r\n\r\n"
+                    )
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecret".to_string()
+                };
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let guarded = http_client(Duration::from_secs(5), false);
+        let error = guarded
+            .get(format!("http://127.0.0.1:{port}/away"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error.is_redirect(), "{error}");
+        assert!(guarded
+            .get(format!("http://localhost:{port}/secret"))
+            .send()
+            .await
+            .is_err());
+
+        let allowed = http_client(Duration::from_secs(5), true);
+        let response = allowed
+            .get(format!("http://127.0.0.1:{port}/away"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "secret");
+    }
+}
@@ -0,0 +1,22 @@
+use crate::crawler::DiscoverySource;
+use crate::entities::Entity;
+use crate::storage::UrlData;
+
+/// Callbacks invoked while `SmartCrawler::run` progresses, so embedding
+/// applications can drive progress bars, webhooks or incremental output
+/// without touching the crawl loop. All methods default to doing nothing.
+pub trait CrawlObserver: Send + Sync {
+    /// A URL was queued for fetching
+    fn on_url_discovered(&self, _url: &str, _source: DiscoverySource) {}
+
+    /// A page was fetched and parsed; duplicate filtering runs after all pages
+    fn on_page_scraped(&self, _page: &UrlData) {}
+
+    /// Entities were extracted from a scraped page, sorted by ID. Pages
+    /// without any don't trigger it.
+    fn on_entities_extracted(&self, _url: &str, _entities: &[Entity]) {}
+
+    /// A page or the sitemap could not be fetched. `target` is the page URL,
+    /// or the domain for sitemap errors.
+    fn on_error(&self, _target: &str, _error: &str) {}
+}
@@ -0,0 +1,251 @@
+use crate::text_normalize::normalize_text;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::Url;
+
+/// Most headings kept per page
+pub const MAX_HEADINGS: usize = 50;
+
+/// Where a page sits in its site and how it's organized
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageStructure {
+    /// Trail from the site root to the page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// h1–h3 outline in document order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub headings: Vec<Heading>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Breadcrumb {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heading {
+    pub level: u8,
+    pub text: String,
+}
+
+impl PageStructure {
+    pub fn is_empty(&self) -> bool {
+        self.breadcrumbs.is_empty() && self.headings.is_empty()
+    }
+}
+
+/// Breadcrumbs and heading outline of a page, or None when it has neither.
+/// A JSON-LD `BreadcrumbList` is preferred over breadcrumb navigation markup.
+pub fn extract_page_structure(html: &str, page_url: &str) -> Option<PageStructure> {
+    let document = Html::parse_document(html);
+    let base = Url::parse(page_url).ok();
+    let mut breadcrumbs = json_ld_breadcrumbs(&document, base.as_ref());
+    if breadcrumbs.is_empty() {
+        breadcrumbs = nav_breadcrumbs(&document, base.as_ref());
+    }
+    let structure = PageStructure {
+        breadcrumbs,
+        headings: headings(&document),
+    };
+    Some(structure).filter(|structure| !structure.is_empty())
+}
+
+fn resolve(href: &str, base: Option<&Url>) -> Option<String> {
+    match base {
+        Some(base) => base.join(href).ok().map(String::from),
+        None => Url::parse(href).ok().map(String::from),
+    }
+}
+
+fn json_ld_breadcrumbs(document: &Html, base: Option<&Url>) -> Vec<Breadcrumb> {
+    let script_selector = Selector::parse(r#"script[type="application/ld+json"]"#).unwrap();
+    document
+        .select(&script_selector)
+        .filter_map(|script| serde_json::from_str::<Value>(&script.text().collect::<String>()).ok())
+        .find_map(|value| find_breadcrumb_list(&value).map(|list| breadcrumb_items(list, base)))
+        .unwrap_or_default()
+}
+
+/// The first `BreadcrumbList` object in a JSON-LD document, which may be
+/// nested in `@graph` or an array
+fn find_breadcrumb_list(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Array(items) => items.iter().find_map(find_breadcrumb_list),
+        Value::Object(fields) => {
+            let is_list = match fields.get("@type") {
+                Some(Value::String(kind)) => kind == "BreadcrumbList",
+                Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "BreadcrumbList"),
+                _ => false,
+            };
+            if is_list {
+                return Some(value);
+            }
+            fields.values().find_map(find_breadcrumb_list)
+        }
+        _ => None,
+    }
+}
+
+fn breadcrumb_items(list: &Value, base: Option<&Url>) -> Vec<Breadcrumb> {
+    let Some(Value::Array(items)) = list.get("itemListElement") else {
+        return Vec::new();
+    };
+    let mut items: Vec<&Value> = items.iter().collect();
+    items.sort_by_key(|item| item.get("position").and_then(Value::as_u64));
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let target = item.get("item");
+            let name = item
+                .get("name")
+                .or_else(|| target.and_then(|target| target.get("name")))
+                .and_then(Value::as_str)
+                .map(normalize_text)
+                .filter(|name| !name.is_empty())?;
+            let href = match target {
+                Some(Value::String(href)) => Some(href.as_str()),
+                Some(target) => target.get("@id").and_then(Value::as_str),
+                None => None,
+            };
+            Some(Breadcrumb {
+                name,
+                url: href.and_then(|href| resolve(href, base)),
+            })
+        })
+        .collect()
+}
+
+/// Items of a `nav[aria-label=breadcrumb]` (any case) or similarly labelled
+/// list, one per `li`, or one per link when there are no list items
+fn nav_breadcrumbs(document: &Html, base: Option<&Url>) -> Vec<Breadcrumb> {
+    let container_selector = Selector::parse("[aria-label]").unwrap();
+    let item_selector = Selector::parse("li").unwrap();
+    let link_selector = Selector::parse("a[href]").unwrap();
+
+    let Some(container) = document.select(&container_selector).find(|element| {
+        element
+            .value()
+            .attr("aria-label")
+            .is_some_and(|label| label.to_lowercase().contains("breadcrumb"))
+    }) else {
+        return Vec::new();
+    };
+    let mut items: Vec<_> = container.select(&item_selector).collect();
+    if items.is_empty() {
+        items = container.select(&link_selector).collect();
+    }
+    items
+        .into_iter()
+        .filter_map(|item| {
+            let name = normalize_text(&item.text().collect::<Vec<_>>().join(" "));
+            if name.is_empty() {
+                return None;
+            }
+            let href = if item.value().name() == "a" {
+                item.value().attr("href")
+            } else {
+                item.select(&link_selector)
+                    .next()
+                    .and_then(|link| link.value().attr("href"))
+            };
+            Some(Breadcrumb {
+                name,
+                url: href.and_then(|href| resolve(href, base)),
+            })
+        })
+        .collect()
+}
+
+fn headings(document: &Html) -> Vec<Heading> {
+    let heading_selector = Selector::parse("h1, h2, h3").unwrap();
+    document
+        .select(&heading_selector)
+        .filter_map(|element| {
+            let text = normalize_text(&element.text().collect::<Vec<_>>().join(" "));
+            let level = element.value().name()[1..].parse().ok()?;
+            Some(Heading { level, text }).filter(|heading| !heading.text.is_empty())
+        })
+        .take(MAX_HEADINGS)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nav_breadcrumbs_and_headings() {
+        let html = r#"<html><body>
+            <nav aria-label="Breadcrumb"><ol>
+                <li><a href="/">Home</a></li>
+                <li><a href="/shoes/">Shoes</a></li>
+                <li aria-current="page">Trail   Runner</li>
+            </ol></nav>
+            <h1>Trail Runner</h1><h2>Specs</h2><h4>Ignored</h4><h3>Sizes</h3><h2> </h2>
+        </body></html>"#;
+        let structure =
+            extract_page_structure(html, "https://shop.example.com/shoes/trail").unwrap();
+        assert_eq!(
+            structure.breadcrumbs,
+            vec![
+                Breadcrumb {
+                    name: "Home".to_string(),
+                    url: Some("https://shop.example.com/".to_string()),
+                },
+                Breadcrumb {
+                    name: "Shoes".to_string(),
+                    url: Some("https://shop.example.com/shoes/".to_string()),
+                },
+                Breadcrumb {
+                    name: "Trail Runner".to_string(),
+                    url: None,
+                },
+            ]
+        );
+        let outline: Vec<(u8, &str)> = structure
+            .headings
+            .iter()
+            .map(|heading| (heading.level, heading.text.as_str()))
+            .collect();
+        assert_eq!(
+            outline,
+            vec![(1, "Trail Runner"), (2, "Specs"), (3, "Sizes")]
+        );
+    }
+
+    #[test]
+    fn test_json_ld_breadcrumbs() {
+        let html = r#"<html><head><script type="application/ld+json">
+            {"@context": "https://schema.org", "@graph": [
+                {"@type": "WebPage", "name": "Trail Runner"},
+                {"@type": "BreadcrumbList", "itemListElement": [
+                    {"@type": "ListItem", "position": 2, "name": "Shoes", "item": "https://shop.example.com/shoes/"},
+                    {"@type": "ListItem", "position": 1, "item": {"@id": "/", "name": "Home"}}
+                ]}
+            ]}
+        </script></head><body><nav aria-label="breadcrumb"><a href="/other">Other</a></nav></body></html>"#;
+        let structure =
+            extract_page_structure(html, "https://shop.example.com/shoes/trail").unwrap();
+        let names: Vec<&str> = structure
+            .breadcrumbs
+            .iter()
+            .map(|crumb| crumb.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Home", "Shoes"]);
+        assert_eq!(
+            structure.breadcrumbs[0].url.as_deref(),
+            Some("https://shop.example.com/")
+        );
+
+        assert_eq!(
+            extract_page_structure(
+                "<html><body><p>Text</p></body></html>",
+                "https://example.com/"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use crate::html_parser::HtmlNode;
+use crate::keywords::KeywordMatcher;
+use crate::list_extraction::ListExtractor;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Records in a sibling group that make a page a listing
+pub const LISTING_MIN_ITEMS: usize = 6;
+
+/// Characters of text from which a page with an `<article>` or `<time>` is an article
+const ARTICLE_MIN_CHARS: usize = 1500;
+
+const CONTACT_WORDS: &[&str] = &["contact", "contact-us", "contacts", "kontakt", "support"];
+const CAREERS_WORDS: &[&str] = &[
+    "careers",
+    "career",
+    "jobs",
+    "join-us",
+    "vacancies",
+    "hiring",
+];
+const ABOUT_WORDS: &[&str] = &["about", "about-us", "team", "company", "people"];
+const LEGAL_WORDS: &[&str] = &[
+    "privacy",
+    "privacy-policy",
+    "terms",
+    "terms-of-service",
+    "legal",
+    "cookies",
+    "imprint",
+    "impressum",
+];
+const ARTICLE_SECTIONS: &[&str] = &[
+    "blog", "news", "articles", "article", "press", "posts", "stories",
+];
+const LISTING_SECTIONS: &[&str] = &[
+    "products",
+    "product",
+    "shop",
+    "store",
+    "catalog",
+    "collections",
+    "category",
+    "categories",
+    "listings",
+    "events",
+];
+/// Segments that keep a section URL a listing, e.g. /blog/page/2 or /blog/tag/rust
+const LISTING_SUFFIXES: &[&str] = &["page", "tag", "tags", "category", "categories", "archive"];
+
+/// Objective words asking for entities, which are found on detail pages
+const ENTITY_WORDS: &[&str] = &["email", "phone", "price", "address", "date"];
+
+/// What kind of page a URL is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageType {
+    Home,
+    Listing,
+    Detail,
+    Article,
+    Contact,
+    About,
+    Careers,
+    Search,
+    Legal,
+    Other,
+}
+
+impl PageType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PageType::Home => "home",
+            PageType::Listing => "listing",
+            PageType::Detail => "detail",
+            PageType::Article => "article",
+            PageType::Contact => "contact",
+            PageType::About => "about",
+            PageType::Careers => "careers",
+            PageType::Search => "search",
+            PageType::Legal => "legal",
+            PageType::Other => "other",
+        }
+    }
+}
+
+/// Page type suggested by a URL alone, for ranking links before they're fetched
+pub fn guess_page_type(url: &str) -> Option<PageType> {
+    let parsed = Url::parse(url).ok()?;
+    let segments: Vec<String> = parsed
+        .path_segments()
+        .map(|segments| {
+            segments
+                .filter(|segment| !segment.is_empty())
+                .map(|segment| {
+                    let segment = segment.to_lowercase();
+                    match segment.rsplit_once('.') {
+                        Some((stem, "html" | "htm" | "php" | "aspx")) => stem.to_string(),
+                        _ => segment,
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let has_query = |names: &[&str]| {
+        parsed
+            .query_pairs()
+            .any(|(name, _)| names.contains(&name.as_ref()))
+    };
+
+    if has_query(&["q", "query", "search"]) || segments.iter().any(|s| s == "search") {
+        return Some(PageType::Search);
+    }
+    if segments.is_empty() || segments == ["index"] {
+        return Some(PageType::Home);
+    }
+    let named = |words: &[&str]| segments.iter().any(|s| words.contains(&s.as_str()));
+    for (words, page_type) in [
+        (CONTACT_WORDS, PageType::Contact),
+        (CAREERS_WORDS, PageType::Careers),
+        (LEGAL_WORDS, PageType::Legal),
+        (ABOUT_WORDS, PageType::About),
+    ] {
+        if named(words) {
+            return Some(page_type);
+        }
+    }
+
+    let is_listing_path = |rest: &[String]| {
+        rest.is_empty() || LISTING_SUFFIXES.contains(&rest[0].as_str()) || has_query(&["page"])
+    };
+    if let Some(index) = segments
+        .iter()
+        .position(|s| ARTICLE_SECTIONS.contains(&s.as_str()))
+    {
+        return Some(if is_listing_path(&segments[index + 1..]) {
+            PageType::Listing
+        } else {
+            PageType::Article
+        });
+    }
+    if let Some(index) = segments
+        .iter()
+        .position(|s| LISTING_SECTIONS.contains(&s.as_str()))
+    {
+        return Some(if is_listing_path(&segments[index + 1..]) {
+            PageType::Listing
+        } else {
+            PageType::Detail
+        });
+    }
+    None
+}
+
+/// Classify a fetched page. Home, contact, careers, about, legal and search
+/// pages are told by their URL; otherwise a large sibling group makes a
+/// listing, long text in an `<article>` or next to a `<time>` an article, and a
+/// single `<h1>` a detail page, before falling back to the URL guess.
+pub fn classify_page(url: &str, tree: &HtmlNode) -> PageType {
+    let guess = guess_page_type(url);
+    if let Some(
+        page_type @ (PageType::Home
+        | PageType::Contact
+        | PageType::Careers
+        | PageType::About
+        | PageType::Legal
+        | PageType::Search),
+    ) = guess
+    {
+        return page_type;
+    }
+
+    let largest_list = ListExtractor::new()
+        .extract(tree, url)
+        .iter()
+        .map(|list| list.records.len())
+        .max()
+        .unwrap_or(0);
+    if largest_list >= LISTING_MIN_ITEMS {
+        return PageType::Listing;
+    }
+    let dated = count_tags(tree, &["article", "time"]) > 0;
+    if dated && tree.text_content().chars().count() >= ARTICLE_MIN_CHARS {
+        return PageType::Article;
+    }
+    if count_tags(tree, &["h1"]) == 1 && guess != Some(PageType::Article) {
+        return PageType::Detail;
+    }
+    guess.unwrap_or(PageType::Other)
+}
+
+fn count_tags(node: &HtmlNode, tags: &[&str]) -> usize {
+    usize::from(tags.contains(&node.tag.as_str()))
+        + node
+            .children
+            .iter()
+            .map(|child| count_tags(child, tags))
+            .sum::<usize>()
+}
+
+/// Page type to visit first: detail pages when the objective asks for
+/// entities such as prices or emails, listing pages when there is no
+/// objective and the crawl is about discovering the site
+pub fn preferred_page_type(objective: Option<&str>) -> Option<PageType> {
+    match objective {
+        None => Some(PageType::Listing),
+        Some(objective) if KeywordMatcher::new(ENTITY_WORDS).matches(objective) => {
+            Some(PageType::Detail)
+        }
+        Some(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::HtmlParser;
+
+    #[test]
+    fn test_guess_page_type() {
+        let cases = [
+            ("https://example.com/", Some(PageType::Home)),
+            ("https://example.com/index.html", Some(PageType::Home)),
+            ("https://example.com/contact-us", Some(PageType::Contact)),
+            (
+                "https://example.com/company/careers/",
+                Some(PageType::Careers),
+            ),
+            ("https://example.com/legal/privacy", Some(PageType::Legal)),
+            (
+                "https://example.com/search?q=kettle",
+                Some(PageType::Search),
+            ),
+            ("https://example.com/blog", Some(PageType::Listing)),
+            ("https://example.com/blog/page/2", Some(PageType::Listing)),
+            (
+                "https://example.com/blog/launch-day",
+                Some(PageType::Article),
+            ),
+            (
+                "https://example.com/products?page=3",
+                Some(PageType::Listing),
+            ),
+            (
+                "https://example.com/products/kettle",
+                Some(PageType::Detail),
+            ),
+            ("https://example.com/pricing", None),
+        ];
+        for (url, expected) in cases {
+            assert_eq!(guess_page_type(url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn test_classify_page() {
+        let parser = HtmlParser::new();
+        let cards: String = (1..=8)
+            .map(|i| {
+                format!(
+                    r#"<div class="card"><a href="/p/{i}">Item {i}</a><span>${i}0</span></div>"#
+                )
+            })
+            .collect();
+        let listing = parser.parse(&format!(
+            "<html><body><h1>All items</h1>{cards}</body></html>"
+        ));
+        assert_eq!(
+            classify_page("https://example.com/items", &listing),
+            PageType::Listing
+        );
+
+        let detail =
+            parser.parse("<html><body><h1>Kettle</h1><p>$49</p><p>1.7 litres</p></body></html>");
+        assert_eq!(
+            classify_page("https://example.com/kettle", &detail),
+            PageType::Detail
+        );
+        assert_eq!(
+            classify_page("https://example.com/contact", &detail),
+            PageType::Contact
+        );
+
+        let body = "Words about the launch. ".repeat(80);
+        let article = parser.parse(&format!(
+            "<html><body><article><h1>Launch</h1><time>2025-03-14</time><p>{body}</p></article></body></html>"
+        ));
+        assert_eq!(
+            classify_page("https://example.com/launch", &article),
+            PageType::Article
+        );
+
+        let empty = parser.parse("<html><body><p>Hello</p></body></html>");
+        assert_eq!(
+            classify_page("https://example.com/pricing", &empty),
+            PageType::Other
+        );
+    }
+
+    #[test]
+    fn test_preferred_page_type() {
+        assert_eq!(preferred_page_type(None), Some(PageType::Listing));
+        assert_eq!(
+            preferred_page_type(Some("Find the products with their prices")),
+            Some(PageType::Detail)
+        );
+        assert_eq!(preferred_page_type(Some("Find the team members")), None);
+    }
+}
@@ -0,0 +1,79 @@
+/// A named crawl objective for a common task, with the URL words that usually
+/// lead to the pages it is after
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectivePreset {
+    pub name: &'static str,
+    pub objective: &'static str,
+    /// Extra keywords used only to rank discovered URLs, e.g. "careers" for jobs
+    pub url_hints: &'static [&'static str],
+}
+
+pub const PRESETS: &[ObjectivePreset] = &[
+    ObjectivePreset {
+        name: "jobs",
+        objective: "Find open job positions with their title, location and how to apply",
+        url_hints: &["careers", "jobs", "hiring", "join", "vacancies", "openings"],
+    },
+    ObjectivePreset {
+        name: "team",
+        objective: "Find the team members with their names and roles",
+        url_hints: &["team", "about", "people", "leadership", "founders", "staff"],
+    },
+    ObjectivePreset {
+        name: "pricing",
+        objective: "Find the pricing plans with their prices and included features",
+        url_hints: &["pricing", "plans", "price", "subscription", "billing"],
+    },
+    ObjectivePreset {
+        name: "events",
+        objective: "Find upcoming events with their date, location and registration link",
+        url_hints: &["events", "calendar", "conference", "webinar", "meetup"],
+    },
+    ObjectivePreset {
+        name: "news",
+        objective: "Find recent news and announcements with their title and date",
+        url_hints: &["news", "blog", "press", "announcements", "updates"],
+    },
+    ObjectivePreset {
+        name: "products",
+        objective: "Find the products with their name, price and description",
+        url_hints: &["products", "shop", "store", "catalog", "collections"],
+    },
+];
+
+impl ObjectivePreset {
+    pub fn find(name: &str) -> Option<&'static ObjectivePreset> {
+        PRESETS
+            .iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        PRESETS.iter().map(|preset| preset.name).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keywords::KeywordMatcher;
+
+    #[test]
+    fn test_find_preset() {
+        assert_eq!(ObjectivePreset::find("Jobs").unwrap().name, "jobs");
+        assert!(ObjectivePreset::find("recipes").is_none());
+        assert_eq!(
+            ObjectivePreset::names(),
+            vec!["jobs", "team", "pricing", "events", "news", "products"]
+        );
+    }
+
+    #[test]
+    fn test_preset_objectives_have_keywords() {
+        for preset in PRESETS {
+            let keywords = KeywordMatcher::from_objective(preset.objective);
+            assert!(!keywords.is_empty(), "{} has no keywords", preset.name);
+            assert!(!preset.url_hints.is_empty());
+        }
+    }
+}
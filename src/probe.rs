@@ -0,0 +1,131 @@
+use crate::network_guard::{check_url, http_client};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tracing::debug;
+
+/// Paths probed for pages when a site has no sitemap
+pub const COMMON_PATHS: &[&str] = &["about", "team", "products", "blog", "careers", "contact"];
+
+/// Longest a single probe may take
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Check which of `paths` exist on the site at `root_url` with HEAD requests
+/// (GET for servers that don't allow HEAD), all at once. Returns the URLs
+/// the existing paths end up at after redirects, in the order of `paths`;
+/// paths redirecting to the homepage count as missing. Unless
+/// `allow_private`, paths on or redirecting to private networks count as
+/// missing too.
+pub async fn probe_paths(root_url: &str, paths: &[String], allow_private: bool) -> Vec<String> {
+    let client = http_client(PROBE_TIMEOUT, allow_private);
+    let Ok(root) = url::Url::parse(root_url) else {
+        return Vec::new();
+    };
+
+    let mut tasks = JoinSet::new();
+    for (index, path) in paths.iter().enumerate() {
+        let Ok(url) = root.join(path.trim_start_matches('/')) else {
+            continue;
+        };
+        tasks.spawn(probe(client.clone(), url, index, allow_private));
+    }
+    let mut found = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok((index, Some(url))) = result {
+            found.push((index, url));
+        }
+    }
+    found.sort_by_key(|(index, _)| *index);
+
+    let mut urls: Vec<String> = Vec::new();
+    for (_, url) in found {
+        if url.path() == "/" || urls.contains(&url.to_string()) {
+            continue;
+        }
+        urls.push(url.to_string());
+    }
+    urls
+}
+
+/// The URL a path ends up at, None when it doesn't answer with success
+async fn probe(
+    client: reqwest::Client,
+    url: url::Url,
+    index: usize,
+    allow_private: bool,
+) -> (usize, Option<url::Url>) {
+    if !allow_private {
+        if let Err(e) = check_url(url.as_str()).await {
+            debug!("Probed {}: {}", url, e);
+            return (index, None);
+        }
+    }
+    let response = match client.head(url.clone()).send().await {
+        Ok(response) if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED => {
+            client.get(url.clone()).send().await
+        }
+        response => response,
+    };
+    match response {
+        Ok(response) if response.status().is_success() => (index, Some(response.url().clone())),
+        Ok(response) => {
+            debug!("Probed {}: HTTP {}", url, response.status());
+            (index, None)
+        }
+        Err(e) => {
+            debug!("Probed {}: {}", url, e);
+            (index, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_probe_paths() {
+        // Serves /team and /blog, which redirects to /news; /about redirects
+        // to the homepage and everything else is missing. HEAD isn't allowed
+        // on /blog.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = vec![0; 1024];
+                    let read = stream.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let mut parts = request.split_whitespace();
+                    let method = parts.next().unwrap_or_default();
+                    let path = parts.next().unwrap_or_default();
+                    let status = match (method, path) {
+                        ("HEAD", "/blog") => "405 Method Not Allowed\r\n",
+                        (_, "/team" | "/news") => "200 OK\r\n",
+                        (_, "/blog") => "301 Moved Permanently\r\nLocation: /news\r\n",
+                        (_, "/about") => "302 Found\r\nLocation: /\r\n",
+                        (_, "/") => "200 OK\r\n",
+                        _ => "404 Not Found\r\n",
+                    };
+                    let response =
+                        format!("HTTP/1.1 {status}Content-Length: 0\r\nConnection: close\r\n\r\n");
+                    stream.write_all(response.as_bytes()).await.ok();
+                });
+            }
+        });
+
+        let paths: Vec<String> = ["/about", "team", "products", "blog"]
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        let root = format!("http://127.0.0.1:{port}/");
+        assert_eq!(
+            probe_paths(&root, &paths, true).await,
+            vec![format!("{root}team"), format!("{root}news")]
+        );
+        assert!(probe_paths(&root, &[], true).await.is_empty());
+        // The test server is on the loopback address
+        assert!(probe_paths(&root, &paths, false).await.is_empty());
+    }
+}
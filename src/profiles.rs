@@ -0,0 +1,116 @@
+use crate::wait::WaitStrategy;
+use std::time::Duration;
+
+/// A named combination of crawl settings trading speed for coverage, so the
+/// settings that interact (pages, waits, timeouts, survey) needn't be tuned
+/// one by one. Applied with `SmartCrawlerBuilder::profile`, before the
+/// settings that win over it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrawlProfile {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Pages rendered per domain, except in a prep crawl
+    pub max_pages: usize,
+    pub wait: WaitStrategy,
+    pub wait_timeout: Duration,
+    /// The default when None
+    pub navigation_timeout: Option<Duration>,
+    /// The default when None
+    pub page_timeout: Option<Duration>,
+    /// The default when None
+    pub max_sitemap_urls: Option<usize>,
+    /// Candidates surveyed before rendering, no survey when None
+    pub survey_candidates: Option<usize>,
+    pub shadow_dom: bool,
+}
+
+pub const PROFILES: &[CrawlProfile] = &[
+    CrawlProfile {
+        name: "fast",
+        description: "A few pages, captured as soon as they have loaded",
+        max_pages: 3,
+        wait: WaitStrategy::ReadyState,
+        wait_timeout: Duration::from_secs(5),
+        navigation_timeout: Some(Duration::from_secs(15)),
+        page_timeout: Some(Duration::from_secs(30)),
+        max_sitemap_urls: Some(1000),
+        survey_candidates: None,
+        shadow_dom: false,
+    },
+    CrawlProfile {
+        name: "balanced",
+        description: "More pages, captured once they stop loading resources",
+        max_pages: 5,
+        wait: WaitStrategy::NetworkIdle {
+            idle: Duration::from_millis(500),
+        },
+        wait_timeout: Duration::from_secs(10),
+        navigation_timeout: None,
+        page_timeout: None,
+        max_sitemap_urls: None,
+        survey_candidates: None,
+        shadow_dom: false,
+    },
+    CrawlProfile {
+        name: "thorough",
+        description: "Many pages picked by a survey, with shadow DOM and long waits",
+        max_pages: 15,
+        wait: WaitStrategy::NetworkIdle {
+            idle: Duration::from_secs(1),
+        },
+        wait_timeout: Duration::from_secs(20),
+        navigation_timeout: Some(Duration::from_secs(60)),
+        page_timeout: Some(Duration::from_secs(120)),
+        max_sitemap_urls: None,
+        survey_candidates: Some(50),
+        shadow_dom: true,
+    },
+];
+
+impl CrawlProfile {
+    pub fn find(name: &str) -> Option<&'static CrawlProfile> {
+        PROFILES
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        PROFILES.iter().map(|profile| profile.name).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::SmartCrawler;
+
+    #[test]
+    fn test_profiles() {
+        assert_eq!(CrawlProfile::names(), vec!["fast", "balanced", "thorough"]);
+        let thorough = CrawlProfile::find("Thorough").unwrap();
+        assert_eq!(thorough.page_timeout, Some(Duration::from_secs(120)));
+        assert!(CrawlProfile::find("exhaustive").is_none());
+
+        let builder = SmartCrawler::builder().profile(thorough);
+        let config = builder.config();
+        assert_eq!(config.max_pages(), 15);
+        assert_eq!(config.timeouts.navigation, Duration::from_secs(60));
+        assert_eq!(config.survey.as_ref().unwrap().max_candidates, 50);
+        assert!(config.shadow_dom);
+
+        // Settings made after the profile win over it
+        let builder = builder
+            .max_pages(4)
+            .wait(WaitStrategy::ReadyState, Duration::from_secs(3));
+        assert_eq!(builder.config().max_pages(), 4);
+        assert_eq!(builder.config().wait, WaitStrategy::ReadyState);
+        assert_eq!(builder.config().timeouts.page, Duration::from_secs(120));
+
+        // A prep crawl keeps its own page count
+        let prep = SmartCrawler::builder()
+            .prep(true)
+            .profile(CrawlProfile::find("fast").unwrap());
+        assert_eq!(prep.config().max_pages(), 10);
+        assert_eq!(prep.config().max_sitemap_urls, 1000);
+    }
+}
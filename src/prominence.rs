@@ -0,0 +1,195 @@
+use crate::bounding_box::BoundingBox;
+use crate::html_parser::{HtmlNode, FILTERED_DUPLICATE};
+use serde::{Deserialize, Serialize};
+
+/// Font size browsers use when a page doesn't set one
+const DEFAULT_FONT_SIZE: f64 = 16.0;
+
+/// A piece of page text with its visual prominence score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextBlock {
+    pub text: String,
+    pub score: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounding_box: Option<BoundingBox>,
+}
+
+/// Ranks text blocks by how prominently they are rendered: large, bold text
+/// above the fold (headlines, prices) ranks above small text in footers.
+/// Without captured boxes and styles it falls back to tag semantics and
+/// document order.
+#[derive(Debug, Clone)]
+pub struct ProminenceScorer {
+    /// Height of the first screen in CSS pixels; text above it gets a boost
+    pub viewport_height: f64,
+}
+
+impl ProminenceScorer {
+    pub fn new() -> Self {
+        ProminenceScorer {
+            viewport_height: 900.0,
+        }
+    }
+
+    /// Text blocks of the tree, most prominent first. Ties keep document order.
+    pub fn rank_text_blocks(&self, root: &HtmlNode) -> Vec<TextBlock> {
+        let boxes_captured = root.bounding_box.is_some();
+        let mut blocks = Vec::new();
+        self.collect_blocks(root, 1.0, boxes_captured, &mut blocks);
+        blocks.sort_by(|a, b| b.score.total_cmp(&a.score));
+        blocks
+    }
+
+    /// Page text ordered by prominence, cut off at `max_chars` so the most
+    /// visible content survives truncation
+    pub fn ordered_text(&self, root: &HtmlNode, max_chars: usize) -> String {
+        let mut text = String::new();
+        for block in self.rank_text_blocks(root) {
+            let separator = if text.is_empty() { 0 } else { 1 };
+            let remaining = max_chars.saturating_sub(text.chars().count() + separator);
+            if remaining == 0 {
+                break;
+            }
+            if separator == 1 {
+                text.push('\n');
+            }
+            text.extend(block.text.chars().take(remaining));
+        }
+        text
+    }
+
+    fn collect_blocks(
+        &self,
+        node: &HtmlNode,
+        inherited_weight: f64,
+        boxes_captured: bool,
+        blocks: &mut Vec<TextBlock>,
+    ) {
+        let weight = inherited_weight * Self::section_weight(&node.tag);
+
+        if !node.content.is_empty() && node.content != FILTERED_DUPLICATE {
+            blocks.push(TextBlock {
+                text: node.content.clone(),
+                score: self.score(node, weight, boxes_captured),
+                bounding_box: node.bounding_box,
+            });
+        }
+
+        for child in &node.children {
+            self.collect_blocks(child, weight, boxes_captured, blocks);
+        }
+    }
+
+    fn score(&self, node: &HtmlNode, section_weight: f64, boxes_captured: bool) -> f64 {
+        let mut score = section_weight * Self::tag_weight(&node.tag);
+
+        if let Some(style) = node.rendered_style {
+            score *= style.font_size / DEFAULT_FONT_SIZE;
+            if style.font_weight >= 600 {
+                score *= 1.25;
+            }
+        }
+
+        match node.bounding_box {
+            Some(bbox) => score *= self.position_weight(&bbox),
+            // Boxes were captured but this element wasn't rendered, so it's hidden
+            None if boxes_captured => score *= 0.1,
+            None => {}
+        }
+
+        score
+    }
+
+    /// Text on the first screen is boosted, text further down decays gradually
+    fn position_weight(&self, bbox: &BoundingBox) -> f64 {
+        if bbox.y < self.viewport_height {
+            1.5
+        } else {
+            let screens_below = (bbox.y - self.viewport_height) / self.viewport_height;
+            1.0 / (1.0 + screens_below / 2.0)
+        }
+    }
+
+    fn tag_weight(tag: &str) -> f64 {
+        match tag {
+            "h1" => 2.0,
+            "h2" => 1.6,
+            "h3" => 1.3,
+            "h4" | "h5" | "h6" | "strong" | "b" | "dt" => 1.1,
+            "small" | "figcaption" => 0.7,
+            _ => 1.0,
+        }
+    }
+
+    /// Weight applied to everything inside boilerplate sections
+    fn section_weight(tag: &str) -> f64 {
+        match tag {
+            "nav" | "footer" | "aside" => 0.4,
+            "header" | "form" => 0.8,
+            "main" | "article" => 1.2,
+            _ => 1.0,
+        }
+    }
+}
+
+impl Default for ProminenceScorer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::html_parser::HtmlParser;
+
+    #[test]
+    fn test_rank_with_rendered_boxes() {
+        let parser = HtmlParser::new();
+        let html = r#"<html data-sc-box="0,0,1280,3000"><body data-sc-box="0,0,1280,3000">
+            <footer data-sc-box="0,2800,1280,200"><p data-sc-box="0,2850,400,20" data-sc-style="12,400">Copyright</p></footer>
+            <p data-sc-box="0,1200,800,20" data-sc-style="16,400">Body text</p>
+            <span data-sc-box="600,300,100,30" data-sc-style="24,700">$49</span>
+            <h1 data-sc-box="0,100,800,60" data-sc-style="40,700">Headline</h1>
+            <p data-sc-style="16,400">Hidden</p>
+        </body></html>"#;
+        let tree = parser.parse(html);
+
+        let ranked: Vec<String> = ProminenceScorer::new()
+            .rank_text_blocks(&tree)
+            .into_iter()
+            .map(|block| block.text)
+            .collect();
+        assert_eq!(
+            ranked,
+            vec!["Headline", "$49", "Body text", "Copyright", "Hidden"]
+        );
+    }
+
+    #[test]
+    fn test_rank_without_boxes_uses_tags_and_order() {
+        let parser = HtmlParser::new();
+        let html =
+            r#"<html><body><nav>Menu</nav><p>First</p><h2>Section</h2><p>Second</p></body></html>"#;
+        let tree = parser.parse(html);
+
+        let ranked: Vec<String> = ProminenceScorer::new()
+            .rank_text_blocks(&tree)
+            .into_iter()
+            .map(|block| block.text)
+            .collect();
+        assert_eq!(ranked, vec!["Section", "First", "Second", "Menu"]);
+    }
+
+    #[test]
+    fn test_ordered_text_truncates() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body><p>Body paragraph</p><h1>Title</h1></body></html>"#;
+        let tree = parser.parse(html);
+        let scorer = ProminenceScorer::new();
+
+        assert_eq!(scorer.ordered_text(&tree, 100), "Title\nBody paragraph");
+        assert_eq!(scorer.ordered_text(&tree, 10), "Title\nBody");
+        assert_eq!(scorer.ordered_text(&tree, 3), "Tit");
+    }
+}
@@ -0,0 +1,132 @@
+use crate::entities::EntityExtractor;
+use crate::html_parser::HtmlNode;
+use crate::storage::{StoredHtml, UrlData};
+use serde_json::Value;
+
+/// Mask emails, phone numbers and street addresses in everything stored for
+/// a page: HTML source, parsed tree, title, captured API responses,
+/// structured payloads and the breadcrumb and heading outline. With `keep_entities`, the page's entities are found
+/// first and kept unmasked in `url_data.entities`.
+pub fn redact_url_data(extractor: &EntityExtractor, url_data: &mut UrlData, keep_entities: bool) {
+    if keep_entities {
+        url_data.entities = url_data
+            .html_tree
+            .as_ref()
+            .map(|tree| url_data.stamp_entities(extractor.extract_from_tree(tree)));
+    }
+    if let Some(html) = url_data.html() {
+        let redacted = extractor.redact_pii(&html);
+        url_data.html_source = Some(StoredHtml::Plain(redacted));
+    }
+    if let Some(tree) = &mut url_data.html_tree {
+        redact_tree(extractor, tree);
+    }
+    if let Some(title) = &mut url_data.title {
+        *title = extractor.redact_pii(title);
+    }
+    for response in &mut url_data.api_responses {
+        redact_json(extractor, &mut response.body);
+    }
+    if let Some(payload) = &mut url_data.structured_payload {
+        payload.text = extractor.redact_pii(&payload.text);
+    }
+    if let Some(structure) = &mut url_data.structure {
+        for crumb in &mut structure.breadcrumbs {
+            crumb.name = extractor.redact_pii(&crumb.name);
+        }
+        for heading in &mut structure.headings {
+            heading.text = extractor.redact_pii(&heading.text);
+        }
+    }
+}
+
+/// Mask PII in the text and `mailto:`/`tel:` links of a tree
+pub fn redact_tree(extractor: &EntityExtractor, node: &mut HtmlNode) {
+    node.content = extractor.redact_pii(&node.content);
+    if let Some(href) = &mut node.href {
+        if href.starts_with("mailto:") || href.starts_with("tel:") {
+            *href = extractor.redact_pii(href);
+        }
+    }
+    for child in &mut node.children {
+        redact_tree(extractor, child);
+    }
+}
+
+/// Mask PII in the string values of a JSON document
+pub fn redact_json(extractor: &EntityExtractor, value: &mut Value) {
+    match value {
+        Value::String(text) => *text = extractor.redact_pii(text),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| redact_json(extractor, item)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| redact_json(extractor, field)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityKind;
+    use crate::html_parser::HtmlParser;
+    use crate::results::{ContentMode, ScrapedWebPage};
+
+    const PAGE: &str = r#"<html><body><p>Contact <a href="mailto:jane@example.com">jane@example.com</a></p><p>Office: 221 Baker Street, London. Tickets $20</p></body></html>"#;
+
+    fn url_data() -> UrlData {
+        let mut url_data = UrlData::new("https://example.com/contact".to_string());
+        url_data.set_html_data(
+            PAGE.to_string(),
+            HtmlParser::new().parse(PAGE),
+            Some("Call 555-010-4000".to_string()),
+        );
+        url_data
+    }
+
+    #[test]
+    fn test_redact_url_data() {
+        let extractor = EntityExtractor::new();
+        let mut url_data = url_data();
+        redact_url_data(&extractor, &mut url_data, false);
+
+        let html_source = url_data.html().unwrap();
+        assert!(!html_source.contains("jane@example.com"));
+        assert!(html_source.contains(r#"<a href="mailto:[email]">[email]</a>"#));
+        assert_eq!(url_data.title.as_deref(), Some("Call [phone]"));
+        let text = url_data.html_tree.as_ref().unwrap().text_content();
+        assert!(text.contains("[address]") && !text.contains("Baker"));
+
+        let page = ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw)
+            .with_entities(&extractor, &url_data);
+        let kinds: Vec<EntityKind> = page.entities.iter().map(|entity| entity.kind).collect();
+        assert_eq!(kinds, vec![EntityKind::Price]);
+    }
+
+    #[test]
+    fn test_redact_keeping_entities() {
+        let extractor = EntityExtractor::new();
+        let mut url_data = url_data();
+        redact_url_data(&extractor, &mut url_data, true);
+
+        let page = ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw)
+            .with_entities(&extractor, &url_data);
+        assert_eq!(page.entities[0].value, "jane@example.com");
+        assert_eq!(page.entities.len(), 3);
+        let provenance = page.entities[0].provenance.as_ref().unwrap();
+        assert_eq!(provenance.url, url_data.url);
+        assert!(provenance.extracted_at.is_some());
+        assert!(!serde_json::to_string(&page.content)
+            .unwrap()
+            .contains("jane@example.com"));
+
+        let mut body = serde_json::json!({"users": [{"email": "jane@example.com", "id": 7}]});
+        redact_json(&extractor, &mut body);
+        assert_eq!(
+            body,
+            serde_json::json!({"users": [{"email": "[email]", "id": 7}]})
+        );
+    }
+}
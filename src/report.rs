@@ -0,0 +1,379 @@
+use crate::crawler::{CrawlFailure, CrawlResult};
+use crate::results::{ContentMode, ScrapedWebPage};
+use crate::utils::url_to_file_stem;
+use chrono::Utc;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Objective matches quoted per page
+const MAX_EXCERPTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// HTML for `.html`/`.htm` files, Markdown otherwise
+    pub fn from_path(path: &str) -> Self {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("html" | "htm") => ReportFormat::Html,
+            _ => ReportFormat::Markdown,
+        }
+    }
+}
+
+/// What a report shows about one crawled domain
+#[derive(Debug, Clone)]
+pub struct DomainReport {
+    pub domain: String,
+    pub pages: Vec<ScrapedWebPage>,
+    /// Pages and steps that failed
+    pub failures: Vec<CrawlFailure>,
+    pub duplicate_count: usize,
+}
+
+impl DomainReport {
+    pub fn from_result(result: &CrawlResult) -> Self {
+        DomainReport {
+            domain: result.domain.clone(),
+            pages: result
+                .pages(ContentMode::Filtered)
+                .into_iter()
+                .filter(|page| page.duplicate_of.is_none())
+                .collect(),
+            failures: result.failures.clone(),
+            duplicate_count: result.duplicate_count(),
+        }
+    }
+
+    fn failed_page_count(&self) -> usize {
+        self.failures
+            .iter()
+            .filter(|failure| failure.is_page_failure())
+            .count()
+    }
+
+    fn entity_count(&self) -> usize {
+        self.pages.iter().map(|page| page.entities.len()).sum()
+    }
+}
+
+/// A readable summary of crawl results: per-domain counts, objective
+/// excerpts, entity tables, errors and, with `screenshot_dir`, the page
+/// screenshots saved by `--visualize-groups`
+pub fn render_report(
+    reports: &[DomainReport],
+    format: ReportFormat,
+    screenshot_dir: Option<&Path>,
+) -> String {
+    let screenshot = |url: &str| {
+        screenshot_dir.map(|dir| {
+            dir.join(format!("{}.png", url_to_file_stem(url)))
+                .display()
+                .to_string()
+        })
+    };
+    match format {
+        ReportFormat::Markdown => render_markdown(reports, &screenshot),
+        ReportFormat::Html => render_html(reports, &screenshot),
+    }
+}
+
+pub fn write_report(
+    path: &str,
+    reports: &[DomainReport],
+    screenshot_dir: Option<&Path>,
+) -> Result<(), String> {
+    let report = render_report(reports, ReportFormat::from_path(path), screenshot_dir);
+    std::fs::write(path, report).map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+fn render_markdown(
+    reports: &[DomainReport],
+    screenshot: &dyn Fn(&str) -> Option<String>,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# Crawl Report\n");
+    let _ = writeln!(
+        out,
+        "Generated {}\n",
+        Utc::now().format("%Y-%m-%d %H:%M UTC")
+    );
+    let _ = writeln!(out, "| Domain | Pages | Failed | Entities |");
+    let _ = writeln!(out, "|--------|-------|--------|----------|");
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} | {} |",
+            markdown_cell(&report.domain),
+            report.pages.len(),
+            report.failed_page_count(),
+            report.entity_count()
+        );
+    }
+
+    for report in reports {
+        let _ = writeln!(out, "\n## {}\n", report.domain);
+        let _ = writeln!(
+            out,
+            "{} pages crawled, {} failed, {} duplicate patterns filtered.",
+            report.pages.len(),
+            report.failed_page_count(),
+            report.duplicate_count
+        );
+
+        if !report.pages.is_empty() {
+            let _ = writeln!(out, "\n### Pages\n");
+        }
+        for page in &report.pages {
+            let title = page.title.as_deref().unwrap_or(&page.url);
+            let _ = writeln!(out, "- [{}]({})", title.replace(['[', ']'], ""), page.url);
+            for excerpt in page.objective_matches.iter().take(MAX_EXCERPTS) {
+                let _ = writeln!(out, "  > {}", excerpt.replace('\n', " "));
+            }
+            if let Some(path) = screenshot(&page.url) {
+                let _ = writeln!(out, "\n  ![Screenshot of {title}]({path})");
+            }
+        }
+
+        if report.entity_count() > 0 {
+            let _ = writeln!(out, "\n### Entities\n");
+            let _ = writeln!(out, "| Kind | Value | Text | Confidence | Page |");
+            let _ = writeln!(out, "|------|-------|------|------------|------|");
+            for page in &report.pages {
+                for entity in &page.entities {
+                    let _ = writeln!(
+                        out,
+                        "| {:?} | {} | {} | {:.1} | {} |",
+                        entity.kind,
+                        markdown_cell(&entity.value),
+                        markdown_cell(&entity.text),
+                        entity.confidence,
+                        page.url
+                    );
+                }
+            }
+        }
+
+        if !report.failures.is_empty() {
+            let _ = writeln!(out, "\n### Errors\n");
+            for failure in &report.failures {
+                let _ = writeln!(
+                    out,
+                    "- {} ({}): {}",
+                    failure.target(),
+                    failure.phase,
+                    failure.error.replace('\n', " ")
+                );
+            }
+        }
+    }
+    out
+}
+
+fn render_html(reports: &[DomainReport], screenshot: &dyn Fn(&str) -> Option<String>) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Crawl Report</title>\n",
+    );
+    out.push_str("<style>body{font-family:sans-serif;max-width:960px;margin:auto}table{border-collapse:collapse}td,th{border:1px solid #ccc;padding:4px 8px;text-align:left}blockquote{color:#555}img{width:240px;border:1px solid #ccc}</style>\n");
+    out.push_str("</head>\n<body>\n<h1>Crawl Report</h1>\n");
+    let _ = writeln!(
+        out,
+        "<p>Generated {}</p>",
+        Utc::now().format("%Y-%m-%d %H:%M UTC")
+    );
+    out.push_str(
+        "<table>\n<tr><th>Domain</th><th>Pages</th><th>Failed</th><th>Entities</th></tr>\n",
+    );
+    for report in reports {
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&report.domain),
+            report.pages.len(),
+            report.failed_page_count(),
+            report.entity_count()
+        );
+    }
+    out.push_str("</table>\n");
+
+    for report in reports {
+        let _ = writeln!(out, "<h2>{}</h2>", escape_html(&report.domain));
+        let _ = writeln!(
+            out,
+            "<p>{} pages crawled, {} failed, {} duplicate patterns filtered.</p>",
+            report.pages.len(),
+            report.failed_page_count(),
+            report.duplicate_count
+        );
+
+        if !report.pages.is_empty() {
+            out.push_str("<h3>Pages</h3>\n<ul>\n");
+        }
+        for page in &report.pages {
+            let title = page.title.as_deref().unwrap_or(&page.url);
+            let _ = write!(
+                out,
+                "<li><a href=\"{}\">{}</a>",
+                escape_html(&page.url),
+                escape_html(title)
+            );
+            for excerpt in page.objective_matches.iter().take(MAX_EXCERPTS) {
+                let _ = write!(out, "<blockquote>{}</blockquote>", escape_html(excerpt));
+            }
+            if let Some(path) = screenshot(&page.url) {
+                let path = escape_html(&path);
+                let _ = write!(
+                    out,
+                    "<br><a href=\"{path}\"><img src=\"{path}\" alt=\"Screenshot\"></a>"
+                );
+            }
+            out.push_str("</li>\n");
+        }
+        if !report.pages.is_empty() {
+            out.push_str("</ul>\n");
+        }
+
+        if report.entity_count() > 0 {
+            out.push_str("<h3>Entities</h3>\n<table>\n<tr><th>Kind</th><th>Value</th><th>Text</th><th>Confidence</th><th>Page</th></tr>\n");
+            for page in &report.pages {
+                for entity in &page.entities {
+                    let _ = writeln!(
+                        out,
+                        "<tr><td>{:?}</td><td>{}</td><td>{}</td><td>{:.1}</td><td>{}</td></tr>",
+                        entity.kind,
+                        escape_html(&entity.value),
+                        escape_html(&entity.text),
+                        entity.confidence,
+                        escape_html(&page.url)
+                    );
+                }
+            }
+            out.push_str("</table>\n");
+        }
+
+        if !report.failures.is_empty() {
+            out.push_str("<h3>Errors</h3>\n<ul>\n");
+            for failure in &report.failures {
+                let _ = writeln!(
+                    out,
+                    "<li>{} ({}): {}</li>",
+                    escape_html(failure.target()),
+                    failure.phase,
+                    escape_html(&failure.error)
+                );
+            }
+            out.push_str("</ul>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::CrawlPhase;
+    use crate::entities::{entity_id, Entity, EntityKind};
+
+    fn sample_reports() -> Vec<DomainReport> {
+        let page = ScrapedWebPage {
+            url: "https://example.com/team".to_string(),
+            domain: "example.com".to_string(),
+            title: Some("Our <Team>".to_string()),
+            content_type: None,
+            encoding: None,
+            language: None,
+            detected_language: None,
+            structure: None,
+            page_type: None,
+            archived: None,
+            brand_assets: Vec::new(),
+            content: None,
+            filtered_content: None,
+            text_blocks: Vec::new(),
+            api_responses: Vec::new(),
+            blocked_requests: None,
+            structured_content: None,
+            content_hash: None,
+            duplicate_of: None,
+            objective_matches: vec!["Meet the team".to_string()],
+            entities: vec![Entity {
+                id: entity_id(EntityKind::Email, "team@example.com"),
+                kind: EntityKind::Email,
+                value: "team@example.com".to_string(),
+                text: "team@example.com".to_string(),
+                confidence: 0.9,
+                flags: Vec::new(),
+                provenance: None,
+                status: None,
+            }],
+        };
+        vec![DomainReport {
+            domain: "example.com".to_string(),
+            pages: vec![page],
+            failures: vec![
+                CrawlFailure {
+                    domain: "example.com".to_string(),
+                    url: Some("https://example.com/jobs".to_string()),
+                    phase: CrawlPhase::Scrape,
+                    timed_out: true,
+                    error: "timeout".to_string(),
+                },
+                CrawlFailure {
+                    domain: "example.com".to_string(),
+                    url: None,
+                    phase: CrawlPhase::Discovery,
+                    timed_out: false,
+                    error: "sitemap not found".to_string(),
+                },
+            ],
+            duplicate_count: 4,
+        }]
+    }
+
+    #[test]
+    fn test_report_format_from_path() {
+        assert_eq!(ReportFormat::from_path("report.HTML"), ReportFormat::Html);
+        assert_eq!(ReportFormat::from_path("report.md"), ReportFormat::Markdown);
+        assert_eq!(ReportFormat::from_path("report"), ReportFormat::Markdown);
+    }
+
+    #[test]
+    fn test_render_report() {
+        let reports = sample_reports();
+
+        let markdown = render_report(&reports, ReportFormat::Markdown, Some(Path::new("shots")));
+        assert!(markdown.contains("| example.com | 1 | 1 | 1 |"));
+        assert!(markdown.contains("1 pages crawled, 1 failed, 4 duplicate patterns filtered."));
+        assert!(markdown.contains("- [Our <Team>](https://example.com/team)\n  > Meet the team"));
+        assert!(markdown.contains("](shots/example.com_team.png)"));
+        assert!(markdown.contains("| Email | team@example.com | team@example.com | 0.9 |"));
+        assert!(markdown.contains("- https://example.com/jobs (scrape): timeout"));
+        assert!(markdown.contains("- example.com (discovery): sitemap not found"));
+
+        let html = render_report(&reports, ReportFormat::Html, None);
+        assert!(html.contains("<a href=\"https://example.com/team\">Our &lt;Team&gt;</a>"));
+        assert!(html.contains("<blockquote>Meet the team</blockquote>"));
+        assert!(!html.contains("<img"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
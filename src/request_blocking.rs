@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+/// Hosts of ad networks blocked by `BlockCategory::Ads`
+const AD_HOSTS: &[&str] = &[
+    "doubleclick.net",
+    "googlesyndication.com",
+    "googleadservices.com",
+    "adservice.google.com",
+    "amazon-adsystem.com",
+    "adnxs.com",
+    "criteo.com",
+    "criteo.net",
+    "taboola.com",
+    "outbrain.com",
+    "pubmatic.com",
+    "rubiconproject.com",
+    "openx.net",
+    "moatads.com",
+];
+
+/// Hosts of analytics and session recording services blocked by
+/// `BlockCategory::Analytics`
+const ANALYTICS_HOSTS: &[&str] = &[
+    "google-analytics.com",
+    "googletagmanager.com",
+    "analytics.google.com",
+    "hotjar.com",
+    "segment.com",
+    "segment.io",
+    "mixpanel.com",
+    "amplitude.com",
+    "heapanalytics.com",
+    "fullstory.com",
+    "clarity.ms",
+    "scorecardresearch.com",
+    "connect.facebook.net",
+    "plausible.io",
+];
+
+/// Kind of request the browser can skip to render pages faster
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockCategory {
+    Images,
+    Fonts,
+    /// Audio and video
+    Media,
+    Ads,
+    Analytics,
+}
+
+impl BlockCategory {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockCategory::Images => "images",
+            BlockCategory::Fonts => "fonts",
+            BlockCategory::Media => "media",
+            BlockCategory::Ads => "ads",
+            BlockCategory::Analytics => "analytics",
+        }
+    }
+
+    /// DevTools Protocol resource type blocked by the category, for
+    /// categories told apart by what is loaded rather than where from
+    pub fn resource_type(&self) -> Option<&'static str> {
+        match self {
+            BlockCategory::Images => Some("Image"),
+            BlockCategory::Fonts => Some("Font"),
+            BlockCategory::Media => Some("Media"),
+            BlockCategory::Ads | BlockCategory::Analytics => None,
+        }
+    }
+
+    /// URL patterns blocked by the category, for categories told apart by host
+    pub fn url_patterns(&self) -> Vec<String> {
+        let hosts = match self {
+            BlockCategory::Ads => AD_HOSTS,
+            BlockCategory::Analytics => ANALYTICS_HOSTS,
+            _ => &[],
+        };
+        hosts.iter().map(|host| format!("*://*{host}/*")).collect()
+    }
+}
+
+impl std::str::FromStr for BlockCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "images" => Ok(BlockCategory::Images),
+            "fonts" => Ok(BlockCategory::Fonts),
+            "media" => Ok(BlockCategory::Media),
+            "ads" => Ok(BlockCategory::Ads),
+            "analytics" => Ok(BlockCategory::Analytics),
+            _ => Err(format!(
+                "Unknown block category: {s} (expected images, fonts, media, ads or analytics)"
+            )),
+        }
+    }
+}
+
+/// Parse a comma separated list of categories, e.g. "images,fonts"
+pub fn parse_block_categories(s: &str) -> Result<Vec<BlockCategory>, String> {
+    let mut categories = Vec::new();
+    for category in s.split(',').filter(|part| !part.trim().is_empty()) {
+        let category: BlockCategory = category.parse()?;
+        if !categories.contains(&category) {
+            categories.push(category);
+        }
+    }
+    Ok(categories)
+}
+
+/// Requests the browser refuses to load
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRules {
+    pub categories: Vec<BlockCategory>,
+    /// URL patterns where `*` matches anything, e.g. `*.woff2`
+    pub url_patterns: Vec<String>,
+}
+
+impl BlockRules {
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty() && self.url_patterns.is_empty()
+    }
+
+    /// DevTools Protocol resource types to block
+    pub fn resource_types(&self) -> Vec<&'static str> {
+        self.categories
+            .iter()
+            .filter_map(BlockCategory::resource_type)
+            .collect()
+    }
+
+    /// The given URL patterns followed by those of the host categories
+    pub fn all_url_patterns(&self) -> Vec<String> {
+        let mut patterns = self.url_patterns.clone();
+        for category in &self.categories {
+            patterns.extend(category.url_patterns());
+        }
+        patterns
+    }
+
+    /// Whether a request for `url` of a DevTools Protocol resource type, e.g.
+    /// "Image", is blocked
+    pub fn matches(&self, url: &str, resource_type: &str) -> bool {
+        self.resource_types().contains(&resource_type)
+            || self
+                .all_url_patterns()
+                .iter()
+                .any(|pattern| wildcard_match(pattern, url))
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` matches anything
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_rules() {
+        let categories = parse_block_categories("images, Fonts,ads,images").unwrap();
+        assert_eq!(
+            categories,
+            vec![
+                BlockCategory::Images,
+                BlockCategory::Fonts,
+                BlockCategory::Ads
+            ]
+        );
+        assert!(parse_block_categories("images,videos").is_err());
+
+        let rules = BlockRules {
+            categories,
+            url_patterns: vec!["*.woff2".to_string()],
+        };
+        assert_eq!(rules.resource_types(), vec!["Image", "Font"]);
+        let patterns = rules.all_url_patterns();
+        assert_eq!(patterns[0], "*.woff2");
+        assert!(patterns.contains(&"*://*doubleclick.net/*".to_string()));
+        assert!(!patterns.iter().any(|pattern| pattern.contains("analytics")));
+        assert!(BlockRules::default().is_empty());
+
+        assert!(rules.matches("https://example.com/logo.png", "Image"));
+        assert!(rules.matches("https://example.com/font.woff2", "Other"));
+        assert!(rules.matches(
+            "https://securepubads.g.doubleclick.net/tag/js/gpt.js",
+            "Script"
+        ));
+        assert!(!rules.matches("https://example.com/app.js", "Script"));
+        assert!(!rules.matches("https://example.com/font.woff2?v=2", "Other"));
+    }
+}
@@ -0,0 +1,444 @@
+use crate::api_capture::ApiResponse;
+use crate::brand_assets::BrandAsset;
+use crate::crawler::CrawlConfig;
+use crate::entities::{Entity, EntityExtractor};
+use crate::html_parser::HtmlNode;
+use crate::keywords::KeywordMatcher;
+use crate::language::DetectedLanguage;
+use crate::page_structure::PageStructure;
+use crate::page_type::PageType;
+use crate::prominence::{ProminenceScorer, TextBlock};
+use crate::storage::UrlData;
+use crate::structured_content::StructuredPayload;
+use crate::utils::{read_output_file, write_output_file};
+use crate::wayback::ArchivedSnapshot;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Which page content is included when results are serialized
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentMode {
+    Raw,
+    #[default]
+    Filtered,
+    Both,
+}
+
+impl ContentMode {
+    pub fn includes_raw(&self) -> bool {
+        matches!(self, ContentMode::Raw | ContentMode::Both)
+    }
+
+    pub fn includes_filtered(&self) -> bool {
+        matches!(self, ContentMode::Filtered | ContentMode::Both)
+    }
+}
+
+impl FromStr for ContentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(ContentMode::Raw),
+            "filtered" => Ok(ContentMode::Filtered),
+            "both" => Ok(ContentMode::Both),
+            other => Err(format!(
+                "Invalid content mode: {other} (expected raw, filtered or both)"
+            )),
+        }
+    }
+}
+
+/// A successfully scraped page as written to the output file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapedWebPage {
+    pub url: String,
+    pub domain: String,
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Encoding the page was decoded with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detected_language: Option<DetectedLanguage>,
+    /// Breadcrumbs and h1–h3 outline of the page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structure: Option<PageStructure>,
+    /// Kind of page, e.g. listing or detail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_type: Option<PageType>,
+    /// Wayback Machine snapshot the content came from instead of the live page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<ArchivedSnapshot>,
+    /// Favicons, logos and share image of the site, on its homepage
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub brand_assets: Vec<BrandAsset>,
+    /// Parsed HTML tree as fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<HtmlNode>,
+    /// Parsed HTML tree with domain duplicates and template values filtered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered_content: Option<HtmlNode>,
+    /// Page text ranked by visual prominence, present when bounding boxes were captured
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text_blocks: Vec<TextBlock>,
+    /// JSON responses the page fetched while rendering, present with `--capture-api`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_responses: Vec<ApiResponse>,
+    /// Requests the browser blocked while loading the page, present with `--block`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_requests: Option<usize>,
+    /// Pretty-printed payload of JSON and CSV URLs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<StructuredPayload>,
+    /// Hash of the page text once boilerplate is filtered out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// Earlier page with the same content. Deduplicated pages are listed
+    /// without content, matches or entities.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+    /// Text of elements mentioning an objective keyword, in document order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub objective_matches: Vec<String>,
+    /// Emails, phone numbers, prices, dates, addresses and measurements
+    /// found in the page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<Entity>,
+}
+
+/// Most objective matches kept per page
+pub const MAX_OBJECTIVE_MATCHES: usize = 50;
+
+impl ScrapedWebPage {
+    pub fn from_url_data(url_data: &UrlData, content_mode: ContentMode) -> Self {
+        let deduplicated = url_data.duplicate_of.is_some();
+        ScrapedWebPage {
+            url: url_data.url.clone(),
+            domain: url_data.domain.clone(),
+            title: url_data.title.clone(),
+            content_type: url_data.content_type.clone(),
+            encoding: url_data.encoding.clone(),
+            language: url_data.language.clone(),
+            detected_language: url_data.detected_language.clone(),
+            structure: url_data.structure.clone(),
+            page_type: url_data.page_type,
+            archived: url_data.archived.clone(),
+            brand_assets: url_data.brand_assets.clone(),
+            content: if content_mode.includes_raw() && !deduplicated {
+                url_data.html_tree.clone()
+            } else {
+                None
+            },
+            filtered_content: if content_mode.includes_filtered() && !deduplicated {
+                url_data.filtered_tree.clone()
+            } else {
+                None
+            },
+            text_blocks: if deduplicated {
+                Vec::new()
+            } else {
+                Self::ranked_text_blocks(url_data)
+            },
+            api_responses: url_data.api_responses.clone(),
+            blocked_requests: url_data.blocked_requests,
+            structured_content: url_data.structured_payload.clone(),
+            content_hash: url_data.content_hash.clone(),
+            duplicate_of: url_data.duplicate_of.clone(),
+            objective_matches: Vec::new(),
+            entities: Vec::new(),
+        }
+    }
+
+    /// Pick out entities from the page as fetched, so contact details in
+    /// headers and footers shared across pages are found too
+    pub fn with_entities(mut self, extractor: &EntityExtractor, url_data: &UrlData) -> Self {
+        self.entities = url_data.extract_entities(extractor);
+        self
+    }
+
+    /// Leave the extraction times out of the entities' provenance, so
+    /// `--deterministic` reruns write the same file
+    pub fn clear_extraction_times(&mut self) {
+        for provenance in self
+            .entities
+            .iter_mut()
+            .filter_map(|e| e.provenance.as_mut())
+        {
+            provenance.extracted_at = None;
+        }
+    }
+
+    /// Collect the page text that mentions the objective's keywords
+    pub fn with_objective_matches(mut self, keywords: &KeywordMatcher, url_data: &UrlData) -> Self {
+        if keywords.is_empty() {
+            return self;
+        }
+        if let Some(tree) = url_data
+            .filtered_tree
+            .as_ref()
+            .or(url_data.html_tree.as_ref())
+        {
+            collect_matching_text(tree, keywords, &mut self.objective_matches);
+        }
+        self
+    }
+
+    fn ranked_text_blocks(url_data: &UrlData) -> Vec<TextBlock> {
+        match url_data
+            .filtered_tree
+            .as_ref()
+            .or(url_data.html_tree.as_ref())
+        {
+            Some(tree) if tree.bounding_box.is_some() => {
+                ProminenceScorer::new().rank_text_blocks(tree)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn collect_matching_text(node: &HtmlNode, keywords: &KeywordMatcher, matches: &mut Vec<String>) {
+    if matches.len() >= MAX_OBJECTIVE_MATCHES {
+        return;
+    }
+    let text = node.content.trim();
+    if !text.is_empty() && keywords.matches(text) && !matches.iter().any(|m| m == text) {
+        matches.push(text.to_string());
+    }
+    for child in &node.children {
+        collect_matching_text(child, keywords, matches);
+    }
+}
+
+/// How a results file was produced, written along with its pages so the
+/// file can be audited or the crawl repeated later
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub crate_version: String,
+    /// When the file was written; None for `--deterministic` runs, whose
+    /// files are meant to be identical for an unchanged site
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<DateTime<Utc>>,
+    /// Arguments the crawler was run with
+    pub command_line: Vec<String>,
+    /// Domains crawled, in output order
+    pub domains: Vec<String>,
+    /// Effective settings of every crawl, with defaults and profile values
+    /// filled in and the domain left empty
+    pub config: CrawlConfig,
+}
+
+impl RunManifest {
+    pub fn new(config: &CrawlConfig, domains: Vec<String>, deterministic: bool) -> Self {
+        RunManifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            generated_at: (!deterministic).then(Utc::now),
+            command_line: std::env::args().collect(),
+            domains,
+            config: CrawlConfig {
+                domain: String::new(),
+                ..config.clone()
+            },
+        }
+    }
+}
+
+/// What `write_results` writes
+#[derive(Serialize)]
+struct ManifestedPages<'a> {
+    manifest: &'a RunManifest,
+    pages: &'a [ScrapedWebPage],
+}
+
+/// A results file: pages with the manifest of the run, or just pages as
+/// written by `write_pages` and before manifests were added
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ResultsFile {
+    WithManifest {
+        manifest: Box<RunManifest>,
+        pages: Vec<ScrapedWebPage>,
+    },
+    Pages(Vec<ScrapedWebPage>),
+}
+
+/// Serialize scraped pages as pretty JSON to the given file, gzipped or
+/// zstd compressed for `.gz`/`.zst` paths
+pub fn write_pages(path: &str, pages: &[ScrapedWebPage]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(pages)
+        .map_err(|e| format!("Failed to serialize results: {e}"))?;
+    write_output_file(path, json.as_bytes())
+}
+
+/// `write_pages` with the run's manifest: a `{"manifest": ..., "pages": [...]}`
+/// object instead of a bare array of pages
+pub fn write_results(
+    path: &str,
+    manifest: &RunManifest,
+    pages: &[ScrapedWebPage],
+) -> Result<(), String> {
+    let file = ManifestedPages { manifest, pages };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialize results: {e}"))?;
+    write_output_file(path, json.as_bytes())
+}
+
+/// Read a file written by `write_results` or `write_pages`, with its
+/// manifest if it has one
+pub fn read_results(path: &str) -> Result<(Option<RunManifest>, Vec<ScrapedWebPage>), String> {
+    let json = read_output_file(path)?;
+    match serde_json::from_str(&json).map_err(|e| format!("Invalid pages file {path}: {e}"))? {
+        ResultsFile::WithManifest { manifest, pages } => Ok((Some(*manifest), pages)),
+        ResultsFile::Pages(pages) => Ok((None, pages)),
+    }
+}
+
+/// Read the pages of a file written by `write_results` or `write_pages`
+pub fn read_pages(path: &str) -> Result<Vec<ScrapedWebPage>, String> {
+    read_results(path).map(|(_, pages)| pages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{entity_id, EntityKind};
+    use crate::html_parser::HtmlParser;
+
+    fn scraped_url_data() -> UrlData {
+        let parser = HtmlParser::new();
+        let mut url_data = UrlData::new("https://example.com/page".to_string());
+        let html = r#"<html><body><p>Raw</p></body></html>"#;
+        url_data.set_html_data(
+            html.to_string(),
+            parser.parse(html),
+            Some("Page".to_string()),
+        );
+        url_data.filtered_tree = Some(HtmlNode::new(
+            "html".to_string(),
+            vec![],
+            None,
+            "Filtered".to_string(),
+        ));
+        url_data
+    }
+
+    #[test]
+    fn test_content_mode_parsing() {
+        assert_eq!("raw".parse::<ContentMode>().unwrap(), ContentMode::Raw);
+        assert_eq!(
+            "filtered".parse::<ContentMode>().unwrap(),
+            ContentMode::Filtered
+        );
+        assert_eq!("both".parse::<ContentMode>().unwrap(), ContentMode::Both);
+        assert!("all".parse::<ContentMode>().is_err());
+    }
+
+    #[test]
+    fn test_scraped_page_content_modes() {
+        let url_data = scraped_url_data();
+
+        let raw = ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw);
+        assert!(raw.content.is_some());
+        assert!(raw.filtered_content.is_none());
+
+        let filtered = ScrapedWebPage::from_url_data(&url_data, ContentMode::Filtered);
+        assert!(filtered.content.is_none());
+        assert_eq!(filtered.filtered_content.unwrap().content, "Filtered");
+
+        let both = ScrapedWebPage::from_url_data(&url_data, ContentMode::Both);
+        assert!(both.content.is_some());
+        assert!(both.filtered_content.is_some());
+        assert_eq!(both.title.as_deref(), Some("Page"));
+        assert!(both.text_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_objective_matches() {
+        let parser = HtmlParser::new();
+        let mut url_data = UrlData::new("https://example.com/about".to_string());
+        let html = r#"<html><body><h2>Our Team</h2><p>Founded in 2010</p><p>Meet the leadership</p><p>Our Team</p></body></html>"#;
+        url_data.set_html_data(html.to_string(), parser.parse(html), None);
+
+        let keywords = KeywordMatcher::from_objective("team members");
+        let page = ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw)
+            .with_objective_matches(&keywords, &url_data);
+        assert_eq!(
+            page.objective_matches,
+            vec!["Our Team", "Meet the leadership"]
+        );
+
+        let page = ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw)
+            .with_objective_matches(&KeywordMatcher::default(), &url_data);
+        assert!(page.objective_matches.is_empty());
+    }
+
+    #[test]
+    fn test_entities() {
+        let parser = HtmlParser::new();
+        let mut url_data = UrlData::new("https://example.com/contact".to_string());
+        let html = r#"<html><body><p>Email hello@example.com</p><footer>Tickets from $49</footer></body></html>"#;
+        url_data.set_html_data(html.to_string(), parser.parse(html), None);
+
+        let page = ScrapedWebPage::from_url_data(&url_data, ContentMode::Filtered)
+            .with_entities(&EntityExtractor::new(), &url_data);
+        // Sorted by ID rather than page order
+        let values: Vec<&str> = page.entities.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["49 USD", "hello@example.com"]);
+        assert_eq!(
+            page.entities[1].id,
+            entity_id(EntityKind::Email, "hello@example.com")
+        );
+    }
+
+    #[test]
+    fn test_scraped_page_text_blocks_with_boxes() {
+        let parser = HtmlParser::new();
+        let mut url_data = UrlData::new("https://example.com/page".to_string());
+        let html = r#"<html data-sc-box="0,0,1280,900"><body data-sc-box="0,0,1280,900"><p data-sc-box="0,500,100,20">Text</p><h1 data-sc-box="0,0,100,40">Title</h1></body></html>"#;
+        url_data.set_html_data(html.to_string(), parser.parse(html), None);
+
+        let page = ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw);
+        let texts: Vec<&str> = page.text_blocks.iter().map(|b| b.text.as_str()).collect();
+        assert_eq!(texts, vec!["Title", "Text"]);
+    }
+
+    #[test]
+    fn test_results_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let page = ScrapedWebPage::from_url_data(&scraped_url_data(), ContentMode::Filtered);
+        let config = CrawlConfig {
+            domain: "example.com".to_string(),
+            objective: Some("pricing".to_string()),
+            ..CrawlConfig::default()
+        };
+        let manifest = RunManifest::new(&config, vec!["example.com".to_string()], true);
+        assert_eq!(manifest.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.generated_at, None);
+        assert_eq!(manifest.config.domain, "");
+
+        let path = dir.path().join("pages.json.gz");
+        let path = path.to_str().unwrap();
+        write_results(path, &manifest, std::slice::from_ref(&page)).unwrap();
+        let (read, pages) = read_results(path).unwrap();
+        assert_eq!(read, Some(manifest));
+        assert_eq!(pages.len(), 1);
+        assert_eq!(read_pages(path).unwrap()[0].url, page.url);
+
+        // Files of bare pages still read
+        let path = dir.path().join("pages.json");
+        let path = path.to_str().unwrap();
+        write_pages(path, &[page]).unwrap();
+        let (read, pages) = read_results(path).unwrap();
+        assert_eq!(read, None);
+        assert_eq!(pages[0].url, "https://example.com/page");
+        assert!(RunManifest::new(&config, Vec::new(), false)
+            .generated_at
+            .is_some());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::entities::{entity_id, Entity, EntityKind};
+use crate::html_parser::HtmlNode;
+use crate::results::ScrapedWebPage;
+use crate::utils::{read_output_file, write_output_file};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Entities below this confidence are written to the review file
+pub const REVIEW_CONFIDENCE_THRESHOLD: f32 = 0.7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// One line of a review file: an uncertain entity with what a reviewer needs
+/// to check it. Reviewers set `status`, and `correction` when the value is wrong.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewItem {
+    pub url: String,
+    pub kind: EntityKind,
+    pub value: String,
+    pub text: String,
+    pub confidence: f32,
+    /// Page text the entity was found in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Screenshot of the page, when screenshots were saved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<String>,
+    #[serde(default)]
+    pub status: ReviewStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correction: Option<String>,
+}
+
+/// Review items for the entities of a page below `threshold`
+pub fn review_items(
+    url: &str,
+    tree: Option<&HtmlNode>,
+    entities: &[Entity],
+    screenshot: Option<&Path>,
+    threshold: f32,
+) -> Vec<ReviewItem> {
+    entities
+        .iter()
+        .filter(|entity| entity.confidence < threshold)
+        .map(|entity| ReviewItem {
+            url: url.to_string(),
+            kind: entity.kind,
+            value: entity.value.clone(),
+            text: entity.text.clone(),
+            confidence: entity.confidence,
+            snippet: tree.and_then(|tree| find_snippet(tree, &entity.text)),
+            screenshot: screenshot.map(|path| path.display().to_string()),
+            status: ReviewStatus::Pending,
+            correction: None,
+        })
+        .collect()
+}
+
+fn find_snippet(node: &HtmlNode, text: &str) -> Option<String> {
+    if node.content.contains(text) {
+        return Some(node.content.trim().to_string());
+    }
+    node.children
+        .iter()
+        .find_map(|child| find_snippet(child, text))
+}
+
+/// Write review items as JSON lines
+pub fn write_review_file(path: &str, items: &[ReviewItem]) -> Result<(), String> {
+    let mut lines = String::new();
+    for item in items {
+        let line = serde_json::to_string(item)
+            .map_err(|e| format!("Failed to serialize review item: {e}"))?;
+        lines.push_str(&line);
+        lines.push('\n');
+    }
+    write_output_file(path, lines.as_bytes())
+}
+
+pub fn load_review_file(path: &str) -> Result<Vec<ReviewItem>, String> {
+    let content = read_output_file(path)?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Invalid review on line {} of {path}: {e}", number + 1))
+        })
+        .collect()
+}
+
+/// How many reviewed entities `merge_reviews` changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MergeSummary {
+    pub accepted: usize,
+    pub corrected: usize,
+    pub rejected: usize,
+}
+
+/// Fold reviews into scraped pages: accepted entities become certain (with
+/// the correction as value when given) and rejected ones are dropped.
+/// Pending reviews and reviews of entities no longer in the pages are ignored.
+pub fn merge_reviews(pages: &mut [ScrapedWebPage], reviews: &[ReviewItem]) -> MergeSummary {
+    let mut summary = MergeSummary::default();
+    for review in reviews {
+        let Some(page) = pages.iter_mut().find(|page| page.url == review.url) else {
+            continue;
+        };
+        let Some(index) = page
+            .entities
+            .iter()
+            .position(|entity| entity.kind == review.kind && entity.value == review.value)
+        else {
+            continue;
+        };
+        match review.status {
+            ReviewStatus::Pending => {}
+            ReviewStatus::Accepted => {
+                let entity = &mut page.entities[index];
+                entity.confidence = 1.0;
+                match &review.correction {
+                    Some(correction) => {
+                        entity.value = correction.clone();
+                        entity.id = entity_id(entity.kind, correction);
+                        summary.corrected += 1;
+                    }
+                    None => summary.accepted += 1,
+                }
+            }
+            ReviewStatus::Rejected => {
+                page.entities.remove(index);
+                summary.rejected += 1;
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+    use crate::html_parser::HtmlParser;
+    use crate::results::ContentMode;
+    use crate::storage::UrlData;
+
+    #[test]
+    fn test_review_round_trip() {
+        let parser = HtmlParser::new();
+        let html = r#"<html><body><p>Doors open 03/04/2025, tickets $20</p><p>Visit 10 Downing Street</p></body></html>"#;
+        let mut url_data = UrlData::new("https://example.com/".to_string());
+        url_data.set_html_data(html.to_string(), parser.parse(html), None);
+        let mut pages = vec![ScrapedWebPage::from_url_data(&url_data, ContentMode::Raw)
+            .with_entities(&EntityExtractor::new(), &url_data)];
+        assert_eq!(pages[0].entities.len(), 3);
+
+        let mut items = review_items(
+            &url_data.url,
+            url_data.html_tree.as_ref(),
+            &pages[0].entities,
+            Some(Path::new("shots/example.png")),
+            REVIEW_CONFIDENCE_THRESHOLD,
+        );
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0].snippet.as_deref(),
+            Some("Doors open 03/04/2025, tickets $20")
+        );
+        assert_eq!(items[0].screenshot.as_deref(), Some("shots/example.png"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("review.jsonl");
+        let path = path.to_str().unwrap();
+        write_review_file(path, &items).unwrap();
+        assert_eq!(load_review_file(path).unwrap(), items);
+
+        items[0].status = ReviewStatus::Accepted;
+        items[0].correction = Some("2025-04-03".to_string());
+        items[1].status = ReviewStatus::Rejected;
+        let summary = merge_reviews(&mut pages, &items);
+        assert_eq!(
+            summary,
+            MergeSummary {
+                accepted: 0,
+                corrected: 1,
+                rejected: 1
+            }
+        );
+        let values: Vec<(&str, f32)> = pages[0]
+            .entities
+            .iter()
+            .map(|entity| (entity.value.as_str(), entity.confidence))
+            .collect();
+        assert_eq!(values, vec![("2025-04-03", 1.0), ("20 USD", 0.9)]);
+    }
+}
@@ -0,0 +1,512 @@
+use crate::crawler::{CrawlError, CrawlResult, CrawlSession, SmartCrawler, SmartCrawlerBuilder};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
+
+/// A domain waiting to be crawled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedDomain {
+    pub domain: String,
+    /// Higher priorities are crawled first, 0 by default
+    pub priority: i32,
+}
+
+/// Domains waiting to be crawled, highest priority first and in insertion
+/// order among equal priorities. A domain is only ever queued once.
+#[derive(Debug, Default)]
+pub struct DomainQueue {
+    pending: Vec<QueuedDomain>,
+    seen: HashSet<String>,
+    /// Woken by every push, so the scheduler fills free slots right away
+    pushed: Arc<Notify>,
+}
+
+/// A `DomainQueue` the scheduler and whatever feeds it can share
+pub type SharedDomainQueue = Arc<Mutex<DomainQueue>>;
+
+impl DomainQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a domain, returning false if it was queued before
+    pub fn push(&mut self, domain: impl Into<String>, priority: i32) -> bool {
+        let domain = domain.into();
+        if !self.seen.insert(domain.clone()) {
+            return false;
+        }
+        // Stable: after every entry with the same or a higher priority
+        let index = self
+            .pending
+            .iter()
+            .position(|queued| queued.priority < priority)
+            .unwrap_or(self.pending.len());
+        self.pending
+            .insert(index, QueuedDomain { domain, priority });
+        self.pushed.notify_one();
+        true
+    }
+
+    pub fn pop(&mut self) -> Option<QueuedDomain> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            Some(self.pending.remove(0))
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Notified whenever a domain is queued
+    pub fn pushed(&self) -> Arc<Notify> {
+        Arc::clone(&self.pushed)
+    }
+
+    pub fn into_shared(self) -> SharedDomainQueue {
+        Arc::new(Mutex::new(self))
+    }
+}
+
+/// Crawls every domain in a queue, up to `max_concurrent` at a time. Each
+/// crawl is capped at its `max_pages`; with `fair_share`, crawls also take
+/// turns of a few pages each, so a slow site can't hold a slot while the rest
+/// of the list waits.
+pub struct DomainScheduler {
+    template: SmartCrawlerBuilder,
+    queue: SharedDomainQueue,
+    max_concurrent: usize,
+    idle_poll: Option<Duration>,
+    /// Pages a crawl fetches before it yields its slot
+    page_budget: usize,
+}
+
+/// How a crawl's turn in a slot ended
+enum Turn {
+    Finished(Box<Result<CrawlResult, CrawlError>>),
+    /// Pages are left; the crawl waits for another turn
+    Paused(Box<(SmartCrawler, CrawlSession)>),
+}
+
+/// Start a crawl or resume a paused one, fetching up to `budget` pages
+async fn take_turn(crawler: SmartCrawler, session: Option<CrawlSession>, budget: usize) -> Turn {
+    let mut session = match session {
+        Some(session) => session,
+        None => match crawler.start().await {
+            Ok(session) => session,
+            Err(e) => return Turn::Finished(Box::new(Err(e))),
+        },
+    };
+    if crawler.crawl_pages(&mut session, budget).await {
+        Turn::Finished(Box::new(Ok(crawler.finish(session))))
+    } else {
+        Turn::Paused(Box::new((crawler, session)))
+    }
+}
+
+impl DomainScheduler {
+    /// `template` holds the settings shared by every crawl; its domain is
+    /// replaced by each queued domain
+    pub fn new(template: SmartCrawlerBuilder, queue: SharedDomainQueue) -> Self {
+        DomainScheduler {
+            template,
+            queue,
+            max_concurrent: 1,
+            idle_poll: None,
+            page_budget: usize::MAX,
+        }
+    }
+
+    /// Let each crawl fetch `pages` pages, then hand its slot to the next
+    /// queued or paused domain and wait for another turn. Domains take turns
+    /// round-robin, new ones alternating with paused ones; a paused crawl
+    /// keeps its pages but closes its WebDriver session.
+    pub fn fair_share(mut self, pages: usize) -> Self {
+        self.page_budget = pages.max(1);
+        self
+    }
+
+    /// Crawls running at once, each in its own WebDriver session
+    pub fn max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = max_concurrent.max(1);
+        self
+    }
+
+    /// Keep running once every domain is done, instead of returning. Queued
+    /// domains start as soon as they're pushed; an idle scheduler also
+    /// checks the queue at this interval.
+    pub fn wait_for_domains(mut self, interval: Duration) -> Self {
+        self.idle_poll = Some(interval);
+        self
+    }
+
+    pub fn queue(&self) -> SharedDomainQueue {
+        Arc::clone(&self.queue)
+    }
+
+    /// Run until the queue is empty and every crawl has finished, handing each
+    /// result to `on_result` as soon as its crawl completes. Domains added to
+    /// the queue meanwhile start at once if a slot is free, else as soon as
+    /// one frees up.
+    pub async fn run<F>(&self, mut on_result: F)
+    where
+        F: FnMut(&str, Result<CrawlResult, CrawlError>),
+    {
+        let Some(pushed) = self.queue.lock().ok().map(|queue| queue.pushed()) else {
+            return;
+        };
+        let mut running = JoinSet::new();
+        let mut paused: VecDeque<(String, SmartCrawler, CrawlSession)> = VecDeque::new();
+        let mut resume_next = false;
+        loop {
+            while running.len() < self.max_concurrent {
+                // Alternate between paused crawls and new domains, so neither
+                // waits for all of the other
+                let resumed = if resume_next || self.queue_is_empty() {
+                    paused.pop_front()
+                } else {
+                    None
+                };
+                resume_next = !resume_next;
+                if let Some((domain, crawler, session)) = resumed {
+                    info!(
+                        "Resuming crawl of {} ({} pages left)",
+                        domain,
+                        session.remaining()
+                    );
+                    let budget = self.page_budget;
+                    running.spawn(async move {
+                        (domain, take_turn(crawler, Some(session), budget).await)
+                    });
+                    continue;
+                }
+                let Some(next) = self.next_domain() else {
+                    if paused.is_empty() {
+                        break;
+                    }
+                    continue;
+                };
+                info!(
+                    "Starting crawl of {} (priority {})",
+                    next.domain, next.priority
+                );
+                match self.template.clone().domain(&next.domain).build() {
+                    Ok(crawler) => {
+                        let budget = self.page_budget;
+                        running.spawn(async move {
+                            (next.domain, take_turn(crawler, None, budget).await)
+                        });
+                    }
+                    Err(e) => on_result(&next.domain, Err(e)),
+                }
+            }
+
+            if running.is_empty() && paused.is_empty() && self.idle_poll.is_none() {
+                break;
+            }
+            let idle_poll = self.idle_poll.unwrap_or_default();
+            tokio::select! {
+                finished = running.join_next(), if !running.is_empty() => match finished {
+                    Some(Ok((domain, Turn::Finished(result)))) => on_result(&domain, *result),
+                    Some(Ok((domain, Turn::Paused(turn)))) => {
+                        let (crawler, session) = *turn;
+                        paused.push_back((domain, crawler, session));
+                    }
+                    Some(Err(e)) => error!("Crawl task failed: {}", e),
+                    None => {}
+                },
+                _ = pushed.notified() => {}
+                _ = tokio::time::sleep(idle_poll), if running.is_empty() => {}
+            }
+        }
+    }
+
+    fn queue_is_empty(&self) -> bool {
+        self.queue.lock().map_or(true, |queue| queue.is_empty())
+    }
+
+    fn next_domain(&self) -> Option<QueuedDomain> {
+        self.queue.lock().ok()?.pop()
+    }
+}
+
+/// Domains listed in a file, one per line with an optional priority after
+/// whitespace (`example.com 5`). Blank lines and `#` comments are skipped.
+pub fn parse_domains_file(content: &str) -> Result<Vec<QueuedDomain>, String> {
+    let mut domains = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let mut fields = line.split_whitespace();
+        let Some(domain) = fields.next() else {
+            continue;
+        };
+        let priority = match fields.next() {
+            Some(priority) => priority
+                .parse()
+                .map_err(|_| format!("Invalid priority on line {}: {line}", number + 1))?,
+            None => 0,
+        };
+        domains.push(QueuedDomain {
+            domain: domain.to_string(),
+            priority,
+        });
+    }
+    Ok(domains)
+}
+
+pub fn load_domains_file(path: &str) -> Result<Vec<QueuedDomain>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    parse_domains_file(&content)
+}
+
+/// Re-read a domains file at every interval and queue domains added to it.
+/// `normalize` turns each entry into the domain crawled (e.g. strips URLs);
+/// entries it rejects are skipped with a warning. Runs until cancelled.
+pub async fn watch_domains_file<F>(
+    path: String,
+    queue: SharedDomainQueue,
+    interval: Duration,
+    normalize: F,
+) where
+    F: Fn(&str) -> Result<String, String>,
+{
+    loop {
+        tokio::time::sleep(interval).await;
+        let entries = match load_domains_file(&path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        let Ok(mut queue) = queue.lock() else {
+            return;
+        };
+        for entry in entries {
+            match normalize(&entry.domain) {
+                Ok(domain) => {
+                    if queue.push(domain.clone(), entry.priority) {
+                        info!("Queued {} from {}", domain, path);
+                    }
+                }
+                Err(e) => warn!("Skipping {} in {}: {}", entry.domain, path, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::SmartCrawler;
+    use crate::wait::WaitStrategy;
+    use crate::webdriver_mock::{MockPage, MockWebDriver};
+
+    #[test]
+    fn test_queue_orders_by_priority_then_insertion() {
+        let mut queue = DomainQueue::new();
+        assert!(queue.push("a.com", 0));
+        assert!(queue.push("b.com", 5));
+        assert!(queue.push("c.com", 0));
+        assert!(queue.push("d.com", 5));
+        assert!(!queue.push("a.com", 10));
+        assert_eq!(queue.len(), 4);
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.pop())
+            .map(|queued| queued.domain)
+            .collect();
+        assert_eq!(order, vec!["b.com", "d.com", "a.com", "c.com"]);
+
+        // Domains stay known after they were popped
+        assert!(!queue.push("b.com", 0));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_parse_domains_file() {
+        let domains = parse_domains_file(
+            "# conferences\nrustconf.com 10\n\nhttps://eurorust.eu/\n  gophercon.com -1 # later\n",
+        )
+        .unwrap();
+        assert_eq!(
+            domains,
+            vec![
+                QueuedDomain {
+                    domain: "rustconf.com".to_string(),
+                    priority: 10
+                },
+                QueuedDomain {
+                    domain: "https://eurorust.eu/".to_string(),
+                    priority: 0
+                },
+                QueuedDomain {
+                    domain: "gophercon.com".to_string(),
+                    priority: -1
+                },
+            ]
+        );
+        assert!(parse_domains_file("example.com high").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_queues_new_domains() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("domains.txt");
+        std::fs::write(&path, "a.com\n").unwrap();
+
+        let mut queue = DomainQueue::new();
+        queue.push("a.com", 0);
+        assert!(queue.pop().is_some());
+        let queue = queue.into_shared();
+
+        let watcher = tokio::spawn(watch_domains_file(
+            path.to_string_lossy().to_string(),
+            Arc::clone(&queue),
+            Duration::from_millis(10),
+            |entry| Ok(entry.to_string()),
+        ));
+        std::fs::write(&path, "a.com\nb.com 3\n").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        watcher.abort();
+
+        let mut queue = queue.lock().unwrap();
+        assert_eq!(
+            queue.pop(),
+            Some(QueuedDomain {
+                domain: "b.com".to_string(),
+                priority: 3
+            })
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_reports_invalid_domains() {
+        let mut queue = DomainQueue::new();
+        queue.push("://invalid", 0);
+        let scheduler = DomainScheduler::new(SmartCrawler::builder(), queue.into_shared());
+
+        let mut results = Vec::new();
+        scheduler
+            .run(|domain, result| results.push((domain.to_string(), result.is_err())))
+            .await;
+        assert_eq!(results, vec![("://invalid".to_string(), true)]);
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_fills_free_slots_with_queued_domains() {
+        let driver = MockWebDriver::start().await;
+        driver.page(
+            "https://slow.example/",
+            MockPage {
+                html: "<html><body>Slow</body></html>".to_string(),
+                delay: Duration::from_secs(2),
+                ..MockPage::default()
+            },
+        );
+        driver.page(
+            "https://fast.example/",
+            MockPage {
+                html: "<html><body>Fast</body></html>".to_string(),
+                ..MockPage::default()
+            },
+        );
+        let template = SmartCrawler::builder()
+            .webdriver_port(driver.port())
+            .max_pages(1)
+            .max_sitemap_urls(0)
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            );
+        let mut queue = DomainQueue::new();
+        queue.push("slow.example", 0);
+        let scheduler = DomainScheduler::new(template, queue.into_shared()).max_concurrent(2);
+
+        // Queued while the slow crawl holds one of the two slots, once it's
+        // loading the page rather than still connecting
+        let queue = scheduler.queue();
+        let push = async {
+            while !driver
+                .requests()
+                .contains(&"POST /session/{id}/url".to_string())
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            queue.lock().unwrap().push("fast.example", 0);
+        };
+        let mut finished = Vec::new();
+        tokio::join!(
+            scheduler.run(|domain, result| finished.push((domain.to_string(), result.is_ok()))),
+            push
+        );
+        assert_eq!(
+            finished,
+            vec![
+                ("fast.example".to_string(), true),
+                ("slow.example".to_string(), true)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fair_share_interleaves_domains() {
+        let driver = MockWebDriver::start().await;
+        let page = |html: &str| MockPage {
+            html: format!("<html><body>{html}</body></html>"),
+            ..MockPage::default()
+        };
+        driver.page(
+            "https://big.example/",
+            page(r#"<a href="/a">A</a><a href="/b">B</a><a href="/c">C</a>"#),
+        );
+        for path in ["a", "b", "c"] {
+            driver.page(&format!("https://big.example/{path}"), page(path));
+        }
+        driver.page("https://small.example/", page("Small"));
+        let template = SmartCrawler::builder()
+            .webdriver_port(driver.port())
+            .max_pages(4)
+            .max_sitemap_urls(0)
+            .wait(
+                WaitStrategy::Fixed {
+                    duration: Duration::ZERO,
+                },
+                Duration::from_secs(1),
+            );
+        let mut queue = DomainQueue::new();
+        queue.push("big.example", 0);
+        queue.push("small.example", 0);
+        let scheduler = DomainScheduler::new(template, queue.into_shared()).fair_share(1);
+
+        // The big site yields its only slot after one page, so the small one
+        // finishes first
+        let mut finished = Vec::new();
+        scheduler
+            .run(|domain, result| {
+                finished.push((
+                    domain.to_string(),
+                    result.unwrap().storage.get_completed_urls().len(),
+                ))
+            })
+            .await;
+        assert_eq!(
+            finished,
+            vec![
+                ("small.example".to_string(), 1),
+                ("big.example".to_string(), 4)
+            ]
+        );
+    }
+}
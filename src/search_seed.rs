@@ -0,0 +1,269 @@
+use crate::network_guard::http_client;
+use serde_json::Value;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::info;
+use url::Url;
+
+/// Environment variable holding the API key for providers that need one
+pub const SEARCH_API_KEY_ENV: &str = "SMART_CRAWLER_SEARCH_API_KEY";
+
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("Search request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid search endpoint: {0}")]
+    InvalidEndpoint(String),
+    #[error("{0} needs an API key in {SEARCH_API_KEY_ENV}")]
+    MissingApiKey(SearchProvider),
+}
+
+/// Web search API used to find domains for an objective
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchProvider {
+    /// A SearxNG instance with the JSON format enabled, no key needed
+    Searxng,
+    Brave,
+    SerpApi,
+}
+
+impl SearchProvider {
+    fn default_endpoint(&self) -> Option<&'static str> {
+        match self {
+            SearchProvider::Searxng => None,
+            SearchProvider::Brave => Some("https://api.search.brave.com/res/v1/web/search"),
+            SearchProvider::SerpApi => Some("https://serpapi.com/search.json"),
+        }
+    }
+}
+
+impl FromStr for SearchProvider {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "searxng" => Ok(SearchProvider::Searxng),
+            "brave" => Ok(SearchProvider::Brave),
+            "serpapi" => Ok(SearchProvider::SerpApi),
+            other => Err(format!(
+                "Unknown search provider: {other} (expected searxng, brave or serpapi)"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for SearchProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SearchProvider::Searxng => "searxng",
+            SearchProvider::Brave => "brave",
+            SearchProvider::SerpApi => "serpapi",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchConfig {
+    pub provider: SearchProvider,
+    /// Search API URL, required for SearxNG (e.g. `https://searx.example.org/search`)
+    pub endpoint: Option<String>,
+    pub api_key: Option<String>,
+    /// Result URLs read from the search
+    pub max_results: usize,
+}
+
+impl SearchConfig {
+    pub fn new(provider: SearchProvider) -> Self {
+        SearchConfig {
+            provider,
+            endpoint: None,
+            api_key: None,
+            max_results: 10,
+        }
+    }
+}
+
+/// Finds seed domains for a crawl by searching the web for the objective,
+/// for when the domains holding the answer aren't known up front
+pub struct SearchSeeder {
+    client: reqwest::Client,
+    config: SearchConfig,
+}
+
+impl SearchSeeder {
+    pub fn new(config: SearchConfig) -> Self {
+        // The endpoint is the user's choice, e.g. a SearxNG on their LAN; the
+        // domains found are checked like any other before they're crawled
+        let client = http_client(Duration::from_secs(30), true);
+
+        SearchSeeder { client, config }
+    }
+
+    /// Result URLs for `query`, best first
+    pub async fn search(&self, query: &str) -> Result<Vec<String>, SearchError> {
+        let mut request = self.client.get(self.request_url(query)?);
+        if self.config.provider == SearchProvider::Brave {
+            request = request.header("X-Subscription-Token", self.api_key()?);
+        }
+        let body: Value = request.send().await?.error_for_status()?.json().await?;
+
+        let mut urls = Self::result_urls(self.config.provider, &body);
+        urls.truncate(self.config.max_results);
+        info!(
+            "Search for \"{}\" on {} returned {} URLs",
+            query,
+            self.config.provider,
+            urls.len()
+        );
+        Ok(urls)
+    }
+
+    /// Distinct domains of the results for `query`, best first
+    pub async fn seed_domains(&self, query: &str) -> Result<Vec<String>, SearchError> {
+        Ok(domains_of(&self.search(query).await?))
+    }
+
+    fn request_url(&self, query: &str) -> Result<Url, SearchError> {
+        let endpoint = self
+            .config
+            .endpoint
+            .as_deref()
+            .or(self.config.provider.default_endpoint())
+            .ok_or_else(|| {
+                SearchError::InvalidEndpoint(format!("{} needs an endpoint", self.config.provider))
+            })?;
+        let mut url =
+            Url::parse(endpoint).map_err(|e| SearchError::InvalidEndpoint(e.to_string()))?;
+        {
+            let mut params = url.query_pairs_mut();
+            params.append_pair("q", query);
+            match self.config.provider {
+                SearchProvider::Searxng => {
+                    params.append_pair("format", "json");
+                }
+                SearchProvider::Brave => {
+                    params.append_pair("count", &self.config.max_results.min(20).to_string());
+                }
+                SearchProvider::SerpApi => {
+                    params.append_pair("engine", "google");
+                    params.append_pair("num", &self.config.max_results.to_string());
+                    params.append_pair("api_key", self.api_key()?);
+                }
+            }
+        }
+        Ok(url)
+    }
+
+    fn api_key(&self) -> Result<&str, SearchError> {
+        self.config
+            .api_key
+            .as_deref()
+            .filter(|key| !key.is_empty())
+            .ok_or(SearchError::MissingApiKey(self.config.provider))
+    }
+
+    /// Result URLs in a provider's JSON response
+    fn result_urls(provider: SearchProvider, body: &Value) -> Vec<String> {
+        let (results, field) = match provider {
+            SearchProvider::Searxng => (&body["results"], "url"),
+            SearchProvider::Brave => (&body["web"]["results"], "url"),
+            SearchProvider::SerpApi => (&body["organic_results"], "link"),
+        };
+        results
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|result| result[field].as_str())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Hosts of `urls` without a leading `www.`, deduplicated in order
+pub fn domains_of(urls: &[String]) -> Vec<String> {
+    let mut domains: Vec<String> = Vec::new();
+    for url in urls {
+        let Some(host) = Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+        else {
+            continue;
+        };
+        let domain = host.trim_start_matches("www.").to_string();
+        if !domains.contains(&domain) {
+            domains.push(domain);
+        }
+    }
+    domains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_request_urls() {
+        let mut config = SearchConfig::new(SearchProvider::Searxng);
+        assert!(SearchSeeder::new(config.clone())
+            .request_url("rust conferences")
+            .is_err());
+
+        config.endpoint = Some("https://searx.example.org/search".to_string());
+        assert_eq!(
+            SearchSeeder::new(config)
+                .request_url("rust conferences")
+                .unwrap()
+                .as_str(),
+            "https://searx.example.org/search?q=rust+conferences&format=json"
+        );
+
+        let mut config = SearchConfig::new(SearchProvider::SerpApi);
+        assert!(matches!(
+            SearchSeeder::new(config.clone()).request_url("rust"),
+            Err(SearchError::MissingApiKey(SearchProvider::SerpApi))
+        ));
+        config.api_key = Some("secret".to_string());
+        assert_eq!(
+            SearchSeeder::new(config)
+                .request_url("rust")
+                .unwrap()
+                .as_str(),
+            "https://serpapi.com/search.json?q=rust&engine=google&num=10&api_key=secret"
+        );
+    }
+
+    #[test]
+    fn test_result_urls() {
+        let searxng = json!({"results": [{"url": "https://rustconf.com/"}, {"title": "no url"}]});
+        assert_eq!(
+            SearchSeeder::result_urls(SearchProvider::Searxng, &searxng),
+            vec!["https://rustconf.com/"]
+        );
+        let brave = json!({"web": {"results": [{"url": "https://eurorust.eu/"}]}});
+        assert_eq!(
+            SearchSeeder::result_urls(SearchProvider::Brave, &brave),
+            vec!["https://eurorust.eu/"]
+        );
+        let serpapi = json!({"organic_results": [{"link": "https://www.rustnationuk.com/"}]});
+        assert_eq!(
+            SearchSeeder::result_urls(SearchProvider::SerpApi, &serpapi),
+            vec!["https://www.rustnationuk.com/"]
+        );
+        assert!(SearchSeeder::result_urls(SearchProvider::Brave, &json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_domains_of() {
+        let urls = [
+            "https://www.rustconf.com/schedule",
+            "https://rustconf.com/",
+            "not a url",
+            "https://eurorust.eu/2025",
+        ]
+        .map(String::from);
+        assert_eq!(domains_of(&urls), vec!["rustconf.com", "eurorust.eu"]);
+    }
+}
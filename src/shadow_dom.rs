@@ -0,0 +1,44 @@
+/// Serializes the document with open shadow roots inlined, so content rendered
+/// by web components ends up in the captured HTML. An element's shadow tree
+/// replaces its light DOM children, and each `<slot>` is replaced by the nodes
+/// assigned to it (or its fallback content). Closed shadow roots are not
+/// reachable from scripts and stay empty.
+pub const FLATTEN_SHADOW_DOM_SCRIPT: &str = r#"
+    const voidTags = new Set(['area', 'base', 'br', 'col', 'embed', 'hr', 'img', 'input',
+        'link', 'meta', 'source', 'track', 'wbr']);
+    const rawTextTags = new Set(['script', 'style']);
+    const escapeText = text => text.replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+    const escapeAttribute = value => value.replace(/&/g, '&amp;').replace(/"/g, '&quot;');
+
+    const serializeChildren = (nodes, rawText) =>
+        Array.from(nodes).map(node => serialize(node, rawText)).join('');
+
+    const serialize = (node, rawText) => {
+        if (node.nodeType === Node.TEXT_NODE) {
+            return rawText ? node.textContent : escapeText(node.textContent);
+        }
+        if (node.nodeType !== Node.ELEMENT_NODE) {
+            return '';
+        }
+
+        const tag = node.localName;
+        if (tag === 'slot') {
+            const assigned = node.assignedNodes({ flatten: true });
+            return serializeChildren(assigned.length ? assigned : node.childNodes, false);
+        }
+
+        const attributes = Array.from(node.attributes)
+            .map(attribute => ` ${attribute.name}="${escapeAttribute(attribute.value)}"`)
+            .join('');
+        if (voidTags.has(tag)) {
+            return `<${tag}${attributes}>`;
+        }
+
+        const children = tag === 'template' ? node.content.childNodes
+            : node.shadowRoot ? node.shadowRoot.childNodes
+            : node.childNodes;
+        return `<${tag}${attributes}>${serializeChildren(children, rawTextTags.has(tag))}</${tag}>`;
+    };
+
+    return '<!DOCTYPE html>' + serialize(document.documentElement, false);
+"#;
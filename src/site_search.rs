@@ -0,0 +1,129 @@
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Names search boxes commonly have, for forms without `type=search`
+const QUERY_NAMES: &[&str] = &["q", "s", "query", "search", "keyword", "keywords", "term"];
+
+/// A site's own search form, which can be submitted by loading a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchForm {
+    /// Absolute URL the form submits to
+    pub action: String,
+    /// Name of the input holding the query
+    pub query_param: String,
+    /// Hidden inputs submitted along with the query
+    pub hidden: Vec<(String, String)>,
+}
+
+impl SearchForm {
+    /// The URL of the results for `query`
+    pub fn url(&self, query: &str) -> String {
+        let Ok(mut url) = Url::parse(&self.action) else {
+            return self.action.clone();
+        };
+        {
+            let mut pairs = url.query_pairs_mut();
+            for (name, value) in &self.hidden {
+                pairs.append_pair(name, value);
+            }
+            pairs.append_pair(&self.query_param, query);
+        }
+        url.into()
+    }
+}
+
+/// The first GET form of a page that searches the site: one with an
+/// `input[type=search]`, or whose action or role mentions search and that
+/// has a text box. Forms posting elsewhere or to other domains are skipped.
+pub fn find_search_form(html: &str, page_url: &str) -> Option<SearchForm> {
+    let document = Html::parse_document(html);
+    let base = Url::parse(page_url).ok()?;
+    let form_selector = Selector::parse("form").unwrap();
+    document
+        .select(&form_selector)
+        .find_map(|form| search_form(form, &base))
+}
+
+fn search_form(form: ElementRef, base: &Url) -> Option<SearchForm> {
+    let method = form.value().attr("method").unwrap_or("get");
+    if !method.eq_ignore_ascii_case("get") {
+        return None;
+    }
+    let action = form.value().attr("action").unwrap_or_default().trim();
+    let action_url = base.join(action).ok()?;
+    if action_url.host_str() != base.host_str() {
+        return None;
+    }
+
+    let input_selector = Selector::parse("input[name]").unwrap();
+    let inputs: Vec<(String, String, String)> = form
+        .select(&input_selector)
+        .map(|input| {
+            let attr = |name| input.value().attr(name).unwrap_or_default().to_string();
+            (attr("type").to_lowercase(), attr("name"), attr("value"))
+        })
+        .collect();
+    let is_text = |kind: &str| kind.is_empty() || kind == "text" || kind == "search";
+    let query_param = inputs
+        .iter()
+        .find(|(kind, _, _)| kind == "search")
+        .or_else(|| {
+            let mentions_search = action.to_lowercase().contains("search")
+                || form
+                    .value()
+                    .attr("role")
+                    .is_some_and(|role| role.eq_ignore_ascii_case("search"));
+            inputs.iter().find(|(kind, name, _)| {
+                is_text(kind)
+                    && (mentions_search || QUERY_NAMES.contains(&name.to_lowercase().as_str()))
+            })
+        })
+        .map(|(_, name, _)| name.clone())?;
+
+    let mut action_url = action_url;
+    action_url.set_query(None);
+    action_url.set_fragment(None);
+    Some(SearchForm {
+        action: action_url.into(),
+        query_param,
+        hidden: inputs
+            .into_iter()
+            .filter(|(kind, _, _)| kind == "hidden")
+            .map(|(_, name, value)| (name, value))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_search_form() {
+        let html = r#"<html><body>
+            <form action="/newsletter" method="post"><input type="email" name="email"></form>
+            <form action="https://other.com/search"><input type="search" name="q"></form>
+            <form action="/search?old=1" role="search">
+                <input type="hidden" name="lang" value="en">
+                <input name="term"><button>Go</button>
+            </form>
+        </body></html>"#;
+        let form = find_search_form(html, "https://example.com/").unwrap();
+        assert_eq!(form.action, "https://example.com/search");
+        assert_eq!(form.query_param, "term");
+        assert_eq!(
+            form.url("pricing plans"),
+            "https://example.com/search?lang=en&term=pricing+plans"
+        );
+
+        // WordPress style: no action, a `s` box
+        let html = r#"<form><input type="text" name="s"></form>"#;
+        let form = find_search_form(html, "https://blog.example.com/posts/").unwrap();
+        assert_eq!(form.url("jobs"), "https://blog.example.com/posts/?s=jobs");
+
+        let login =
+            r#"<form action="/login"><input name="user"><input type="password" name="pw"></form>"#;
+        assert_eq!(find_search_form(login, "https://example.com/"), None);
+    }
+}
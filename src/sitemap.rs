@@ -0,0 +1,1008 @@
+use crate::network_guard::{check_url, http_client};
+use crate::utils::construct_root_url;
+use chrono::{DateTime, Duration as ChronoDuration, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io::Read;
+use std::str::FromStr;
+use std::time::Duration;
+use thiserror::Error;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// Distinct path prefixes tracked by `SamplingStrategy::PathPrefix` before
+/// further prefixes share a single overflow bucket
+const MAX_PATH_PREFIXES: usize = 64;
+const OVERFLOW_PREFIX: &str = "*";
+
+/// Largest sitemap read, compressed or not: the sitemap protocol's limit
+const MAX_SITEMAP_BYTES: u64 = 50 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum SitemapError {
+    #[error("Sitemap request failed: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Failed to parse sitemap XML: {0}")]
+    ParseError(String),
+    #[error("Failed to decompress sitemap: {0}")]
+    DecompressError(#[from] std::io::Error),
+    #[error("Refused to read sitemap: {0}")]
+    PrivateNetwork(String),
+    #[error("Sitemap is over the {MAX_SITEMAP_BYTES} byte limit")]
+    TooLarge,
+}
+
+/// A single `<url>` entry from a sitemap
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SitemapUrl {
+    pub loc: String,
+    pub lastmod: Option<String>,
+    pub changefreq: Option<String>,
+    pub priority: Option<f32>,
+}
+
+impl SitemapUrl {
+    pub fn new(loc: String) -> Self {
+        SitemapUrl {
+            loc,
+            lastmod: None,
+            changefreq: None,
+            priority: None,
+        }
+    }
+
+    /// Parsed `lastmod` value; W3C datetimes and plain dates are supported
+    pub fn lastmod_datetime(&self) -> Option<DateTime<Utc>> {
+        parse_lastmod(self.lastmod.as_deref()?)
+    }
+
+    /// Whether the URL was modified at or after the cutoff. URLs without a
+    /// usable `lastmod` are treated as not recently modified.
+    pub fn modified_since(&self, cutoff: &DateTime<Utc>) -> bool {
+        self.lastmod_datetime()
+            .is_some_and(|lastmod| lastmod >= *cutoff)
+    }
+
+    /// First path segment of the URL, used to group URLs by site section
+    pub fn path_prefix(&self) -> String {
+        url::Url::parse(&self.loc)
+            .ok()
+            .and_then(|url| {
+                url.path_segments()
+                    .and_then(|mut segments| segments.next().map(|s| s.to_string()))
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a sitemap `lastmod` value such as `2024-01-01`, `2024-01-01T10:00Z`
+/// or `2024-01-01T10:00:00+02:00`
+pub fn parse_lastmod(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.with_timezone(&Utc));
+    }
+    // W3C datetime allows omitting seconds
+    if let Ok(datetime) = DateTime::parse_from_str(value, "%Y-%m-%dT%H:%M%:z") {
+        return Some(datetime.with_timezone(&Utc));
+    }
+    if let Ok(datetime) = chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%MZ") {
+        return Some(datetime.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+}
+
+/// Parse a relative window like `30d`, `2w` or `12h` into a duration
+pub fn parse_modified_within(value: &str) -> Result<ChronoDuration, String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid time window: {value} (expected e.g. 12h, 30d or 4w)");
+    if value.len() < 2 {
+        return Err(invalid());
+    }
+
+    let (amount, unit) = value.split_at(value.len() - 1);
+    let amount: i64 = amount.parse().map_err(|_| invalid())?;
+    if amount <= 0 {
+        return Err(invalid());
+    }
+
+    match unit {
+        "h" => Ok(ChronoDuration::hours(amount)),
+        "d" => Ok(ChronoDuration::days(amount)),
+        "w" => Ok(ChronoDuration::weeks(amount)),
+        _ => Err(invalid()),
+    }
+}
+
+/// How URLs are picked once a sitemap holds more than `max_urls` entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SamplingStrategy {
+    /// Keep URLs in document order and stop reading once the cap is reached
+    First,
+    /// Uniform random sample over every URL in the sitemaps
+    Reservoir,
+    /// Random sample per leading path segment so every site section is represented
+    #[default]
+    #[serde(rename = "prefix")]
+    PathPrefix,
+}
+
+impl FromStr for SamplingStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(SamplingStrategy::First),
+            "reservoir" => Ok(SamplingStrategy::Reservoir),
+            "prefix" => Ok(SamplingStrategy::PathPrefix),
+            other => Err(format!(
+                "Invalid sampling strategy: {other} (expected first, reservoir or prefix)"
+            )),
+        }
+    }
+}
+
+/// How `smart-crawler sitemap` prints the URL inventory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SitemapFormat {
+    /// One URL per line followed by its lastmod and priority
+    #[default]
+    Text,
+    Json,
+    Csv,
+}
+
+/// Render sitemap URLs with their lastmod, changefreq and priority
+pub fn format_sitemap_urls(urls: &[SitemapUrl], format: SitemapFormat) -> Result<String, String> {
+    match format {
+        SitemapFormat::Text => Ok(urls
+            .iter()
+            .map(|url| {
+                let priority = url.priority.map(|p| p.to_string());
+                format!(
+                    "{}\t{}\t{}\n",
+                    url.loc,
+                    url.lastmod.as_deref().unwrap_or("-"),
+                    priority.as_deref().unwrap_or("-")
+                )
+            })
+            .collect()),
+        SitemapFormat::Json => serde_json::to_string_pretty(urls)
+            .map(|json| json + "\n")
+            .map_err(|e| format!("Failed to serialize sitemap URLs: {e}")),
+        SitemapFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            let csv_error = |e: csv::Error| format!("Failed to write sitemap CSV: {e}");
+            writer
+                .write_record(["loc", "lastmod", "changefreq", "priority"])
+                .map_err(csv_error)?;
+            for url in urls {
+                let priority = url.priority.map(|p| p.to_string()).unwrap_or_default();
+                writer
+                    .write_record([
+                        url.loc.as_str(),
+                        url.lastmod.as_deref().unwrap_or_default(),
+                        url.changefreq.as_deref().unwrap_or_default(),
+                        priority.as_str(),
+                    ])
+                    .map_err(csv_error)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| format!("Failed to write sitemap CSV: {e}"))?;
+            String::from_utf8(bytes).map_err(|e| format!("Failed to write sitemap CSV: {e}"))
+        }
+    }
+}
+
+/// Sampling seed of `--deterministic` runs
+pub const DETERMINISTIC_SEED: u64 = 0;
+
+#[derive(Debug, Clone)]
+pub struct SitemapConfig {
+    /// Maximum number of URLs returned for a domain
+    pub max_urls: usize,
+    pub sampling: SamplingStrategy,
+    /// Maximum number of sitemap files fetched, including nested index entries
+    pub max_sitemaps: usize,
+    /// Seed for the sampling RNG, random when not set
+    pub seed: Option<u64>,
+    /// Only keep URLs whose `lastmod` is at or after this time
+    pub modified_since: Option<DateTime<Utc>>,
+    /// Read sitemaps on private networks, which are refused by default
+    pub allow_private: bool,
+}
+
+impl Default for SitemapConfig {
+    fn default() -> Self {
+        SitemapConfig {
+            max_urls: 10_000,
+            sampling: SamplingStrategy::default(),
+            max_sitemaps: 500,
+            seed: None,
+            modified_since: None,
+            allow_private: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PrefixReservoir {
+    seen: usize,
+    urls: Vec<SitemapUrl>,
+}
+
+/// Collects URLs as they are parsed, keeping memory bounded by the configured cap
+pub struct UrlSampler {
+    max_urls: usize,
+    strategy: SamplingStrategy,
+    rng: StdRng,
+    seen: usize,
+    reservoir: PrefixReservoir,
+    prefixes: BTreeMap<String, PrefixReservoir>,
+    /// URLs held across all prefixes, at most `max_urls`
+    prefixed: usize,
+}
+
+impl UrlSampler {
+    pub fn new(max_urls: usize, strategy: SamplingStrategy, seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        UrlSampler {
+            max_urls,
+            strategy,
+            rng,
+            seen: 0,
+            reservoir: PrefixReservoir::default(),
+            prefixes: BTreeMap::new(),
+            prefixed: 0,
+        }
+    }
+
+    /// Number of URLs offered so far, including those not kept
+    pub fn seen(&self) -> usize {
+        self.seen
+    }
+
+    /// Whether further URLs can no longer change the sample
+    pub fn is_full(&self) -> bool {
+        self.strategy == SamplingStrategy::First && self.reservoir.urls.len() >= self.max_urls
+    }
+
+    pub fn offer(&mut self, url: SitemapUrl) {
+        self.seen += 1;
+        if self.max_urls == 0 {
+            return;
+        }
+
+        match self.strategy {
+            SamplingStrategy::First => {
+                if self.reservoir.urls.len() < self.max_urls {
+                    self.reservoir.urls.push(url);
+                }
+            }
+            SamplingStrategy::Reservoir => {
+                Self::sample_into(&mut self.reservoir, url, self.max_urls, &mut self.rng);
+            }
+            SamplingStrategy::PathPrefix => {
+                let mut prefix = url.path_prefix();
+                if !self.prefixes.contains_key(&prefix) && self.prefixes.len() >= MAX_PATH_PREFIXES
+                {
+                    prefix = OVERFLOW_PREFIX.to_string();
+                }
+                self.prefixes.entry(prefix.clone()).or_default();
+                // Prefixes share the budget: each samples up to an equal share
+                // of it, and the fullest give up random URLs to stay within it
+                let share = self.max_urls.div_ceil(self.prefixes.len());
+                let Some(bucket) = self.prefixes.get_mut(&prefix) else {
+                    return;
+                };
+                let held = bucket.urls.len();
+                Self::sample_into(bucket, url, share, &mut self.rng);
+                self.prefixed += bucket.urls.len() - held;
+                while self.prefixed > self.max_urls {
+                    let Some(fullest) = self.prefixes.values_mut().max_by_key(|b| b.urls.len())
+                    else {
+                        break;
+                    };
+                    let index = self.rng.gen_range(0..fullest.urls.len());
+                    fullest.urls.swap_remove(index);
+                    self.prefixed -= 1;
+                }
+            }
+        }
+    }
+
+    /// Algorithm R: every URL offered to the bucket has an equal chance of being kept
+    fn sample_into(
+        bucket: &mut PrefixReservoir,
+        url: SitemapUrl,
+        capacity: usize,
+        rng: &mut StdRng,
+    ) {
+        bucket.seen += 1;
+        if bucket.urls.len() < capacity {
+            bucket.urls.push(url);
+        } else {
+            let index = rng.gen_range(0..bucket.seen);
+            if index < capacity {
+                bucket.urls[index] = url;
+            }
+        }
+    }
+
+    pub fn into_urls(self) -> Vec<SitemapUrl> {
+        match self.strategy {
+            SamplingStrategy::First | SamplingStrategy::Reservoir => self.reservoir.urls,
+            SamplingStrategy::PathPrefix => {
+                // Interleave sections so a truncated list still covers all of them
+                let mut buckets: Vec<VecDeque<SitemapUrl>> = self
+                    .prefixes
+                    .into_values()
+                    .map(|bucket| bucket.urls.into())
+                    .collect();
+                let mut urls = Vec::new();
+                while urls.len() < self.max_urls && buckets.iter().any(|b| !b.is_empty()) {
+                    for bucket in buckets.iter_mut() {
+                        if urls.len() >= self.max_urls {
+                            break;
+                        }
+                        if let Some(url) = bucket.pop_front() {
+                            urls.push(url);
+                        }
+                    }
+                }
+                urls
+            }
+        }
+    }
+}
+
+pub struct SitemapParser {
+    client: reqwest::Client,
+    config: SitemapConfig,
+}
+
+impl SitemapParser {
+    pub fn new(config: SitemapConfig) -> Self {
+        let client = http_client(Duration::from_secs(30), config.allow_private);
+        SitemapParser { client, config }
+    }
+
+    /// Find sitemap locations on the domain from robots.txt, falling back to
+    /// /sitemap.xml
+    pub async fn discover_sitemaps(&self, domain: &str) -> Vec<String> {
+        let root_url = construct_root_url(domain);
+        let robots_url = format!("{root_url}robots.txt");
+        let host = crawl_host(domain);
+        let mut sitemaps = Vec::new();
+
+        match self.fetch(&robots_url).await {
+            Ok(body) => sitemaps.extend(
+                Self::sitemaps_from_robots(&String::from_utf8_lossy(&body))
+                    .into_iter()
+                    .filter(|sitemap| on_site(sitemap, &host)),
+            ),
+            Err(e) => debug!("Failed to fetch {}: {}", robots_url, e),
+        }
+
+        if sitemaps.is_empty() {
+            sitemaps.push(format!("{root_url}sitemap.xml"));
+        }
+        sitemaps
+    }
+
+    fn sitemaps_from_robots(robots: &str) -> Vec<String> {
+        robots
+            .lines()
+            .filter_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                if key.trim().eq_ignore_ascii_case("sitemap") {
+                    Some(value.trim().to_string())
+                } else {
+                    None
+                }
+            })
+            .filter(|value| !value.is_empty())
+            .collect()
+    }
+
+    /// Stream every sitemap of the domain through a sampler and return the sampled URLs.
+    /// Nested sitemap indexes and gzipped sitemaps are followed transparently,
+    /// each sitemap once. Sitemaps and URLs on other sites are skipped.
+    /// Unless `allow_private`, a domain on a private network is an error and
+    /// sitemaps listed on one are skipped.
+    pub async fn get_all_urls(&self, domain: &str) -> Result<Vec<SitemapUrl>, SitemapError> {
+        if !self.config.allow_private {
+            check_url(&construct_root_url(domain))
+                .await
+                .map_err(SitemapError::PrivateNetwork)?;
+        }
+        let host = crawl_host(domain);
+        let mut pending: VecDeque<String> = self.discover_sitemaps(domain).await.into();
+        let mut visited: HashSet<String> = pending.iter().cloned().collect();
+        let mut sampler =
+            UrlSampler::new(self.config.max_urls, self.config.sampling, self.config.seed);
+        let mut fetched = 0;
+
+        while let Some(sitemap_url) = pending.pop_front() {
+            if sampler.is_full() || fetched >= self.config.max_sitemaps {
+                break;
+            }
+            fetched += 1;
+
+            let body = match self.fetch(&sitemap_url).await {
+                Ok(body) => body,
+                Err(e) => {
+                    warn!("Skipping sitemap {}: {}", sitemap_url, e);
+                    continue;
+                }
+            };
+
+            let mut nested = Vec::new();
+            if let Err(e) = self.parse_sitemap(&body, domain, &mut sampler, &mut nested) {
+                warn!("Failed to parse sitemap {}: {}", sitemap_url, e);
+            }
+            for sitemap in nested {
+                if on_site(&sitemap, &host) && visited.insert(sitemap.clone()) {
+                    pending.push_back(sitemap);
+                }
+            }
+        }
+
+        info!(
+            "Read {} sitemap URLs from {} sitemap(s) for {}",
+            sampler.seen(),
+            fetched,
+            domain
+        );
+        Ok(sampler.into_urls())
+    }
+
+    async fn fetch(&self, url: &str) -> Result<Vec<u8>, SitemapError> {
+        if !self.config.allow_private {
+            check_url(url).await.map_err(SitemapError::PrivateNetwork)?;
+        }
+        let mut response = self.client.get(url).send().await?.error_for_status()?;
+        if response
+            .content_length()
+            .is_some_and(|length| length > MAX_SITEMAP_BYTES)
+        {
+            return Err(SitemapError::TooLarge);
+        }
+        let mut bytes = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if (bytes.len() + chunk.len()) as u64 > MAX_SITEMAP_BYTES {
+                return Err(SitemapError::TooLarge);
+            }
+            bytes.extend_from_slice(&chunk);
+        }
+        Self::decompress(&bytes)
+    }
+
+    /// Decompress gzipped sitemaps, detected by their magic bytes rather than
+    /// the extension. Decompressing stops past the size limit, so a small
+    /// gzip bomb can't fill memory.
+    fn decompress(bytes: &[u8]) -> Result<Vec<u8>, SitemapError> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            let mut decoded = Vec::new();
+            GzDecoder::new(bytes)
+                .take(MAX_SITEMAP_BYTES + 1)
+                .read_to_end(&mut decoded)?;
+            if decoded.len() as u64 > MAX_SITEMAP_BYTES {
+                return Err(SitemapError::TooLarge);
+            }
+            Ok(decoded)
+        } else {
+            Ok(bytes.to_vec())
+        }
+    }
+
+    /// Parse a `<urlset>` or `<sitemapindex>` document event by event, offering
+    /// each URL on `domain` to the sampler and collecting nested sitemap
+    /// locations
+    pub fn parse_sitemap(
+        &self,
+        xml: &[u8],
+        domain: &str,
+        sampler: &mut UrlSampler,
+        nested_sitemaps: &mut Vec<String>,
+    ) -> Result<(), SitemapError> {
+        let host = crawl_host(domain);
+        let mut reader = Reader::from_reader(xml);
+        reader.config_mut().trim_text(true);
+
+        let mut buf = Vec::new();
+        let mut current_url: Option<SitemapUrl> = None;
+        let mut in_sitemap_entry = false;
+        let mut current_field = String::new();
+
+        loop {
+            match reader.read_event_into(&mut buf) {
+                Ok(Event::Start(element)) => {
+                    let name = String::from_utf8_lossy(element.local_name().as_ref()).to_string();
+                    match name.as_str() {
+                        "url" => current_url = Some(SitemapUrl::new(String::new())),
+                        "sitemap" => in_sitemap_entry = true,
+                        _ => {}
+                    }
+                    current_field = name;
+                }
+                Ok(Event::Text(text)) => {
+                    let value = text
+                        .unescape()
+                        .map_err(|e| SitemapError::ParseError(e.to_string()))?
+                        .trim()
+                        .to_string();
+                    Self::set_field(
+                        &current_field,
+                        value,
+                        current_url.as_mut(),
+                        in_sitemap_entry,
+                        nested_sitemaps,
+                    );
+                }
+                Ok(Event::CData(data)) => {
+                    let value = String::from_utf8_lossy(&data.into_inner())
+                        .trim()
+                        .to_string();
+                    Self::set_field(
+                        &current_field,
+                        value,
+                        current_url.as_mut(),
+                        in_sitemap_entry,
+                        nested_sitemaps,
+                    );
+                }
+                Ok(Event::End(element)) => {
+                    match element.local_name().as_ref() {
+                        b"url" => {
+                            if let Some(url) = current_url.take() {
+                                if on_site(&url.loc, &host) && self.is_within_window(&url) {
+                                    sampler.offer(url);
+                                    if sampler.is_full() {
+                                        return Ok(());
+                                    }
+                                }
+                            }
+                        }
+                        b"sitemap" => in_sitemap_entry = false,
+                        _ => {}
+                    }
+                    current_field.clear();
+                }
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(e) => return Err(SitemapError::ParseError(e.to_string())),
+            }
+            buf.clear();
+        }
+
+        Ok(())
+    }
+
+    fn is_within_window(&self, url: &SitemapUrl) -> bool {
+        match &self.config.modified_since {
+            Some(cutoff) => url.modified_since(cutoff),
+            None => true,
+        }
+    }
+
+    fn set_field(
+        field: &str,
+        value: String,
+        current_url: Option<&mut SitemapUrl>,
+        in_sitemap_entry: bool,
+        nested_sitemaps: &mut Vec<String>,
+    ) {
+        if value.is_empty() {
+            return;
+        }
+
+        if let Some(url) = current_url {
+            match field {
+                "loc" => url.loc = value,
+                "lastmod" => url.lastmod = Some(value),
+                "changefreq" => url.changefreq = Some(value),
+                "priority" => url.priority = value.parse().ok(),
+                _ => {}
+            }
+        } else if in_sitemap_entry && field == "loc" {
+            nested_sitemaps.push(value);
+        }
+    }
+}
+
+/// Host of the crawled site, from a domain as `construct_root_url` takes it
+fn crawl_host(domain: &str) -> String {
+    Url::parse(&construct_root_url(domain))
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Whether a URL is on the crawled site or one of its subdomains, as for
+/// the links followed
+fn on_site(url: &str, host: &str) -> bool {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .is_some_and(|url_host| {
+            url_host == host
+                || url_host
+                    .strip_suffix(host)
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+}
+
+impl Default for SitemapParser {
+    fn default() -> Self {
+        Self::new(SitemapConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::collections::HashMap;
+    use std::io::Write;
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+  <url>
+    <loc>https://example.com/blog/first</loc>
+    <lastmod>2024-03-01</lastmod>
+    <changefreq>weekly</changefreq>
+    <priority>0.8</priority>
+  </url>
+  <url><loc><![CDATA[https://example.com/products/1?a=1&b=2]]></loc></url>
+  <url><loc>https://example.com/blog/second?x=1&amp;y=2</loc></url>
+</urlset>"#;
+
+    fn urls(prefix: &str, count: usize) -> Vec<SitemapUrl> {
+        (0..count)
+            .map(|i| SitemapUrl::new(format!("https://example.com/{prefix}/{i}")))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_urlset() {
+        let mut sampler = UrlSampler::new(10, SamplingStrategy::First, None);
+        let mut nested = Vec::new();
+        SitemapParser::default()
+            .parse_sitemap(URLSET.as_bytes(), "example.com", &mut sampler, &mut nested)
+            .unwrap();
+
+        let parsed = sampler.into_urls();
+        assert!(nested.is_empty());
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0].loc, "https://example.com/blog/first");
+        assert_eq!(parsed[0].lastmod.as_deref(), Some("2024-03-01"));
+        assert_eq!(parsed[0].changefreq.as_deref(), Some("weekly"));
+        assert_eq!(parsed[0].priority, Some(0.8));
+        assert_eq!(parsed[1].loc, "https://example.com/products/1?a=1&b=2");
+        assert_eq!(parsed[2].loc, "https://example.com/blog/second?x=1&y=2");
+    }
+
+    #[test]
+    fn test_parse_sitemap_index() {
+        let index = r#"<sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <sitemap><loc>https://example.com/sitemap-1.xml.gz</loc><lastmod>2024-01-01</lastmod></sitemap>
+            <sitemap><loc>https://example.com/sitemap-2.xml</loc></sitemap>
+        </sitemapindex>"#;
+        let mut sampler = UrlSampler::new(10, SamplingStrategy::First, None);
+        let mut nested = Vec::new();
+        SitemapParser::default()
+            .parse_sitemap(index.as_bytes(), "example.com", &mut sampler, &mut nested)
+            .unwrap();
+
+        assert_eq!(sampler.seen(), 0);
+        assert_eq!(
+            nested,
+            vec![
+                "https://example.com/sitemap-1.xml.gz",
+                "https://example.com/sitemap-2.xml"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_first_strategy_stops_at_cap() {
+        let mut sampler = UrlSampler::new(2, SamplingStrategy::First, None);
+        let mut nested = Vec::new();
+        SitemapParser::default()
+            .parse_sitemap(URLSET.as_bytes(), "example.com", &mut sampler, &mut nested)
+            .unwrap();
+
+        assert!(sampler.is_full());
+        assert_eq!(sampler.seen(), 2);
+        assert_eq!(sampler.into_urls().len(), 2);
+    }
+
+    #[test]
+    fn test_reservoir_sampling_is_bounded_and_seeded() {
+        let sample = |seed| {
+            let mut sampler = UrlSampler::new(10, SamplingStrategy::Reservoir, Some(seed));
+            for url in urls("page", 1000) {
+                sampler.offer(url);
+            }
+            assert_eq!(sampler.seen(), 1000);
+            sampler.into_urls()
+        };
+
+        let first = sample(7);
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, sample(7));
+        // A uniform sample of 10 out of 1000 is very unlikely to be the first 10
+        assert!(first.iter().any(|url| {
+            let index: usize = url.loc.rsplit('/').next().unwrap().parse().unwrap();
+            index >= 10
+        }));
+    }
+
+    #[test]
+    fn test_prefix_sampling_covers_every_section() {
+        let mut sampler = UrlSampler::new(6, SamplingStrategy::PathPrefix, Some(1));
+        for url in urls("products", 500)
+            .into_iter()
+            .chain(urls("blog", 3))
+            .chain(urls("team", 1))
+        {
+            sampler.offer(url);
+        }
+
+        let sampled = sampler.into_urls();
+        assert_eq!(sampled.len(), 6);
+        for prefix in ["products", "blog", "team"] {
+            assert!(sampled.iter().any(|url| url.path_prefix() == prefix));
+        }
+    }
+
+    #[test]
+    fn test_prefix_sampling_shares_budget() {
+        let mut sampler = UrlSampler::new(10, SamplingStrategy::PathPrefix, Some(1));
+        for section in 0..100 {
+            for url in urls(&format!("section{section}"), 5) {
+                sampler.offer(url);
+                let held: usize = sampler.prefixes.values().map(|b| b.urls.len()).sum();
+                assert_eq!(held, sampler.prefixed);
+                assert!(held <= 10);
+            }
+        }
+        assert_eq!(sampler.prefixes.len(), MAX_PATH_PREFIXES + 1);
+        assert_eq!(sampler.into_urls().len(), 10);
+    }
+
+    #[test]
+    fn test_parse_lastmod_formats() {
+        let date = parse_lastmod("2024-03-01").unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-03-01T00:00:00+00:00");
+
+        let with_offset = parse_lastmod("2024-03-01T10:30:00+02:00").unwrap();
+        assert_eq!(with_offset.to_rfc3339(), "2024-03-01T08:30:00+00:00");
+
+        let minutes = parse_lastmod("2024-03-01T10:30+01:00").unwrap();
+        assert_eq!(minutes.to_rfc3339(), "2024-03-01T09:30:00+00:00");
+
+        assert!(parse_lastmod("2024-03-01T10:30Z").is_some());
+        assert!(parse_lastmod("yesterday").is_none());
+    }
+
+    #[test]
+    fn test_parse_modified_within() {
+        assert_eq!(
+            parse_modified_within("30d").unwrap(),
+            ChronoDuration::days(30)
+        );
+        assert_eq!(
+            parse_modified_within("2w").unwrap(),
+            ChronoDuration::weeks(2)
+        );
+        assert_eq!(
+            parse_modified_within("12h").unwrap(),
+            ChronoDuration::hours(12)
+        );
+        assert!(parse_modified_within("30").is_err());
+        assert!(parse_modified_within("-1d").is_err());
+        assert!(parse_modified_within("d").is_err());
+    }
+
+    #[test]
+    fn test_lastmod_window_filter() {
+        let parser = SitemapParser::new(SitemapConfig {
+            modified_since: parse_lastmod("2024-02-01"),
+            ..SitemapConfig::default()
+        });
+        let xml = r#"<urlset>
+            <url><loc>https://example.com/new</loc><lastmod>2024-03-01</lastmod></url>
+            <url><loc>https://example.com/old</loc><lastmod>2023-12-01</lastmod></url>
+            <url><loc>https://example.com/unknown</loc></url>
+        </urlset>"#;
+
+        let mut sampler = UrlSampler::new(10, SamplingStrategy::First, None);
+        let mut nested = Vec::new();
+        parser
+            .parse_sitemap(xml.as_bytes(), "example.com", &mut sampler, &mut nested)
+            .unwrap();
+
+        let urls = sampler.into_urls();
+        assert_eq!(urls.len(), 1);
+        assert_eq!(urls[0].loc, "https://example.com/new");
+    }
+
+    #[test]
+    fn test_decompress_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let decoded = SitemapParser::decompress(&compressed).unwrap();
+        assert_eq!(decoded, URLSET.as_bytes());
+        assert_eq!(
+            SitemapParser::decompress(URLSET.as_bytes()).unwrap(),
+            URLSET.as_bytes()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_private_sitemaps() {
+        // `smart-crawler sitemap localhost` requests nothing
+        let parser = SitemapParser::default();
+        assert!(matches!(
+            parser.get_all_urls("localhost").await,
+            Err(SitemapError::PrivateNetwork(_))
+        ));
+        // Sitemaps listed in robots.txt or an index may point anywhere
+        assert!(matches!(
+            parser.fetch("http://169.254.169.254/sitemap.xml").await,
+            Err(SitemapError::PrivateNetwork(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sitemaps_stay_on_the_domain() {
+        use std::sync::{Arc, Mutex};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let site = format!("http://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let files = HashMap::from([
+            (
+                "/robots.txt".to_string(),
+                format!("Sitemap: {site}/index.xml\nSitemap: https://elsewhere.example/sitemap.xml\n"),
+            ),
+            // An index listing itself, a sitemap twice and another site's
+            (
+                "/index.xml".to_string(),
+                format!(
+                    "<sitemapindex><sitemap><loc>{site}/index.xml</loc></sitemap>\
+                     <sitemap><loc>{site}/pages.xml</loc></sitemap>\
+                     <sitemap><loc>{site}/pages.xml</loc></sitemap>\
+                     <sitemap><loc>https://elsewhere.example/other.xml</loc></sitemap></sitemapindex>"
+                ),
+            ),
+            (
+                "/pages.xml".to_string(),
+                format!(
+                    "<urlset><url><loc>{site}/about</loc></url>\
+                     <url><loc>https://elsewhere.example/about</loc></url></urlset>"
+                ),
+            ),
+        ]);
+        {
+            let requested = requested.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let mut request = vec![0; 1024];
+                    let read = socket.read(&mut request).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&request[..read]).to_string();
+                    let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+                    let body = files.get(&path).cloned().unwrap_or_default();
+                    requested.lock().unwrap().push(path);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                }
+            });
+        }
+
+        let parser = SitemapParser::new(SitemapConfig {
+            allow_private: true,
+            ..SitemapConfig::default()
+        });
+        let urls = parser.get_all_urls(&site).await.unwrap();
+        let locs: Vec<&str> = urls.iter().map(|url| url.loc.as_str()).collect();
+        assert_eq!(locs, vec![format!("{site}/about")]);
+        assert_eq!(
+            *requested.lock().unwrap(),
+            vec!["/robots.txt", "/index.xml", "/pages.xml"]
+        );
+    }
+
+    #[test]
+    fn test_decompress_stops_at_limit() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let zeros = vec![0; 1024 * 1024];
+        for _ in 0..=MAX_SITEMAP_BYTES / zeros.len() as u64 {
+            encoder.write_all(&zeros).unwrap();
+        }
+        let bomb = encoder.finish().unwrap();
+        assert!(bomb.len() < 1024 * 1024);
+        assert!(matches!(
+            SitemapParser::decompress(&bomb),
+            Err(SitemapError::TooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_format_sitemap_urls() {
+        let parser = SitemapParser::default();
+        let mut sampler = UrlSampler::new(10, SamplingStrategy::First, None);
+        parser
+            .parse_sitemap(
+                URLSET.as_bytes(),
+                "example.com",
+                &mut sampler,
+                &mut Vec::new(),
+            )
+            .unwrap();
+        let urls = sampler.into_urls();
+
+        let text = format_sitemap_urls(&urls, SitemapFormat::Text).unwrap();
+        assert_eq!(
+            text.lines().next(),
+            Some("https://example.com/blog/first\t2024-03-01\t0.8")
+        );
+        assert_eq!(text.lines().count(), 3);
+
+        let csv = format_sitemap_urls(&urls, SitemapFormat::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("loc,lastmod,changefreq,priority"));
+        assert_eq!(
+            lines.next(),
+            Some("https://example.com/blog/first,2024-03-01,weekly,0.8")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("https://example.com/products/1?a=1&b=2,,,")
+        );
+
+        let json: Vec<SitemapUrl> =
+            serde_json::from_str(&format_sitemap_urls(&urls, SitemapFormat::Json).unwrap())
+                .unwrap();
+        assert_eq!(json, urls);
+    }
+
+    #[test]
+    fn test_sitemaps_from_robots() {
+        let robots = "User-agent: *\nDisallow: /admin\nSitemap: https://example.com/a.xml\nsitemap:https://example.com/b.xml\n";
+        assert_eq!(
+            SitemapParser::sitemaps_from_robots(robots),
+            vec!["https://example.com/a.xml", "https://example.com/b.xml"]
+        );
+
+        let host = crawl_host("example.com");
+        assert!(on_site("https://example.com/a.xml", &host));
+        assert!(on_site("https://blog.example.com/a.xml", &host));
+        assert!(!on_site("https://notexample.com/a.xml", &host));
+        assert!(!on_site("/a.xml", &host));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::html_parser::HtmlNode;
+use crate::storage::StoredHtml;
+use crate::utils::url_to_file_stem;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+/// When `UrlStorage` moves the HTML and trees of finished pages to disk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpillConfig {
+    /// Directory the pages are written to, created when needed
+    pub dir: PathBuf,
+    /// Estimated bytes of HTML and trees kept in memory before the least
+    /// recently used pages are spilled
+    pub max_resident_bytes: usize,
+}
+
+impl SpillConfig {
+    /// A directory of its own under the system temp dir, so concurrent
+    /// crawls don't share files
+    pub fn default_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("smart-crawler-{}", std::process::id()))
+    }
+}
+
+/// The parts of a page moved out of memory
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SpilledPage {
+    pub html_source: Option<StoredHtml>,
+    pub html_tree: Option<HtmlNode>,
+    pub filtered_tree: Option<HtmlNode>,
+}
+
+/// Gzipped JSON files of spilled pages, one per URL. Files are removed when
+/// the store is dropped.
+#[derive(Debug)]
+pub struct SpillStore {
+    config: SpillConfig,
+    written: HashSet<PathBuf>,
+}
+
+impl SpillStore {
+    pub fn new(config: SpillConfig) -> Self {
+        SpillStore {
+            config,
+            written: HashSet::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SpillConfig {
+        &self.config
+    }
+
+    /// File of a URL: its file stem, with a hash of the full URL since
+    /// stems of different URLs can be the same
+    fn path(&self, url: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        url.hash(&mut hasher);
+        self.config.dir.join(format!(
+            "{}-{:016x}.json.gz",
+            url_to_file_stem(url),
+            hasher.finish()
+        ))
+    }
+
+    pub fn write(&mut self, url: &str, page: &SpilledPage) -> Result<(), String> {
+        std::fs::create_dir_all(&self.config.dir)
+            .map_err(|e| format!("Failed to create {}: {e}", self.config.dir.display()))?;
+        let path = self.path(url);
+        let file =
+            File::create(&path).map_err(|e| format!("Failed to create {}: {e}", path.display()))?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::fast());
+        serde_json::to_writer(&mut encoder, page)
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        encoder
+            .finish()
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))?;
+        self.written.insert(path);
+        Ok(())
+    }
+
+    pub fn read(&self, url: &str) -> Result<SpilledPage, String> {
+        let path = self.path(url);
+        let file =
+            File::open(&path).map_err(|e| format!("Failed to open {}: {e}", path.display()))?;
+        serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        let path = self.path(url);
+        if self.written.remove(&path) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for SpillStore {
+    fn drop(&mut self) {
+        for path in self.written.drain() {
+            let _ = std::fs::remove_file(path);
+        }
+        // Only removed when nothing else was put there
+        let _ = std::fs::remove_dir(&self.config.dir);
+    }
+}
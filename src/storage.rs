@@ -1,10 +1,29 @@
-use crate::html_parser::HtmlNode;
+use crate::api_capture::ApiResponse;
+use crate::brand_assets::BrandAsset;
+use crate::entities::{Entity, EntityExtractor};
+use crate::html_parser::{
+    HtmlNode, HtmlParser, RobotsDirectives, StructureHashes, FILTERED_DUPLICATE,
+};
+use crate::keywords::KeywordMatcher;
+use crate::language::DetectedLanguage;
+use crate::page_structure::PageStructure;
+use crate::page_type::PageType;
+use crate::spill::{SpillConfig, SpillStore, SpilledPage};
+use crate::structured_content::StructuredPayload;
+use crate::template_detection::TemplateDetector;
 use crate::utils::extract_domain_from_url;
+use crate::wayback::ArchivedSnapshot;
 use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::hash_map::DefaultHasher;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
-use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::str::FromStr;
+use tracing::{info, warn};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FetchStatus {
@@ -12,6 +31,10 @@ pub enum FetchStatus {
     InProgress,
     Success,
     Failed(String),
+    /// The page didn't load or render within the page timeouts
+    TimedOut,
+    /// Not fetched, with why: too large or not a page, e.g. a zip file
+    Skipped(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,9 +42,64 @@ pub struct UrlData {
     pub url: String,
     pub domain: String,
     pub status: FetchStatus,
-    pub html_source: Option<String>,
+    pub html_source: Option<StoredHtml>,
+    /// `html_source` was cut at `RetentionConfig::max_html_bytes`
+    #[serde(default)]
+    pub html_truncated: bool,
     pub html_tree: Option<HtmlNode>,
+    /// `html_tree` with domain duplicates marked and template values normalized
+    pub filtered_tree: Option<HtmlNode>,
+    /// The HTML and trees were moved to disk to save memory, see
+    /// `UrlStorage::load`
+    #[serde(skip)]
+    pub spilled: bool,
+    /// Hash of the filtered text, see `content_hash`
+    #[serde(default)]
+    pub content_hash: Option<String>,
+    /// An earlier page of the crawl with the same content hash, e.g. for a
+    /// print view or a URL with tracking parameters
+    #[serde(default)]
+    pub duplicate_of: Option<String>,
     pub title: Option<String>,
+    /// Language declared by the page, e.g. "en-us"
+    pub language: Option<String>,
+    /// Language detected from the page text
+    pub detected_language: Option<DetectedLanguage>,
+    /// JSON responses the page fetched while rendering
+    pub api_responses: Vec<ApiResponse>,
+    /// Requests the browser blocked while loading the page
+    #[serde(default)]
+    pub blocked_requests: Option<usize>,
+    /// MIME type the page was served as
+    pub content_type: Option<String>,
+    /// HTTP status the page was served with, where the browser reports it
+    #[serde(default)]
+    pub http_status: Option<u16>,
+    /// Wayback Machine snapshot the content came from, when the live page
+    /// was missing or blocked
+    #[serde(default)]
+    pub archived: Option<ArchivedSnapshot>,
+    /// Encoding the page was decoded with, e.g. "windows-1251"
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// Payload of JSON and CSV URLs, which have no HTML tree
+    pub structured_payload: Option<StructuredPayload>,
+    /// Directives of the page's robots meta tag
+    #[serde(default)]
+    pub robots: RobotsDirectives,
+    /// Breadcrumbs and heading outline
+    #[serde(default)]
+    pub structure: Option<PageStructure>,
+    /// Kind of page, e.g. listing or detail
+    #[serde(default)]
+    pub page_type: Option<PageType>,
+    /// Favicons, logos and share image, collected for the homepage
+    #[serde(default)]
+    pub brand_assets: Vec<BrandAsset>,
+    /// Entities found before PII was redacted, used instead of extracting
+    /// them from the redacted tree
+    #[serde(default)]
+    pub entities: Option<Vec<Entity>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -36,13 +114,58 @@ impl UrlData {
             domain,
             status: FetchStatus::Pending,
             html_source: None,
+            html_truncated: false,
             html_tree: None,
+            filtered_tree: None,
+            spilled: false,
+            content_hash: None,
+            duplicate_of: None,
             title: None,
+            language: None,
+            detected_language: None,
+            api_responses: Vec::new(),
+            blocked_requests: None,
+            content_type: None,
+            http_status: None,
+            archived: None,
+            encoding: None,
+            structured_payload: None,
+            robots: RobotsDirectives::default(),
+            structure: None,
+            page_type: None,
+            brand_assets: Vec::new(),
+            entities: None,
             created_at: now,
             updated_at: now,
         }
     }
 
+    /// Entities of the page as fetched, sorted by ID so reruns list them in
+    /// the same order: those found before PII was redacted, else the ones
+    /// `extractor` finds in the tree. Either way only the kinds `extractor`
+    /// extracts, with the page in their provenance.
+    pub fn extract_entities(&self, extractor: &EntityExtractor) -> Vec<Entity> {
+        let mut entities = match (&self.entities, &self.html_tree) {
+            (Some(entities), _) => entities.clone(),
+            (None, Some(tree)) => self.stamp_entities(extractor.extract_from_tree(tree)),
+            (None, None) => Vec::new(),
+        };
+        entities.retain(|entity| extractor.extracts(entity.kind));
+        entities.sort_by(|a, b| a.id.cmp(&b.id));
+        entities
+    }
+
+    /// Set the page URL and extraction time in the provenance of entities
+    /// just extracted from the page
+    pub fn stamp_entities(&self, mut entities: Vec<Entity>) -> Vec<Entity> {
+        let now = Utc::now();
+        for provenance in entities.iter_mut().filter_map(|e| e.provenance.as_mut()) {
+            provenance.url = self.url.clone();
+            provenance.extracted_at = Some(now);
+        }
+        entities
+    }
+
     pub fn update_status(&mut self, status: FetchStatus) {
         self.status = status;
         self.updated_at = Utc::now();
@@ -54,17 +177,246 @@ impl UrlData {
         html_tree: HtmlNode,
         title: Option<String>,
     ) {
-        self.html_source = Some(html_source);
+        self.html_source = Some(StoredHtml::Plain(html_source));
         self.html_tree = Some(html_tree);
         self.title = title;
         self.updated_at = Utc::now();
     }
+
+    pub fn set_structured_data(&mut self, payload: StructuredPayload) {
+        self.content_type = Some(payload.content_type.clone());
+        self.structured_payload = Some(payload);
+        self.updated_at = Utc::now();
+    }
+
+    /// Estimated memory held by the raw HTML and trees
+    pub fn resident_bytes(&self) -> usize {
+        let html = match &self.html_source {
+            Some(StoredHtml::Plain(html)) => html.len(),
+            Some(StoredHtml::Gzip { gzip }) => gzip.len(),
+            None => 0,
+        };
+        html + [&self.html_tree, &self.filtered_tree]
+            .into_iter()
+            .flatten()
+            .map(HtmlNode::estimated_bytes)
+            .sum::<usize>()
+    }
+
+    fn take_spillable(&mut self) -> SpilledPage {
+        SpilledPage {
+            html_source: self.html_source.take(),
+            html_tree: self.html_tree.take(),
+            filtered_tree: self.filtered_tree.take(),
+        }
+    }
+
+    fn restore(&mut self, page: SpilledPage) {
+        self.html_source = page.html_source;
+        self.html_tree = page.html_tree;
+        self.filtered_tree = page.filtered_tree;
+        self.spilled = false;
+    }
+
+    /// The raw HTML, decompressed if needed
+    pub fn html(&self) -> Option<Cow<'_, str>> {
+        self.html_source.as_ref()?.text()
+    }
+
+    /// Drop, cut or compress the raw HTML of a page that is done, as the
+    /// policy says. Parsed trees are kept either way.
+    pub fn apply_retention(&mut self, config: &RetentionConfig) {
+        let keep = match config.keep_html {
+            HtmlRetention::None => false,
+            HtmlRetention::FailedOnly => {
+                matches!(self.status, FetchStatus::Failed(_) | FetchStatus::TimedOut)
+            }
+            HtmlRetention::All => true,
+        };
+        if !keep {
+            self.html_source = None;
+            return;
+        }
+        let Some(StoredHtml::Plain(html)) = &mut self.html_source else {
+            return;
+        };
+        if let Some(max_bytes) = config.max_html_bytes {
+            if html.len() > max_bytes {
+                let mut end = max_bytes;
+                while !html.is_char_boundary(end) {
+                    end -= 1;
+                }
+                html.truncate(end);
+                self.html_truncated = true;
+            }
+        }
+        if config.compress_html {
+            self.html_source = Some(StoredHtml::compress(html));
+        }
+    }
+}
+
+/// Hash of a page's text once boilerplate is filtered out, ignoring case
+/// and whitespace, so pages showing the same content get the same hash.
+/// `None` for pages without text.
+pub fn content_hash(filtered_tree: &HtmlNode) -> Option<String> {
+    let text = filtered_tree
+        .text_content()
+        .replace(FILTERED_DUPLICATE, " ")
+        .to_lowercase();
+    if text.trim().is_empty() {
+        return None;
+    }
+    // FNV-1a, which unlike the std hasher stays the same across builds
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for word in text.split_whitespace() {
+        for byte in word.bytes().chain([b' ']) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    Some(format!("{hash:016x}"))
+}
+
+/// Which pages keep their raw HTML once processed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HtmlRetention {
+    None,
+    /// Only pages that failed, for debugging them
+    FailedOnly,
+    #[default]
+    All,
+}
+
+impl FromStr for HtmlRetention {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(HtmlRetention::None),
+            "failed-only" => Ok(HtmlRetention::FailedOnly),
+            "all" => Ok(HtmlRetention::All),
+            other => Err(format!(
+                "Invalid HTML retention: {other} (expected none, failed-only or all)"
+            )),
+        }
+    }
+}
+
+/// How much raw HTML a crawl holds on to
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionConfig {
+    pub keep_html: HtmlRetention,
+    /// Gzip kept HTML
+    pub compress_html: bool,
+    /// Cut kept HTML to this many bytes
+    pub max_html_bytes: Option<usize>,
+}
+
+/// Raw HTML of a page, as text or gzipped. Serialized as a string, or as
+/// `{"gzip": "<base64>"}` when compressed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StoredHtml {
+    Plain(String),
+    Gzip {
+        #[serde(with = "base64_bytes")]
+        gzip: Vec<u8>,
+    },
+}
+
+impl StoredHtml {
+    pub fn compress(html: &str) -> Self {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        // Writing to a Vec can't fail
+        let _ = encoder.write_all(html.as_bytes());
+        StoredHtml::Gzip {
+            gzip: encoder.finish().unwrap_or_default(),
+        }
+    }
+
+    /// The HTML text; `None` when compressed data is corrupt
+    pub fn text(&self) -> Option<Cow<'_, str>> {
+        match self {
+            StoredHtml::Plain(html) => Some(Cow::Borrowed(html)),
+            StoredHtml::Gzip { gzip } => {
+                let mut html = String::new();
+                GzDecoder::new(gzip.as_slice())
+                    .read_to_string(&mut html)
+                    .ok()?;
+                Some(Cow::Owned(html))
+            }
+        }
+    }
+}
+
+mod base64_bytes {
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Knobs controlling which nodes are flagged as domain-level duplicates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DedupConfig {
+    /// Minimum number of fetched pages required before anything is flagged
+    pub min_pages: usize,
+    /// Fraction of analyzed pages a node must appear on to be a duplicate
+    pub min_page_fraction: f64,
+    /// Absolute minimum number of pages a node must appear on
+    pub min_page_count: usize,
+    /// Repeated blocks with more text than this, in characters, are kept
+    /// with everything in them: that much shared text is more likely content
+    /// (a description shared by product variants) than a menu or footer
+    pub max_duplicate_chars: usize,
+    /// Duplicates whose text mentions any of these keywords are kept
+    pub keep_keywords: KeywordMatcher,
+}
+
+impl DedupConfig {
+    /// Number of pages a node must appear on, scaled to the number of pages analyzed
+    pub fn page_threshold(&self, pages_analyzed: usize) -> usize {
+        let proportional = (pages_analyzed as f64 * self.min_page_fraction).ceil() as usize;
+        proportional.max(self.min_page_count)
+    }
+
+    fn mentions_keyword(&self, text: &str) -> bool {
+        self.keep_keywords.matches(text)
+    }
+}
+
+impl Default for DedupConfig {
+    fn default() -> Self {
+        DedupConfig {
+            min_pages: 3,
+            min_page_fraction: 0.6,
+            min_page_count: 2,
+            max_duplicate_chars: 2000,
+            keep_keywords: KeywordMatcher::default(),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct UrlStorage {
     urls_by_domain: HashMap<String, HashMap<String, UrlData>>,
     domain_duplicates: HashMap<String, DomainDuplicates>,
+    dedup_config: DedupConfig,
+    retention_config: RetentionConfig,
+    spill: Option<SpillStore>,
+    /// Finished pages holding HTML or trees in memory with their estimated
+    /// size, least recently used first. Only tracked when spilling.
+    resident: Vec<(String, usize)>,
 }
 
 impl UrlStorage {
@@ -72,6 +424,174 @@ impl UrlStorage {
         UrlStorage {
             urls_by_domain: HashMap::new(),
             domain_duplicates: HashMap::new(),
+            dedup_config: DedupConfig::default(),
+            retention_config: RetentionConfig::default(),
+            spill: None,
+            resident: Vec::new(),
+        }
+    }
+
+    pub fn with_dedup_config(dedup_config: DedupConfig) -> Self {
+        UrlStorage {
+            dedup_config,
+            ..Self::new()
+        }
+    }
+
+    pub fn dedup_config(&self) -> &DedupConfig {
+        &self.dedup_config
+    }
+
+    pub fn set_retention_config(&mut self, retention_config: RetentionConfig) {
+        self.retention_config = retention_config;
+    }
+
+    /// Move the HTML and trees of finished pages to disk once they take more
+    /// memory than the config allows, least recently used first. Spilled
+    /// pages are read back by `load` and `get_url_data_mut`.
+    pub fn set_spill_config(&mut self, config: SpillConfig) {
+        self.spill = Some(SpillStore::new(config));
+        self.refresh_resident();
+    }
+
+    /// Estimated bytes of HTML and trees held in memory by finished pages,
+    /// when spilling
+    pub fn resident_bytes(&self) -> usize {
+        self.resident.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    fn find_mut<'a>(
+        urls_by_domain: &'a mut HashMap<String, HashMap<String, UrlData>>,
+        url: &str,
+    ) -> Option<&'a mut UrlData> {
+        let domain = extract_domain_from_url(url)?;
+        urls_by_domain.get_mut(&domain)?.get_mut(url)
+    }
+
+    /// Record a page as the most recently used, with its current size
+    fn track(&mut self, url: &str) {
+        self.resident
+            .retain(|(resident_url, _)| resident_url != url);
+        let bytes = self.get_url_data(url).map_or(0, UrlData::resident_bytes);
+        if bytes > 0 {
+            self.resident.push((url.to_string(), bytes));
+        }
+    }
+
+    /// Re-measure resident pages, keeping their order, and spill what
+    /// doesn't fit
+    fn refresh_resident(&mut self) {
+        let urls: Vec<String> = self.resident.iter().map(|(url, _)| url.clone()).collect();
+        for url in urls {
+            self.track(&url);
+        }
+        self.enforce_memory_cap(None);
+    }
+
+    /// Spill least recently used pages, except `keep`, until the resident
+    /// ones fit in the configured memory
+    fn enforce_memory_cap(&mut self, keep: Option<&str>) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        let max_bytes = spill.config().max_resident_bytes;
+        let mut total: usize = self.resident.iter().map(|(_, bytes)| bytes).sum();
+        let mut index = 0;
+        while total > max_bytes && index < self.resident.len() {
+            let (url, bytes) = self.resident[index].clone();
+            let Some(url_data) = Self::find_mut(&mut self.urls_by_domain, &url) else {
+                self.resident.remove(index);
+                continue;
+            };
+            if keep == Some(url.as_str()) {
+                index += 1;
+                continue;
+            }
+            let page = url_data.take_spillable();
+            match spill.write(&url, &page) {
+                Ok(()) => {
+                    url_data.spilled = true;
+                    total -= bytes;
+                    self.resident.remove(index);
+                }
+                Err(e) => {
+                    warn!("Failed to spill {} to disk: {}", url, e);
+                    url_data.restore(page);
+                    index += 1;
+                }
+            }
+        }
+    }
+
+    /// Bring a spilled page back into memory, spilling others to make room
+    fn reload(&mut self, url: &str) {
+        let Some(spill) = &mut self.spill else {
+            return;
+        };
+        let Some(url_data) = Self::find_mut(&mut self.urls_by_domain, url) else {
+            return;
+        };
+        if !url_data.spilled {
+            return;
+        }
+        match spill.read(url) {
+            Ok(page) => {
+                url_data.restore(page);
+                spill.remove(url);
+            }
+            Err(e) => {
+                warn!("Failed to reload {} from disk: {}", url, e);
+                return;
+            }
+        }
+        self.track(url);
+        self.enforce_memory_cap(Some(url));
+    }
+
+    /// A page with its HTML and trees, read back from disk if they were
+    /// spilled. The spilled copy stays on disk, so going through every
+    /// page this way doesn't grow memory.
+    pub fn load<'a>(&self, url_data: &'a UrlData) -> Cow<'a, UrlData> {
+        let Some(spill) = self.spill.as_ref().filter(|_| url_data.spilled) else {
+            return Cow::Borrowed(url_data);
+        };
+        match spill.read(&url_data.url) {
+            Ok(page) => {
+                let mut loaded = url_data.clone();
+                loaded.restore(page);
+                Cow::Owned(loaded)
+            }
+            Err(e) => {
+                warn!("Failed to reload {} from disk: {}", url_data.url, e);
+                Cow::Borrowed(url_data)
+            }
+        }
+    }
+
+    /// Set a URL's status, applying the retention policy to its HTML once
+    /// it succeeded, failed or timed out, then spilling pages to disk if
+    /// they take too much memory
+    pub fn update_status(&mut self, url: &str, status: FetchStatus) {
+        let Some(domain) = extract_domain_from_url(url) else {
+            return;
+        };
+        let Some(url_data) = self
+            .urls_by_domain
+            .get_mut(&domain)
+            .and_then(|domain_urls| domain_urls.get_mut(url))
+        else {
+            return;
+        };
+        url_data.update_status(status);
+        if matches!(
+            url_data.status,
+            FetchStatus::Success | FetchStatus::Failed(_) | FetchStatus::TimedOut
+        ) {
+            url_data.apply_retention(&self.retention_config);
+            if self.spill.is_some() {
+                self.track(url);
+                self.enforce_memory_cap(Some(url));
+            }
         }
     }
 
@@ -88,25 +608,33 @@ impl UrlStorage {
         }
     }
 
+    /// The page of a URL. Spilled pages come without their HTML and trees,
+    /// see `load`.
     pub fn get_url_data(&self, url: &str) -> Option<&UrlData> {
         let domain = extract_domain_from_url(url)?;
         self.urls_by_domain.get(&domain)?.get(url)
     }
 
+    /// The page of a URL, with its HTML and trees brought back into memory
+    /// if they were spilled
     pub fn get_url_data_mut(&mut self, url: &str) -> Option<&mut UrlData> {
-        let domain = extract_domain_from_url(url)?;
-        self.urls_by_domain.get_mut(&domain)?.get_mut(url)
+        self.reload(url);
+        Self::find_mut(&mut self.urls_by_domain, url)
     }
 
     pub fn get_urls_by_domain(&self, domain: &str) -> Option<&HashMap<String, UrlData>> {
         self.urls_by_domain.get(domain)
     }
 
+    /// All URLs, sorted so results don't depend on hash order
     pub fn get_all_urls(&self) -> Vec<&UrlData> {
-        self.urls_by_domain
+        let mut urls: Vec<&UrlData> = self
+            .urls_by_domain
             .values()
             .flat_map(|domain_urls| domain_urls.values())
-            .collect()
+            .collect();
+        urls.sort_by(|a, b| a.url.cmp(&b.url));
+        urls
     }
 
     pub fn get_completed_urls(&self) -> Vec<&UrlData> {
@@ -117,50 +645,189 @@ impl UrlStorage {
     }
 
     pub fn analyze_domain_duplicates(&mut self, domain: &str) {
-        if let Some(domain_urls) = self.urls_by_domain.get(domain) {
-            let completed_urls: Vec<_> = domain_urls
+        if let Some(domain_urls) = self.urls_by_domain.get_mut(domain) {
+            let spilled_urls: Vec<String> = domain_urls
+                .values()
+                .filter(|url_data| {
+                    url_data.spilled && matches!(url_data.status, FetchStatus::Success)
+                })
+                .map(|url_data| url_data.url.clone())
+                .collect();
+            let html_trees: Vec<&HtmlNode> = domain_urls
                 .values()
                 .filter(|url_data| matches!(url_data.status, FetchStatus::Success))
+                .filter_map(|url_data| url_data.html_tree.as_ref())
                 .collect();
 
-            if completed_urls.len() < 2 {
-                return; // Need at least 2 pages to find duplicates
+            let pages_analyzed = html_trees.len() + spilled_urls.len();
+            if pages_analyzed < self.dedup_config.min_pages.max(2) {
+                return; // Need enough pages to tell templates from content
             }
 
-            let mut node_occurrence_count: HashMap<NodeSignature, usize> = HashMap::new();
+            // Hash each tree bottom-up once, then collect the set of node signatures
+            // on each page in parallel and count how many pages each one appears on
+            // Large blocks are collected too, to keep them whole if they repeat
+            let max_chars = self.dedup_config.max_duplicate_chars;
+            let tree_hashes: Vec<StructureHashes> = html_trees
+                .par_iter()
+                .map(|html_tree| StructureHashes::new(html_tree))
+                .collect();
+            let (mut page_counts, mut large) = html_trees
+                .par_iter()
+                .zip(&tree_hashes)
+                .map(|(html_tree, hashes)| {
+                    let mut signatures = HashSet::new();
+                    let mut large = HashSet::new();
+                    Self::collect_node_signatures(
+                        html_tree,
+                        hashes,
+                        max_chars,
+                        &mut signatures,
+                        &mut large,
+                    );
+                    (signatures, large)
+                })
+                .fold(
+                    || (HashMap::new(), HashSet::new()),
+                    |(mut total, mut all_large), (page_signatures, page_large)| {
+                        for signature in page_signatures {
+                            *total.entry(signature).or_insert(0) += 1;
+                        }
+                        all_large.extend(page_large);
+                        (total, all_large)
+                    },
+                )
+                .reduce(
+                    || (HashMap::new(), HashSet::new()),
+                    |(mut total, mut all_large),
+                     (partial, partial_large): (HashMap<NodeSignature, usize>, HashSet<_>)| {
+                        for (signature, count) in partial {
+                            *total.entry(signature).or_insert(0) += count;
+                        }
+                        all_large.extend(partial_large);
+                        (total, all_large)
+                    },
+                );
+            // Spilled trees are read back one at a time, so they never all
+            // sit in memory together
+            for url in &spilled_urls {
+                let Some(html_tree) = Self::read_spilled_tree(self.spill.as_ref(), url) else {
+                    continue;
+                };
+                let hashes = StructureHashes::new(&html_tree);
+                let mut signatures = HashSet::new();
+                Self::collect_node_signatures(
+                    &html_tree,
+                    &hashes,
+                    max_chars,
+                    &mut signatures,
+                    &mut large,
+                );
+                for signature in signatures {
+                    *page_counts.entry(signature).or_insert(0) += 1;
+                }
+            }
 
-            // Count occurrences of each node signature across all pages
-            for url_data in &completed_urls {
-                if let Some(html_tree) = &url_data.html_tree {
-                    Self::collect_node_signatures(html_tree, &mut node_occurrence_count);
+            // Nodes in large blocks present on enough pages are kept, as the
+            // blocks themselves are
+            let threshold = self.dedup_config.page_threshold(pages_analyzed);
+            large.retain(|signature| {
+                page_counts
+                    .get(signature)
+                    .is_some_and(|&count| count >= threshold)
+            });
+            let mut kept = HashSet::new();
+            if !large.is_empty() {
+                for (html_tree, hashes) in html_trees.iter().zip(&tree_hashes) {
+                    Self::collect_block_signatures(html_tree, hashes, &large, false, &mut kept);
+                }
+                for url in &spilled_urls {
+                    if let Some(html_tree) = Self::read_spilled_tree(self.spill.as_ref(), url) {
+                        let hashes = StructureHashes::new(&html_tree);
+                        Self::collect_block_signatures(
+                            &html_tree, &hashes, &large, false, &mut kept,
+                        );
+                    }
                 }
             }
 
-            // Mark nodes that appear in 2 or more pages as duplicates
+            // Mark nodes present on enough of the analyzed pages as duplicates,
+            // unless they mention something the crawl is looking for
             let domain_duplicates = self
                 .domain_duplicates
                 .entry(domain.to_string())
                 .or_default();
-            for (signature, count) in node_occurrence_count {
-                if count >= 2 {
+            for (signature, count) in page_counts {
+                if count >= threshold
+                    && !kept.contains(&signature)
+                    && !self.dedup_config.mentions_keyword(&signature.content)
+                {
                     domain_duplicates.add_duplicate_node(signature);
                 }
             }
         }
     }
 
-    fn collect_node_signatures(node: &HtmlNode, signatures: &mut HashMap<NodeSignature, usize>) {
+    /// A spilled page's tree, None when it can't be read back
+    fn read_spilled_tree(spill: Option<&SpillStore>, url: &str) -> Option<HtmlNode> {
+        match spill?.read(url) {
+            Ok(SpilledPage {
+                html_tree: Some(html_tree),
+                ..
+            }) => Some(html_tree),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Failed to reload {} from disk: {}", url, e);
+                None
+            }
+        }
+    }
+
+    /// Collect the signatures of a tree's nodes, and separately those of
+    /// nodes with more than `max_chars` of text. Returns the node's text length.
+    fn collect_node_signatures<'a>(
+        node: &'a HtmlNode,
+        hashes: &StructureHashes<'a>,
+        max_chars: usize,
+        signatures: &mut HashSet<NodeSignature>,
+        large: &mut HashSet<NodeSignature>,
+    ) -> usize {
+        let mut text_chars = node.content.chars().count();
+        for child in &node.children {
+            text_chars +=
+                Self::collect_node_signatures(child, hashes, max_chars, signatures, large);
+        }
+
         // Skip structural/container elements that naturally appear on every page
         if !Self::is_structural_element(&node.tag) {
-            let signature = NodeSignature::from_html_node(node);
+            let signature = NodeSignature::with_hash(node, hashes.get(node));
             // Only count nodes with meaningful content or specific styling
             if Self::is_meaningful_node(node) {
-                *signatures.entry(signature).or_insert(0) += 1;
+                if text_chars > max_chars {
+                    large.insert(signature.clone());
+                }
+                signatures.insert(signature);
             }
         }
+        text_chars
+    }
 
+    /// Collect the signatures of `blocks` found in a tree and of every node
+    /// in them
+    fn collect_block_signatures<'a>(
+        node: &'a HtmlNode,
+        hashes: &StructureHashes<'a>,
+        blocks: &HashSet<NodeSignature>,
+        in_block: bool,
+        signatures: &mut HashSet<NodeSignature>,
+    ) {
+        let signature = NodeSignature::with_hash(node, hashes.get(node));
+        let in_block = in_block || blocks.contains(&signature);
+        if in_block {
+            signatures.insert(signature);
+        }
         for child in &node.children {
-            Self::collect_node_signatures(child, signatures);
+            Self::collect_block_signatures(child, hashes, blocks, in_block, signatures);
         }
     }
 
@@ -194,6 +861,85 @@ impl UrlStorage {
             )
     }
 
+    /// Build the filtered tree of every fetched page in the domain by marking
+    /// domain duplicates and normalizing template values like "42 comments",
+    /// then mark pages whose filtered content repeats an earlier page's
+    pub fn filter_domain_pages(&mut self, domain: &str, template_detector: &TemplateDetector) {
+        let empty_duplicates = DomainDuplicates::new();
+        let domain_duplicates = self
+            .domain_duplicates
+            .get(domain)
+            .unwrap_or(&empty_duplicates);
+
+        if let Some(domain_urls) = self.urls_by_domain.get_mut(domain) {
+            domain_urls
+                .values_mut()
+                .filter(|url_data| matches!(url_data.status, FetchStatus::Success))
+                .for_each(|url_data| {
+                    url_data.filtered_tree = url_data.html_tree.as_ref().map(|html_tree| {
+                        let filtered =
+                            HtmlParser::filter_domain_duplicates(html_tree, domain_duplicates);
+                        template_detector.normalize_tree(&filtered)
+                    });
+                    url_data.content_hash = url_data.filtered_tree.as_ref().and_then(content_hash);
+                });
+
+            // Spilled pages are filtered on disk
+            if let Some(spill) = &mut self.spill {
+                for url_data in domain_urls.values_mut().filter(|url_data| {
+                    url_data.spilled && matches!(url_data.status, FetchStatus::Success)
+                }) {
+                    let result = spill.read(&url_data.url).and_then(|mut page| {
+                        page.filtered_tree = page.html_tree.as_ref().map(|html_tree| {
+                            let filtered =
+                                HtmlParser::filter_domain_duplicates(html_tree, domain_duplicates);
+                            template_detector.normalize_tree(&filtered)
+                        });
+                        url_data.content_hash = page.filtered_tree.as_ref().and_then(content_hash);
+                        spill.write(&url_data.url, &page)
+                    });
+                    if let Err(e) = result {
+                        warn!("Failed to filter spilled page {}: {}", url_data.url, e);
+                    }
+                }
+            }
+
+            Self::mark_content_duplicates(domain_urls);
+        }
+        if self.spill.is_some() {
+            self.refresh_resident();
+        }
+    }
+
+    /// Point each page at the first page, in discovery order, with the same
+    /// content hash
+    fn mark_content_duplicates(domain_urls: &mut HashMap<String, UrlData>) {
+        let mut pages: Vec<&mut UrlData> = domain_urls
+            .values_mut()
+            .filter(|url_data| matches!(url_data.status, FetchStatus::Success))
+            .collect();
+        pages.sort_by(|a, b| (a.created_at, &a.url).cmp(&(b.created_at, &b.url)));
+        let mut first_with_hash: HashMap<String, String> = HashMap::new();
+        for url_data in pages {
+            url_data.duplicate_of = None;
+            let Some(hash) = &url_data.content_hash else {
+                continue;
+            };
+            match first_with_hash.get(hash) {
+                Some(first) => {
+                    info!(
+                        "{} has the same content as {}, deduplicated",
+                        url_data.url, first
+                    );
+                    url_data.duplicate_of = Some(first.clone());
+                }
+                None => {
+                    first_with_hash.insert(hash.clone(), url_data.url.clone());
+                }
+            }
+        }
+    }
+
     pub fn get_domain_duplicates(&self, domain: &str) -> Option<&DomainDuplicates> {
         self.domain_duplicates.get(domain)
     }
@@ -216,6 +962,21 @@ mod tests {
         assert!(storage.add_url("https://example.com".to_string()));
         assert!(!storage.add_url("https://example.com".to_string())); // duplicate
         assert!(storage.add_url("https://example.org".to_string()));
+        assert!(storage.add_url("https://example.com/about".to_string()));
+
+        let urls: Vec<&str> = storage
+            .get_all_urls()
+            .iter()
+            .map(|url_data| url_data.url.as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com",
+                "https://example.com/about",
+                "https://example.org"
+            ]
+        );
     }
 
     #[test]
@@ -257,6 +1018,65 @@ mod tests {
         assert!(url_data.updated_at > original_time);
     }
 
+    #[test]
+    fn test_html_retention() {
+        let html = format!("<html><body>{}</body></html>", "é".repeat(1000));
+        let mut storage = UrlStorage::new();
+        storage.set_retention_config(RetentionConfig {
+            keep_html: HtmlRetention::All,
+            compress_html: true,
+            max_html_bytes: Some(101),
+        });
+        for url in ["https://example.com/a", "https://example.com/b"] {
+            storage.add_url(url.to_string());
+            let url_data = storage.get_url_data_mut(url).unwrap();
+            let tree = HtmlNode::new("html".to_string(), Vec::new(), None, String::new());
+            url_data.set_html_data(html.clone(), tree, None);
+        }
+
+        // Kept HTML stays plain until the page is done
+        storage.update_status("https://example.com/a", FetchStatus::InProgress);
+        let url_data = storage.get_url_data("https://example.com/a").unwrap();
+        assert_eq!(url_data.html_source, Some(StoredHtml::Plain(html.clone())));
+
+        storage.update_status("https://example.com/a", FetchStatus::Success);
+        let url_data = storage.get_url_data("https://example.com/a").unwrap();
+        assert!(matches!(
+            url_data.html_source,
+            Some(StoredHtml::Gzip { .. })
+        ));
+        assert!(url_data.html_truncated);
+        // Cut back to a character boundary
+        assert_eq!(url_data.html().unwrap(), &html[..100]);
+
+        let json = serde_json::to_string(url_data).unwrap();
+        assert!(json.contains(r#""html_source":{"gzip":""#));
+        let restored: UrlData = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.html().unwrap(), &html[..100]);
+
+        storage.set_retention_config(RetentionConfig {
+            keep_html: HtmlRetention::FailedOnly,
+            ..RetentionConfig::default()
+        });
+        storage.update_status("https://example.com/b", FetchStatus::Success);
+        assert!(storage
+            .get_url_data("https://example.com/b")
+            .unwrap()
+            .html_source
+            .is_none());
+
+        let mut failed = UrlData::new("https://example.com/c".to_string());
+        failed.html_source = Some(StoredHtml::Plain(html.clone()));
+        failed.update_status(FetchStatus::Failed("timeout".to_string()));
+        failed.apply_retention(&RetentionConfig {
+            keep_html: HtmlRetention::FailedOnly,
+            ..RetentionConfig::default()
+        });
+        assert_eq!(failed.html().as_deref(), Some(html.as_str()));
+        assert_eq!("failed-only".parse(), Ok(HtmlRetention::FailedOnly));
+        assert!("some".parse::<HtmlRetention>().is_err());
+    }
+
     #[test]
     fn test_add_urls_from_same_domain() {
         let mut storage = UrlStorage::new();
@@ -277,7 +1097,7 @@ mod tests {
     fn test_analyze_domain_duplicates() {
         use crate::html_parser::HtmlParser;
 
-        let mut storage = UrlStorage::new();
+        let mut storage = two_page_storage();
         let parser = HtmlParser::new();
 
         storage.add_url("https://example.com/page1".to_string());
@@ -309,6 +1129,223 @@ mod tests {
         assert!(duplicates.unwrap().get_duplicate_count() > 0);
     }
 
+    /// Storage flagging nodes shared by as few as two pages
+    fn two_page_storage() -> UrlStorage {
+        UrlStorage::with_dedup_config(DedupConfig {
+            min_pages: 2,
+            ..DedupConfig::default()
+        })
+    }
+
+    fn store_page(storage: &mut UrlStorage, url: &str, html: &str) {
+        storage.add_url(url.to_string());
+        let tree = HtmlParser::new().parse(html);
+        if let Some(url_data) = storage.get_url_data_mut(url) {
+            url_data.set_html_data(html.to_string(), tree, None);
+            url_data.update_status(FetchStatus::Success);
+        }
+    }
+
+    #[test]
+    fn test_dedup_threshold_scales_with_pages() {
+        let config = DedupConfig::default();
+        assert_eq!(config.page_threshold(2), 2);
+        assert_eq!(config.page_threshold(3), 2);
+        assert_eq!(config.page_threshold(5), 3);
+        assert_eq!(config.page_threshold(10), 6);
+    }
+
+    #[test]
+    fn test_duplicates_counted_per_page() {
+        let mut storage = two_page_storage();
+
+        // The promo block repeats within page 1 but never appears on page 2
+        store_page(
+            &mut storage,
+            "https://example.com/a",
+            r#"<html><body><nav>Menu</nav><p class="promo">Sale</p><p class="promo">Sale</p></body></html>"#,
+        );
+        store_page(
+            &mut storage,
+            "https://example.com/b",
+            r#"<html><body><nav>Menu</nav><p>Other</p></body></html>"#,
+        );
+
+        storage.analyze_domain_duplicates("example.com");
+        let duplicates = storage.get_domain_duplicates("example.com").unwrap();
+
+        let promo = HtmlNode::new(
+            "p".to_string(),
+            vec!["promo".to_string()],
+            None,
+            "Sale".to_string(),
+        );
+        let nav = HtmlNode::new("nav".to_string(), vec![], None, "Menu".to_string());
+        assert!(!duplicates.is_duplicate(&NodeSignature::from_html_node(&promo)));
+        assert!(duplicates.is_duplicate(&NodeSignature::from_html_node(&nav)));
+    }
+
+    #[test]
+    fn test_dedup_keeps_keyword_nodes() {
+        let config = DedupConfig {
+            keep_keywords: KeywordMatcher::new(&["pricing"]),
+            ..DedupConfig::default()
+        };
+        let mut storage = UrlStorage::with_dedup_config(config);
+
+        let html = r#"<html><body><nav>Menu</nav><aside>See our Pricing</aside></body></html>"#;
+        store_page(&mut storage, "https://example.com/a", html);
+        store_page(&mut storage, "https://example.com/b", html);
+        store_page(&mut storage, "https://example.com/c", html);
+
+        storage.analyze_domain_duplicates("example.com");
+        let duplicates = storage.get_domain_duplicates("example.com").unwrap();
+
+        let nav = HtmlNode::new("nav".to_string(), vec![], None, "Menu".to_string());
+        let aside = HtmlNode::new(
+            "aside".to_string(),
+            vec![],
+            None,
+            "See our Pricing".to_string(),
+        );
+        assert!(duplicates.is_duplicate(&NodeSignature::from_html_node(&nav)));
+        assert!(!duplicates.is_duplicate(&NodeSignature::from_html_node(&aside)));
+    }
+
+    #[test]
+    fn test_two_pages_keep_shared_content() {
+        let mut storage = UrlStorage::new();
+        let html = r#"<html><body><nav>Menu</nav><p>Shared</p></body></html>"#;
+        store_page(&mut storage, "https://example.com/a", html);
+        store_page(&mut storage, "https://example.com/b", html);
+
+        storage.analyze_domain_duplicates("example.com");
+        assert!(storage.get_domain_duplicates("example.com").is_none());
+    }
+
+    #[test]
+    fn test_dedup_keeps_large_blocks() {
+        let mut storage = UrlStorage::new();
+        let description = "A very long product description. ".repeat(100);
+        let html = format!(
+            "<html><body><nav>Menu</nav><div class=\"details\"><p>{description}</p><p>Ships in 2 days</p></div></body></html>"
+        );
+        for url in [
+            "https://example.com/a",
+            "https://example.com/b",
+            "https://example.com/c",
+        ] {
+            store_page(&mut storage, url, &html);
+        }
+
+        storage.analyze_domain_duplicates("example.com");
+        let duplicates = storage.get_domain_duplicates("example.com").unwrap();
+        let nav = HtmlNode::new("nav".to_string(), vec![], None, "Menu".to_string());
+        let shipping = HtmlNode::new("p".to_string(), vec![], None, "Ships in 2 days".to_string());
+        assert!(duplicates.is_duplicate(&NodeSignature::from_html_node(&nav)));
+        // Small, but part of the large block
+        assert!(!duplicates.is_duplicate(&NodeSignature::from_html_node(&shipping)));
+        assert_eq!(duplicates.get_duplicate_count(), 1);
+    }
+
+    #[test]
+    fn test_dedup_ignores_objective_stop_words() {
+        let config = DedupConfig {
+            keep_keywords: KeywordMatcher::from_objective("find the pricing of all the plans"),
+            ..DedupConfig::default()
+        };
+        let mut storage = UrlStorage::with_dedup_config(config);
+        let html = r#"<html><body><nav>Home and the blog</nav><aside>See our pricing</aside></body></html>"#;
+        for url in [
+            "https://example.com/a",
+            "https://example.com/b",
+            "https://example.com/c",
+        ] {
+            store_page(&mut storage, url, html);
+        }
+
+        storage.analyze_domain_duplicates("example.com");
+        let duplicates = storage.get_domain_duplicates("example.com").unwrap();
+        let nav = HtmlNode::new(
+            "nav".to_string(),
+            vec![],
+            None,
+            "Home and the blog".to_string(),
+        );
+        let aside = HtmlNode::new(
+            "aside".to_string(),
+            vec![],
+            None,
+            "See our pricing".to_string(),
+        );
+        assert!(duplicates.is_duplicate(&NodeSignature::from_html_node(&nav)));
+        assert!(!duplicates.is_duplicate(&NodeSignature::from_html_node(&aside)));
+    }
+
+    #[test]
+    fn test_filter_domain_pages() {
+        let mut storage = two_page_storage();
+        store_page(
+            &mut storage,
+            "https://example.com/a",
+            r#"<html><body><nav>Menu</nav><p>5 comments</p></body></html>"#,
+        );
+        store_page(
+            &mut storage,
+            "https://example.com/b",
+            r#"<html><body><nav>Menu</nav><p>Other</p></body></html>"#,
+        );
+
+        storage.analyze_domain_duplicates("example.com");
+        storage.filter_domain_pages("example.com", &TemplateDetector::new());
+
+        let url_data = storage.get_url_data("https://example.com/a").unwrap();
+        let body = &url_data.filtered_tree.as_ref().unwrap().children[0];
+        assert_eq!(body.children[0].content, "[FILTERED DUPLICATE]");
+        assert_eq!(body.children[1].content, "{count} comments");
+    }
+
+    #[test]
+    fn test_content_duplicates() {
+        let mut storage = UrlStorage::new();
+        let page = |text: &str| format!("<html><body><nav>Menu</nav><p>{text}</p></body></html>");
+        store_page(
+            &mut storage,
+            "https://example.com/post",
+            &page("Opening hours are 9 to 5"),
+        );
+        store_page(
+            &mut storage,
+            "https://example.com/post?utm_source=feed",
+            &page("Opening  hours are 9 to 5"),
+        );
+        for other in ["a", "b", "c"] {
+            store_page(
+                &mut storage,
+                &format!("https://example.com/{other}"),
+                &page(&format!("Page {other}")),
+            );
+        }
+
+        storage.analyze_domain_duplicates("example.com");
+        storage.filter_domain_pages("example.com", &TemplateDetector::new());
+
+        let post = storage.get_url_data("https://example.com/post").unwrap();
+        assert!(post.content_hash.is_some());
+        assert_eq!(post.duplicate_of, None);
+        let tracked = storage
+            .get_url_data("https://example.com/post?utm_source=feed")
+            .unwrap();
+        assert_eq!(tracked.content_hash, post.content_hash);
+        assert_eq!(
+            tracked.duplicate_of.as_deref(),
+            Some("https://example.com/post")
+        );
+        let other = storage.get_url_data("https://example.com/a").unwrap();
+        assert_ne!(other.content_hash, post.content_hash);
+        assert_eq!(other.duplicate_of, None);
+    }
+
     #[test]
     fn test_node_signature_creation() {
         use crate::html_parser::HtmlNode;
@@ -372,6 +1409,63 @@ mod tests {
         // sig1 and sig3 should be identical
         assert_eq!(sig1.content_hash, sig3.content_hash);
     }
+
+    #[test]
+    fn test_spill_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let spill_dir = dir.path().join("spill");
+        let mut storage = UrlStorage::new();
+        storage.set_spill_config(SpillConfig {
+            dir: spill_dir.clone(),
+            max_resident_bytes: 1,
+        });
+        let parser = HtmlParser::new();
+        for page in ["a", "b", "c"] {
+            let url = format!("https://example.com/{page}");
+            let html = format!(
+                r#"<html><body><nav class="menu">Home</nav><p>Page {page}</p></body></html>"#
+            );
+            storage.add_url(url.clone());
+            if let Some(url_data) = storage.get_url_data_mut(&url) {
+                url_data.set_html_data(html.clone(), parser.parse(&html), None);
+            }
+            storage.update_status(&url, FetchStatus::Success);
+        }
+
+        // Only the page just finished stays in memory
+        let spilled = |storage: &UrlStorage, page: &str| {
+            let url_data = storage.get_url_data(&format!("https://example.com/{page}"));
+            url_data.unwrap().spilled
+        };
+        assert!(spilled(&storage, "a") && spilled(&storage, "b"));
+        assert!(!spilled(&storage, "c"));
+        let url_data = storage.get_url_data("https://example.com/a").unwrap();
+        assert!(url_data.html_tree.is_none());
+        let loaded = storage.load(url_data);
+        assert!(loaded.html().unwrap().contains("Page a"));
+        assert!(loaded.html_tree.is_some());
+
+        // Spilled trees still count towards domain duplicates and get filtered
+        storage.analyze_domain_duplicates("example.com");
+        assert!(
+            storage
+                .get_domain_duplicates("example.com")
+                .unwrap()
+                .get_duplicate_count()
+                > 0
+        );
+        storage.filter_domain_pages("example.com", &TemplateDetector::new());
+        let url_data = storage.get_url_data("https://example.com/b").unwrap();
+        assert!(storage.load(url_data).filtered_tree.is_some());
+
+        // Reloading for changes brings the page back and spills another
+        let url_data = storage.get_url_data_mut("https://example.com/a").unwrap();
+        assert!(!url_data.spilled && url_data.html_tree.is_some());
+        assert!(spilled(&storage, "c"));
+
+        drop(storage);
+        assert!(!spill_dir.exists());
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -385,39 +1479,17 @@ pub struct NodeSignature {
 
 impl NodeSignature {
     pub fn from_html_node(node: &HtmlNode) -> Self {
-        let content_hash = Self::compute_content_hash(node);
+        Self::with_hash(node, node.structure_hash())
+    }
 
+    /// Signature of a node whose `structure_hash` is already known
+    pub fn with_hash(node: &HtmlNode, structure_hash: u64) -> Self {
         NodeSignature {
             tag: node.tag.clone(),
             classes: node.classes.clone(),
             id: node.id.clone(),
             content: node.content.clone(),
-            content_hash,
-        }
-    }
-
-    fn compute_content_hash(node: &HtmlNode) -> String {
-        let mut hasher = DefaultHasher::new();
-
-        // Hash the complete structure: tag, classes, id, content, and children structure
-        node.tag.hash(&mut hasher);
-        node.classes.hash(&mut hasher);
-        node.id.hash(&mut hasher);
-        node.content.hash(&mut hasher);
-
-        // Recursively hash children structure
-        Self::hash_children(&node.children, &mut hasher);
-
-        format!("{:x}", hasher.finish())
-    }
-
-    fn hash_children(children: &[HtmlNode], hasher: &mut DefaultHasher) {
-        for child in children {
-            child.tag.hash(hasher);
-            child.classes.hash(hasher);
-            child.id.hash(hasher);
-            child.content.hash(hasher);
-            Self::hash_children(&child.children, hasher);
+            content_hash: format!("{structure_hash:x}"),
         }
     }
 }
@@ -0,0 +1,225 @@
+use crate::encoding::decode_body;
+use crate::fetch_limits::{read_body, FetchLimits};
+use crate::network_guard::{check_url, http_client};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Longest pretty-printed payload kept per page
+pub const MAX_STRUCTURED_CHARS: usize = 20_000;
+
+/// Script reading the MIME type the browser loaded the current document as
+pub const CONTENT_TYPE_SCRIPT: &str = "return document.contentType || '';";
+
+/// Script returning the raw text browsers show for JSON and CSV documents
+pub const BODY_TEXT_SCRIPT: &str = "return document.body ? document.body.innerText : '';";
+
+/// Non-HTML formats crawled as data instead of being parsed as HTML
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StructuredKind {
+    Json,
+    Csv,
+}
+
+impl StructuredKind {
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let mime = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        if mime == "application/json" || mime.ends_with("+json") || mime == "text/json" {
+            Some(StructuredKind::Json)
+        } else if mime == "text/csv" || mime == "application/csv" {
+            Some(StructuredKind::Csv)
+        } else {
+            None
+        }
+    }
+
+    /// Guess from the URL path extension. Browsers download CSV files instead of
+    /// rendering them, so these URLs are fetched directly.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url::Url::parse(url).ok()?.path().to_lowercase();
+        if path.ends_with(".json") {
+            Some(StructuredKind::Json)
+        } else if path.ends_with(".csv") {
+            Some(StructuredKind::Csv)
+        } else {
+            None
+        }
+    }
+}
+
+/// A JSON or CSV response, pretty-printed and truncated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructuredPayload {
+    pub content_type: String,
+    pub kind: StructuredKind,
+    /// JSON pretty-printed; CSV converted to a JSON array of row objects
+    pub text: String,
+    pub truncated: bool,
+}
+
+impl StructuredPayload {
+    pub fn from_body(
+        content_type: &str,
+        kind: StructuredKind,
+        body: &str,
+        max_chars: usize,
+    ) -> Result<Self, String> {
+        let value = match kind {
+            StructuredKind::Json => serde_json::from_str::<serde_json::Value>(body)
+                .map_err(|e| format!("Invalid JSON: {e}"))?,
+            StructuredKind::Csv => csv_to_json(body)?,
+        };
+        let pretty = serde_json::to_string_pretty(&value)
+            .map_err(|e| format!("Failed to format payload: {e}"))?;
+
+        let truncated = pretty.chars().count() > max_chars;
+        let text = if truncated {
+            pretty.chars().take(max_chars).collect()
+        } else {
+            pretty
+        };
+
+        Ok(StructuredPayload {
+            content_type: content_type.to_string(),
+            kind,
+            text,
+            truncated,
+        })
+    }
+}
+
+/// Rows of a CSV document as objects keyed by the header row
+fn csv_to_json(body: &str) -> Result<serde_json::Value, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(body.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| format!("Invalid CSV: {e}"))?
+        .clone();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Invalid CSV: {e}"))?;
+        let row: serde_json::Map<String, serde_json::Value> = headers
+            .iter()
+            .zip(record.iter())
+            .map(|(header, value)| (header.to_string(), serde_json::json!(value)))
+            .collect();
+        rows.push(serde_json::Value::Object(row));
+    }
+    Ok(serde_json::Value::Array(rows))
+}
+
+/// Fetch a JSON or CSV URL without the browser, failing once it's over the
+/// size limit; unless `allow_private`, not from or through private networks
+pub async fn fetch_structured(
+    url: &str,
+    limits: &FetchLimits,
+    allow_private: bool,
+) -> Result<(StructuredPayload, &'static str), String> {
+    if !allow_private {
+        check_url(url).await?;
+    }
+    let client = http_client(Duration::from_secs(30), allow_private);
+
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {url}: {e}"))?;
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let kind = StructuredKind::from_content_type(&content_type)
+        .or_else(|| StructuredKind::from_url(url))
+        .ok_or_else(|| format!("{url} is not JSON or CSV ({content_type})"))?;
+
+    let bytes = read_body(response, limits.max_file_size)
+        .await
+        .map_err(|e| format!("Failed to read {url}: {e}"))?;
+    let (body, encoding) = decode_body(&bytes, &content_type);
+    let payload = StructuredPayload::from_body(&content_type, kind, &body, MAX_STRUCTURED_CHARS)?;
+    Ok((payload, encoding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_kind_detection() {
+        assert_eq!(
+            StructuredKind::from_content_type("application/json; charset=utf-8"),
+            Some(StructuredKind::Json)
+        );
+        assert_eq!(
+            StructuredKind::from_content_type("application/ld+json"),
+            Some(StructuredKind::Json)
+        );
+        assert_eq!(
+            StructuredKind::from_content_type("text/csv"),
+            Some(StructuredKind::Csv)
+        );
+        assert_eq!(StructuredKind::from_content_type("text/html"), None);
+
+        assert_eq!(
+            StructuredKind::from_url("https://example.com/data/team.CSV?v=2"),
+            Some(StructuredKind::Csv)
+        );
+        assert_eq!(StructuredKind::from_url("https://example.com/about"), None);
+    }
+
+    #[test]
+    fn test_json_payload_is_pretty_printed_and_truncated() {
+        let payload = StructuredPayload::from_body(
+            "application/json",
+            StructuredKind::Json,
+            r#"{"name":"Widget","price":49}"#,
+            1000,
+        )
+        .unwrap();
+        assert_eq!(
+            payload.text,
+            "{\n  \"name\": \"Widget\",\n  \"price\": 49\n}"
+        );
+        assert!(!payload.truncated);
+
+        let truncated = StructuredPayload::from_body(
+            "application/json",
+            StructuredKind::Json,
+            r#"{"name":"Widget","price":49}"#,
+            10,
+        )
+        .unwrap();
+        assert_eq!(truncated.text.chars().count(), 10);
+        assert!(truncated.truncated);
+
+        assert!(StructuredPayload::from_body("", StructuredKind::Json, "<html>", 10).is_err());
+    }
+
+    #[test]
+    fn test_csv_payload_becomes_records() {
+        let payload = StructuredPayload::from_body(
+            "text/csv",
+            StructuredKind::Csv,
+            "name,role\nAda,Engineer\nGrace,Admiral\n",
+            1000,
+        )
+        .unwrap();
+        let rows: serde_json::Value = serde_json::from_str(&payload.text).unwrap();
+        assert_eq!(rows.as_array().unwrap().len(), 2);
+        assert_eq!(rows[1]["name"], "Grace");
+        assert_eq!(rows[1]["role"], "Admiral");
+    }
+}
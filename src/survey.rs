@@ -0,0 +1,227 @@
+use crate::encoding::decode_body;
+use crate::fetch_limits::{read_body, FetchLimits};
+use crate::keywords::KeywordMatcher;
+use crate::network_guard::{check_url, http_client};
+use crate::page_structure::extract_page_structure;
+use crate::text_normalize::normalize_text;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::{debug, info};
+
+/// Longest a single survey request may take
+const SURVEY_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How the survey phase of a two-phase crawl reads candidate pages before
+/// the browser renders the most promising ones
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurveyConfig {
+    /// Candidate pages discovered and surveyed, of which `max_pages` are rendered
+    pub max_candidates: usize,
+    /// Time the whole survey may take; pages not surveyed by then rank by URL only
+    #[serde(with = "crate::wait::duration_ms")]
+    pub time_budget: Duration,
+    /// Pages fetched at once
+    pub concurrency: usize,
+}
+
+impl Default for SurveyConfig {
+    fn default() -> Self {
+        SurveyConfig {
+            max_candidates: 30,
+            time_budget: Duration::from_secs(20),
+            concurrency: 8,
+        }
+    }
+}
+
+/// Title, description and headings of a page, read from its HTML without
+/// rendering it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageSurvey {
+    pub url: String,
+    pub title: Option<String>,
+    /// Meta description, else `og:description`
+    pub description: Option<String>,
+    /// h1–h3 text in document order
+    pub headings: Vec<String>,
+}
+
+impl PageSurvey {
+    pub fn from_html(url: &str, html: &str) -> Self {
+        let document = Html::parse_document(html);
+        let text_of = |selector: &str, attr: Option<&str>| {
+            let selector = Selector::parse(selector).unwrap();
+            document
+                .select(&selector)
+                .map(|element| match attr {
+                    Some(attr) => normalize_text(element.value().attr(attr).unwrap_or_default()),
+                    None => normalize_text(&element.text().collect::<String>()),
+                })
+                .find(|text| !text.is_empty())
+        };
+        PageSurvey {
+            url: url.to_string(),
+            title: text_of("title", None),
+            description: text_of(r#"meta[name="description"]"#, Some("content"))
+                .or_else(|| text_of(r#"meta[property="og:description"]"#, Some("content"))),
+            headings: extract_page_structure(html, url)
+                .map(|structure| {
+                    structure
+                        .headings
+                        .into_iter()
+                        .map(|heading| heading.text)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Keyword matches in the title (counted twice), description and headings
+    pub fn score(&self, keywords: &KeywordMatcher) -> usize {
+        let title = self
+            .title
+            .as_deref()
+            .map_or(0, |title| keywords.match_count(title));
+        let description = self
+            .description
+            .as_deref()
+            .map_or(0, |description| keywords.match_count(description));
+        let headings: usize = self
+            .headings
+            .iter()
+            .map(|heading| keywords.match_count(heading))
+            .sum();
+        2 * title + description + headings
+    }
+}
+
+/// Pages surveyed by `survey_pages`, and those that couldn't be fetched
+#[derive(Debug, Clone, Default)]
+pub struct SurveyResults {
+    pub surveys: Vec<PageSurvey>,
+    /// URLs that failed with their error
+    pub failures: Vec<(String, String)>,
+}
+
+/// Fetch and survey HTML pages over plain HTTP, a few at a time, until all
+/// are done or the time budget runs out. Pages that aren't HTML are left out,
+/// pages over the size limit fail, and unless `allow_private` so do pages on
+/// or redirecting to private networks.
+pub async fn survey_pages(
+    urls: &[String],
+    config: &SurveyConfig,
+    limits: &FetchLimits,
+    allow_private: bool,
+) -> SurveyResults {
+    let client = http_client(
+        SURVEY_REQUEST_TIMEOUT.min(config.time_budget),
+        allow_private,
+    );
+    let deadline = Instant::now() + config.time_budget;
+    let mut pending = urls.iter().cloned();
+    let mut tasks = JoinSet::new();
+    for url in pending.by_ref().take(config.concurrency.max(1)) {
+        tasks.spawn(survey_page(
+            client.clone(),
+            url,
+            limits.max_file_size,
+            allow_private,
+        ));
+    }
+
+    let mut results = SurveyResults::default();
+    loop {
+        match tokio::time::timeout_at(deadline, tasks.join_next()).await {
+            Ok(Some(Ok((_, Ok(Some(survey)))))) => results.surveys.push(survey),
+            Ok(Some(Ok((url, Err(e))))) => {
+                debug!("Failed to survey {}: {}", url, e);
+                results.failures.push((url, e));
+            }
+            Ok(Some(_)) => {}
+            Ok(None) => break,
+            Err(_) => {
+                info!(
+                    "Survey time budget ran out with {} pages left",
+                    tasks.len() + pending.len()
+                );
+                break;
+            }
+        }
+        if let Some(url) = pending.next() {
+            tasks.spawn(survey_page(
+                client.clone(),
+                url,
+                limits.max_file_size,
+                allow_private,
+            ));
+        }
+    }
+    results
+}
+
+/// The URL with its survey, None when it isn't HTML
+async fn survey_page(
+    client: reqwest::Client,
+    url: String,
+    max_bytes: Option<u64>,
+    allow_private: bool,
+) -> (String, Result<Option<PageSurvey>, String>) {
+    if !allow_private {
+        if let Err(e) = check_url(&url).await {
+            return (url, Err(e));
+        }
+    }
+    let response = match client.get(&url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => return (url, Err(format!("HTTP {}", response.status()))),
+        Err(e) => return (url, Err(e.to_string())),
+    };
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.is_empty() && !content_type.contains("html") {
+        return (url, Ok(None));
+    }
+    let bytes = match read_body(response, max_bytes).await {
+        Ok(bytes) => bytes,
+        Err(e) => return (url, Err(e.to_string())),
+    };
+    let (html, _) = decode_body(&bytes, &content_type);
+    let survey = PageSurvey::from_html(&url, &html);
+    (url, Ok(Some(survey)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_survey() {
+        let html = r#"<html><head>
+            <title>Careers | Acme</title>
+            <meta name="description" content="">
+            <meta property="og:description" content="Open roles in  engineering">
+        </head><body>
+            <h1>Join the team</h1><h2>Engineering jobs</h2><h4>Fine print</h4>
+        </body></html>"#;
+        let survey = PageSurvey::from_html("https://acme.com/careers", html);
+        assert_eq!(survey.title.as_deref(), Some("Careers | Acme"));
+        assert_eq!(
+            survey.description.as_deref(),
+            Some("Open roles in engineering")
+        );
+        assert_eq!(survey.headings, vec!["Join the team", "Engineering jobs"]);
+
+        let keywords = KeywordMatcher::from_objective("engineering jobs");
+        assert!(survey.score(&keywords) >= 2);
+        let empty = PageSurvey::from_html("https://acme.com/about", "<p>About us</p>");
+        assert_eq!(empty.score(&keywords), 0);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::text_normalize::normalize_text;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -122,17 +123,18 @@ impl TemplateDetector {
 
     /// Detect template pattern in given text content
     pub fn detect_template(&self, content: &str) -> Option<Template> {
-        let content = content.trim();
+        // Invisible characters and odd spacing would make equal text differ
+        let content = normalize_text(content);
         if content.is_empty() {
             return None;
         }
 
         // First try to detect float patterns, then number patterns
-        if let Some(template) = self.detect_float_pattern(content) {
+        if let Some(template) = self.detect_float_pattern(&content) {
             return Some(template);
         }
 
-        if let Some(template) = self.detect_number_pattern(content) {
+        if let Some(template) = self.detect_number_pattern(&content) {
             return Some(template);
         }
 
@@ -302,6 +304,23 @@ impl TemplateDetector {
         }
     }
 
+    /// Return a copy of the tree with every node's content replaced by its template pattern
+    pub fn normalize_tree(&self, node: &crate::HtmlNode) -> crate::HtmlNode {
+        let mut normalized = crate::HtmlNode::new(
+            node.tag.clone(),
+            node.classes.clone(),
+            node.id.clone(),
+            self.apply_template(&node.content),
+        );
+        normalized.href = node.href.clone();
+        normalized.bounding_box = node.bounding_box;
+        normalized.rendered_style = node.rendered_style;
+        for child in &node.children {
+            normalized.add_child(self.normalize_tree(child));
+        }
+        normalized
+    }
+
     /// Extract templates with their element paths from an HTML tree
     pub fn extract_templates_with_paths(&self, root: &crate::HtmlNode) -> TemplatePathStore {
         let mut store = TemplatePathStore::new();
@@ -465,6 +484,21 @@ mod tests {
         assert_eq!(detector.apply_template("Hello world"), "Hello world");
     }
 
+    #[test]
+    fn test_normalize_tree() {
+        let detector = TemplateDetector::new();
+        let parser = crate::HtmlParser::new();
+        let tree = parser.parse(
+            r#"<html><body><span class="meta">42 comments</span><p>Hello</p></body></html>"#,
+        );
+
+        let normalized = detector.normalize_tree(&tree);
+        let body = &normalized.children[0];
+        assert_eq!(body.children[0].content, "{count} comments");
+        assert_eq!(body.children[0].classes, vec!["meta"]);
+        assert_eq!(body.children[1].content, "Hello");
+    }
+
     #[test]
     fn test_edge_cases() {
         let detector = TemplateDetector::new();
@@ -496,10 +530,15 @@ mod tests {
         let detector = TemplateDetector::new();
 
         let template = detector.detect_template("  42   comments  ").unwrap();
-        assert_eq!(template.pattern, "{count}   comments");
+        assert_eq!(template.pattern, "{count} comments");
 
         let template = detector.detect_template("16\thours\tago").unwrap();
-        assert_eq!(template.pattern, "{time}\thours\tago");
+        assert_eq!(template.pattern, "{time} hours ago");
+
+        let template = detector
+            .detect_template("42\u{200B}&nbsp;comments")
+            .unwrap();
+        assert_eq!(template.pattern, "{count} comments");
     }
 
     #[test]
@@ -589,9 +628,13 @@ mod tests {
     #[test]
     fn test_template_based_duplicate_detection() {
         use crate::html_parser::HtmlParser;
-        use crate::storage::{FetchStatus, UrlStorage};
+        use crate::storage::{DedupConfig, FetchStatus, UrlStorage};
 
-        let mut storage = UrlStorage::new();
+        // Two pages are enough to flag what they share
+        let mut storage = UrlStorage::with_dedup_config(DedupConfig {
+            min_pages: 2,
+            ..DedupConfig::default()
+        });
         let parser = HtmlParser::new();
         let detector = TemplateDetector::new();
 
@@ -0,0 +1,128 @@
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+
+/// Characters that take no space but break text comparison
+const INVISIBLE_CHARS: [char; 6] = [
+    '\u{200B}', // zero width space
+    '\u{200C}', // zero width non-joiner
+    '\u{200D}', // zero width joiner
+    '\u{2060}', // word joiner
+    '\u{FEFF}', // byte order mark / zero width no-break space
+    '\u{00AD}', // soft hyphen
+];
+
+/// Named entities left in text, e.g. from double-escaped markup or payloads
+const NAMED_ENTITIES: [(&str, char); 18] = [
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{00A0}'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("hellip", '…'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("euro", '€'),
+    ("middot", '·'),
+];
+
+/// The one cleanup applied to page text before it's stored, compared or
+/// templated: decode HTML entities, normalize to NFC, drop zero-width
+/// characters and collapse whitespace (including non-breaking spaces)
+pub fn normalize_text(text: &str) -> String {
+    let decoded = decode_html_entities(text);
+    let composed: String = decoded
+        .nfc()
+        .filter(|c| !INVISIBLE_CHARS.contains(c))
+        .collect();
+    composed.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decode `&amp;`-style named entities and `&#38;`/`&#x26;` numeric
+/// references; anything unrecognized is kept as written
+pub fn decode_html_entities(text: &str) -> Cow<'_, str> {
+    if !text.contains('&') {
+        return Cow::Borrowed(text);
+    }
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .and_then(|end| decode_entity(&rest[1..=end]).map(|c| (c, end + 2)));
+        match entity {
+            Some((c, length)) => {
+                decoded.push(c);
+                rest = &rest[length..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+/// The character of an entity name without `&` and `;`
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    NAMED_ENTITIES
+        .iter()
+        .find(|(entity, _)| *entity == name)
+        .map(|(_, c)| *c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_html_entities() {
+        assert_eq!(
+            decode_html_entities("Tom &amp; Jerry &#8211; &#x2764; &euro;5"),
+            "Tom & Jerry – ❤ €5"
+        );
+        assert_eq!(
+            decode_html_entities("AT&T &unknown; & &#xZZ;"),
+            "AT&T &unknown; & &#xZZ;"
+        );
+        assert!(matches!(
+            decode_html_entities("plain"),
+            Cow::Borrowed("plain")
+        ));
+    }
+
+    #[test]
+    fn test_normalize_text() {
+        assert_eq!(
+            normalize_text("  42\u{200B}&nbsp;comments\n\t posted  "),
+            "42 comments posted"
+        );
+        // "é" as e + combining acute becomes the single code point
+        assert_eq!(normalize_text("Cafe\u{0301}"), "Caf\u{e9}");
+        assert_eq!(normalize_text("co\u{00AD}operate"), "cooperate");
+        assert_eq!(normalize_text(" \u{FEFF} "), "");
+        assert_eq!(
+            normalize_text("line1\n  line2  \n\nline3"),
+            "line1 line2 line3"
+        );
+    }
+}
@@ -0,0 +1,233 @@
+/// Currency codes recognized in prices, before or after the amount
+pub const CURRENCY_CODES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CHF", "CAD", "AUD", "INR", "CNY",
+];
+
+/// Currency symbols and the code they stand for
+const CURRENCY_SYMBOLS: &[(char, &str)] = &[
+    ('$', "USD"),
+    ('€', "EUR"),
+    ('£', "GBP"),
+    ('¥', "JPY"),
+    ('₹', "INR"),
+];
+
+/// Spellings of billing periods after a price (`/mo`, `per year`, `a week`)
+const PERIODS: &[(&str, &str)] = &[
+    ("month", "month"),
+    ("mo", "month"),
+    ("year", "year"),
+    ("yr", "year"),
+    ("annum", "year"),
+    ("week", "week"),
+    ("wk", "week"),
+    ("day", "day"),
+    ("hour", "hour"),
+    ("hr", "hour"),
+];
+
+/// What a measurement measures; values of each are converted to one unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Area,
+    Weight,
+    Duration,
+}
+
+impl Dimension {
+    /// The unit values are converted to
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Dimension::Area => "m2",
+            Dimension::Weight => "kg",
+            Dimension::Duration => "s",
+        }
+    }
+}
+
+/// Dimension and size in the dimension's unit of a lowercase unit spelling
+fn unit(spelling: &str) -> Option<(Dimension, f64)> {
+    let unit = match spelling {
+        "m²" | "m2" | "sqm" | "sq m" | "square meter" | "square meters" | "square metre"
+        | "square metres" => (Dimension::Area, 1.0),
+        "km²" | "km2" => (Dimension::Area, 1_000_000.0),
+        "ha" | "hectare" | "hectares" => (Dimension::Area, 10_000.0),
+        "ft²" | "ft2" | "sqft" | "sq ft" | "square foot" | "square feet" => {
+            (Dimension::Area, 0.09290304)
+        }
+        "acre" | "acres" => (Dimension::Area, 4046.8564224),
+        "mg" => (Dimension::Weight, 0.000001),
+        "g" | "gram" | "grams" => (Dimension::Weight, 0.001),
+        "kg" | "kgs" | "kilogram" | "kilograms" => (Dimension::Weight, 1.0),
+        "tonne" | "tonnes" => (Dimension::Weight, 1000.0),
+        "lb" | "lbs" => (Dimension::Weight, 0.45359237),
+        "oz" | "ounce" | "ounces" => (Dimension::Weight, 0.028349523125),
+        "sec" | "secs" | "second" | "seconds" => (Dimension::Duration, 1.0),
+        "min" | "mins" | "minute" | "minutes" => (Dimension::Duration, 60.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => (Dimension::Duration, 3600.0),
+        "day" | "days" => (Dimension::Duration, 86_400.0),
+        "week" | "weeks" => (Dimension::Duration, 604_800.0),
+        _ => return None,
+    };
+    Some(unit)
+}
+
+/// A number as written on a page (`1,299.00`, `1.299,00`, `1'299`, `19,99`)
+/// with a `.` decimal point and no grouping. A separator is the decimal
+/// point when it's the last of both `,` and `.`, or the only separator and
+/// not followed by exactly three digits; the others group thousands.
+pub fn normalize_amount(text: &str) -> Option<String> {
+    let number: String = text
+        .chars()
+        .filter(|c| c.is_ascii_digit() || matches!(c, ',' | '.' | '\''))
+        .collect();
+    let number = number.trim_matches(|c: char| !c.is_ascii_digit());
+    if number.is_empty() {
+        return None;
+    }
+
+    let separators: Vec<char> = number.chars().filter(|c| matches!(c, ',' | '.')).collect();
+    let decimal = number.rfind([',', '.']).filter(|&index| {
+        let last = separators[separators.len() - 1];
+        if separators.iter().any(|&separator| separator != last) {
+            true
+        } else {
+            separators.len() == 1 && number.len() - index != 4
+        }
+    });
+    let digits = |part: &str| {
+        part.chars()
+            .filter(char::is_ascii_digit)
+            .collect::<String>()
+    };
+    Some(match decimal {
+        Some(index) => format!("{}.{}", digits(&number[..index]), &number[index + 1..]),
+        None => digits(number),
+    })
+}
+
+/// A converted value with up to three decimals, without trailing zeros
+fn format_number(value: f64) -> String {
+    let text = format!("{value:.3}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// The billing period at the end of a price, e.g. `month` for `€49/mo` or
+/// `$10 a month`
+pub fn billing_period(text: &str) -> Option<&'static str> {
+    let text = text.trim().to_lowercase();
+    let (_, last) = text.rsplit_once(['/', ' '])?;
+    PERIODS
+        .iter()
+        .find(|(spelling, _)| *spelling == last.trim_end_matches('.'))
+        .map(|(_, period)| *period)
+}
+
+/// A price as its amount and currency code, plus the billing period when
+/// there is one: `$1,299.00` as `1299.00 USD`, `from €49/mo` as
+/// `49 EUR/month`, `CHF 1'299` as `1299 CHF`
+pub fn normalize_price(text: &str) -> Option<String> {
+    let currency = CURRENCY_CODES
+        .iter()
+        .find(|code| text.contains(*code))
+        .copied()
+        .or_else(|| {
+            CURRENCY_SYMBOLS
+                .iter()
+                .find(|(symbol, _)| text.contains(*symbol))
+                .map(|(_, code)| *code)
+        })?;
+    let period = billing_period(text);
+    let amount = match period {
+        Some(_) => text
+            .trim()
+            .rsplit_once(['/', ' '])
+            .map_or(text, |(amount, _)| amount),
+        None => text,
+    };
+    let amount = normalize_amount(amount)?;
+    Some(match period {
+        Some(period) => format!("{amount} {currency}/{period}"),
+        None => format!("{amount} {currency}"),
+    })
+}
+
+/// An area, weight or duration converted to square metres, kilograms or
+/// seconds: `1,200 sq ft` as `111.484 m2`, `500g` as `0.5 kg`, `2 hrs` as
+/// `7200 s`
+pub fn normalize_measure(text: &str) -> Option<(Dimension, String)> {
+    let text = text.trim();
+    let split = text.find(|c: char| !(c.is_ascii_digit() || matches!(c, ',' | '.' | '\'')))?;
+    let spelling = text[split..]
+        .to_lowercase()
+        .replace('.', "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let (dimension, factor) = unit(&spelling)?;
+    let amount: f64 = normalize_amount(&text[..split])?.parse().ok()?;
+    Some((
+        dimension,
+        format!("{} {}", format_number(amount * factor), dimension.unit()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_amount() {
+        for (text, amount) in [
+            ("1,299.00", "1299.00"),
+            ("1.299,00", "1299.00"),
+            ("1'299.50", "1299.50"),
+            ("19,99", "19.99"),
+            ("1,299", "1299"),
+            ("1.000.000", "1000000"),
+            ("2.5", "2.5"),
+            ("49", "49"),
+        ] {
+            assert_eq!(normalize_amount(text).as_deref(), Some(amount), "{text}");
+        }
+        assert_eq!(normalize_amount("per month"), None);
+    }
+
+    #[test]
+    fn test_normalize_price() {
+        for (text, value) in [
+            ("$1,299.00", "1299.00 USD"),
+            ("19,99 €", "19.99 EUR"),
+            ("€49/mo", "49 EUR/month"),
+            ("$10 a month", "10 USD/month"),
+            ("£120 per year", "120 GBP/year"),
+            ("CHF 1'299", "1299 CHF"),
+            ("1.299,00 EUR", "1299.00 EUR"),
+            ("₹ 999", "999 INR"),
+        ] {
+            assert_eq!(normalize_price(text).as_deref(), Some(value), "{text}");
+        }
+        assert_eq!(normalize_price("49"), None);
+    }
+
+    #[test]
+    fn test_normalize_measure() {
+        for (text, dimension, value) in [
+            ("120 m²", Dimension::Area, "120 m2"),
+            ("1,200 sq. ft", Dimension::Area, "111.484 m2"),
+            ("2 ha", Dimension::Area, "20000 m2"),
+            ("500g", Dimension::Weight, "0.5 kg"),
+            ("2,5 kg", Dimension::Weight, "2.5 kg"),
+            ("10 lbs", Dimension::Weight, "4.536 kg"),
+            ("45 min", Dimension::Duration, "2700 s"),
+            ("1.5 Hours", Dimension::Duration, "5400 s"),
+        ] {
+            assert_eq!(
+                normalize_measure(text),
+                Some((dimension, value.to_string())),
+                "{text}"
+            );
+        }
+        assert_eq!(normalize_measure("3 apples"), None);
+    }
+}
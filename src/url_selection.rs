@@ -0,0 +1,171 @@
+use crate::crawler::DiscoverySource;
+use crate::keywords::KeywordMatcher;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// A page picked for the crawl and why it was picked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UrlSelection {
+    pub url: String,
+    pub source: DiscoverySource,
+    /// Objective keywords and URL hints found in the URL path and query
+    pub url_keywords: Vec<String>,
+    /// The URL looks like the type of page the objective is best served by
+    #[serde(default)]
+    pub preferred_page_type: bool,
+    /// Keyword matches in the surveyed title, description and headings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub survey_score: Option<usize>,
+    /// Priority of the URL in its sitemap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sitemap_priority: Option<f32>,
+}
+
+impl UrlSelection {
+    pub fn new(url: String, source: DiscoverySource, url_ranking: &KeywordMatcher) -> Self {
+        let url_keywords = url_ranking
+            .url_keywords(&url)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        UrlSelection {
+            url,
+            source,
+            url_keywords,
+            preferred_page_type: false,
+            survey_score: None,
+            sitemap_priority: None,
+        }
+    }
+
+    /// Objective keywords and URL hints found in the URL; a result of
+    /// searching the site for the objective counts as one more
+    pub fn objective_score(&self) -> usize {
+        self.url_keywords.len() + usize::from(self.source == DiscoverySource::SiteSearch)
+    }
+}
+
+/// Picks which discovered candidates the browser renders when there are more
+/// than `max_pages` of them. Candidates come in discovery order with their
+/// URL keywords, sitemap priority and, after a survey, survey score filled in.
+pub trait UrlSelectionStrategy: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Keep at most `slots` candidates, in the order they should be crawled
+    fn select(&self, candidates: Vec<UrlSelection>, slots: usize) -> Vec<UrlSelection>;
+}
+
+/// The first candidates discovered
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiscoveryOrder;
+
+impl UrlSelectionStrategy for DiscoveryOrder {
+    fn name(&self) -> &'static str {
+        "order"
+    }
+
+    fn select(&self, mut candidates: Vec<UrlSelection>, slots: usize) -> Vec<UrlSelection> {
+        candidates.truncate(slots);
+        candidates
+    }
+}
+
+/// Candidates with the most keyword matches in their URL and survey; ties
+/// keep their discovery order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeywordRanking;
+
+impl UrlSelectionStrategy for KeywordRanking {
+    fn name(&self) -> &'static str {
+        "keywords"
+    }
+
+    fn select(&self, mut candidates: Vec<UrlSelection>, slots: usize) -> Vec<UrlSelection> {
+        candidates.sort_by_key(|candidate| {
+            std::cmp::Reverse(candidate.objective_score() + candidate.survey_score.unwrap_or(0))
+        });
+        candidates.truncate(slots);
+        candidates
+    }
+}
+
+/// Built-in strategies that can be picked by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UrlSelectionMethod {
+    DiscoveryOrder,
+    #[default]
+    Keywords,
+}
+
+impl UrlSelectionMethod {
+    pub fn strategy(&self) -> Arc<dyn UrlSelectionStrategy> {
+        match self {
+            UrlSelectionMethod::DiscoveryOrder => Arc::new(DiscoveryOrder),
+            UrlSelectionMethod::Keywords => Arc::new(KeywordRanking),
+        }
+    }
+}
+
+impl FromStr for UrlSelectionMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "order" => Ok(UrlSelectionMethod::DiscoveryOrder),
+            "keywords" => Ok(UrlSelectionMethod::Keywords),
+            other => Err(format!(
+                "Invalid URL selection: {other} (expected order or keywords)"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selection_strategies() {
+        let ranking = KeywordMatcher::from_objective("pricing");
+        let candidates: Vec<UrlSelection> = ["/blog", "/about", "/pricing"]
+            .iter()
+            .map(|path| {
+                UrlSelection::new(
+                    format!("https://example.com{path}"),
+                    DiscoverySource::Homepage,
+                    &ranking,
+                )
+            })
+            .collect();
+        let urls = |selected: Vec<UrlSelection>| -> Vec<String> {
+            selected
+                .into_iter()
+                .map(|selection| selection.url)
+                .collect()
+        };
+
+        assert_eq!(
+            urls(DiscoveryOrder.select(candidates.clone(), 2)),
+            vec!["https://example.com/blog", "https://example.com/about"]
+        );
+
+        let mut surveyed = candidates.clone();
+        surveyed[1].survey_score = Some(2);
+        let strategy = "keywords".parse::<UrlSelectionMethod>().unwrap().strategy();
+        assert_eq!(strategy.name(), "keywords");
+        assert_eq!(
+            urls(strategy.select(surveyed, 2)),
+            vec!["https://example.com/about", "https://example.com/pricing"]
+        );
+        assert!("llm".parse::<UrlSelectionMethod>().is_err());
+
+        // The site's own search found the page
+        let mut searched = candidates;
+        searched[0].source = DiscoverySource::SiteSearch;
+        assert_eq!(
+            urls(KeywordRanking.select(searched, 1)),
+            vec!["https://example.com/blog"]
+        );
+    }
+}
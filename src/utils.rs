@@ -1,17 +1,7 @@
-use regex::Regex;
-
-pub fn trim_and_clean_text(text: &str) -> String {
-    let cleaned = text
-        .trim()
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .collect::<Vec<&str>>()
-        .join(" ");
-
-    let re = Regex::new(r"\s+").unwrap();
-    re.replace_all(&cleaned, " ").to_string()
-}
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
 
 pub fn extract_domain_from_url(url: &str) -> Option<String> {
     url::Url::parse(url)
@@ -19,8 +9,14 @@ pub fn extract_domain_from_url(url: &str) -> Option<String> {
         .and_then(|parsed| parsed.host_str().map(|host| host.to_string()))
 }
 
+/// Root URL of a site, https unless the site is given as `http://domain`.
+/// A root URL is returned as is.
 pub fn construct_root_url(domain: &str) -> String {
-    let url_string = format!("https://{domain}");
+    let url_string = if domain.starts_with("http://") || domain.starts_with("https://") {
+        domain.to_string()
+    } else {
+        format!("https://{domain}")
+    };
     // Normalize the URL the same way CLI does to ensure consistency
     if let Ok(parsed) = url::Url::parse(&url_string) {
         parsed.to_string()
@@ -29,6 +25,79 @@ pub fn construct_root_url(domain: &str) -> String {
     }
 }
 
+/// The domain of a domain name or URL, in ASCII (international domains
+/// become punycode). A URL starting with `http://` keeps that prefix to
+/// crawl a site that isn't served over https.
+pub fn normalize_domain(input: &str) -> Result<String, String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Err("A domain is required".to_string());
+    }
+
+    let (url_str, http) = match trimmed.split_once("://") {
+        Some((scheme, _)) if scheme.eq_ignore_ascii_case("https") => (trimmed.to_string(), false),
+        Some((scheme, _)) if scheme.eq_ignore_ascii_case("http") => (trimmed.to_string(), true),
+        Some(("", _)) => return Err(format!("Invalid domain or URL: {input}")),
+        Some((scheme, _)) => {
+            return Err(format!(
+            "Unsupported scheme {scheme}:// in {input}, only http and https sites can be crawled"
+        ))
+        }
+        None => (format!("https://{trimmed}"), false),
+    };
+
+    let url = url::Url::parse(&url_str).map_err(|_| format!("Invalid domain or URL: {input}"))?;
+    let domain = match url.host() {
+        Some(url::Host::Domain(domain)) => {
+            check_domain_name(domain).map_err(|e| format!("Invalid domain {input}: {e}"))?;
+            domain.to_string()
+        }
+        Some(host) => host.to_string(),
+        None => return Err(format!("Could not extract domain from: {input}")),
+    };
+    Ok(if http {
+        format!("http://{domain}")
+    } else {
+        domain
+    })
+}
+
+/// DNS rules for an ASCII domain name: dot separated labels of letters,
+/// digits and inner hyphens, at most 63 characters each and 253 in all. A
+/// bare name is only accepted for `localhost`.
+fn check_domain_name(domain: &str) -> Result<(), String> {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    if domain.len() > 253 {
+        return Err("longer than 253 characters".to_string());
+    }
+    for label in domain.split('.') {
+        if label.is_empty() {
+            return Err("empty label".to_string());
+        }
+        if label.len() > 63 {
+            return Err(format!("label {label} is longer than 63 characters"));
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!(
+                "label {label} has characters other than letters, digits and hyphens"
+            ));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("label {label} starts or ends with a hyphen"));
+        }
+    }
+    if !domain.contains('.') && domain != "localhost" {
+        return Err("no top-level domain, e.g. .com".to_string());
+    }
+    Ok(())
+}
+
+/// Domain of a site given as `domain` or, when served over plain http only,
+/// `http://domain`
+pub fn site_host(site: &str) -> &str {
+    site.strip_prefix("http://").unwrap_or(site)
+}
+
 pub fn is_root_url(url: &str) -> bool {
     if let Ok(parsed) = url::Url::parse(url) {
         let path = parsed.path();
@@ -42,19 +111,239 @@ pub fn is_root_url(url: &str) -> bool {
     }
 }
 
+/// Split an objective into lowercase keywords, skipping stop words and words
+/// too short to be meaningful
+pub fn extract_objective_keywords(objective: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = Vec::new();
+    for word in objective
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() > 2)
+    {
+        let word = word.to_lowercase();
+        if !crate::keywords::is_stop_word(&word) && !keywords.contains(&word) {
+            keywords.push(word);
+        }
+    }
+    keywords
+}
+
+/// Turn a URL into a file name stem, e.g. `https://example.com/blog/post?id=1`
+/// becomes `example.com_blog_post_id_1`
+pub fn url_to_file_stem(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let mut stem = String::new();
+    for c in without_scheme.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+            stem.push(c);
+        } else if !stem.ends_with('_') {
+            stem.push('_');
+        }
+    }
+    let stem = stem.trim_matches('_');
+    if stem.is_empty() {
+        "page".to_string()
+    } else {
+        stem.chars().take(150).collect()
+    }
+}
+
+/// Compression of an output file, chosen by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl FileCompression {
+    /// Gzip for `.gz` paths, zstd for `.zst` paths
+    pub fn from_path(path: &str) -> Self {
+        let path = path.to_lowercase();
+        if path.ends_with(".gz") {
+            FileCompression::Gzip
+        } else if path.ends_with(".zst") {
+            FileCompression::Zstd
+        } else {
+            FileCompression::None
+        }
+    }
+
+    /// By magic number, so renamed files still read
+    fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            FileCompression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            FileCompression::Zstd
+        } else {
+            FileCompression::None
+        }
+    }
+}
+
+/// The path without a `.gz`/`.zst` extension, for telling the format of
+/// e.g. `lists.csv.gz`
+pub fn strip_compression_extension(path: &str) -> &str {
+    let lower = path.to_lowercase();
+    [".gz", ".zst"]
+        .iter()
+        .find(|extension| lower.ends_with(*extension))
+        .map(|extension| &path[..path.len() - extension.len()])
+        .unwrap_or(path)
+}
+
+/// Fail for URLs like `s3://bucket/pages.json`, which would otherwise be
+/// written as a local file under `s3:`. Outputs only go to local paths.
+pub fn check_local_path(path: &str) -> Result<(), String> {
+    match path.split_once("://") {
+        Some((scheme, _))
+            if scheme.len() > 1
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)) =>
+        {
+            Err(format!(
+                "Can't write to {path}: {scheme}:// URLs aren't supported, write to a local path and upload it afterwards"
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Write a file, compressed as its extension says
+pub fn write_output_file(path: &str, contents: &[u8]) -> Result<(), String> {
+    check_local_path(path)?;
+    let bytes = match FileCompression::from_path(path) {
+        FileCompression::None => contents.to_vec(),
+        FileCompression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(contents)
+                .and_then(|_| encoder.finish())
+                .map_err(|e| format!("Failed to compress {path}: {e}"))?
+        }
+        FileCompression::Zstd => {
+            zstd::encode_all(contents, 0).map_err(|e| format!("Failed to compress {path}: {e}"))?
+        }
+    };
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write {path}: {e}"))
+}
+
+/// Read a text file, decompressing gzip and zstd files whatever their name
+pub fn read_output_file(path: &str) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let bytes = match FileCompression::detect(&bytes) {
+        FileCompression::None => bytes,
+        FileCompression::Gzip => {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decompressed)
+                .map_err(|e| format!("Failed to decompress {path}: {e}"))?;
+            decompressed
+        }
+        FileCompression::Zstd => zstd::decode_all(bytes.as_slice())
+            .map_err(|e| format!("Failed to decompress {path}: {e}"))?,
+    };
+    String::from_utf8(bytes).map_err(|e| format!("Failed to read {path}: {e}"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_trim_and_clean_text() {
-        assert_eq!(trim_and_clean_text("  hello   world  "), "hello world");
+    fn test_check_local_path() {
+        assert!(check_local_path("pages.json").is_ok());
+        assert!(check_local_path("/tmp/run 1/pages.json.gz").is_ok());
+        assert!(check_local_path("C://pages.json").is_ok());
+        let error = check_local_path("s3://bucket/pages.json").unwrap_err();
+        assert!(error.contains("s3:// URLs aren't supported"));
+        assert!(check_local_path("gcs://bucket/prefix/").is_err());
+        assert!(write_output_file("s3://bucket/pages.json", b"{}").is_err());
+        assert!(!std::path::Path::new("s3:").exists());
+    }
+
+    #[test]
+    fn test_normalize_domain() {
+        // Test URL with protocol
+        assert_eq!(
+            normalize_domain("https://example.com").unwrap(),
+            "example.com"
+        );
+        assert_eq!(
+            normalize_domain("http://example.com/path").unwrap(),
+            "http://example.com"
+        );
+
+        // Test domain without protocol
+        assert_eq!(normalize_domain("example.com").unwrap(), "example.com");
+        assert_eq!(normalize_domain("  example.com  ").unwrap(), "example.com");
+
+        // International domains become punycode, plain http is kept
+        assert_eq!(
+            normalize_domain("Bücher.example").unwrap(),
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(
+            normalize_domain("http://münchen.de/stadt").unwrap(),
+            "http://xn--mnchen-3ya.de"
+        );
+        assert_eq!(
+            normalize_domain("HTTPS://Example.COM").unwrap(),
+            "example.com"
+        );
+        assert_eq!(normalize_domain("localhost").unwrap(), "localhost");
+        assert_eq!(normalize_domain("127.0.0.1").unwrap(), "127.0.0.1");
+    }
+
+    #[test]
+    fn test_normalize_domain_error() {
+        // Test that invalid domain extraction returns error
+        let result = normalize_domain("://invalid");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid domain or URL"));
+
+        for (input, error) in [
+            ("invalid..domain", "empty label"),
+            ("-acme.com", "hyphen"),
+            ("acme_corp.com", "characters other than"),
+            ("intranet", "top-level domain"),
+            ("ftp://example.com", "Unsupported scheme"),
+            ("   ", "A domain is required"),
+        ] {
+            let message = normalize_domain(input).unwrap_err();
+            assert!(message.contains(error), "{input}: {message}");
+        }
+        assert!(normalize_domain(&format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn test_compressed_output_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let contents = r#"[{"url": "https://example.com/"}]"#.repeat(100);
+        for name in ["pages.json", "pages.json.gz", "pages.json.zst"] {
+            let path = dir.path().join(name);
+            let path = path.to_str().unwrap();
+            write_output_file(path, contents.as_bytes()).unwrap();
+            assert_eq!(read_output_file(path).unwrap(), contents);
+        }
+        let plain = std::fs::metadata(dir.path().join("pages.json")).unwrap();
+        let zstd = std::fs::metadata(dir.path().join("pages.json.zst")).unwrap();
+        assert!(zstd.len() < plain.len() / 10);
+
+        // Detected by content, not name
+        std::fs::rename(
+            dir.path().join("pages.json.gz"),
+            dir.path().join("renamed.json"),
+        )
+        .unwrap();
+        let renamed = dir.path().join("renamed.json");
         assert_eq!(
-            trim_and_clean_text("line1\n  line2  \n\nline3"),
-            "line1 line2 line3"
+            read_output_file(renamed.to_str().unwrap()).unwrap(),
+            contents
         );
-        assert_eq!(trim_and_clean_text(""), "");
-        assert_eq!(trim_and_clean_text("   \n  \n  "), "");
+
+        assert_eq!(strip_compression_extension("lists.CSV.gz"), "lists.CSV");
+        assert_eq!(strip_compression_extension("lists.csv"), "lists.csv");
     }
 
     #[test]
@@ -79,6 +368,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_construct_root_url_keeps_http() {
+        assert_eq!(
+            construct_root_url("http://example.com"),
+            "http://example.com/"
+        );
+        assert_eq!(
+            construct_root_url("https://example.com/"),
+            "https://example.com/"
+        );
+    }
+
     #[test]
     fn test_construct_root_url_matches_cli_normalization() {
         // Test that construct_root_url produces URLs that match CLI normalization
@@ -94,6 +395,15 @@ mod tests {
         assert_eq!(constructed_root, "https://news.ycombinator.com/");
     }
 
+    #[test]
+    fn test_extract_objective_keywords() {
+        assert_eq!(
+            extract_objective_keywords("Find the team, and their roles at ACME"),
+            vec!["team", "roles", "acme"]
+        );
+        assert!(extract_objective_keywords("a to").is_empty());
+    }
+
     #[test]
     fn test_is_root_url() {
         assert!(is_root_url("https://example.com"));
@@ -107,4 +417,14 @@ mod tests {
         assert!(!is_root_url("https://example.com/path?query=value"));
         assert!(!is_root_url("invalid-url"));
     }
+
+    #[test]
+    fn test_url_to_file_stem() {
+        assert_eq!(
+            url_to_file_stem("https://example.com/blog/post?id=1"),
+            "example.com_blog_post_id_1"
+        );
+        assert_eq!(url_to_file_stem("https://example.com/"), "example.com");
+        assert_eq!(url_to_file_stem("://"), "page");
+    }
 }
@@ -0,0 +1,255 @@
+use crate::entities::{Entity, EntityKind};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// What happens to an entity that fails a rule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Keep the entity with the rule's name in its `flags`
+    #[default]
+    Flag,
+    /// Leave the entity out of the results
+    Drop,
+}
+
+/// A constraint on the entities of one kind, e.g. prices above zero or
+/// dates within the next two years
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationRule {
+    pub kind: EntityKind,
+    /// Regex the normalized value must match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Smallest number allowed: the amount of a price or measurement, or for
+    /// an ISO date the days from the day of the run (negative in the past)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Largest number allowed, counted as for `min`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Shown in flags and stats, a description of the rule by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl ValidationRule {
+    /// The rule's name, else e.g. `price >= 0.01` or `email matches /@acme/`
+    pub fn label(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let mut parts = Vec::new();
+        if let Some(pattern) = &self.pattern {
+            parts.push(format!("matches /{pattern}/"));
+        }
+        if let Some(min) = self.min {
+            parts.push(format!(">= {min}"));
+        }
+        if let Some(max) = self.max {
+            parts.push(format!("<= {max}"));
+        }
+        format!("{} {}", self.kind.as_str(), parts.join(" and "))
+    }
+}
+
+/// Entities of a kind a rule checked and how many failed it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleStats {
+    pub rule: String,
+    pub action: RuleAction,
+    pub checked: usize,
+    pub failed: usize,
+}
+
+/// Checks extracted entities against validation rules
+#[derive(Debug, Clone, Default)]
+pub struct EntityValidator {
+    rules: Vec<(ValidationRule, Option<Regex>)>,
+}
+
+impl EntityValidator {
+    pub fn new(rules: &[ValidationRule]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        for rule in rules {
+            let pattern = rule
+                .pattern
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| format!("Invalid pattern in rule {}: {e}", rule.label()))?;
+            compiled.push((rule.clone(), pattern));
+        }
+        Ok(EntityValidator { rules: compiled })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Stats of every rule, with nothing checked yet
+    pub fn empty_stats(&self) -> Vec<RuleStats> {
+        self.rules
+            .iter()
+            .map(|(rule, _)| RuleStats {
+                rule: rule.label(),
+                action: rule.action,
+                checked: 0,
+                failed: 0,
+            })
+            .collect()
+    }
+
+    /// Flag or drop the entities failing a rule. Dates are counted from
+    /// `today`.
+    pub fn validate(&self, entities: Vec<Entity>, today: NaiveDate) -> Vec<Entity> {
+        self.validate_counting(entities, today, &mut self.empty_stats())
+    }
+
+    /// `validate`, counting the entities checked and failed in `stats` (from
+    /// `empty_stats`)
+    pub fn validate_counting(
+        &self,
+        entities: Vec<Entity>,
+        today: NaiveDate,
+        stats: &mut [RuleStats],
+    ) -> Vec<Entity> {
+        let mut valid = Vec::new();
+        for mut entity in entities {
+            let mut dropped = false;
+            for ((rule, pattern), stats) in self.rules.iter().zip(stats.iter_mut()) {
+                if rule.kind != entity.kind {
+                    continue;
+                }
+                stats.checked += 1;
+                if passes(rule, pattern.as_ref(), &entity, today) {
+                    continue;
+                }
+                stats.failed += 1;
+                match rule.action {
+                    RuleAction::Flag => entity.flags.push(rule.label()),
+                    RuleAction::Drop => dropped = true,
+                }
+            }
+            if !dropped {
+                valid.push(entity);
+            }
+        }
+        valid
+    }
+}
+
+fn passes(
+    rule: &ValidationRule,
+    pattern: Option<&Regex>,
+    entity: &Entity,
+    today: NaiveDate,
+) -> bool {
+    if pattern.is_some_and(|pattern| !pattern.is_match(&entity.value)) {
+        return false;
+    }
+    if rule.min.is_none() && rule.max.is_none() {
+        return true;
+    }
+    // A range can't be checked on a value without a number, such as an
+    // ambiguous date, so those fail it
+    let Some(number) = numeric_value(entity, today) else {
+        return false;
+    };
+    rule.min.is_none_or(|min| number >= min) && rule.max.is_none_or(|max| number <= max)
+}
+
+/// The amount of a price or measurement, or the days from `today` to an ISO
+/// date
+fn numeric_value(entity: &Entity, today: NaiveDate) -> Option<f64> {
+    match entity.kind {
+        EntityKind::Price | EntityKind::Area | EntityKind::Weight | EntityKind::Duration => {
+            entity.value.split_whitespace().next()?.parse().ok()
+        }
+        EntityKind::Date => NaiveDate::parse_from_str(&entity.value, "%Y-%m-%d")
+            .ok()
+            .map(|date| (date - today).num_days() as f64),
+        EntityKind::Email | EntityKind::Phone | EntityKind::Address => None,
+    }
+}
+
+/// Rules from a JSON array, checked before the crawl starts
+pub fn load_validation_rules(path: &str) -> Result<Vec<ValidationRule>, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let rules: Vec<ValidationRule> = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid validation rules {path}: {e}"))?;
+    EntityValidator::new(&rules)?;
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityExtractor;
+
+    #[test]
+    fn test_validate_entities() {
+        let rules: Vec<ValidationRule> = serde_json::from_str(
+            r#"[
+                {"kind": "email", "pattern": "@example\\.com$", "action": "drop"},
+                {"kind": "price", "min": 0.01},
+                {"kind": "date", "min": 0, "max": 730, "name": "upcoming"}
+            ]"#,
+        )
+        .unwrap();
+        let validator = EntityValidator::new(&rules).unwrap();
+        let entities = EntityExtractor::new().extract(
+            "sales@example.com, spam@other.net. Free at $0, Pro at $49. \
+             Opens 2025-06-01, closed since 2019-01-01, back 03/04/2025.",
+        );
+        let today = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let mut stats = validator.empty_stats();
+        let valid = validator.validate_counting(entities, today, &mut stats);
+
+        let results: Vec<(&str, Vec<&str>)> = valid
+            .iter()
+            .map(|entity| {
+                (
+                    entity.value.as_str(),
+                    entity.flags.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            results,
+            vec![
+                ("sales@example.com", vec![]),
+                ("0 USD", vec!["price >= 0.01"]),
+                ("49 USD", vec![]),
+                ("2025-06-01", vec![]),
+                ("2019-01-01", vec!["upcoming"]),
+                ("03/04/2025", vec!["upcoming"]),
+            ]
+        );
+        assert_eq!(
+            stats
+                .iter()
+                .map(|stats| (stats.rule.as_str(), stats.checked, stats.failed))
+                .collect::<Vec<_>>(),
+            vec![
+                ("email matches /@example\\.com$/", 2, 1),
+                ("price >= 0.01", 2, 1),
+                ("upcoming", 3, 2),
+            ]
+        );
+
+        let invalid = [ValidationRule {
+            kind: EntityKind::Email,
+            pattern: Some("(".to_string()),
+            min: None,
+            max: None,
+            action: RuleAction::Flag,
+            name: None,
+        }];
+        assert!(EntityValidator::new(&invalid).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Condition script for `WaitStrategy::ReadyState`
+pub const READY_STATE_SCRIPT: &str = "return document.readyState === 'complete';";
+
+/// Condition script for `WaitStrategy::Selector`, takes the selector as its argument
+pub const SELECTOR_PRESENT_SCRIPT: &str = "return document.querySelector(arguments[0]) !== null;";
+
+/// Returns the number of resources the page has requested so far and whether
+/// the document finished loading, polled for `WaitStrategy::NetworkIdle`
+pub const RESOURCE_COUNT_SCRIPT: &str = r#"
+    return [
+        performance.getEntriesByType('resource').length,
+        document.readyState === 'complete'
+    ];
+"#;
+
+/// How long to wait after navigation before the page is considered ready
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum WaitStrategy {
+    /// Sleep for a fixed time
+    Fixed {
+        #[serde(with = "duration_ms")]
+        duration: Duration,
+    },
+    /// Wait until `document.readyState` is `complete`
+    ReadyState,
+    /// Wait until a CSS selector matches an element
+    Selector { selector: String },
+    /// Wait until the page stops requesting resources for `idle`
+    NetworkIdle {
+        #[serde(with = "duration_ms")]
+        idle: Duration,
+    },
+    /// Wait until a JavaScript expression evaluates to a truthy value
+    Condition { script: String },
+}
+
+impl WaitStrategy {
+    /// Script polled until it returns true, `None` for strategies that don't poll a condition
+    pub fn condition_script(&self) -> Option<String> {
+        match self {
+            WaitStrategy::ReadyState => Some(READY_STATE_SCRIPT.to_string()),
+            WaitStrategy::Selector { .. } => Some(SELECTOR_PRESENT_SCRIPT.to_string()),
+            WaitStrategy::Condition { script } => Some(format!("return !!({script});")),
+            WaitStrategy::Fixed { .. } | WaitStrategy::NetworkIdle { .. } => None,
+        }
+    }
+}
+
+impl Default for WaitStrategy {
+    fn default() -> Self {
+        WaitStrategy::Fixed {
+            duration: Duration::from_millis(2000),
+        }
+    }
+}
+
+impl FromStr for WaitStrategy {
+    type Err = String;
+
+    /// Parse `2s`, `fixed:500ms`, `ready`, `selector:CSS`, `network-idle[:500ms]` or `js:EXPR`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (kind, value) = match s.split_once(':') {
+            Some((kind, value)) => (kind.trim().to_lowercase(), Some(value.trim())),
+            None => (s.to_lowercase(), None),
+        };
+
+        match (kind.as_str(), value) {
+            ("ready" | "load", None) => Ok(WaitStrategy::ReadyState),
+            ("fixed", Some(value)) => Ok(WaitStrategy::Fixed {
+                duration: parse_duration(value)?,
+            }),
+            ("selector", Some(selector)) if !selector.is_empty() => Ok(WaitStrategy::Selector {
+                selector: selector.to_string(),
+            }),
+            ("network-idle", None) => Ok(WaitStrategy::NetworkIdle {
+                idle: Duration::from_millis(500),
+            }),
+            ("network-idle", Some(value)) => Ok(WaitStrategy::NetworkIdle {
+                idle: parse_duration(value)?,
+            }),
+            ("js", Some(script)) if !script.is_empty() => Ok(WaitStrategy::Condition {
+                script: script.to_string(),
+            }),
+            (_, None) => parse_duration(s)
+                .map(|duration| WaitStrategy::Fixed { duration })
+                .map_err(|_| format!("Invalid wait strategy '{s}'")),
+            _ => Err(format!(
+                "Invalid wait strategy '{s}', expected a duration, ready, selector:CSS, network-idle[:DURATION] or js:EXPR"
+            )),
+        }
+    }
+}
+
+/// Parse a duration like `500ms`, `2s`, `1.5s` or `1m`; bare numbers are milliseconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration '{value}'"))?;
+
+    let millis = match unit.trim() {
+        "" | "ms" => number,
+        "s" => number * 1000.0,
+        "m" => number * 60_000.0,
+        _ => {
+            return Err(format!(
+                "Invalid duration unit in '{value}', use ms, s or m"
+            ))
+        }
+    };
+    Ok(Duration::from_millis(millis.round() as u64))
+}
+
+pub(crate) mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_millis() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1500"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("1m"), Ok(Duration::from_secs(60)));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("5h").is_err());
+    }
+
+    #[test]
+    fn test_parse_wait_strategy() {
+        assert_eq!(
+            "2s".parse::<WaitStrategy>(),
+            Ok(WaitStrategy::Fixed {
+                duration: Duration::from_secs(2)
+            })
+        );
+        assert_eq!(
+            "ready".parse::<WaitStrategy>(),
+            Ok(WaitStrategy::ReadyState)
+        );
+        assert_eq!(
+            "selector:#app .product-list".parse::<WaitStrategy>(),
+            Ok(WaitStrategy::Selector {
+                selector: "#app .product-list".to_string()
+            })
+        );
+        assert_eq!(
+            "network-idle".parse::<WaitStrategy>(),
+            Ok(WaitStrategy::NetworkIdle {
+                idle: Duration::from_millis(500)
+            })
+        );
+        assert_eq!(
+            "network-idle:1s".parse::<WaitStrategy>(),
+            Ok(WaitStrategy::NetworkIdle {
+                idle: Duration::from_secs(1)
+            })
+        );
+        assert_eq!(
+            "js:window.app && window.app.ready".parse::<WaitStrategy>(),
+            Ok(WaitStrategy::Condition {
+                script: "window.app && window.app.ready".to_string()
+            })
+        );
+
+        assert!("selector:".parse::<WaitStrategy>().is_err());
+        assert!("forever".parse::<WaitStrategy>().is_err());
+    }
+
+    #[test]
+    fn test_condition_scripts() {
+        let condition = WaitStrategy::Condition {
+            script: "window.loaded".to_string(),
+        };
+        assert_eq!(
+            condition.condition_script().as_deref(),
+            Some("return !!(window.loaded);")
+        );
+        assert!(WaitStrategy::default().condition_script().is_none());
+    }
+
+    #[test]
+    fn test_wait_strategy_serde() {
+        let strategy = WaitStrategy::NetworkIdle {
+            idle: Duration::from_millis(750),
+        };
+        let json = serde_json::to_string(&strategy).unwrap();
+        assert_eq!(json, r#"{"type":"network-idle","idle":750}"#);
+        assert_eq!(
+            serde_json::from_str::<WaitStrategy>(&json).unwrap(),
+            strategy
+        );
+    }
+}
@@ -0,0 +1,253 @@
+use crate::network_guard::http_client;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// HTTP status of the current document, from the Navigation Timing API
+/// (null where the browser doesn't report it)
+pub const DOCUMENT_STATUS_SCRIPT: &str = r#"
+    const entry = performance.getEntriesByType('navigation')[0];
+    return entry && entry.responseStatus ? entry.responseStatus : null;
+"#;
+
+/// Wayback Machine availability API
+pub const WAYBACK_AVAILABILITY_URL: &str = "https://archive.org/wayback/available";
+
+/// Wayback Machine CDX API, listing the captures of a URL
+pub const WAYBACK_CDX_URL: &str = "https://web.archive.org/cdx/search/cdx";
+
+/// Longest the availability lookup may take
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Titles of error and bot-challenge pages served with a success status
+const UNAVAILABLE_TITLES: &[&str] = &[
+    "page not found",
+    "not found",
+    "access denied",
+    "forbidden",
+    "attention required",
+    "just a moment",
+];
+
+/// How pages are looked up in the Wayback Machine: as a fallback for
+/// missing and blocked pages, or for every page of a historical crawl
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WaybackConfig {
+    /// Fallback snapshot closest to this date (`YYYY`, `YYYYMM`,
+    /// `YYYYMMDD`...), the latest when None
+    pub timestamp: Option<String>,
+    /// Crawl every page as it was on this day, from its last capture on or
+    /// before it, instead of the live site
+    pub as_of: Option<NaiveDate>,
+    pub availability_url: String,
+    pub cdx_url: String,
+}
+
+impl Default for WaybackConfig {
+    fn default() -> Self {
+        WaybackConfig {
+            timestamp: None,
+            as_of: None,
+            availability_url: WAYBACK_AVAILABILITY_URL.to_string(),
+            cdx_url: WAYBACK_CDX_URL.to_string(),
+        }
+    }
+}
+
+/// Where a page's content came from when the live page was unavailable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchivedSnapshot {
+    /// Wayback Machine URL of the snapshot
+    pub url: String,
+    /// When the snapshot was taken
+    pub timestamp: DateTime<Utc>,
+    /// Why the live page wasn't used, e.g. "HTTP 404"
+    pub reason: String,
+}
+
+impl ArchivedSnapshot {
+    /// The snapshot as originally served, without the Wayback Machine
+    /// toolbar or rewritten links
+    pub fn raw_url(&self) -> String {
+        let stamp = self.timestamp.format("%Y%m%d%H%M%S").to_string();
+        self.url
+            .replacen(&format!("/{stamp}/"), &format!("/{stamp}id_/"), 1)
+    }
+}
+
+/// Why a rendered page is an error or a block page rather than content:
+/// a 404, 410, 401, 403, 429, 451 or 5xx status, or, where the browser
+/// doesn't report the status, a title such as "Page not found"
+pub fn unavailable_reason(status: Option<u16>, title: Option<&str>) -> Option<String> {
+    match status {
+        Some(status @ (401 | 403 | 404 | 410 | 429 | 451 | 500..=599)) => {
+            return Some(format!("HTTP {status}"));
+        }
+        Some(_) => return None,
+        None => {}
+    }
+    let title = title?.trim().to_lowercase();
+    (title.starts_with("404") || UNAVAILABLE_TITLES.iter().any(|t| title.starts_with(t)))
+        .then(|| format!("error page \"{title}\""))
+}
+
+/// The snapshot in an availability API response, if the page was archived
+pub fn parse_availability(response: &Value, reason: &str) -> Option<ArchivedSnapshot> {
+    let closest = &response["archived_snapshots"]["closest"];
+    if closest["available"].as_bool() == Some(false) {
+        return None;
+    }
+    let timestamp =
+        NaiveDateTime::parse_from_str(closest["timestamp"].as_str()?, "%Y%m%d%H%M%S").ok()?;
+    Some(ArchivedSnapshot {
+        url: closest["url"].as_str()?.replacen("http://", "https://", 1),
+        timestamp: timestamp.and_utc(),
+        reason: reason.to_string(),
+    })
+}
+
+/// The capture in a CDX API response with `output=json`: a header row, then
+/// `timestamp, original` rows of which the last is used
+pub fn parse_cdx(response: &Value, reason: &str) -> Option<ArchivedSnapshot> {
+    let row = response.as_array()?.iter().skip(1).last()?;
+    let timestamp = row.get(0)?.as_str()?;
+    let original = row.get(1)?.as_str()?;
+    let parsed = NaiveDateTime::parse_from_str(timestamp, "%Y%m%d%H%M%S").ok()?;
+    Some(ArchivedSnapshot {
+        url: format!("https://web.archive.org/web/{timestamp}/{original}"),
+        timestamp: parsed.and_utc(),
+        reason: reason.to_string(),
+    })
+}
+
+/// The last successful capture of `url` on or before `as_of`, None when
+/// there is none. The lookup isn't redirected to private networks unless
+/// `allow_private`.
+pub async fn snapshot_as_of(
+    url: &str,
+    as_of: NaiveDate,
+    config: &WaybackConfig,
+    allow_private: bool,
+) -> Result<Option<ArchivedSnapshot>, String> {
+    let client = http_client(LOOKUP_TIMEOUT, allow_private);
+    let to = as_of.format("%Y%m%d235959").to_string();
+    let response = client
+        .get(&config.cdx_url)
+        .query(&[
+            ("url", url),
+            ("to", &to),
+            ("output", "json"),
+            ("fl", "timestamp,original"),
+            ("filter", "statuscode:200"),
+            ("limit", "-1"),
+        ])
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Wayback Machine lookup failed: {e}"))?;
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Invalid Wayback Machine response: {e}"))?;
+    // URLs without captures get an empty body rather than an empty array
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    let body: Value = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid Wayback Machine response: {e}"))?;
+    Ok(parse_cdx(&body, &format!("as of {as_of}")))
+}
+
+/// Look up the Wayback Machine snapshot of `url` closest to the configured
+/// date, None when the page was never archived. The lookup isn't
+/// redirected to private networks unless `allow_private`.
+pub async fn find_snapshot(
+    url: &str,
+    reason: &str,
+    config: &WaybackConfig,
+    allow_private: bool,
+) -> Result<Option<ArchivedSnapshot>, String> {
+    let client = http_client(LOOKUP_TIMEOUT, allow_private);
+    let mut query = vec![("url", url)];
+    if let Some(timestamp) = &config.timestamp {
+        query.push(("timestamp", timestamp));
+    }
+    let response = client
+        .get(&config.availability_url)
+        .query(&query)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Wayback Machine lookup failed: {e}"))?;
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid Wayback Machine response: {e}"))?;
+    Ok(parse_availability(&body, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unavailable_reason() {
+        assert_eq!(
+            unavailable_reason(Some(404), None).as_deref(),
+            Some("HTTP 404")
+        );
+        assert_eq!(
+            unavailable_reason(Some(503), Some("Shop")).as_deref(),
+            Some("HTTP 503")
+        );
+        assert_eq!(unavailable_reason(Some(200), Some("404 Not Found")), None);
+        assert!(unavailable_reason(None, Some("Just a moment...")).is_some());
+        assert!(unavailable_reason(None, Some("Page Not Found | Acme")).is_some());
+        assert_eq!(unavailable_reason(None, Some("Pricing | Acme")), None);
+        assert_eq!(unavailable_reason(None, None), None);
+    }
+
+    #[test]
+    fn test_parse_availability() {
+        let response = json!({
+            "url": "example.com/pricing",
+            "archived_snapshots": {
+                "closest": {
+                    "status": "200",
+                    "available": true,
+                    "url": "http://web.archive.org/web/20220315093000/https://example.com/pricing",
+                    "timestamp": "20220315093000"
+                }
+            }
+        });
+        let snapshot = parse_availability(&response, "HTTP 404").unwrap();
+        assert_eq!(snapshot.timestamp.to_rfc3339(), "2022-03-15T09:30:00+00:00");
+        assert_eq!(snapshot.reason, "HTTP 404");
+        assert_eq!(
+            snapshot.raw_url(),
+            "https://web.archive.org/web/20220315093000id_/https://example.com/pricing"
+        );
+
+        let missing = json!({"url": "example.com/new", "archived_snapshots": {}});
+        assert_eq!(parse_availability(&missing, "HTTP 404"), None);
+    }
+
+    #[test]
+    fn test_parse_cdx() {
+        let response = json!([
+            ["timestamp", "original"],
+            ["20230101080000", "https://example.com/pricing"],
+            ["20230528120000", "http://example.com/pricing"]
+        ]);
+        let snapshot = parse_cdx(&response, "as of 2023-06-01").unwrap();
+        assert_eq!(
+            snapshot.raw_url(),
+            "https://web.archive.org/web/20230528120000id_/http://example.com/pricing"
+        );
+        assert_eq!(snapshot.reason, "as of 2023-06-01");
+        assert_eq!(parse_cdx(&json!([["timestamp", "original"]]), ""), None);
+    }
+}
@@ -0,0 +1,261 @@
+//! A WebDriver server for tests that answers the commands `Browser` sends
+//! with canned pages, so navigation, timeouts and session restarts can be
+//! tested without geckodriver or chromedriver.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// What the mock serves for a URL
+#[derive(Debug, Clone, Default)]
+pub struct MockPage {
+    pub title: String,
+    pub html: String,
+    /// How long navigating to the page takes
+    pub delay: Duration,
+    /// The first navigation to the page ends the session, as when the
+    /// browser crashes
+    pub crash: bool,
+    /// Navigating to the page ends up at this URL instead
+    pub redirect: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    pages: HashMap<String, MockPage>,
+    /// Responses to scripts containing the key
+    scripts: Vec<(String, Value)>,
+    session: Option<String>,
+    sessions_created: usize,
+    /// Sessions created before new ones are refused
+    max_sessions: Option<usize>,
+    current_url: String,
+    /// `METHOD /path` of every request, session ids replaced by `{id}`
+    requests: Vec<String>,
+}
+
+pub struct MockWebDriver {
+    port: u16,
+    state: Arc<Mutex<MockState>>,
+    server: JoinHandle<()>,
+}
+
+impl MockWebDriver {
+    pub async fn start() -> Self {
+        rustls::crypto::ring::default_provider()
+            .install_default()
+            .ok();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let server_state = state.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_connection(stream, server_state.clone()));
+            }
+        });
+        MockWebDriver {
+            port,
+            state,
+            server,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub fn page(&self, url: &str, page: MockPage) {
+        self.state
+            .lock()
+            .unwrap()
+            .pages
+            .insert(url.to_string(), page);
+    }
+
+    /// Answer scripts containing `key` with `value` instead of null
+    pub fn script(&self, key: &str, value: Value) {
+        self.state
+            .lock()
+            .unwrap()
+            .scripts
+            .push((key.to_string(), value));
+    }
+
+    /// Refuse new sessions once `max` were created, as when the browser
+    /// can't be started again
+    pub fn max_sessions(&self, max: usize) {
+        self.state.lock().unwrap().max_sessions = Some(max);
+    }
+
+    pub fn sessions_created(&self) -> usize {
+        self.state.lock().unwrap().sessions_created
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for MockWebDriver {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn serve_connection(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let mut stream = BufReader::new(stream);
+    loop {
+        let mut request_line = String::new();
+        if stream.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            if stream.read_line(&mut header).await.unwrap_or(0) == 0 {
+                return;
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        if stream.read_exact(&mut body).await.is_err() {
+            return;
+        }
+        let body: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+        let (delay, status, value) = respond(&state, &method, &path, &body);
+        tokio::time::sleep(delay).await;
+
+        let body = json!({ "value": value }).to_string();
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        if stream
+            .get_mut()
+            .write_all(response.as_bytes())
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Delay, HTTP status and `value` of the response to a command
+fn respond(
+    state: &Mutex<MockState>,
+    method: &str,
+    path: &str,
+    body: &Value,
+) -> (Duration, &'static str, Value) {
+    let mut state = state.lock().unwrap();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let command = match segments.as_slice() {
+        ["session", _, rest @ ..] => format!("/session/{{id}}/{}", rest.join("/")),
+        _ => path.to_string(),
+    };
+    state
+        .requests
+        .push(format!("{method} {}", command.trim_end_matches('/')));
+
+    if method == "POST" && segments == ["session"] {
+        if state
+            .max_sessions
+            .is_some_and(|max| state.sessions_created >= max)
+        {
+            return (
+                Duration::ZERO,
+                "500 Internal Server Error",
+                json!({
+                    "error": "session not created",
+                    "message": "session not created: browser failed to start",
+                    "stacktrace": ""
+                }),
+            );
+        }
+        state.sessions_created += 1;
+        let id = format!("session-{}", state.sessions_created);
+        state.session = Some(id.clone());
+        state.current_url = "about:blank".to_string();
+        return (
+            Duration::ZERO,
+            "200 OK",
+            json!({ "sessionId": id, "capabilities": {} }),
+        );
+    }
+    if segments.get(1).copied() != state.session.as_deref() {
+        return (
+            Duration::ZERO,
+            "404 Not Found",
+            json!({
+                "error": "invalid session id",
+                "message": "invalid session id",
+                "stacktrace": ""
+            }),
+        );
+    }
+
+    let current = state
+        .pages
+        .get(&state.current_url)
+        .cloned()
+        .unwrap_or_default();
+    let value = match (method, &segments[2..]) {
+        ("DELETE", []) => {
+            state.session = None;
+            Value::Null
+        }
+        ("GET", ["url"]) => json!(state.current_url),
+        ("POST", ["url"]) => {
+            let url = body["url"].as_str().unwrap_or_default().to_string();
+            let page = state.pages.get(&url).cloned().unwrap_or_default();
+            state.current_url = page.redirect.clone().unwrap_or_else(|| url.clone());
+            if page.crash {
+                if let Some(page) = state.pages.get_mut(&url) {
+                    page.crash = false;
+                }
+                state.session = None;
+                return (
+                    page.delay,
+                    "500 Internal Server Error",
+                    json!({
+                        "error": "unknown error",
+                        "message": "unknown error: session deleted because of page crash",
+                        "stacktrace": ""
+                    }),
+                );
+            }
+            return (page.delay, "200 OK", Value::Null);
+        }
+        ("GET", ["title"]) => json!(current.title),
+        ("GET", ["source"]) => json!(current.html),
+        ("POST", ["execute", _]) => {
+            let script = body["script"].as_str().unwrap_or_default();
+            state
+                .scripts
+                .iter()
+                .find(|(key, _)| script.contains(key.as_str()))
+                .map(|(_, value)| value.clone())
+                .unwrap_or(Value::Null)
+        }
+        _ => Value::Null,
+    };
+    (Duration::ZERO, "200 OK", value)
+}
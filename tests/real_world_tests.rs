@@ -15,7 +15,7 @@ async fn full_crawl_pipeline(
     let mut storage = UrlStorage::new();
 
     // Extract domain from initial URL
-    let domain = smart_crawler::utils::extract_domain_from_url(initial_url)
+    let domain = smart_crawler::extract_domain_from_url(initial_url)
         .ok_or("Failed to extract domain from URL")?;
 
     println!("Starting full SmartCrawler pipeline for domain: {domain}");
@@ -37,7 +37,7 @@ async fn full_crawl_pipeline(
     storage.add_url(initial_url.to_string());
 
     // Add root URL for the domain if not already present
-    let root_url = smart_crawler::utils::construct_root_url(&domain);
+    let root_url = smart_crawler::construct_root_url(&domain);
     if !domain_urls[&domain].contains(&root_url) {
         domain_urls
             .get_mut(&domain)
@@ -99,7 +99,7 @@ async fn full_crawl_pipeline(
 
     // Finally, add all other discovered URLs
     for url in &domain_urls[&domain] {
-        if url != initial_url && !smart_crawler::utils::is_root_url(url) {
+        if url != initial_url && !smart_crawler::is_root_url(url) {
             all_urls.push(url.clone());
         }
     }